@@ -0,0 +1,92 @@
+//! Benchmarks for position engine.
+//!
+//! Save baseline before change and compare after it:
+//! `cargo bench --bench position -- --save-baseline main`
+//! `cargo bench --bench position -- --baseline main`
+
+use std::sync::{Arc, Mutex};
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use dashmap::DashMap;
+use htmx_lsp2::{
+    htmx_tree_sitter::{LspFiles, Parsers},
+    init_hx::LangType,
+    position::{get_position_from_lsp_completion, query_position, QueryType},
+    query_helper::{HTMLQueries, Queries},
+};
+use ropey::Rope;
+use tower_lsp::lsp_types::{Position, TextDocumentIdentifier, TextDocumentPositionParams, Url};
+use tree_sitter::Point;
+
+const URI: &str = "file:///templates/large.html";
+
+/// Template with 1k+ lines, cursor is placed in last `hx-swap` value.
+fn large_template() -> (String, Point) {
+    let mut text = String::from("<html>\n<body>\n");
+    for i in 0..300 {
+        text.push_str(&format!(
+            "<div id=\"row-{i}\" class=\"row\">\n  <button hx-get=\"/rows/{i}\" hx-target=\"#row-{i}\" hx-lsp=\"row{i}\">Load</button>\n  <p>{{{{ rows[{i}].name }}}}</p>\n</div>\n"
+        ));
+    }
+    let line = text.lines().count();
+    text.push_str("<div hx-swap=\"\"></div>\n</body>\n</html>\n");
+    (text, Point::new(line, 13))
+}
+
+fn bench_parse(c: &mut Criterion) {
+    let (text, _) = large_template();
+    let parsers = Parsers::default();
+    c.bench_function("parse large template", |b| {
+        b.iter(|| parsers.parse(LangType::Template, black_box(&text), None))
+    });
+}
+
+fn bench_query_position(c: &mut Criterion) {
+    let (text, point) = large_template();
+    let tree = Parsers::default()
+        .parse(LangType::Template, &text, None)
+        .unwrap();
+    let query = HTMLQueries::default();
+    c.bench_function("query_position", |b| {
+        b.iter(|| {
+            query_position(
+                tree.root_node(),
+                black_box(&text),
+                point,
+                QueryType::Completion,
+                &query,
+            )
+        })
+    });
+}
+
+fn bench_completion(c: &mut Criterion) {
+    let (text, point) = large_template();
+    let document_map = DashMap::new();
+    document_map.insert(String::from(URI), Rope::from_str(&text));
+    let lsp_files = Arc::new(Mutex::new(LspFiles::default()));
+    if let Ok(lsp_files) = lsp_files.lock() {
+        let index = lsp_files.add_file(String::from(URI)).unwrap();
+        lsp_files.add_tree(index, LangType::Template, &text, None);
+    }
+    let queries = Arc::new(Mutex::new(Queries::default()));
+    let params = TextDocumentPositionParams::new(
+        TextDocumentIdentifier::new(Url::parse(URI).unwrap()),
+        Position::new(point.row as u32, point.column as u32),
+    );
+    c.bench_function("completion end-to-end", |b| {
+        b.iter(|| {
+            get_position_from_lsp_completion(
+                black_box(&params),
+                &document_map,
+                String::from(URI),
+                QueryType::Completion,
+                &lsp_files,
+                &queries,
+            )
+        })
+    });
+}
+
+criterion_group!(benches, bench_parse, bench_query_position, bench_completion);
+criterion_main!(benches);
@@ -0,0 +1,116 @@
+//! Generates htmx catalogue from `src/md/htmx.json`.
+//!
+//! Snapshot has htmx version, `attributes` and `values` of attributes as
+//! `[name, path]` pairs, paths are relative to `src/md`. Order of pairs is order
+//! of completion items.
+//!
+//! Translated documentation bundles are `src/md/locales/<locale>.json` files.
+
+use std::{env, fmt::Write, fs, path::Path};
+
+use serde_json::Value;
+
+const REFERENCE: &str = "src/md/htmx.json";
+const LOCALES: &str = "src/md/locales";
+
+fn main() {
+    println!("cargo:rerun-if-changed={REFERENCE}");
+    println!("cargo:rerun-if-changed={LOCALES}");
+    let root = env::var("CARGO_MANIFEST_DIR").unwrap();
+    let md = Path::new(&root).join("src/md");
+    let content = fs::read_to_string(Path::new(&root).join(REFERENCE)).unwrap();
+    let reference: Value =
+        serde_json::from_str(&content).unwrap_or_else(|err| panic!("{REFERENCE}: {err}"));
+
+    let version = reference["htmx"].as_str().expect("htmx version is missing");
+    let attributes = pairs(&md, &reference["attributes"], "attributes");
+    let values: Vec<(String, Vec<(String, String)>)> = reference["values"]
+        .as_array()
+        .unwrap_or_else(|| fail("values", "[attribute, pairs]"))
+        .iter()
+        .map(|entry| match entry.as_array().map(Vec::as_slice) {
+            Some([Value::String(attribute), entries]) => {
+                (attribute.clone(), pairs(&md, entries, attribute))
+            }
+            _ => fail("values", "[attribute, pairs]"),
+        })
+        .collect();
+
+    let mut out = String::new();
+    writeln!(out, "pub const HTMX_VERSION: &str = {version:?};").unwrap();
+    writeln!(
+        out,
+        "pub fn attributes() -> Vec<(&'static str, &'static str)> {{"
+    )
+    .unwrap();
+    writeln!(out, "    vec![{}]\n}}", entries(&attributes)).unwrap();
+    writeln!(
+        out,
+        "pub fn values() -> Vec<(&'static str, Vec<(&'static str, &'static str)>)> {{\n    vec!["
+    )
+    .unwrap();
+    for (attribute, entries_) in &values {
+        writeln!(out, "        ({attribute:?}, vec![{}]),", entries(entries_)).unwrap();
+    }
+    writeln!(out, "    ]\n}}").unwrap();
+    writeln!(
+        out,
+        "pub fn locales() -> Vec<(&'static str, &'static str)> {{"
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "    vec![{}]\n}}",
+        entries(&locales(&Path::new(&root).join(LOCALES)))
+    )
+    .unwrap();
+    let dest = Path::new(&env::var("OUT_DIR").unwrap()).join("catalogue.rs");
+    fs::write(dest, out).unwrap();
+}
+
+/// Locale name and path of every bundle, sorted by locale.
+fn locales(dir: &Path) -> Vec<(String, String)> {
+    let mut locales: Vec<(String, String)> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            let locale = path.file_stem()?.to_str()?.to_string();
+            (path.extension()? == "json").then(|| (locale, path.display().to_string()))
+        })
+        .collect();
+    locales.sort();
+    locales
+}
+
+/// `[name, path]` pairs of `table`, with path of existing markdown file.
+fn pairs(md: &Path, pairs: &Value, table: &str) -> Vec<(String, String)> {
+    pairs
+        .as_array()
+        .unwrap_or_else(|| fail(table, "[name, path]"))
+        .iter()
+        .map(|pair| match pair.as_array().map(Vec::as_slice) {
+            Some([Value::String(name), Value::String(path)]) => {
+                let path = md.join(path);
+                if !path.is_file() {
+                    panic!("{REFERENCE}: missing file {}", path.display());
+                }
+                (name.clone(), path.display().to_string())
+            }
+            _ => fail(table, "[name, path]"),
+        })
+        .collect()
+}
+
+fn fail(table: &str, item: &str) -> ! {
+    panic!("{REFERENCE}: `{table}` must be array of `{item}`")
+}
+
+fn entries(entries: &[(String, String)]) -> String {
+    entries
+        .iter()
+        .map(|(name, path)| format!("({name:?}, include_str!({path:?}))"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -74,7 +74,7 @@ function getServer(): { valid: boolean, name: string } {
 		const windows = process.platform === "win32";
 		const suffix = windows ? ".exe" : "";
 		const binaryName = name + suffix;
-		const validation = child_process.spawnSync(name);
+		const validation = child_process.spawnSync(name, ["--version"]);
 		if (validation.status === 0) {
 			return { valid: true, name: binaryName };
 		}
@@ -0,0 +1,123 @@
+use tree_sitter::{Node, Point};
+
+/// String literal argument of `htmx.ajax(verb, path, context)` call.
+#[derive(Debug, PartialEq)]
+pub struct AjaxLiteral {
+    pub value: String,
+    /// Index of call argument, object with `target` or `source` is also searched.
+    pub argument: usize,
+}
+
+/// String nodes of supported languages.
+const STRING_KINDS: [&str; 5] = [
+    "string",
+    "string_literal",
+    "interpreted_string_literal",
+    "raw_string_literal",
+    "template_string",
+];
+
+/// String literal under `point`, if it is argument of `htmx.ajax` call.
+pub fn ajax_literal(root: Node<'_>, source: &str, point: Point) -> Option<AjaxLiteral> {
+    let mut node = root.named_descendant_for_point_range(point, point)?;
+    while !STRING_KINDS.contains(&node.kind()) {
+        node = node.parent()?;
+        if node.kind() == "arguments" {
+            return None;
+        }
+    }
+    let value = string_value(node, source)?;
+    let mut child = node;
+    let mut parent = node.parent()?;
+    while parent.kind() != "arguments" {
+        child = parent;
+        parent = parent.parent()?;
+    }
+    let call = parent.parent()?;
+    let function = call.child_by_field_name("function")?;
+    if call.kind() != "call_expression"
+        || function.utf8_text(source.as_bytes()).ok()? != "htmx.ajax"
+    {
+        return None;
+    }
+    let mut cursor = parent.walk();
+    let argument = parent
+        .named_children(&mut cursor)
+        .position(|argument| argument.id() == child.id())?;
+    Some(AjaxLiteral { value, argument })
+}
+
+/// Content of string literal, without quotes and Python prefixes.
+pub fn string_value(node: Node<'_>, source: &str) -> Option<String> {
+    let text = node.utf8_text(source.as_bytes()).ok()?;
+    let text = text.trim_start_matches(|c: char| c.is_ascii_alphabetic());
+    let quote = text.chars().next()?;
+    if !matches!(quote, '"' | '\'' | '`') {
+        return None;
+    }
+    let text = text.trim_matches(quote);
+    Some(text.to_string())
+}
+
+/// String literals in tree with `value`.
+pub fn string_literals<'a>(root: Node<'a>, source: &str, value: &str) -> Vec<Node<'a>> {
+    let mut literals = vec![];
+    let mut stack = vec![root];
+    while let Some(node) = stack.pop() {
+        if STRING_KINDS.contains(&node.kind()) {
+            if string_value(node, source).as_deref() == Some(value) {
+                literals.push(node);
+            }
+            continue;
+        }
+        let mut cursor = node.walk();
+        stack.extend(node.named_children(&mut cursor));
+    }
+    literals.sort_by_key(|node| node.start_byte());
+    literals
+}
+
+#[cfg(test)]
+mod tests {
+    use tree_sitter::{Parser, Point};
+
+    use super::{ajax_literal, string_literals, AjaxLiteral};
+
+    #[test]
+    fn literal_in_ajax_call() {
+        let text = r##"htmx.ajax("GET", "/users", "#list");
+htmx.ajax("POST", "/users", { target: "#form", source: "#button" });
+fetch("/users");
+"##;
+        let mut parser = Parser::new();
+        parser
+            .set_language(tree_sitter_javascript::language())
+            .unwrap();
+        let tree = parser.parse(text, None).unwrap();
+        let literal = |row, column| ajax_literal(tree.root_node(), text, Point::new(row, column));
+        assert_eq!(
+            literal(0, 19),
+            Some(AjaxLiteral {
+                value: String::from("/users"),
+                argument: 1
+            })
+        );
+        assert_eq!(literal(0, 29).unwrap().value, "#list");
+        assert_eq!(
+            literal(1, 40),
+            Some(AjaxLiteral {
+                value: String::from("#form"),
+                argument: 2
+            })
+        );
+        assert_eq!(literal(2, 8), None);
+        assert_eq!(literal(0, 2), None);
+
+        let backend = "app.get(\"/users\", users)\nprint('/users')\n";
+        parser.set_language(tree_sitter_python::language()).unwrap();
+        let tree = parser.parse(backend, None).unwrap();
+        let literals = string_literals(tree.root_node(), backend, "/users");
+        assert_eq!(literals.len(), 2);
+        assert_eq!(literals[1].start_position(), Point::new(1, 6));
+    }
+}
@@ -0,0 +1,230 @@
+use std::{
+    collections::BTreeMap,
+    path::Path,
+    sync::{Arc, Mutex, RwLock},
+};
+
+use dashmap::DashMap;
+use serde_json::{json, Value};
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, Url};
+
+use crate::{
+    config::{read_config, validate_config},
+    config_file::{read_config_file, CONFIG_FILE},
+    htmx_tree_sitter::LspFiles,
+    indexing::IndexCoordinator,
+    query_helper::Queries,
+    state::{Recover, RecoverRw, State},
+    suppress::{Suppressions, RULES},
+};
+
+/// Output of `check` command.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    /// `path:line:column: severity [code] message`, one per line.
+    Text,
+    /// SARIF 2.1.0 log for code scanning tools.
+    Sarif,
+}
+
+/// Diagnostics for one file, path is relative to project root.
+pub type Report = Vec<(String, Vec<Diagnostic>)>;
+
+/// Index project with `htmx-lsp.toml` config from `root` and run every check.
+/// Relative directories in config are resolved from current directory.
+/// Suppression comments are respected, files and diagnostics are sorted.
+pub async fn check_project(root: &Path) -> anyhow::Result<Report> {
+    let config = read_config_file(root)
+        .and_then(|config| validate_config(Some(config)))
+        .filter(|config| !config.template_only)
+        .ok_or_else(|| {
+            anyhow::Error::msg(format!(
+                "{CONFIG_FILE} with full config is needed, run `htmx-lsp2 init`."
+            ))
+        })?;
+    let config = RwLock::new(config);
+    let lsp_files = Arc::new(Mutex::new(LspFiles::default()));
+    let queries = Arc::new(Mutex::new(Queries::default()));
+    let document_map = DashMap::new();
+    let token = IndexCoordinator::default().start();
+    let tags = read_config(
+        &config,
+        &lsp_files,
+        &queries,
+        &document_map,
+        &DashMap::new(),
+        &token,
+    )
+    .await?;
+    let config = config.recover_read(State::Config).clone();
+    let hm = lsp_files
+        .recover(State::Index)
+        .project_diagnostics(tags, &config, &document_map);
+    Ok(report(root, hm, &document_map))
+}
+
+fn report(
+    root: &Path,
+    hm: BTreeMap<String, Vec<Diagnostic>>,
+    document_map: &DashMap<String, ropey::Rope>,
+) -> Report {
+    let root = std::fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf());
+    let mut report: Report = hm
+        .into_iter()
+        .filter(|(_, diagnostics)| !diagnostics.is_empty())
+        .map(|(uri, mut diagnostics)| {
+            if let Some(rope) = document_map.get(&uri) {
+                Suppressions::new(&rope.to_string()).filter(&mut diagnostics);
+            }
+            (relative_path(&root, &uri), diagnostics)
+        })
+        .filter(|(_, diagnostics)| !diagnostics.is_empty())
+        .collect();
+    report.sort_by(|a, b| a.0.cmp(&b.0));
+    report
+}
+
+/// Path from file uri relative to `root`, with `/` separators.
+fn relative_path(root: &Path, uri: &str) -> String {
+    let Some(path) = Url::parse(uri).ok().and_then(|uri| uri.to_file_path().ok()) else {
+        return uri.to_string();
+    };
+    let path = path.strip_prefix(root).unwrap_or(&path);
+    path.to_string_lossy().replace('\\', "/")
+}
+
+fn code(diagnostic: &Diagnostic) -> &str {
+    match &diagnostic.code {
+        Some(NumberOrString::String(code)) => code,
+        _ => "",
+    }
+}
+
+fn severity(diagnostic: &Diagnostic) -> &'static str {
+    match diagnostic.severity {
+        Some(DiagnosticSeverity::ERROR) => "error",
+        Some(DiagnosticSeverity::WARNING) => "warning",
+        _ => "note",
+    }
+}
+
+/// Number of errors and warnings, hints are not counted.
+pub fn problems(report: &Report) -> usize {
+    report
+        .iter()
+        .flat_map(|(_, diagnostics)| diagnostics)
+        .filter(|diagnostic| severity(diagnostic) != "note")
+        .count()
+}
+
+pub fn to_text(report: &Report) -> String {
+    let mut text = String::new();
+    for (path, diagnostics) in report {
+        for diagnostic in diagnostics {
+            let start = diagnostic.range.start;
+            text.push_str(&format!(
+                "{path}:{}:{}: {} [{}] {}\n",
+                start.line + 1,
+                start.character + 1,
+                severity(diagnostic),
+                code(diagnostic),
+                diagnostic.message
+            ));
+        }
+    }
+    text
+}
+
+/// SARIF log, every code from rules registry is listed as rule.
+pub fn to_sarif(report: &Report) -> Value {
+    let rules: Vec<Value> = RULES
+        .iter()
+        .map(|(id, description)| json!({ "id": id, "shortDescription": { "text": description } }))
+        .collect();
+    let mut results = vec![];
+    for (path, diagnostics) in report {
+        for diagnostic in diagnostics {
+            let code = code(diagnostic);
+            let mut result = json!({
+                "ruleId": code,
+                "level": severity(diagnostic),
+                "message": { "text": diagnostic.message },
+                "locations": [{
+                    "physicalLocation": {
+                        "artifactLocation": { "uri": path },
+                        "region": {
+                            "startLine": diagnostic.range.start.line + 1,
+                            "startColumn": diagnostic.range.start.character + 1,
+                            "endLine": diagnostic.range.end.line + 1,
+                            "endColumn": diagnostic.range.end.character + 1,
+                        }
+                    }
+                }]
+            });
+            if let Some(index) = RULES.iter().position(|(id, _)| *id == code) {
+                result["ruleIndex"] = json!(index);
+            }
+            results.push(result);
+        }
+    }
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": "https://github.com/uros-5/htmx-lsp2",
+                    "rules": rules,
+                }
+            },
+            "results": results,
+        }]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_project, problems, to_sarif, to_text};
+    use crate::test_dir::TestDir;
+
+    #[tokio::test]
+    async fn checks_project_with_sarif_output() {
+        let root = TestDir::new();
+        let template = "<div hx-swap=\"innerHTML fade:1s\" hx-lsp=\"a\"></div>\n<!-- htmx-lsp-disable-next-line swap-modifier -->\n<p hx-swap=\"x:1\"></p>";
+        root.write("templates/index.html", template);
+        let windows = "\u{feff}<p hx-swap=\"x:1\"></p>\r\n<p hx-swap=\"y:1\"></p>\r\n";
+        root.write("templates/windows.html", windows);
+        let backend = "fn a() {\n    // hx@a\n}\nfn b() {\n    // hx@a\n}\n";
+        root.write("src/main.rs", backend);
+        let config = format!(
+            "lang = \"rust\"\ntemplate_ext = \"html\"\ntemplates = [{:?}]\njs_tags = []\nbackend_tags = [{:?}]\n",
+            root.join("templates").display().to_string(),
+            root.join("src").display().to_string()
+        );
+        root.write("htmx-lsp.toml", config);
+
+        let report = check_project(&root).await.unwrap();
+        let text = to_text(&report);
+        assert!(text.starts_with("src/main.rs:5:11: warning [duplicate-tag]"));
+        assert!(text.contains("templates/index.html:1:25: error [swap-modifier]"));
+        assert!(text.contains("templates/windows.html:1:13: error [swap-modifier]"));
+        assert!(text.contains("templates/windows.html:2:13: error [swap-modifier]"));
+        assert_eq!(problems(&report), 4);
+
+        let sarif = to_sarif(&report);
+        let results = sarif["runs"][0]["results"].as_array().unwrap();
+        assert_eq!(results.len(), 4);
+        assert_eq!(results[0]["ruleId"], "duplicate-tag");
+        assert_eq!(
+            results[1]["locations"][0]["physicalLocation"]["artifactLocation"]["uri"],
+            "templates/index.html"
+        );
+        let rules = &sarif["runs"][0]["tool"]["driver"]["rules"];
+        assert_eq!(
+            rules[results[1]["ruleIndex"].as_u64().unwrap() as usize]["id"],
+            "swap-modifier"
+        );
+    }
+}
@@ -0,0 +1,130 @@
+use serde_json::{json, Value};
+
+use std::path::Path;
+
+use crate::{
+    check::{check_project, problems, to_sarif, to_text, Format},
+    config::{BACKEND_LANGS, JS_EXTS},
+    init_hx::HTMX_VERSION,
+    scaffold::{init, Editor, Written},
+};
+
+/// Output for `--version` flag.
+pub fn version_info() -> Value {
+    json!({
+        "name": env!("CARGO_PKG_NAME"),
+        "version": env!("CARGO_PKG_VERSION"),
+    })
+}
+
+/// Output for `--capabilities` flag. `tree_sitter_abi` is newest grammar ABI
+/// version that parser supports, it's useful for `custom_languages`.
+pub fn capabilities_info() -> Value {
+    json!({
+        "name": env!("CARGO_PKG_NAME"),
+        "version": env!("CARGO_PKG_VERSION"),
+        "tree_sitter_abi": tree_sitter::LANGUAGE_VERSION,
+        "grammars": ["html", "javascript", "typescript", "tsx", "rust", "python", "go"],
+        "htmx": HTMX_VERSION,
+        "backend": BACKEND_LANGS,
+        "javascript": JS_EXTS,
+        "template": "any extension, parsed as html",
+    })
+}
+
+/// Daemon flags, `--listen <addr>` starts daemon with shared index and
+/// `--connect <addr>` forwards stdio to it.
+pub enum Mode {
+    Stdio,
+    Listen(String),
+    Connect(String),
+}
+
+/// Server mode from command line flags.
+pub fn mode(args: &[String]) -> Mode {
+    let addr = || {
+        args.get(1)
+            .cloned()
+            .unwrap_or_else(|| String::from(DEFAULT_ADDR))
+    };
+    match args.first().map(String::as_str) {
+        Some("--listen") => Mode::Listen(addr()),
+        Some("--connect") => Mode::Connect(addr()),
+        _ => Mode::Stdio,
+    }
+}
+
+/// Default daemon address.
+pub const DEFAULT_ADDR: &str = "127.0.0.1:7650";
+
+/// `init --editor helix|kak|nvim|vscode`, writes `htmx-lsp.toml` and editor
+/// config for project in current directory.
+fn init_command(args: &[String]) -> Result<(), String> {
+    let usage = "Usage: htmx-lsp2 init --editor helix|kak|nvim|vscode";
+    let editor = match args {
+        [flag, name] if flag == "--editor" => Editor::from_name(name).ok_or(usage)?,
+        _ => return Err(usage.to_string()),
+    };
+    for written in init(Path::new("."), editor).map_err(|err| err.to_string())? {
+        match written {
+            Written::Created(path) => println!("Created {}", path.display()),
+            Written::Exists(path, content) => {
+                println!("{} already exists, add:\n\n{content}", path.display())
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `check [--format text|sarif]`, validates project in current directory.
+/// Exit code is 1 when errors or warnings are found.
+async fn check_command(args: &[String]) -> Result<bool, String> {
+    let usage = "Usage: htmx-lsp2 check [--format text|sarif]";
+    let format = match args {
+        [] => Format::Text,
+        [flag, format] if flag == "--format" => match format.as_str() {
+            "text" => Format::Text,
+            "sarif" => Format::Sarif,
+            _ => return Err(usage.to_string()),
+        },
+        _ => return Err(usage.to_string()),
+    };
+    let report = check_project(Path::new("."))
+        .await
+        .map_err(|err| err.to_string())?;
+    match format {
+        Format::Text => print!("{}", to_text(&report)),
+        Format::Sarif => println!("{:#}", to_sarif(&report)),
+    }
+    Ok(problems(&report) == 0)
+}
+
+/// Handle command line flags. Returns `true` if flag is handled and server
+/// shouldn't start.
+pub async fn handle_args(args: &[String]) -> bool {
+    let output = match args.first().map(String::as_str) {
+        Some("--version") | Some("-V") => version_info(),
+        Some("--capabilities") => capabilities_info(),
+        Some("check") => {
+            match check_command(&args[1..]).await {
+                Ok(true) => (),
+                Ok(false) => std::process::exit(1),
+                Err(err) => {
+                    eprintln!("{err}");
+                    std::process::exit(2);
+                }
+            }
+            return true;
+        }
+        Some("init") => {
+            if let Err(err) = init_command(&args[1..]) {
+                eprintln!("{err}");
+                std::process::exit(1);
+            }
+            return true;
+        }
+        _ => return false,
+    };
+    println!("{output}");
+    true
+}
@@ -0,0 +1,141 @@
+use tower_lsp::lsp_types::{CodeLens, Command, Position, Range};
+use tree_sitter::{Node, Point};
+
+use crate::position::tag_attributes;
+
+/// Attributes that issue request.
+pub const VERBS: [&str; 5] = ["hx-get", "hx-post", "hx-put", "hx-patch", "hx-delete"];
+
+/// Element with verb attribute, but without own or inherited `hx-target`.
+#[derive(Debug, PartialEq)]
+pub struct Untargeted {
+    pub verb: &'static str,
+    /// Start of element start tag.
+    pub point: Point,
+}
+
+/// Number of every verb in template and elements that swap into themselves.
+#[derive(Debug, Default, PartialEq)]
+pub struct VerbStats {
+    pub counts: [usize; 5],
+    pub untargeted: Vec<Untargeted>,
+}
+
+/// Count verbs of every element, `hx-target` is inherited by descendants.
+/// Elements with `hx-swap="none"` or `hx-swap="delete"` don't swap into themselves.
+pub fn verb_stats(root: Node<'_>, source: &str) -> VerbStats {
+    let mut stats = VerbStats::default();
+    let mut stack = vec![(root, false)];
+    while let Some((node, inherited)) = stack.pop() {
+        let mut targeted = inherited;
+        if let Some(start_tag) = node
+            .child(0)
+            .filter(|_| node.kind() == "element")
+            .filter(|tag| tag.kind() == "start_tag" || tag.kind() == "self_closing_tag")
+        {
+            let mut verb = None;
+            let mut swaps = true;
+            for (name, value) in tag_attributes(start_tag, source) {
+                let name = name.strip_prefix("data-").unwrap_or(&name);
+                let value = value.and_then(|value| value.utf8_text(source.as_bytes()).ok());
+                if let Some(i) = VERBS.iter().position(|verb| *verb == name) {
+                    stats.counts[i] += 1;
+                    verb = verb.or(Some(VERBS[i]));
+                }
+                match name {
+                    "hx-target" => targeted = true,
+                    "hx-swap" => {
+                        let style = value.unwrap_or_default().split_whitespace().next();
+                        swaps = !matches!(style, Some("none" | "delete"));
+                    }
+                    _ => (),
+                }
+            }
+            if let Some(verb) = verb.filter(|_| !targeted && swaps) {
+                stats.untargeted.push(Untargeted {
+                    verb,
+                    point: start_tag.start_position(),
+                });
+            }
+        }
+        let mut cursor = node.walk();
+        let children: Vec<Node<'_>> = node.children(&mut cursor).collect();
+        stack.extend(children.into_iter().rev().map(|child| (child, targeted)));
+    }
+    stats
+}
+
+/// Lens with verb counts on first line and warning lens on every element that
+/// swaps into itself. Lenses only show text, their command is empty.
+pub fn verb_lenses(stats: &VerbStats) -> Vec<CodeLens> {
+    let lens = |point: Point, title: String| CodeLens {
+        range: Range::new(
+            Position::new(point.row as u32, point.column as u32),
+            Position::new(point.row as u32, point.column as u32),
+        ),
+        command: Some(Command {
+            title,
+            command: String::new(),
+            arguments: None,
+        }),
+        data: None,
+    };
+    let counts: Vec<String> = VERBS
+        .iter()
+        .zip(stats.counts)
+        .filter(|(_, count)| *count > 0)
+        .map(|(verb, count)| format!("{verb}: {count}"))
+        .collect();
+    let mut lenses = vec![];
+    if !counts.is_empty() {
+        lenses.push(lens(Point::new(0, 0), counts.join(" | ")));
+    }
+    for element in &stats.untargeted {
+        let title = format!(
+            "Warning: {} swaps into this element, there is no hx-target",
+            element.verb
+        );
+        lenses.push(lens(element.point, title));
+    }
+    lenses
+}
+
+#[cfg(test)]
+mod tests {
+    use tree_sitter::{Parser, Point};
+
+    use super::{verb_lenses, verb_stats, Untargeted};
+
+    #[test]
+    fn verbs_and_untargeted_elements() {
+        let text = r##"<div hx-target="#list">
+  <button hx-get="/a"></button>
+</div>
+<form hx-post="/b"><input data-hx-get="/c" hx-target="this"></form>
+<button hx-delete="/d" hx-swap="none"></button>"##;
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_html::language()).unwrap();
+        let tree = parser.parse(text, None).unwrap();
+        let stats = verb_stats(tree.root_node(), text);
+        assert_eq!(stats.counts, [2, 1, 0, 0, 1]);
+        assert_eq!(
+            stats.untargeted,
+            vec![Untargeted {
+                verb: "hx-post",
+                point: Point::new(3, 0)
+            }]
+        );
+        let lenses = verb_lenses(&stats);
+        let titles: Vec<&str> = lenses
+            .iter()
+            .filter_map(|lens| Some(lens.command.as_ref()?.title.as_str()))
+            .collect();
+        assert_eq!(
+            titles,
+            [
+                "hx-get: 2 | hx-post: 1 | hx-delete: 1",
+                "Warning: hx-post swaps into this element, there is no hx-target"
+            ]
+        );
+    }
+}
@@ -0,0 +1,117 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+use serde::Serialize;
+
+use crate::{
+    position::Position,
+    state::{Recover, State},
+};
+
+/// Number of cached completion positions.
+pub const CACHE_CAPACITY: usize = 32;
+
+/// Document revision and cursor position.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheKey {
+    pub uri: String,
+    pub revision: u64,
+    pub line: u32,
+    pub column: u32,
+}
+
+/// Small LRU cache for completion `Position`. Clients retrigger completion
+/// on same line many times, tree is queried only once per document revision.
+#[derive(Default)]
+pub struct CompletionCache {
+    entries: Mutex<VecDeque<(CacheKey, Option<Position>)>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub hit_rate: f64,
+}
+
+impl CompletionCache {
+    /// Cached position, or result of `query` that is saved for later.
+    pub fn get_or_query(
+        &self,
+        key: CacheKey,
+        query: impl FnOnce() -> Option<Position>,
+    ) -> Option<Position> {
+        {
+            let mut entries = self.entries.recover(State::Cache);
+            if let Some(index) = entries.iter().position(|(cached, _)| cached == &key) {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                let entry = entries.remove(index)?;
+                let position = entry.1.clone();
+                entries.push_front(entry);
+                return position;
+            }
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let position = query();
+        let mut entries = self.entries.recover(State::Cache);
+        entries.push_front((key, position.clone()));
+        entries.truncate(CACHE_CAPACITY);
+        position
+    }
+
+    pub fn clear(&self) {
+        self.entries.recover(State::Cache).clear();
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let hits = self.hits.load(Ordering::Relaxed);
+        let misses = self.misses.load(Ordering::Relaxed);
+        let hit_rate = match hits + misses {
+            0 => 0.0,
+            total => hits as f64 / total as f64,
+        };
+        CacheStats {
+            hits,
+            misses,
+            hit_rate,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CacheKey, CompletionCache, CACHE_CAPACITY};
+    use crate::position::Position;
+
+    fn key(revision: u64, column: u32) -> CacheKey {
+        CacheKey {
+            uri: String::from("file:///index.html"),
+            revision,
+            line: 0,
+            column,
+        }
+    }
+
+    #[test]
+    fn positions_are_cached_per_revision() {
+        let cache = CompletionCache::default();
+        let name = || Some(Position::AttributeName(String::from("hx-get")));
+        assert_eq!(cache.get_or_query(key(1, 5), name), name());
+        assert_eq!(cache.get_or_query(key(1, 5), || None), name());
+        assert_eq!(cache.get_or_query(key(2, 5), || None), None);
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses), (1, 2));
+
+        for column in 0..CACHE_CAPACITY as u32 {
+            cache.get_or_query(key(3, column), || None);
+        }
+        assert_eq!(cache.get_or_query(key(1, 5), || None), None);
+    }
+}
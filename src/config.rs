@@ -1,22 +1,43 @@
 use dashmap::DashMap;
 use ropey::Rope;
+use schemars::{schema_for, JsonSchema};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::{
     fs::read_to_string,
-    path::Path,
+    path::{Path, PathBuf},
     sync::{Arc, Mutex, MutexGuard, RwLock},
 };
 
+use tree_sitter::Query;
+
 use crate::{
+    grammar::load_language,
     htmx_tags::Tag,
     htmx_tree_sitter::LspFiles,
+    indexing::IndexToken,
     init_hx::{LangType, LangTypes},
     query_helper::Queries,
+    state::{Recover, RecoverRw, State},
+    stylesheet::STYLESHEET_EXTS,
+    to_input_edit::strip_bom,
+    uri::{path_to_uri, NormalizedPath},
 };
 
+/// Supported backend languages.
+pub const BACKEND_LANGS: [&str; 3] = ["rust", "python", "go"];
+/// JavaScript/TypeScript file extensions, all are indexed as JavaScript. `TS_EXTS`
+/// have TypeScript grammars, others are parsed with JavaScript grammar.
+pub const JS_EXTS: [&str; 4] = ["js", "ts", "jsx", "tsx"];
+/// TypeScript file extensions, `tsx` has its own grammar (TypeScript with JSX).
+pub const TS_EXTS: [&str; 2] = ["ts", "tsx"];
+/// Default `max_file_size`, larger files are usually generated or bundled.
+pub const MAX_FILE_SIZE: u64 = 1024 * 1024;
+/// Default `slow_request_ms`.
+pub const SLOW_REQUEST_MS: u64 = 500;
+
 /// Help language server by providing additional info about your htmx project.
-#[derive(Serialize, Deserialize, Debug, Default)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone, JsonSchema)]
 pub struct HtmxConfig {
     /// Backend language for htmx project.
     pub lang: String,
@@ -36,23 +57,246 @@ pub struct HtmxConfig {
     pub js_tags: Vec<String>,
     /// List of directories for selected backend language, it must contain relative paths.
     /// ```json
-    /// { "backend_tags": ["./src"] }
+    /// { "backend_tags": ["./src", { "path": "./svc-go", "lang": "go" }] }
+    /// ````
+    /// Language server searches for proper backend file extension. Directory with
+    /// own `lang` is searched for file extension of that language.
+    pub backend_tags: Vec<BackendDir>,
+    /// Hover for standard HTML attributes (`class`, `aria-label`...) in templates.
+    /// Useful when no other HTML language server is attached.
+    #[serde(default)]
+    pub html_hover: bool,
+    /// Warn when templates use htmx attributes, but htmx is not included with
+    /// `<script>` tag or as `package.json` dependency. Keep it off if htmx is
+    /// injected by server.
+    #[serde(default)]
+    pub htmx_script_check: bool,
+    /// JSON file with custom attributes and values, they extend built-in ones.
+    /// It's reloaded after save.
+    #[serde(default)]
+    pub extra_attributes: Option<String>,
+    /// Locale of attribute documentation (`de`), translated documentation falls back
+    /// to English. Client locale is used when it's not set.
+    #[serde(default)]
+    pub docs_locale: Option<String>,
+    /// Directory with translated documentation (`<locale>.json`, same format as
+    /// `extra_attributes`), it's preferred over bundled translations.
+    #[serde(default)]
+    pub docs_dir: Option<String>,
+    /// Same tag can be defined once in backend and once in javascript.
+    /// Goto definition returns both locations.
+    #[serde(default)]
+    pub multiple_definitions: bool,
+    /// Hint for tags that are never used in templates.
+    #[serde(default)]
+    pub unused_tags: bool,
+    /// Warn when `hx-swap-oob` element id doesn't exist in other templates.
+    #[serde(default)]
+    pub oob_check: bool,
+    /// Directories (or files) with CSS/SCSS stylesheets, relative paths. Classes in
+    /// `hx-indicator` selectors and on `htmx-indicator` elements must be defined
+    /// in them, and class names are completed in selector values.
+    /// ```json
+    /// { "css": ["./static/css"] }
+    /// ````
+    #[serde(default)]
+    pub css: Vec<String>,
+    /// Ids from all indexed templates are completed after `#` in selector values,
+    /// not only ids from current document.
+    #[serde(default)]
+    pub project_ids: bool,
+    /// Backend file (relative path) where function stub with `hx@` tag is
+    /// appended after "Extract htmx fragment" code action.
+    #[serde(default)]
+    pub backend_stub: Option<String>,
+    /// Where "Create tag" quick fix adds `hx@` comment for unknown tag.
+    #[serde(default)]
+    pub tag_stub: TagStub,
+    /// Collect completion latency and parse times for `htmx/metrics` request.
+    #[serde(default)]
+    pub metrics: bool,
+    /// Backend grammars loaded from shared libraries, `lang` selects one by name.
+    /// ```json
+    /// { "lang": "kotlin", "custom_languages": [{ "name": "kotlin", "lib": "./libtree-sitter-kotlin.so", "ext": "kt", "tag_query": "(line_comment) @hx_comment" }] }
+    /// ````
+    #[serde(default)]
+    pub custom_languages: Vec<CustomLanguage>,
+    /// Code lens on first line of template with number of every htmx verb, and warning
+    /// lens on elements with verb, but without own or inherited `hx-target`.
+    #[serde(default)]
+    pub code_lens: bool,
+    /// Files larger than this (in bytes) are not indexed, open documents that are
+    /// larger have only features that don't need syntax tree. Default is 1 MiB, 0 is
+    /// no limit.
+    #[serde(default)]
+    pub max_file_size: Option<u64>,
+    /// Requests that take longer (in milliseconds) are logged as warning with
+    /// method, document and position. Default is 500, 0 disables warning.
+    #[serde(default)]
+    pub slow_request_ms: Option<u64>,
+    /// Workspace is not walked at startup, but on first request that needs index
+    /// (definition, references, implementation).
+    #[serde(default)]
+    pub lazy_index: bool,
+    /// Attribute completion and hover only, other fields are not needed.
+    /// ```json
+    /// { "template_only": true }
     /// ````
-    /// Language server searches for proper backend file extension.
-    pub backend_tags: Vec<String>,
+    /// Same mode is used when initialization options are missing.
+    #[serde(default)]
+    pub template_only: bool,
     #[serde(skip)]
     /// This field is not serializable/deserializable.
     /// Every LSP request supported by HtmxBackend first checks if config is valid
     /// (hover and completion works without checks).
     pub is_valid: bool,
+    #[serde(skip)]
+    /// Workspace root from initialization request, current directory is used
+    /// without it.
+    pub root: Option<PathBuf>,
+}
+
+/// Backend language that is not bundled with language server.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct CustomLanguage {
+    /// Language name, library must export `tree_sitter_<name>` function.
+    pub name: String,
+    /// Path to compiled tree-sitter grammar(`.so`/`.dylib`).
+    pub lib: String,
+    /// File extension for backend files.
+    pub ext: String,
+    /// Query for tag comments, comment must be captured as `@hx_comment`.
+    /// Function or class of tag can be captured as `@hx_item`.
+    pub tag_query: String,
+}
+
+/// Directory with backend files, its language can be different from `lang`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(untagged)]
+pub enum BackendDir {
+    Path(String),
+    Lang {
+        path: String,
+        /// One of bundled backend languages (`rust`, `python`, `go`).
+        lang: String,
+    },
+}
+
+impl BackendDir {
+    pub fn path(&self) -> &str {
+        match self {
+            BackendDir::Path(path) | BackendDir::Lang { path, .. } => path,
+        }
+    }
+
+    /// Language of directory, `None` if it's same as `lang` from config.
+    pub fn lang(&self) -> Option<&str> {
+        match self {
+            BackendDir::Path(_) => None,
+            BackendDir::Lang { lang, .. } => Some(lang),
+        }
+    }
+}
+
+impl From<String> for BackendDir {
+    fn from(path: String) -> Self {
+        BackendDir::Path(path)
+    }
+}
+
+/// Location of `hx@` comment added by "Create tag" quick fix.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TagStub {
+    /// Top of `backend_stub` file, file is created if it doesn't exist.
+    #[default]
+    Top,
+    /// Below handler of nearest tag in same template, `backend_stub` is fallback.
+    Handler,
+}
+
+/// Language server features, some of them need full config.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feature {
+    Completion,
+    Hover,
+    Definition,
+    References,
+    Implementation,
+    CodeActions,
+    Diagnostics,
+}
+
+impl Feature {
+    pub const ALL: [Feature; 7] = [
+        Feature::Completion,
+        Feature::Hover,
+        Feature::Definition,
+        Feature::References,
+        Feature::Implementation,
+        Feature::CodeActions,
+        Feature::Diagnostics,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Feature::Completion => "completion",
+            Feature::Hover => "hover",
+            Feature::Definition => "definition",
+            Feature::References => "references",
+            Feature::Implementation => "implementation",
+            Feature::CodeActions => "code actions",
+            Feature::Diagnostics => "diagnostics",
+        }
+    }
 }
 
 impl HtmxConfig {
+    /// Config used when client doesn't send initialization options.
+    pub fn template_only() -> Self {
+        Self {
+            template_only: true,
+            ..Default::default()
+        }
+    }
+
+    /// Completion and hover always work, everything else needs indexed project.
+    pub fn enabled(&self, feature: Feature) -> bool {
+        match feature {
+            Feature::Completion | Feature::Hover => true,
+            _ => self.is_valid && !self.template_only,
+        }
+    }
+
+    /// Log line with enabled and disabled features.
+    pub fn features_message(&self) -> String {
+        let (enabled, disabled): (Vec<_>, Vec<_>) = Feature::ALL
+            .iter()
+            .partition(|feature| self.enabled(**feature));
+        let names = |features: Vec<&Feature>| {
+            features
+                .iter()
+                .map(|feature| feature.name())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let mut msg = format!("enabled: {}", names(enabled));
+        if !disabled.is_empty() {
+            let reason = match self.template_only {
+                true => "template-only mode",
+                false => "config not found",
+            };
+            msg.push_str(&format!("; disabled ({reason}): {}", names(disabled)));
+        }
+        msg
+    }
+
     /// Check if passed file extension is in client config.
     pub fn file_ext(&self, path: &Path) -> Option<LangTypes> {
         match path.extension()?.to_str() {
             Some(e) => match e {
-                "js" | "ts" => Some(LangTypes::One(LangType::JavaScript)),
+                e if JS_EXTS.contains(&e) => Some(LangTypes::One(LangType::JavaScript)),
                 other => {
                     if self.is_backend(other) {
                         match self.template_ext == other {
@@ -69,136 +313,764 @@ impl HtmxConfig {
             None => None,
         }
     }
+    /// Maximum size of indexed file in bytes.
+    pub fn file_size_limit(&self) -> u64 {
+        match self.max_file_size {
+            Some(0) => u64::MAX,
+            Some(size) => size,
+            None => MAX_FILE_SIZE,
+        }
+    }
+
+    /// Threshold for slow request warning, in milliseconds.
+    pub fn slow_request_ms(&self) -> u64 {
+        self.slow_request_ms.unwrap_or(SLOW_REQUEST_MS)
+    }
+
+    /// Languages that handle requests for document at `path`, in order they are tried.
+    /// Template part of single-file component comes first. Every document is template
+    /// when there is no valid config, files without extension are templates too.
+    pub fn routes(&self, path: &Path) -> Vec<LangType> {
+        if !self.is_valid || path.extension().is_none() {
+            return vec![LangType::Template];
+        }
+        let mut langs = self
+            .file_ext(path)
+            .map(|lang_types| lang_types.langs())
+            .unwrap_or_default();
+        langs.sort_by_key(|lang_type| *lang_type != LangType::Template);
+        langs
+    }
+
     /// Checks if passed file extension is supported backend.
     pub fn is_backend(&self, ext: &str) -> bool {
+        self.backend_exts().contains(&ext)
+    }
+
+    /// File extension of selected backend language.
+    pub fn backend_ext(&self) -> Option<&str> {
+        lang_ext(&self.lang).or_else(|| self.custom_language().map(|custom| custom.ext.as_str()))
+    }
+
+    /// File extensions of selected backend language and of directories with own `lang`.
+    pub fn backend_exts(&self) -> Vec<&str> {
+        let mut exts: Vec<&str> = self.backend_ext().into_iter().collect();
+        for (_, ext) in self.backend_langs() {
+            if !exts.contains(&ext) {
+                exts.push(ext);
+            }
+        }
+        exts
+    }
+
+    /// Languages (with file extension) of `backend_tags` directories, that are not
+    /// selected with `lang`. Unsupported languages are skipped.
+    pub fn backend_langs(&self) -> Vec<(&str, &'static str)> {
+        let mut langs = vec![];
+        for lang in self.backend_tags.iter().filter_map(BackendDir::lang) {
+            let Some(ext) = lang_ext(lang) else {
+                continue;
+            };
+            if lang != self.lang && !langs.contains(&(lang, ext)) {
+                langs.push((lang, ext));
+            }
+        }
+        langs
+    }
+
+    /// Extension of backend files in `dir`.
+    fn dir_ext(&self, dir: &BackendDir) -> Option<&str> {
+        match dir.lang() {
+            Some(lang) => lang_ext(lang),
+            None => self.backend_ext(),
+        }
+    }
+
+    /// Custom language selected with `lang`.
+    pub fn custom_language(&self) -> Option<&CustomLanguage> {
+        self.custom_languages
+            .iter()
+            .find(|custom| custom.name == self.lang)
+    }
+
+    /// Path from config, relative path is resolved from workspace root.
+    pub fn resolve(&self, path: &str) -> PathBuf {
+        match &self.root {
+            Some(root) => root.join(path),
+            None => PathBuf::from(path),
+        }
+    }
+
+    /// Function stub for selected backend language, it contains `hx@name` tag.
+    pub fn backend_stub_fn(&self, name: &str) -> Option<String> {
         match self.lang.as_str() {
-            "rust" => ext == "rs",
-            "python" => ext == "py",
-            "go" => ext == "go",
-            _ => false,
+            "rust" => Some(format!("\nfn {name}() {{\n    // hx@{name}\n}}\n")),
+            "python" => Some(format!("\n\ndef {name}():\n    # hx@{name}\n    pass\n")),
+            "go" => Some(format!("\nfunc {name}() {{\n\t// hx@{name}\n}}\n")),
+            _ => None,
+        }
+    }
+
+    /// `hx@name` tag comment in backend language of file at `path`, language of
+    /// directory with own `lang` is found by file extension.
+    pub fn tag_comment(&self, name: &str, path: &Path) -> String {
+        let ext = path.extension().and_then(|ext| ext.to_str());
+        let lang = self
+            .backend_langs()
+            .into_iter()
+            .find(|(_, lang_ext)| Some(*lang_ext) == ext)
+            .map_or(self.lang.as_str(), |(lang, _)| lang);
+        match lang {
+            "python" => format!("# hx@{name}"),
+            _ => format!("// hx@{name}"),
         }
     }
 
     pub fn is_supported_backend(&self) -> bool {
-        matches!(self.lang.as_str(), "python" | "rust" | "go")
+        BACKEND_LANGS.contains(&self.lang.as_str()) || self.custom_language().is_some()
+    }
+
+    /// Language of `backend_tags` directory that is not bundled.
+    pub fn unsupported_dir_lang(&self) -> Option<&str> {
+        self.backend_tags
+            .iter()
+            .filter_map(BackendDir::lang)
+            .find(|lang| !BACKEND_LANGS.contains(lang))
+    }
+}
+
+/// File extension of bundled backend language.
+pub fn lang_ext(lang: &str) -> Option<&'static str> {
+    match lang {
+        "rust" => Some("rs"),
+        "python" => Some("py"),
+        "go" => Some("go"),
+        _ => None,
     }
 }
 
-/// Quickly check config on initialization request.
+/// JSON Schema for `HtmxConfig`. Editor frontends can use it to validate
+/// and autocomplete initialization options.
+pub fn config_schema() -> Value {
+    serde_json::to_value(schema_for!(HtmxConfig)).unwrap_or_default()
+}
+
+/// Quickly check config on initialization request. Missing options or
+/// `{"template_only": true}` give template-only config.
 pub fn validate_config(config: Option<Value>) -> Option<HtmxConfig> {
-    if let Some(config) = config {
-        if let Ok(mut config) = serde_json::from_value::<HtmxConfig>(config) {
-            config.is_valid = true;
-            return Some(config);
-        }
+    let Some(config) = config.filter(|config| !config.is_null()) else {
+        return Some(HtmxConfig::template_only());
+    };
+    if config.get("template_only") == Some(&Value::Bool(true)) {
+        return Some(HtmxConfig::template_only());
+    }
+    if let Ok(mut config) = serde_json::from_value::<HtmxConfig>(config) {
+        config.is_valid = true;
+        return Some(config);
     }
     None
 }
 
 /// Read config. Language server can be used even if config
 /// haven't passed all checks
-pub fn read_config(
+///
+/// Documents in `open` are opened by client, their text in `document_map` is
+/// newer than file on disk and it's indexed instead.
+pub async fn read_config(
     config: &RwLock<HtmxConfig>,
     lsp_files: &Arc<Mutex<LspFiles>>,
     queries: &Arc<Mutex<Queries>>,
     document_map: &DashMap<String, Rope>,
+    open: &DashMap<String, i32>,
+    token: &IndexToken,
 ) -> anyhow::Result<Vec<Tag>> {
-    if let Ok(config) = config.read() {
-        if config.template_ext.is_empty() || config.template_ext.contains(' ') {
-            return Err(anyhow::Error::msg("Template extension not found."));
-        } else if !config.is_supported_backend() {
-            return Err(anyhow::Error::msg(format!(
-                "Language {} is not supported.",
-                config.lang
-            )));
+    let config = config.recover_read(State::Config).clone();
+    if config.template_only {
+        return Ok(vec![]);
+    }
+    if config.template_ext.is_empty() || config.template_ext.contains(' ') {
+        return Err(anyhow::Error::msg("Template extension not found."));
+    } else if !config.is_supported_backend() {
+        return Err(anyhow::Error::msg(format!(
+            "Language {} is not supported.",
+            config.lang
+        )));
+    } else if let Some(lang) = config.unsupported_dir_lang() {
+        return Err(anyhow::Error::msg(format!(
+            "Language {lang} of backend_tags directory is not supported."
+        )));
+    }
+    walkdir(config, lsp_files, queries, document_map, open, token).await
+}
+
+/// Opt-in project check, returns warning if htmx is used but never loaded.
+pub fn check_htmx_script(
+    config: &RwLock<HtmxConfig>,
+    lsp_files: &Arc<Mutex<LspFiles>>,
+    queries: &Arc<Mutex<Queries>>,
+    document_map: &DashMap<String, Rope>,
+) -> Option<String> {
+    let root = {
+        let config = config.recover_read(State::Config);
+        if !config.htmx_script_check {
+            return None;
         }
-        walkdir(&config, lsp_files, queries, document_map)
-    } else {
-        Err(anyhow::Error::msg("Config is not found"))
+        config.root.clone().unwrap_or_default()
+    };
+    let (used, loaded) = {
+        let lsp_files = lsp_files.recover(State::Index);
+        let queries = queries.recover(State::Queries);
+        lsp_files.htmx_usage(document_map, &queries.html)
+    };
+    if !used || loaded {
+        return None;
+    }
+    let dependency = read_to_string(root.join("package.json"))
+        .is_ok_and(|package| package.contains("\"htmx.org\""));
+    if dependency {
+        return None;
     }
+    Some(String::from(
+        "Templates use htmx attributes, but htmx is not loaded. Add <script src=\"https://unpkg.com/htmx.org\"></script> or htmx.org dependency.",
+    ))
 }
 
+/// Number of files that are read at the same time during indexing.
+const READ_CONCURRENCY: usize = 16;
+
 /// Walk through all directories and files. In this process it catches all
 /// duplicated tag errors.
-fn walkdir(
-    config: &HtmxConfig,
+///
+/// Files are read in batches with `tokio::fs`, `lsp_files` is locked only while
+/// one file is added, so requests for open documents are answered during indexing.
+/// Walk stops between batches with `IndexCanceled` when newer run is started.
+async fn walkdir(
+    config: HtmxConfig,
     lsp_files: &Arc<Mutex<LspFiles>>,
     queries: &Arc<Mutex<Queries>>,
     document_map: &DashMap<String, Rope>,
+    open: &DashMap<String, i32>,
+    token: &IndexToken,
 ) -> anyhow::Result<Vec<Tag>> {
-    let lsp_files = lsp_files.lock().unwrap();
+    token.check()?;
     let mut diagnostics = vec![];
-    lsp_files.reset();
-    let directories = [&config.templates, &config.js_tags, &config.backend_tags];
-    queries
-        .lock()
-        .ok()
-        .and_then(|mut queries| queries.change_backend(&config.lang));
+    let custom = match config.custom_language() {
+        Some(custom) => {
+            let language = load_language(Path::new(&custom.lib), &custom.name)?;
+            let query = Query::new(language, &custom.tag_query).map_err(|err| {
+                anyhow::Error::msg(format!("Invalid tag_query for {}: {err}", custom.name))
+            })?;
+            Some((language, query))
+        }
+        None => None,
+    };
+    {
+        let lsp_files = lsp_files.recover(State::Index);
+        lsp_files.reset();
+        let limit = usize::try_from(config.file_size_limit()).unwrap_or(usize::MAX);
+        lsp_files.set_max_file_size(limit);
+        lsp_files.parsers.set_backends(&config.backend_langs());
+        match &custom {
+            Some((language, _)) => lsp_files.parsers.set_backend(*language),
+            None => {
+                lsp_files
+                    .parsers
+                    .change_backend(&config.lang, LangType::Backend);
+            }
+        }
+    }
+    {
+        let mut queries = queries.recover(State::Queries);
+        queries.set_backends(&config.backend_langs());
+        match custom {
+            Some((_, query)) => queries.backend = query,
+            None => {
+                queries.change_backend(&config.lang);
+            }
+        }
+    }
+    let multiple_definitions = config.multiple_definitions;
+    let (files, stylesheets) = tokio::task::spawn_blocking(move || {
+        collect_files(&config).map(|files| (files, collect_stylesheets(&config)))
+    })
+    .await??;
+    token.set_total(files.len());
+    for path in stylesheets {
+        token.check()?;
+        if let Some((uri, content)) = read_file(&path).await {
+            lsp_files
+                .recover(State::Index)
+                .add_stylesheet(uri, &content);
+        }
+    }
+    for chunk in files.chunks(READ_CONCURRENCY) {
+        token.check()?;
+        let mut reads = tokio::task::JoinSet::new();
+        for (i, (path, _)) in chunk.iter().enumerate() {
+            let path = path.clone();
+            reads.spawn(async move { (i, read_file(&path).await) });
+        }
+        let mut contents = vec![None; chunk.len()];
+        while let Some(read) = reads.join_next().await {
+            let (i, content) = read?;
+            contents[i] = content;
+        }
+        // Files are added in walk order, so duplicated tag errors are stable.
+        for ((path, lang_types), content) in chunk.iter().zip(contents) {
+            let Some(content) = content else {
+                return Err(anyhow::Error::msg(format!(
+                    "Template path: {} does not exist",
+                    path.display()
+                )));
+            };
+            let lsp_files = lsp_files.recover(State::Index);
+            let queries = queries.recover(State::Queries);
+            let (uri, content) = content;
+            let content = match open.contains_key(&uri) {
+                true => document_map.get(&uri).map(|rope| rope.to_string()),
+                false => None,
+            }
+            .unwrap_or(content);
+            add_file(
+                (uri, content),
+                &lsp_files,
+                lang_types,
+                &queries,
+                &mut diagnostics,
+                multiple_definitions,
+                document_map,
+            );
+            token.file_indexed();
+        }
+    }
+    Ok(diagnostics)
+}
+
+/// All files from configured directories, with languages that are indexed for them.
+fn collect_files(config: &HtmxConfig) -> anyhow::Result<Vec<(PathBuf, LangTypes)>> {
+    let mut files = vec![];
+    let backend_tags: Vec<String> = config
+        .backend_tags
+        .iter()
+        .map(|dir| dir.path().to_string())
+        .collect();
+    let directories = [&config.templates, &config.js_tags, &backend_tags];
     for (index, dir) in directories.iter().enumerate() {
         let lang_type = LangType::from(index);
-        lsp_files
-            .parsers
-            .lock()
-            .ok()
-            .and_then(|mut parsers| parsers.change_backend(&config.lang, lang_type));
-        for file in dir.iter() {
-            for entry in walkdir::WalkDir::new(file) {
+        for (i, file) in dir.iter().enumerate() {
+            // Backend directory has files of its own language only.
+            let backend_ext = match lang_type {
+                LangType::Backend => config.dir_ext(&config.backend_tags[i]),
+                _ => None,
+            };
+            // Sorted walk, so first definition of duplicated tag doesn't depend on file system.
+            let walk = walkdir::WalkDir::new(NormalizedPath::new(file).to_path_buf());
+            for entry in walk.sort_by_file_name() {
                 let entry = entry?;
                 let metadata = entry.metadata()?;
-                if metadata.is_file() {
-                    let path = &entry.path();
-                    let ext = config.file_ext(path);
-                    if !ext.is_some_and(|ext| ext.is_lang(lang_type)) {
-                        continue;
-                    }
-                    if queries
-                        .lock()
-                        .ok()
-                        .and_then(|queries| {
-                            add_file(
-                                path,
-                                &lsp_files,
-                                lang_type,
-                                &queries,
-                                &mut diagnostics,
-                                false,
-                                document_map,
-                            )
-                        })
-                        .is_none()
-                    {
-                        return Err(anyhow::Error::msg(format!(
-                            "Template path: {} does not exist",
-                            file
-                        )));
-                    };
+                if !metadata.is_file() || too_large(config, entry.path(), metadata.len()) {
+                    continue;
+                }
+                let Some(lang_types) = config.file_ext(entry.path()) else {
+                    continue;
+                };
+                let ext = entry.path().extension().and_then(|ext| ext.to_str());
+                if lang_type == LangType::Backend && backend_ext != ext {
+                    continue;
+                }
+                if lang_types.is_lang(lang_type) {
+                    files.push((entry.into_path(), lang_types));
                 }
             }
         }
     }
-    Ok(diagnostics)
+    Ok(files)
 }
 
-/// Get path, read contents of file, parse TreeSitter tree and check for tags.
+/// Stylesheets from `css` directories. Stylesheets are optional, so missing
+/// directories are skipped.
+fn collect_stylesheets(config: &HtmxConfig) -> Vec<PathBuf> {
+    config
+        .css
+        .iter()
+        .flat_map(|dir| {
+            walkdir::WalkDir::new(NormalizedPath::new(dir).to_path_buf()).sort_by_file_name()
+        })
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .filter(|entry| {
+            entry
+                .path()
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| STYLESHEET_EXTS.contains(&ext))
+        })
+        .filter(|entry| {
+            let size = entry.metadata().map_or(0, |metadata| metadata.len());
+            !too_large(config, entry.path(), size)
+        })
+        .map(|entry| entry.into_path())
+        .collect()
+}
+
+/// Files over `max_file_size` are skipped, with notice in log.
+fn too_large(config: &HtmxConfig, path: &Path, size: u64) -> bool {
+    let limit = config.file_size_limit();
+    if size <= limit {
+        return false;
+    }
+    log::warn!(
+        "{} has {size} bytes, more than max_file_size ({limit}), it's not indexed.",
+        path.display()
+    );
+    true
+}
+
+/// Uri and content of file.
+async fn read_file(path: &Path) -> Option<(String, String)> {
+    let name = tokio::fs::canonicalize(path).await.ok()?;
+    let uri = path_to_uri(&name)?;
+    let content = tokio::fs::read_to_string(name).await.ok()?;
+    Some((uri, strip_bom(&content).to_string()))
+}
+
+/// Parse TreeSitter tree for uri and content of file, and check for tags.
+/// Files with two languages are parsed with both grammars.
 fn add_file(
-    path: &&Path,
+    (uri, content): (String, String),
     lsp_files: &MutexGuard<LspFiles>,
-    lang_type: LangType,
+    lang_types: &LangTypes,
     queries: &Queries,
     diags: &mut Vec<Tag>,
-    _skip: bool,
+    multiple_definitions: bool,
     document_map: &DashMap<String, Rope>,
-) -> Option<bool> {
-    if let Ok(name) = std::fs::canonicalize(path) {
-        let name = name.to_str()?;
-        let file = lsp_files.add_file(format!("file://{}", name))?;
-        return read_to_string(name).ok().map(|content| {
-            let rope = ropey::Rope::from_str(&content);
-            document_map.insert(format!("file://{}", name).to_string(), rope);
-            lsp_files.add_tree(file, lang_type, &content, None);
-            let _ = lsp_files.add_tags_from_file(file, lang_type, &content, false, queries, diags);
-            true
+) -> Option<()> {
+    let file = lsp_files.add_file(uri.clone())?;
+    document_map.insert(uri, Rope::from_str(&content));
+    for lang_type in lang_types.langs() {
+        lsp_files.add_tree(file, lang_type, &content, None);
+        if lang_type == LangType::Template {
+            lsp_files.index_template(file, &content, &queries.html);
+        }
+    }
+    lsp_files.add_tags_from_langs(
+        file,
+        lang_types,
+        &content,
+        multiple_definitions,
+        queries,
+        diags,
+    );
+    Some(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex, RwLock};
+
+    use dashmap::DashMap;
+    use tower_lsp::lsp_types::{Position, TextDocumentIdentifier, TextDocumentPositionParams, Url};
+
+    use super::{
+        check_htmx_script, config_schema, read_config, validate_config, Feature, HtmxConfig,
+    };
+    use crate::{
+        htmx_tree_sitter::LspFiles,
+        indexing::{IndexCanceled, IndexCoordinator},
+        init_hx::{LangType, LangTypes},
+        position::{get_position_from_lsp_completion, Position as CompletionPosition, QueryType},
+        query_helper::Queries,
+        test_dir::TestDir,
+        uri::path_to_uri,
+    };
+
+    #[test]
+    fn schema_contains_config_fields() {
+        let schema = config_schema();
+        let properties = schema.get("properties").expect("schema has properties");
+        for field in [
+            "lang",
+            "template_ext",
+            "templates",
+            "js_tags",
+            "backend_tags",
+        ] {
+            assert!(properties.get(field).is_some(), "missing {field}");
+        }
+        assert!(properties.get("is_valid").is_none());
+    }
+
+    #[test]
+    fn template_only_features() {
+        let config = validate_config(None).unwrap();
+        assert!(config.template_only);
+        let config = validate_config(Some(serde_json::json!({"template_only": true}))).unwrap();
+        assert!(config.enabled(Feature::Completion));
+        assert!(config.enabled(Feature::Hover));
+        assert!(!config.enabled(Feature::Definition));
+        assert!(!config.enabled(Feature::Diagnostics));
+        assert!(config
+            .features_message()
+            .ends_with("disabled (template-only mode): definition, references, implementation, code actions, diagnostics"));
+
+        let config = validate_config(Some(serde_json::json!({
+            "lang": "rust",
+            "template_ext": "html",
+            "templates": [],
+            "js_tags": [],
+            "backend_tags": []
+        })))
+        .unwrap();
+        assert!(Feature::ALL.iter().all(|feature| config.enabled(*feature)));
+        assert!(validate_config(Some(serde_json::json!({"lang": 1}))).is_none());
+    }
+
+    #[test]
+    fn custom_language_backend() {
+        let config: HtmxConfig = serde_json::from_value(serde_json::json!({
+            "lang": "kotlin",
+            "template_ext": "html",
+            "templates": [],
+            "js_tags": [],
+            "backend_tags": [],
+            "custom_languages": [{
+                "name": "kotlin",
+                "lib": "./libtree-sitter-kotlin.so",
+                "ext": "kt",
+                "tag_query": "(line_comment) @hx_comment"
+            }]
+        }))
+        .unwrap();
+        assert!(config.is_supported_backend());
+        assert!(config.is_backend("kt"));
+        assert!(!config.is_backend("rs"));
+        assert!(matches!(
+            config.file_ext(std::path::Path::new("/src/Main.kt")),
+            Some(LangTypes::One(LangType::Backend))
+        ));
+    }
+
+    #[tokio::test]
+    async fn indexes_project_directories() {
+        let root = TestDir::new();
+        for i in 0..20 {
+            let template = format!("<div hx-get=\"/{i}\" hx-lsp=\"tag{i}\"></div>");
+            root.write(format!("templates/{i}.html"), template);
+        }
+        let backend = "fn a() {\n    // hx@tag1\n}\nfn b() {\n    // hx@tag1\n}\n";
+        root.write("src/main.rs", backend);
+        // Generated file over size limit is skipped.
+        let bundle = "<div hx-lsp=\"bundle\"></div>".repeat(100);
+        root.write("templates/bundle.html", bundle);
+
+        let config = RwLock::new(HtmxConfig {
+            lang: String::from("rust"),
+            template_ext: String::from("html"),
+            templates: vec![root.join("templates").display().to_string()],
+            backend_tags: vec![root.join("src").display().to_string().into()],
+            max_file_size: Some(1000),
+            is_valid: true,
+            ..Default::default()
         });
+        let lsp_files = Arc::new(Mutex::new(LspFiles::default()));
+        let document_map = DashMap::new();
+        let open = DashMap::new();
+        let queries = Arc::new(Mutex::new(Queries::default()));
+        let coordinator = IndexCoordinator::default();
+        let canceled = coordinator.start();
+        let token = coordinator.start();
+        let err = read_config(
+            &config,
+            &lsp_files,
+            &queries,
+            &document_map,
+            &open,
+            &canceled,
+        )
+        .await
+        .unwrap_err();
+        assert!(err.is::<IndexCanceled>());
+        assert!(document_map.is_empty());
+        let diagnostics = read_config(&config, &lsp_files, &queries, &document_map, &open, &token)
+            .await
+            .unwrap();
+        assert_eq!(document_map.len(), 21);
+        assert_eq!(diagnostics.len(), 1);
+        let status = coordinator.status();
+        assert_eq!((status.indexed, status.total), (21, 21));
+        token.finish();
+        assert!(!coordinator.status().partial);
+        assert!(lsp_files.lock().unwrap().tags.contains_key("tag1"));
+    }
+
+    #[tokio::test]
+    async fn backend_directories_with_own_language() {
+        let root = TestDir::new();
+        let files = [
+            ("src/main.rs", "fn a() {\n    // hx@rust\n}\n"),
+            // Only files of directory language are indexed.
+            (
+                "src/other.go",
+                "package main\nfunc b() {\n    // hx@skipped\n}\n",
+            ),
+            (
+                "svc-go/main.go",
+                "package main\nfunc c() {\n    // hx@go\n}\n",
+            ),
+            ("svc-py/app.py", "def d():\n    # hx@python\n    pass\n"),
+        ];
+        for (path, text) in files {
+            root.write(path, text);
+        }
+        let dir = |path: &str| root.join(path).display().to_string();
+        let config: HtmxConfig = serde_json::from_value(serde_json::json!({
+            "lang": "rust",
+            "template_ext": "html",
+            "templates": [],
+            "js_tags": [],
+            "backend_tags": [
+                dir("src"),
+                { "path": dir("svc-go"), "lang": "go" },
+                { "path": dir("svc-py"), "lang": "python" }
+            ],
+        }))
+        .unwrap();
+        assert_eq!(config.backend_exts(), ["rs", "go", "py"]);
+        assert_eq!(
+            config.tag_comment("cart", std::path::Path::new("svc-py/app.py")),
+            "# hx@cart"
+        );
+        let config = RwLock::new(HtmxConfig {
+            is_valid: true,
+            ..config
+        });
+        let lsp_files = Arc::new(Mutex::new(LspFiles::default()));
+        let document_map = DashMap::new();
+        let queries = Arc::new(Mutex::new(Queries::default()));
+        let coordinator = IndexCoordinator::default();
+        let token = coordinator.start();
+        read_config(
+            &config,
+            &lsp_files,
+            &queries,
+            &document_map,
+            &DashMap::new(),
+            &token,
+        )
+        .await
+        .unwrap();
+        let tags = |lsp_files: &LspFiles| {
+            let mut tags: Vec<String> = lsp_files.tags.iter().map(|t| t.key().clone()).collect();
+            tags.sort();
+            tags
+        };
+        let lsp_files = lsp_files.lock().unwrap();
+        assert_eq!(tags(&lsp_files), ["go", "python", "rust"]);
+
+        // Saved file in Go directory is parsed with Go grammar again.
+        let path = std::fs::canonicalize(root.join("svc-go/main.go")).unwrap();
+        let uri = path_to_uri(&path).unwrap();
+        let text = "package main\nfunc c() {\n    // hx@go_saved\n}\n";
+        document_map.insert(uri.clone(), ropey::Rope::from_str(text));
+        lsp_files.reindex_file(&uri, &mut vec![], &config, &document_map, &queries);
+        assert_eq!(tags(&lsp_files), ["go_saved", "python", "rust"]);
+    }
+
+    #[tokio::test]
+    async fn file_is_backend_and_template() {
+        let root = TestDir::new();
+        let text = "def cart():\n    # hx@cart\n    return '<div hx-swap=\"\"></div>'\n";
+        root.write("views.py", text);
+        let dir = root.display().to_string();
+        let config = RwLock::new(HtmxConfig {
+            lang: String::from("python"),
+            template_ext: String::from("py"),
+            templates: vec![dir.clone()],
+            backend_tags: vec![dir.into()],
+            is_valid: true,
+            ..Default::default()
+        });
+        assert!(matches!(
+            config
+                .read()
+                .unwrap()
+                .file_ext(std::path::Path::new("views.py")),
+            Some(LangTypes::Two { .. })
+        ));
+        let lsp_files = Arc::new(Mutex::new(LspFiles::default()));
+        let document_map = DashMap::new();
+        let queries = Arc::new(Mutex::new(Queries::default()));
+        let token = IndexCoordinator::default().start();
+        read_config(
+            &config,
+            &lsp_files,
+            &queries,
+            &document_map,
+            &DashMap::new(),
+            &token,
+        )
+        .await
+        .unwrap();
+        assert_eq!(document_map.len(), 1);
+        let uri = document_map.iter().next().unwrap().key().clone();
+        {
+            let lsp_files = lsp_files.lock().unwrap();
+            assert!(lsp_files.tags.contains_key("cart"));
+            let index = lsp_files.get_index(&uri).unwrap();
+            assert!(lsp_files.get_tree(LangType::Backend, index).is_some());
+            assert!(lsp_files.get_tree(LangType::Template, index).is_some());
+        }
+
+        // Template part of backend file has htmx completion.
+        let params = TextDocumentPositionParams::new(
+            TextDocumentIdentifier::new(Url::parse(&uri).unwrap()),
+            Position::new(2, 26),
+        );
+        let position = get_position_from_lsp_completion(
+            &params,
+            &document_map,
+            uri,
+            QueryType::Completion,
+            &lsp_files,
+            &queries,
+        );
+        assert!(matches!(
+            position,
+            Some(CompletionPosition::AttributeValue { name, .. }) if name == "hx-swap"
+        ));
+    }
+
+    #[tokio::test]
+    async fn package_json_from_workspace_root() {
+        let root = TestDir::new();
+        root.write("templates/index.html", r#"<div hx-get="/a"></div>"#);
+        let config = RwLock::new(HtmxConfig {
+            lang: String::from("rust"),
+            template_ext: String::from("html"),
+            templates: vec![root.join("templates").display().to_string()],
+            htmx_script_check: true,
+            is_valid: true,
+            root: Some(root.to_path_buf()),
+            ..Default::default()
+        });
+        let lsp_files = Arc::new(Mutex::new(LspFiles::default()));
+        let document_map = DashMap::new();
+        let queries = Arc::new(Mutex::new(Queries::default()));
+        let token = IndexCoordinator::default().start();
+        read_config(
+            &config,
+            &lsp_files,
+            &queries,
+            &document_map,
+            &DashMap::new(),
+            &token,
+        )
+        .await
+        .unwrap();
+        let check = || check_htmx_script(&config, &lsp_files, &queries, &document_map);
+        assert!(check().is_some());
+        let package = r#"{ "dependencies": { "htmx.org": "^1.9.0" } }"#;
+        root.write("package.json", package);
+        assert!(check().is_none());
     }
-    None
 }
@@ -0,0 +1,102 @@
+use std::path::Path;
+
+use serde_json::Value;
+
+/// Project config file, used when editor sends no initialization options.
+pub const CONFIG_FILE: &str = "htmx-lsp.toml";
+
+/// Read `htmx-lsp.toml` from project root. It's checked by `validate_config`,
+/// same as initialization options.
+pub fn read_config_file(root: &Path) -> Option<Value> {
+    let text = std::fs::read_to_string(root.join(CONFIG_FILE)).ok()?;
+    match toml::from_str(&text) {
+        Ok(config) => Some(config),
+        Err(err) => {
+            log::error!("{CONFIG_FILE}: {err}");
+            None
+        }
+    }
+}
+
+/// Write config as TOML, `null` fields are skipped.
+pub fn to_toml(config: &Value) -> String {
+    toml::to_string(&without_nulls(config)).unwrap_or_default()
+}
+
+/// TOML has no `null`, field without value is same as missing field.
+fn without_nulls(value: &Value) -> Value {
+    match value {
+        Value::Object(table) => table
+            .iter()
+            .filter(|(_, value)| !value.is_null())
+            .map(|(key, value)| (key.clone(), without_nulls(value)))
+            .collect(),
+        Value::Array(values) => values.iter().map(without_nulls).collect(),
+        value => value.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::to_toml;
+    use crate::config::{validate_config, BackendDir};
+
+    fn parse(text: &str) -> Value {
+        toml::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn toml_round_trip() {
+        let config = json!({
+            "lang": "python",
+            "template_ext": "html",
+            "templates": ["./app/templates", "./other \"x\" \u{1}"],
+            "js_tags": [],
+            "unused_tags": true,
+            "extra_attributes": null,
+            "metrics": {"enabled": true, "slow_ms": null},
+        });
+        let toml = to_toml(&config);
+        assert!(!toml.contains("extra_attributes") && !toml.contains("slow_ms"));
+        let mut expected = config.clone();
+        expected.as_object_mut().unwrap().remove("extra_attributes");
+        expected["metrics"] = json!({"enabled": true});
+        assert_eq!(parse(&toml), expected);
+
+        let dirs = json!({"backend_tags": [".", {"path": "./svc-go", "lang": "go"}]});
+        assert_eq!(parse(&to_toml(&dirs)), dirs);
+        assert!(toml::from_str::<Value>("lang = rust").is_err());
+    }
+
+    #[test]
+    fn toml_config_is_validated() {
+        let text = r#"
+# htmx-lsp
+lang = 'python'
+template_ext = "html"
+templates = [
+    './templates', # app
+    'C:\Users\me\templates',
+]
+js_tags = []
+backend_tags = [
+    ".",
+    { path = "./svc-go", lang = "go" },
+]
+"#;
+        let config = validate_config(Some(parse(text))).unwrap();
+        assert!(config.is_valid && !config.template_only);
+        assert_eq!(config.lang, "python");
+        assert_eq!(
+            config.templates,
+            vec!["./templates", "C:\\Users\\me\\templates"]
+        );
+        assert_eq!(config.backend_tags[0], BackendDir::from(String::from(".")));
+        assert_eq!(config.backend_tags.len(), 2);
+
+        let config = validate_config(Some(parse("template_only = true"))).unwrap();
+        assert!(config.template_only);
+    }
+}
@@ -0,0 +1,233 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use dashmap::DashMap;
+use ropey::Rope;
+use tokio::{
+    io::AsyncWriteExt,
+    net::{TcpListener, TcpStream},
+};
+use tower_lsp::{lsp_types::Diagnostic, Client, Server};
+
+use crate::{
+    htmx_tags::Tag,
+    htmx_tree_sitter::LspFiles,
+    indexing::IndexCoordinator,
+    query_helper::Queries,
+    server::{build_service, BackendHtmx},
+    state::{Recover, State},
+};
+
+/// Project index shared by every editor session connected to daemon.
+///
+/// Workspace is walked only once per config, other sessions reuse trees, tags
+/// and diagnostics from first one. Document text is shared too, trees are
+/// always parsed from it.
+#[derive(Default)]
+pub struct SharedIndex {
+    pub lsp_files: Arc<Mutex<LspFiles>>,
+    pub queries: Arc<Mutex<Queries>>,
+    pub document_map: Arc<DashMap<String, Rope>>,
+    pub versions: Arc<DashMap<String, i32>>,
+    pub revisions: Arc<DashMap<String, u64>>,
+    pub published: Arc<DashMap<String, Vec<Diagnostic>>>,
+    /// Index runs of every session, newer run cancels older one.
+    pub indexing: Arc<IndexCoordinator>,
+    /// Tag diagnostics from workspace walk, key is serialized config.
+    indexed: Mutex<HashMap<String, Vec<Tag>>>,
+    /// Connected editors with their session id, all of them receive same diagnostics.
+    clients: Mutex<Vec<(u64, Client)>>,
+    /// Id of next editor session.
+    next_session: AtomicU64,
+}
+
+impl SharedIndex {
+    /// Diagnostics from previous walk with same config.
+    pub fn indexed(&self, config: &str) -> Option<Vec<Tag>> {
+        self.indexed.recover(State::Index).get(config).cloned()
+    }
+
+    /// Save diagnostics after walk. Index now belongs to this config only.
+    pub fn set_indexed(&self, config: String, diagnostics: &[Tag]) {
+        let mut indexed = self.indexed.recover(State::Index);
+        indexed.clear();
+        indexed.insert(config, diagnostics.to_vec());
+    }
+
+    /// Id for new editor session, its client is added with it.
+    pub fn new_session(&self) -> u64 {
+        self.next_session.fetch_add(1, Ordering::Relaxed)
+    }
+
+    pub fn add_client(&self, session: u64, client: Client) {
+        self.clients.recover(State::Client).push((session, client));
+    }
+
+    /// Editor disconnected, it doesn't receive diagnostics anymore.
+    pub fn remove_client(&self, session: u64) {
+        self.clients
+            .recover(State::Client)
+            .retain(|(id, _)| *id != session);
+    }
+
+    pub fn clients(&self) -> Vec<Client> {
+        self.clients
+            .recover(State::Client)
+            .iter()
+            .map(|(_, client)| client.clone())
+            .collect()
+    }
+}
+
+/// Run daemon on `addr`. Every connection is separate LSP session with shared index.
+pub async fn listen(addr: &str) -> std::io::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    log::info!("htmx-lsp daemon listening on {}", listener.local_addr()?);
+    let index = Arc::new(SharedIndex::default());
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        log::info!("editor connected: {peer}");
+        let index = index.clone();
+        tokio::spawn(async move {
+            let (read, write) = tokio::io::split(stream);
+            let session = index.new_session();
+            let shutdown = Arc::new(AtomicBool::new(false));
+            let (service, socket) = build_service(|client| {
+                index.add_client(session, client.clone());
+                BackendHtmx::with_index(client, shutdown.clone(), index.clone())
+            });
+            Server::new(read, write, socket).serve(service).await;
+            index.remove_client(session);
+            match shutdown.load(Ordering::SeqCst) {
+                true => log::info!("editor disconnected: {peer}"),
+                false => log::warn!("editor disconnected without shutdown: {peer}"),
+            }
+        });
+    }
+}
+
+/// Forward stdio of editor to daemon on `addr`, so editor can spawn server as usual.
+pub async fn connect(addr: &str) -> std::io::Result<()> {
+    let stream = TcpStream::connect(addr).await?;
+    let (mut read, mut write) = tokio::io::split(stream);
+    let mut stdin = tokio::io::stdin();
+    let mut stdout = tokio::io::stdout();
+    let input = async {
+        tokio::io::copy(&mut stdin, &mut write).await?;
+        // Editor is gone, daemon closes session after this.
+        write.shutdown().await
+    };
+    let output = tokio::io::copy(&mut read, &mut stdout);
+    let (input, output) = tokio::join!(input, output);
+    input.and(output.map(|_| ()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{atomic::AtomicBool, Arc};
+
+    use futures::StreamExt;
+    use tower_lsp::{
+        lsp_types::{
+            InitializedParams, Position, ReferenceContext, ReferenceParams, TextDocumentIdentifier,
+            TextDocumentPositionParams, Url,
+        },
+        LanguageServer, LspService,
+    };
+
+    use crate::{
+        config::HtmxConfig,
+        server::BackendHtmx,
+        state::{RecoverRw, State},
+        test_dir::TestDir,
+    };
+
+    use super::SharedIndex;
+
+    #[test]
+    fn index_is_reused_for_same_config() {
+        let index = SharedIndex::default();
+        assert!(index.indexed("a").is_none());
+        index.set_indexed(String::from("a"), &[]);
+        assert_eq!(index.indexed("a"), Some(vec![]));
+        index.set_indexed(String::from("b"), &[]);
+        assert!(index.indexed("a").is_none());
+    }
+
+    #[tokio::test]
+    async fn disconnected_clients_are_removed() {
+        let index = SharedIndex::default();
+        let sessions = [index.new_session(), index.new_session()];
+        assert_ne!(sessions[0], sessions[1]);
+        for session in sessions {
+            let (_service, _socket) = LspService::new(|client| {
+                index.add_client(session, client.clone());
+                BackendHtmx::new(client)
+            });
+        }
+        assert_eq!(index.clients().len(), 2);
+        index.remove_client(sessions[0]);
+        assert_eq!(index.clients().len(), 1);
+        index.remove_client(sessions[1]);
+        assert!(index.clients().is_empty());
+    }
+
+    #[tokio::test]
+    async fn later_session_reuses_documents() {
+        let root = TestDir::new();
+        let template = root.write("templates/index.html", r#"<div hx-lsp="cart"></div>"#);
+        root.write("templates/list.html", r#"<ul hx-lsp="cart"></ul>"#);
+        root.write("src/main.rs", "fn cart() {\n    // hx@cart\n}\n");
+        let config = HtmxConfig {
+            lang: String::from("rust"),
+            template_ext: String::from("html"),
+            templates: vec![root.join("templates").display().to_string()],
+            backend_tags: vec![root.join("src").display().to_string().into()],
+            is_valid: true,
+            ..Default::default()
+        };
+        let index = Arc::new(SharedIndex::default());
+        let mut sessions = vec![];
+        for _ in 0..2 {
+            let (service, socket) = LspService::new(|client| {
+                BackendHtmx::with_index(client, Arc::new(AtomicBool::new(false)), index.clone())
+            });
+            tokio::spawn(socket.for_each(|_| async {}));
+            *service.inner().htmx_config.recover_write(State::Config) = config.clone();
+            // Second session finds index of first one.
+            service.inner().initialized(InitializedParams {}).await;
+            sessions.push(service);
+        }
+
+        let uri = Url::from_file_path(template).unwrap();
+        let params = ReferenceParams {
+            text_document_position: TextDocumentPositionParams::new(
+                TextDocumentIdentifier::new(uri),
+                Position::new(0, 15),
+            ),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+            context: ReferenceContext {
+                include_declaration: true,
+            },
+        };
+        let locations = sessions[1]
+            .inner()
+            .references(params)
+            .await
+            .unwrap()
+            .unwrap();
+        // Other template is never opened in second session.
+        let mut files: Vec<&str> = locations
+            .iter()
+            .filter_map(|location| location.uri.path_segments()?.next_back())
+            .collect();
+        files.sort_unstable();
+        assert_eq!(files, ["index.html", "list.html"]);
+    }
+}
@@ -0,0 +1,113 @@
+use std::{collections::HashMap, fmt::Write};
+
+use serde::{Deserialize, Serialize};
+
+use crate::init_hx::{HxCompletion, HTMX_VERSION};
+
+/// Params of `htmx/docs` request.
+#[derive(Debug, Default, Deserialize)]
+pub struct DocsParams {
+    /// Attribute (`hx-get`, `data-hx-get` or `get`), whole reference if it's missing.
+    #[serde(default)]
+    pub attribute: Option<String>,
+}
+
+/// Markdown reference that client can show as read-only document, `uri` is
+/// name of virtual document (`htmx://docs/hx-get.md`).
+#[derive(Debug, Serialize, PartialEq)]
+pub struct Docs {
+    pub uri: String,
+    pub content: String,
+}
+
+/// Reference of all attributes, or only of `attribute`. It's rendered from same
+/// attributes and values that are used for completion, extra attributes included.
+/// `None` if attribute doesn't exist.
+pub fn render_docs(
+    attributes: &[HxCompletion],
+    values: &HashMap<String, Vec<HxCompletion>>,
+    attribute: Option<&str>,
+) -> Option<Docs> {
+    let Some(attribute) = attribute else {
+        let mut content = format!("# htmx {HTMX_VERSION} attributes\n\n");
+        for item in attributes {
+            let name = format!("hx-{}", item.name);
+            let detail = item.detail.as_deref().unwrap_or("core");
+            writeln!(content, "* [{name}](htmx://docs/{name}.md) - {detail}").unwrap();
+        }
+        for item in attributes {
+            content.push('\n');
+            render_attribute(&mut content, item, values);
+        }
+        return Some(Docs {
+            uri: String::from("htmx://docs/reference.md"),
+            content,
+        });
+    };
+    let name = attribute.strip_prefix("data-").unwrap_or(attribute);
+    let name = name.strip_prefix("hx-").unwrap_or(name);
+    let item = attributes.iter().find(|item| item.name == name)?;
+    let mut content = String::new();
+    render_attribute(&mut content, item, values);
+    Some(Docs {
+        uri: format!("htmx://docs/hx-{name}.md"),
+        content,
+    })
+}
+
+fn render_attribute(
+    content: &mut String,
+    item: &HxCompletion,
+    values: &HashMap<String, Vec<HxCompletion>>,
+) {
+    let name = format!("hx-{}", item.name);
+    writeln!(content, "## {name}\n").unwrap();
+    if item.deprecated {
+        content.push_str("**Deprecated**\n\n");
+    }
+    let desc = item.desc.trim();
+    // Reference files start with bold attribute name, heading already has it.
+    let desc = desc
+        .strip_prefix(&format!("**{name}**"))
+        .unwrap_or(desc)
+        .trim_start();
+    if !desc.is_empty() {
+        writeln!(content, "{desc}\n").unwrap();
+    }
+    let Some(values) = values.get(&name).filter(|values| !values.is_empty()) else {
+        return;
+    };
+    content.push_str("### Values\n\n");
+    for value in values {
+        let desc = value.desc.trim();
+        match desc.is_empty() {
+            true => writeln!(content, "#### `{}`\n", value.name).unwrap(),
+            false => writeln!(content, "#### `{}`\n\n{desc}\n", value.name).unwrap(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::init_hx::{init_hx_tags, init_hx_values};
+
+    use super::render_docs;
+
+    #[test]
+    fn reference_from_catalogue() {
+        let attributes = init_hx_tags();
+        let values = init_hx_values();
+        let reference = render_docs(&attributes, &values, None).unwrap();
+        assert_eq!(reference.uri, "htmx://docs/reference.md");
+        for attribute in &attributes {
+            let heading = format!("\n## hx-{}\n", attribute.name);
+            assert!(reference.content.contains(&heading));
+        }
+
+        let swap = render_docs(&attributes, &values, Some("data-hx-swap")).unwrap();
+        assert_eq!(swap.uri, "htmx://docs/hx-swap.md");
+        assert!(swap.content.starts_with("## hx-swap\n\n"));
+        assert!(swap.content.contains("#### `innerHTML`"));
+        assert_eq!(render_docs(&attributes, &values, Some("hx-nope")), None);
+    }
+}
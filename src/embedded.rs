@@ -0,0 +1,151 @@
+use tree_sitter::{Node, Point};
+
+use crate::{
+    htmx_tree_sitter::Parsers,
+    init_hx::LangType,
+    position::{start_tags, tag_attributes},
+};
+
+/// Attributes where value can be JavaScript expression (`js:` or `javascript:` prefix).
+pub const JS_ATTRIBUTES: [&str; 3] = ["hx-vals", "hx-headers", "hx-request"];
+
+/// JavaScript expression in attribute value and its byte offset in value.
+///
+/// `js:{lastKey: event.key}` - `{lastKey: event.key}` at offset 3.
+pub fn js_expression(value: &str) -> Option<(usize, &str)> {
+    let trimmed = value.trim_start();
+    let skipped = value.len() - trimmed.len();
+    ["js:", "javascript:"].iter().find_map(|prefix| {
+        let expression = trimmed.strip_prefix(prefix)?;
+        Some((skipped + prefix.len(), expression))
+    })
+}
+
+/// Byte range (in `expression`) of first syntax error. Expression is parsed in
+/// parentheses, same as htmx evaluates it, with parser from JavaScript pool.
+pub fn js_syntax_error(expression: &str, parsers: &Parsers) -> Option<(usize, usize)> {
+    let source = format!("({expression})");
+    let tree = parsers.pool(LangType::JavaScript).parse(&source, None)?;
+    let root = tree.root_node();
+    if !root.has_error() {
+        return None;
+    }
+    let error = first_error(root).unwrap_or(root);
+    let start = error.start_byte().saturating_sub(1).min(expression.len());
+    let end = error
+        .end_byte()
+        .saturating_sub(1)
+        .clamp(start, expression.len());
+    // Missing node has no width, next character is marked.
+    match start == end && end < expression.len() {
+        true => Some((start, end + 1)),
+        false => Some((start, end)),
+    }
+}
+
+fn first_error(node: Node<'_>) -> Option<Node<'_>> {
+    if node.is_error() || node.is_missing() {
+        return Some(node);
+    }
+    let mut cursor = node.walk();
+    let children: Vec<Node<'_>> = node.children(&mut cursor).collect();
+    children
+        .into_iter()
+        .filter(|child| child.has_error())
+        .find_map(first_error)
+}
+
+/// Point after `text` is written at `point`.
+pub fn advance(point: Point, text: &str) -> Point {
+    match text.rfind('\n') {
+        Some(last) => Point::new(
+            point.row + text.matches('\n').count(),
+            text.len() - last - 1,
+        ),
+        None => Point::new(point.row, point.column + text.len()),
+    }
+}
+
+pub struct JsSyntaxError {
+    pub attribute: String,
+    pub range: (Point, Point),
+}
+
+/// JavaScript values with syntax errors in start tags accepted by `keep`.
+pub fn js_value_errors(
+    root: Node<'_>,
+    source: &str,
+    parsers: &Parsers,
+    keep: impl Fn(Point, Point) -> bool,
+) -> Vec<JsSyntaxError> {
+    start_tags(root)
+        .into_iter()
+        .filter(|tag| keep(tag.start_position(), tag.end_position()))
+        .flat_map(|tag| tag_errors(tag, source, parsers))
+        .collect()
+}
+
+fn tag_errors(start_tag: Node<'_>, source: &str, parsers: &Parsers) -> Vec<JsSyntaxError> {
+    let mut errors = vec![];
+    for (name, value) in tag_attributes(start_tag, source) {
+        if !JS_ATTRIBUTES.contains(&name.as_str()) {
+            continue;
+        }
+        let Some(value) = value else {
+            continue;
+        };
+        let Ok(text) = value.utf8_text(source.as_bytes()) else {
+            continue;
+        };
+        let Some((offset, expression)) = js_expression(text) else {
+            continue;
+        };
+        if let Some((start, end)) = js_syntax_error(expression, parsers) {
+            let value_start = value.start_position();
+            let start = advance(value_start, &text[..offset + start]);
+            let end = advance(value_start, &text[..offset + end]);
+            errors.push(JsSyntaxError {
+                attribute: name,
+                range: (start, end),
+            });
+        }
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use tree_sitter::{Parser, Point};
+
+    use super::{js_expression, js_syntax_error, js_value_errors};
+    use crate::{htmx_tree_sitter::Parsers, init_hx::LangType};
+
+    #[test]
+    fn js_expressions() {
+        assert_eq!(js_expression("js:{a: 1}"), Some((3, "{a: 1}")));
+        assert_eq!(
+            js_expression(" javascript:getVals()"),
+            Some((12, "getVals()"))
+        );
+        assert_eq!(js_expression(r#"{"a": 1}"#), None);
+
+        let parsers = Parsers::default();
+        assert_eq!(js_syntax_error("{a: 1, b: event.key}", &parsers), None);
+        assert_eq!(js_syntax_error("{a: }", &parsers), Some((3, 4)));
+        assert!(js_syntax_error("{a: 1", &parsers).is_some());
+        // All expressions were parsed with one pooled parser.
+        assert_eq!(parsers.pool(LangType::JavaScript).created(), 1);
+    }
+
+    #[test]
+    fn errors_in_templates() {
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_html::language()).unwrap();
+        let text = "<div hx-vals='js:{a: 1}'></div>\n<div hx-vals='js:{a: }'></div>\n<div hx-target='js:{'></div>";
+        let tree = parser.parse(text, None).unwrap();
+        let errors = js_value_errors(tree.root_node(), text, &Parsers::default(), |_, _| true);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].attribute, "hx-vals");
+        assert_eq!(errors[0].range.0, Point::new(1, 20));
+    }
+}
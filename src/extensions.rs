@@ -0,0 +1,217 @@
+use tree_sitter::{Node, Point};
+
+use crate::{
+    embedded::advance,
+    init_hx::HxCompletion,
+    position::{start_tags, tag_attributes},
+};
+
+/// Attribute added by htmx extension. Its values are offered only in documents
+/// where extension is enabled with `hx-ext`.
+pub struct ExtensionAttribute {
+    pub extension: &'static str,
+    pub name: &'static str,
+    pub desc: &'static str,
+    pub values: &'static [(&'static str, &'static str)],
+}
+
+impl ExtensionAttribute {
+    /// Static values, `sse-swap` events are added from backend index.
+    pub fn completions(&self) -> Vec<HxCompletion> {
+        self.values
+            .iter()
+            .map(|(name, desc)| HxCompletion {
+                name: name.to_string(),
+                desc: desc.to_string(),
+                detail: Some(format!("{} extension", self.extension)),
+                deprecated: false,
+            })
+            .collect()
+    }
+}
+
+pub const EXTENSION_ATTRIBUTES: [ExtensionAttribute; 4] = [
+    ExtensionAttribute {
+        extension: "sse",
+        name: "sse-connect",
+        desc: include_str!("./md/extensions/sse-connect.md"),
+        values: &[],
+    },
+    ExtensionAttribute {
+        extension: "sse",
+        name: "sse-swap",
+        desc: include_str!("./md/extensions/sse-swap.md"),
+        values: &[("message", include_str!("./md/sse-swap/message.md"))],
+    },
+    ExtensionAttribute {
+        extension: "ws",
+        name: "ws-connect",
+        desc: include_str!("./md/extensions/ws-connect.md"),
+        values: &[],
+    },
+    ExtensionAttribute {
+        extension: "preload",
+        name: "preload",
+        desc: include_str!("./md/extensions/preload.md"),
+        values: &[
+            ("mousedown", include_str!("./md/preload/mousedown.md")),
+            ("mouseover", include_str!("./md/preload/mouseover.md")),
+            ("preload:init", include_str!("./md/preload/init.md")),
+        ],
+    },
+];
+
+/// Extension attribute with name, `data-` prefix is allowed.
+pub fn extension_attribute(name: &str) -> Option<&'static ExtensionAttribute> {
+    let name = name.strip_prefix("data-").unwrap_or(name);
+    EXTENSION_ATTRIBUTES
+        .iter()
+        .find(|attribute| attribute.name == name)
+}
+
+/// Extension names from `hx-ext` value, `ignore:` entries are skipped.
+pub fn hx_ext_names(value: &str) -> impl Iterator<Item = &str> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty() && !name.starts_with("ignore:"))
+}
+
+/// Every extension enabled in document.
+pub fn document_extensions(root: Node<'_>, source: &str) -> Vec<String> {
+    let mut extensions: Vec<String> = vec![];
+    for tag in start_tags(root) {
+        for (name, value) in tag_attributes(tag, source) {
+            if name != "hx-ext" && name != "data-hx-ext" {
+                continue;
+            }
+            let Some(Ok(value)) = value.map(|value| value.utf8_text(source.as_bytes())) else {
+                continue;
+            };
+            for extension in hx_ext_names(value) {
+                if !extensions.iter().any(|old| old == extension) {
+                    extensions.push(extension.to_string());
+                }
+            }
+        }
+    }
+    extensions
+}
+
+fn is_event_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | ':' | '.')
+}
+
+/// Event name at start of `text`.
+fn event_name(text: &str) -> Option<&str> {
+    let end = text.find(|c: char| !is_event_char(c)).unwrap_or(text.len());
+    let name = text[..end].trim_end_matches([':', '.']);
+    (!name.is_empty()).then_some(name)
+}
+
+/// SSE event names sent by backend: `event: name` line in stream,
+/// `.event("name")` (axum) and `event="name"` (Python).
+pub fn sse_event_names(source: &str) -> Vec<String> {
+    let mut names: Vec<String> = vec![];
+    for pattern in ["event: ", ".event(\"", "event=\"", "event='"] {
+        for (start, _) in source.match_indices(pattern) {
+            let Some(name) = event_name(&source[start + pattern.len()..]) else {
+                continue;
+            };
+            if !names.iter().any(|old| old == name) {
+                names.push(name.to_string());
+            }
+        }
+    }
+    names.sort();
+    names
+}
+
+/// WebSocket URL can be path on current host or `ws://`/`wss://` URL.
+pub fn ws_connect_error(url: &str) -> Option<String> {
+    let url = url.trim();
+    if url.is_empty() {
+        return Some(String::from("ws-connect expects WebSocket URL."));
+    }
+    let (scheme, _) = url.split_once("://")?;
+    let is_scheme = scheme.chars().all(|c| c.is_ascii_alphabetic());
+    (is_scheme && scheme != "ws" && scheme != "wss")
+        .then(|| format!("ws-connect expects ws:// or wss:// URL or path, found {scheme}:// URL."))
+}
+
+pub struct ExtensionDiagnostic {
+    pub range: (Point, Point),
+    pub message: String,
+}
+
+/// Invalid `ws-connect` URLs in start tags accepted by `keep`.
+pub fn extension_value_errors(
+    root: Node<'_>,
+    source: &str,
+    keep: impl Fn(Point, Point) -> bool,
+) -> Vec<ExtensionDiagnostic> {
+    let mut diagnostics = vec![];
+    for tag in start_tags(root) {
+        if !keep(tag.start_position(), tag.end_position()) {
+            continue;
+        }
+        for (name, value) in tag_attributes(tag, source) {
+            if name != "ws-connect" && name != "data-ws-connect" {
+                continue;
+            }
+            let Some(value) = value else {
+                continue;
+            };
+            let Ok(text) = value.utf8_text(source.as_bytes()) else {
+                continue;
+            };
+            if let Some(message) = ws_connect_error(text) {
+                let start = value.start_position();
+                diagnostics.push(ExtensionDiagnostic {
+                    range: (start, advance(start, text)),
+                    message,
+                });
+            }
+        }
+    }
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use tree_sitter::Parser;
+
+    use super::{document_extensions, extension_attribute, sse_event_names, ws_connect_error};
+
+    #[test]
+    fn extensions_in_document() {
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_html::language()).unwrap();
+        let text = r#"<body hx-ext="sse, preload"><div hx-ext="ignore:preload,ws"></div></body>"#;
+        let tree = parser.parse(text, None).unwrap();
+        assert_eq!(
+            document_extensions(tree.root_node(), text),
+            vec!["sse", "preload", "ws"]
+        );
+        assert_eq!(
+            extension_attribute("data-sse-swap").map(|attribute| attribute.extension),
+            Some("sse")
+        );
+        assert!(extension_attribute("hx-swap").is_none());
+    }
+
+    #[test]
+    fn backend_events_and_ws_urls() {
+        let rust = r#"Event::default().event("new-message").data(html); yield "event: ping\n""#;
+        let python = "ServerSentEvent(data=html, event='chat.update')\nf\"event: {name}\\n\"";
+        assert_eq!(sse_event_names(rust), vec!["new-message", "ping"]);
+        assert_eq!(sse_event_names(python), vec!["chat.update"]);
+
+        assert_eq!(ws_connect_error("/chatroom"), None);
+        assert_eq!(ws_connect_error("wss://example.com/chat"), None);
+        assert!(ws_connect_error("https://example.com/chat")
+            .unwrap()
+            .contains("https://"));
+        assert!(ws_connect_error(" ").is_some());
+    }
+}
@@ -0,0 +1,86 @@
+use dashmap::DashMap;
+use ropey::Rope;
+use tower_lsp::lsp_types::{
+    CodeAction, CodeActionKind, CreateFile, DocumentChangeOperation, DocumentChanges, OneOf,
+    OptionalVersionedTextDocumentIdentifier, Range, ResourceOp, TextDocumentEdit, TextEdit, Url,
+    WorkspaceEdit,
+};
+
+use crate::{
+    config::HtmxConfig,
+    to_input_edit::{strip_bom, ToInputEdit},
+    uri::{canonicalize, path_to_uri},
+};
+
+/// Element selected for "Extract htmx fragment" code action.
+pub struct Fragment {
+    /// Element id or `fragment_<line>`, it's used for file, route and tag.
+    pub name: String,
+    pub text: String,
+    pub range: Range,
+}
+
+/// "Extract htmx fragment" code action. Selected element is moved to new file
+/// in first templates directory, original element is replaced with element
+/// that loads fragment and function stub with `hx@` tag is added to backend.
+///
+/// It checks and reads files, so it's called on blocking thread.
+pub fn extract_fragment(
+    fragment: Fragment,
+    uri: &Url,
+    config: &HtmxConfig,
+    document_map: &DashMap<String, Rope>,
+) -> Option<CodeAction> {
+    let Fragment { name, text, range } = fragment;
+    let directory = canonicalize(config.resolve(config.templates.first()?).to_str()?)?;
+    let mut file = directory.join(format!("{name}.{}", config.template_ext));
+    let mut suffix = 1;
+    while file.exists() {
+        file = directory.join(format!("{name}_{suffix}.{}", config.template_ext));
+        suffix += 1;
+    }
+    let new_uri = Url::from_file_path(&file).ok()?;
+
+    let text_edit = |uri: Url, range: Range, text: String| {
+        DocumentChangeOperation::Edit(TextDocumentEdit {
+            text_document: OptionalVersionedTextDocumentIdentifier { uri, version: None },
+            edits: vec![OneOf::Left(TextEdit::new(range, text))],
+        })
+    };
+    let mut operations = vec![
+        DocumentChangeOperation::Op(ResourceOp::Create(CreateFile {
+            uri: new_uri.clone(),
+            options: None,
+            annotation_id: None,
+        })),
+        text_edit(new_uri, Range::default(), format!("{text}\n")),
+        text_edit(
+            uri.clone(),
+            range,
+            format!(r#"<div hx-get="/{name}" hx-trigger="load" hx-lsp="{name}"></div>"#),
+        ),
+    ];
+    if let (Some(stub), Some(stub_fn)) = (&config.backend_stub, config.backend_stub_fn(&name)) {
+        let path = canonicalize(config.resolve(stub).to_str()?)?;
+        let stub_uri = path_to_uri(&path)?;
+        let content = match document_map.get(&stub_uri) {
+            Some(content) => content.value().clone(),
+            None => Rope::from_str(strip_bom(&std::fs::read_to_string(&path).ok()?)),
+        };
+        let end = content.to_position(content.len_bytes());
+        operations.push(text_edit(
+            Url::from_file_path(&path).ok()?,
+            Range::new(end, end),
+            stub_fn,
+        ));
+    }
+    Some(CodeAction {
+        title: String::from("Extract htmx fragment"),
+        kind: Some(CodeActionKind::REFACTOR_EXTRACT),
+        edit: Some(WorkspaceEdit {
+            document_changes: Some(DocumentChanges::Operations(operations)),
+            ..Default::default()
+        }),
+        ..Default::default()
+    })
+}
@@ -0,0 +1,155 @@
+use serde::{Deserialize, Serialize};
+use tower_lsp::lsp_types::Range;
+use tree_sitter::Node;
+
+use crate::{
+    embedded::advance,
+    position::{start_tags, tag_attributes},
+    to_input_edit::to_position2,
+};
+
+/// Params of `htmx/findValue` request.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FindValueParams {
+    /// Text that is searched in values, characters can be apart (`mdl` matches `#modal`).
+    pub pattern: String,
+    /// Search only values of this attribute, `data-` prefix is ignored.
+    #[serde(default)]
+    pub attribute: Option<String>,
+}
+
+/// Attribute value that matches pattern, `uri` and `range` form LSP `Location`.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ValueMatch {
+    pub uri: String,
+    pub range: Range,
+    pub attribute: String,
+    pub value: String,
+    /// Exact match is best, then substring, then characters apart.
+    pub score: u32,
+}
+
+/// Score of `value` for case insensitive `pattern`, `None` if it doesn't match.
+pub fn fuzzy_score(pattern: &str, value: &str) -> Option<u32> {
+    let pattern = pattern.to_lowercase();
+    let value = value.to_lowercase();
+    if pattern.is_empty() {
+        return None;
+    }
+    if value == pattern {
+        return Some(1000);
+    }
+    if let Some(start) = value.find(&pattern) {
+        return Some(900 - start.min(400) as u32);
+    }
+    // Every pattern character in order, each gap lowers score.
+    let mut gaps = 0;
+    let mut chars = value.chars();
+    for c in pattern.chars() {
+        let mut skipped = 0;
+        loop {
+            match chars.next() {
+                Some(next) if next == c => break,
+                Some(_) => skipped += 1,
+                None => return None,
+            }
+        }
+        gaps += skipped.min(1);
+    }
+    Some(400u32.saturating_sub(gaps * 20).max(1))
+}
+
+fn strip_data(name: &str) -> &str {
+    name.strip_prefix("data-").unwrap_or(name)
+}
+
+/// Attribute values in template that match `params`, in document order.
+pub fn find_values(
+    uri: &str,
+    root: Node<'_>,
+    source: &str,
+    params: &FindValueParams,
+) -> Vec<ValueMatch> {
+    let attribute = params.attribute.as_deref().map(strip_data);
+    let mut matches = vec![];
+    for tag in start_tags(root) {
+        for (name, value) in tag_attributes(tag, source) {
+            if attribute.is_some_and(|attribute| attribute != strip_data(&name)) {
+                continue;
+            }
+            let Some(value) = value else {
+                continue;
+            };
+            let Ok(text) = value.utf8_text(source.as_bytes()) else {
+                continue;
+            };
+            let Some(score) = fuzzy_score(&params.pattern, text) else {
+                continue;
+            };
+            let start = value.start_position();
+            matches.push(ValueMatch {
+                uri: uri.to_string(),
+                range: Range::new(to_position2(start), to_position2(advance(start, text))),
+                attribute: name,
+                value: text.to_string(),
+                score,
+            });
+        }
+    }
+    matches
+}
+
+/// Best matches first, then by location.
+pub fn sort_matches(matches: &mut [ValueMatch]) {
+    matches.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then_with(|| a.uri.cmp(&b.uri))
+            .then_with(|| a.range.start.cmp(&b.range.start))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp::lsp_types::Position;
+    use tree_sitter::Parser;
+
+    use super::{find_values, fuzzy_score, sort_matches, FindValueParams};
+
+    #[test]
+    fn fuzzy_scores() {
+        assert_eq!(fuzzy_score("#modal", "#modal"), Some(1000));
+        assert!(fuzzy_score("modal", "closest #modal") > fuzzy_score("mdl", "#modal"));
+        assert!(fuzzy_score("MODAL", "#modal").is_some());
+        assert!(fuzzy_score("mdl", "#modal").is_some());
+        assert_eq!(fuzzy_score("lm", "#modal"), None);
+        assert_eq!(fuzzy_score("", "#modal"), None);
+    }
+
+    #[test]
+    fn values_in_template() {
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_html::language()).unwrap();
+        let text = "<button hx-target=\"#modal\" id=\"open\"></button>\n<form data-hx-target=\"closest #modal-body\" hx-get=\"/modal\"></form>";
+        let tree = parser.parse(text, None).unwrap();
+        let params = FindValueParams {
+            pattern: String::from("#modal"),
+            attribute: Some(String::from("hx-target")),
+        };
+        let mut matches = find_values("file:///a.html", tree.root_node(), text, &params);
+        sort_matches(&mut matches);
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matches[0].value, "#modal");
+        assert_eq!(matches[0].range.start, Position::new(0, 19));
+        assert_eq!(matches[0].range.end, Position::new(0, 25));
+        assert_eq!(matches[1].attribute, "data-hx-target");
+
+        let params = FindValueParams {
+            pattern: String::from("modal"),
+            attribute: None,
+        };
+        let matches = find_values("file:///a.html", tree.root_node(), text, &params);
+        assert_eq!(matches.len(), 3);
+    }
+}
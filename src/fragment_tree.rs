@@ -0,0 +1,148 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::Serialize;
+use tower_lsp::lsp_types::{Position, Range};
+use tree_sitter::Node;
+
+use crate::{
+    htmx_tags::{get_tags, Tag},
+    init_hx::LangType,
+    position::{start_tags, tag_attributes},
+};
+
+/// Tag definition(`hx@name` comment) in backend or JavaScript file.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Handler {
+    pub uri: String,
+    pub range: Range,
+    pub lang_type: Option<LangType>,
+}
+
+/// Tag from `hx-lsp` attribute and handlers that serve it.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct Fragment {
+    pub tag: String,
+    pub range: Range,
+    pub handlers: Vec<Handler>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct TemplateNode {
+    pub uri: String,
+    pub fragments: Vec<Fragment>,
+}
+
+/// Response for `htmx/fragmentTree`: template -> fragments -> handlers.
+/// Tags that are defined but never referenced are listed separately.
+#[derive(Debug, Default, Serialize, PartialEq)]
+pub struct FragmentTree {
+    pub templates: Vec<TemplateNode>,
+    pub unreferenced: Vec<String>,
+}
+
+/// Every tag from `hx-lsp` attributes in template, in document order.
+pub fn hx_lsp_tags(root: Node<'_>, source: &str) -> Vec<Tag> {
+    let mut tags = vec![];
+    for start_tag in start_tags(root) {
+        for (name, value) in tag_attributes(start_tag, source) {
+            if name != "hx-lsp" && name != "data-hx-lsp" {
+                continue;
+            }
+            let Some(value) = value else {
+                continue;
+            };
+            let Ok(text) = value.utf8_text(source.as_bytes()) else {
+                continue;
+            };
+            let start = value.start_position();
+            tags.extend(get_tags(text, start.column, start.row).unwrap_or_default());
+        }
+    }
+    tags
+}
+
+/// Build tree from tags used in every template and handlers for every tag name.
+/// Templates and handlers are sorted, so same index gives same response.
+pub fn fragment_tree(
+    templates: Vec<(String, Vec<Tag>)>,
+    handlers: BTreeMap<String, Vec<Handler>>,
+) -> FragmentTree {
+    let mut referenced = BTreeSet::new();
+    let mut nodes: Vec<TemplateNode> = templates
+        .into_iter()
+        .map(|(uri, tags)| {
+            let fragments = tags
+                .into_iter()
+                .map(|tag| {
+                    referenced.insert(tag.name.clone());
+                    Fragment {
+                        handlers: handlers.get(&tag.name).cloned().unwrap_or_default(),
+                        range: Range::new(
+                            Position::new(tag.start.row as u32, tag.start.column as u32),
+                            Position::new(tag.end.row as u32, tag.end.column as u32),
+                        ),
+                        tag: tag.name,
+                    }
+                })
+                .collect();
+            TemplateNode { uri, fragments }
+        })
+        .collect();
+    nodes.sort_by(|a, b| a.uri.cmp(&b.uri));
+    let unreferenced = handlers
+        .into_keys()
+        .filter(|name| !referenced.contains(name))
+        .collect();
+    FragmentTree {
+        templates: nodes,
+        unreferenced,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use tower_lsp::lsp_types::{Position, Range};
+    use tree_sitter::Parser;
+
+    use super::{fragment_tree, hx_lsp_tags, Handler};
+    use crate::init_hx::LangType;
+
+    #[test]
+    fn templates_fragments_and_handlers() {
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_html::language()).unwrap();
+        let text = "<div hx-lsp=\"list\">\n  <p hx-lsp=\"item edit\"></p>\n</div>";
+        let tree = parser.parse(text, None).unwrap();
+        let tags = hx_lsp_tags(tree.root_node(), text);
+        let names: Vec<&str> = tags.iter().map(|tag| tag.name.as_str()).collect();
+        assert_eq!(names, vec!["list", "item", "edit"]);
+
+        let handler = |name: &str| Handler {
+            uri: format!("file:///src/{name}.rs"),
+            range: Range::default(),
+            lang_type: Some(LangType::Backend),
+        };
+        let handlers = BTreeMap::from([
+            (String::from("list"), vec![handler("list")]),
+            (String::from("delete"), vec![handler("delete")]),
+        ]);
+        let tree = fragment_tree(
+            vec![
+                (String::from("file:///templates/b.html"), tags),
+                (String::from("file:///templates/a.html"), vec![]),
+            ],
+            handlers,
+        );
+        assert_eq!(tree.templates[0].uri, "file:///templates/a.html");
+        let fragments = &tree.templates[1].fragments;
+        assert_eq!(fragments[0].handlers, vec![handler("list")]);
+        assert_eq!(
+            fragments[1].range,
+            Range::new(Position::new(1, 13), Position::new(1, 17))
+        );
+        assert!(fragments[1].handlers.is_empty());
+        assert_eq!(tree.unreferenced, vec!["delete"]);
+    }
+}
@@ -0,0 +1,131 @@
+#[cfg(unix)]
+use std::ffi::{CStr, CString};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use tree_sitter::Language;
+
+use crate::state::{Recover, State};
+
+/// Library path and grammar name.
+type GrammarKey = (PathBuf, String);
+
+/// Languages with libraries they come from, same grammar is loaded only once.
+/// Library is kept open as long as its language, entries are never removed.
+static LOADED: Mutex<Option<HashMap<GrammarKey, (Library, Language)>>> = Mutex::new(None);
+
+/// Load tree-sitter grammar from shared library. Library must export
+/// `tree_sitter_<name>` function (`-` in name is replaced with `_`).
+pub fn load_language(lib: &Path, name: &str) -> anyhow::Result<Language> {
+    let key = (lib.to_path_buf(), name.to_string());
+    let mut loaded = LOADED.recover(State::Parsers);
+    let loaded = loaded.get_or_insert_with(HashMap::new);
+    if let Some((_, language)) = loaded.get(&key) {
+        return Ok(*language);
+    }
+    let symbol = format!("tree_sitter_{}", name.replace('-', "_"));
+    let library = Library::open(lib)?;
+    let language = library.language(&symbol)?;
+    loaded.insert(key, (library, language));
+    Ok(language)
+}
+
+/// Open shared library, it's closed on drop.
+struct Library {
+    path: PathBuf,
+    #[cfg(unix)]
+    handle: std::ptr::NonNull<std::ffi::c_void>,
+}
+
+// SAFETY: handle is only passed to `dlsym`/`dlclose`, both are thread-safe.
+unsafe impl Send for Library {}
+
+#[cfg(unix)]
+impl Library {
+    fn open(lib: &Path) -> anyhow::Result<Self> {
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = CString::new(lib.as_os_str().as_bytes())?;
+        // SAFETY: path is valid C string. Grammars are plain C code without
+        // library constructors.
+        let handle = unsafe { libc::dlopen(path.as_ptr(), libc::RTLD_NOW) };
+        match std::ptr::NonNull::new(handle) {
+            Some(handle) => Ok(Self {
+                path: lib.to_path_buf(),
+                handle,
+            }),
+            None => anyhow::bail!("Can't load {}: {}", lib.display(), dl_error()),
+        }
+    }
+
+    /// Call exported `symbol` (`tree_sitter_<name>`) to get language.
+    fn language(&self, symbol: &str) -> anyhow::Result<Language> {
+        let name = CString::new(symbol)?;
+        // SAFETY: handle is open until drop, name is valid C string.
+        let function = unsafe { libc::dlsym(self.handle.as_ptr(), name.as_ptr()) };
+        if function.is_null() {
+            anyhow::bail!("{} doesn't export {symbol}", self.path.display());
+        }
+        // SAFETY: `tree_sitter_<name>` functions have this signature, returned
+        // language points to static data of library that is kept open with it.
+        let function: unsafe extern "C" fn() -> Language = unsafe { std::mem::transmute(function) };
+        Ok(unsafe { function() })
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Library {
+    fn drop(&mut self) {
+        // SAFETY: handle is open and it's not used after this.
+        unsafe { libc::dlclose(self.handle.as_ptr()) };
+    }
+}
+
+#[cfg(unix)]
+fn dl_error() -> String {
+    // SAFETY: dlerror returns null or pointer to C string that is valid until
+    // next dl call, it's copied right away.
+    let error = unsafe { libc::dlerror() };
+    match error.is_null() {
+        true => String::from("unknown error"),
+        false => unsafe { CStr::from_ptr(error) }
+            .to_string_lossy()
+            .to_string(),
+    }
+}
+
+#[cfg(not(unix))]
+impl Library {
+    fn open(lib: &Path) -> anyhow::Result<Self> {
+        anyhow::bail!(
+            "Can't load {}: custom grammars are supported only on Unix",
+            lib.display()
+        )
+    }
+
+    fn language(&self, _symbol: &str) -> anyhow::Result<Language> {
+        unreachable!("{} can't be opened", self.path.display())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::load_language;
+
+    #[test]
+    fn missing_library_or_symbol() {
+        let err = load_language(Path::new("/nonexistent/libtree-sitter-x.so"), "x").unwrap_err();
+        assert!(err.to_string().starts_with("Can't load"));
+        if cfg!(target_os = "linux") {
+            let err = load_language(Path::new("libc.so.6"), "kotlin").unwrap_err();
+            assert!(err
+                .to_string()
+                .ends_with("doesn't export tree_sitter_kotlin"));
+        }
+    }
+}
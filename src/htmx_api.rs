@@ -0,0 +1,172 @@
+/// Member of htmx JavaScript API, `htmx.<name>`.
+#[derive(Debug, PartialEq)]
+pub struct ApiMember {
+    pub name: &'static str,
+    /// Arguments of method, `None` for property.
+    pub args: Option<&'static str>,
+    pub desc: &'static str,
+}
+
+impl ApiMember {
+    /// `htmx.ajax(verb, path, context)`
+    pub fn signature(&self) -> String {
+        match self.args {
+            Some(args) => format!("htmx.{}({args})", self.name),
+            None => format!("htmx.{}", self.name),
+        }
+    }
+}
+
+const fn method(name: &'static str, args: &'static str, desc: &'static str) -> ApiMember {
+    ApiMember {
+        name,
+        args: Some(args),
+        desc,
+    }
+}
+
+const fn property(name: &'static str, desc: &'static str) -> ApiMember {
+    ApiMember {
+        name,
+        args: None,
+        desc,
+    }
+}
+
+/// htmx JavaScript API, methods and properties of `htmx` object.
+pub const HTMX_API: [ApiMember; 24] = [
+    method(
+        "addClass",
+        "elt, class, delay",
+        "Adds a class to the given element.",
+    ),
+    method(
+        "ajax",
+        "verb, path, context",
+        "Issues an htmx-style ajax request.",
+    ),
+    method(
+        "closest",
+        "elt, selector",
+        "Finds the closest matching element in the given elements parentage, inclusive of the element.",
+    ),
+    property("config", "A property that holds the current htmx config object."),
+    property(
+        "createEventSource",
+        "A property holding the function to create SSE EventSource objects for htmx.",
+    ),
+    property(
+        "createWebSocket",
+        "A property holding the function to create WebSocket objects for htmx.",
+    ),
+    method("defineExtension", "name, ext", "Defines a new htmx extension."),
+    method("find", "elt, selector", "Finds an element matching the selector."),
+    method(
+        "findAll",
+        "elt, selector",
+        "Finds all elements matching the selector.",
+    ),
+    method("logAll", "", "Log all htmx events, useful for debugging."),
+    method("logNone", "", "Turns off logging of htmx events."),
+    property("logger", "The logger htmx uses to log with."),
+    method(
+        "off",
+        "elt, eventName, listener",
+        "Removes an event listener from an element.",
+    ),
+    method(
+        "on",
+        "elt, eventName, listener",
+        "Adds an event listener to an element.",
+    ),
+    method(
+        "onLoad",
+        "callback",
+        "Adds a callback for the `htmx:load` event. This can be used to process new content, for example initializing the content with a javascript library.",
+    ),
+    method(
+        "parseInterval",
+        "str",
+        "Parses an interval string consistent with the way htmx does. Useful for plugins that have timing-related attributes.",
+    ),
+    method(
+        "process",
+        "elt",
+        "Processes new content, enabling htmx behavior. This can be useful if you have content that is added to the DOM outside of the normal htmx request cycle but still want htmx attributes to work.",
+    ),
+    method("remove", "elt, delay", "Removes an element from the DOM."),
+    method(
+        "removeClass",
+        "elt, class, delay",
+        "Removes a class from the given element.",
+    ),
+    method(
+        "removeExtension",
+        "name",
+        "Removes the given extension from htmx.",
+    ),
+    method(
+        "takeClass",
+        "elt, class",
+        "Takes the given class from its siblings, so that among its siblings, only the given element will have the class.",
+    ),
+    method(
+        "toggleClass",
+        "elt, class",
+        "Toggles the given class on an element.",
+    ),
+    method(
+        "trigger",
+        "elt, name, detail",
+        "Triggers a given event on an element.",
+    ),
+    method(
+        "values",
+        "elt, requestType",
+        "Returns the input values that would resolve for a given element via the htmx value resolution mechanism.",
+    ),
+];
+
+fn is_identifier(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+/// Typed member name when text before cursor ends with `htmx.<name>`.
+pub fn api_prefix(before: &str) -> Option<&str> {
+    let name_start = before.trim_end_matches(is_identifier).len();
+    let object = before[..name_start].strip_suffix("htmx.")?;
+    if object.ends_with(is_identifier) {
+        return None;
+    }
+    Some(&before[name_start..])
+}
+
+/// API member under cursor in `line`, like `htmx.aj|ax(...)`.
+pub fn api_member(line: &str, column: usize) -> Option<&'static ApiMember> {
+    let rest = line.get(column.min(line.len())..)?;
+    let end = line.len() - rest.trim_start_matches(is_identifier).len();
+    let name = api_prefix(&line[..end])?;
+    HTMX_API.iter().find(|member| member.name == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{api_member, api_prefix};
+
+    #[test]
+    fn members_after_htmx_object() {
+        assert_eq!(api_prefix("  htmx.aj"), Some("aj"));
+        assert_eq!(api_prefix("htmx."), Some(""));
+        assert_eq!(api_prefix("myhtmx.aj"), None);
+        assert_eq!(api_prefix("window.htmx.aj"), Some("aj"));
+        assert_eq!(api_prefix("htmx.ajax("), None);
+
+        let line = "htmx.ajax(\"GET\", \"/users\");";
+        assert_eq!(api_member(line, 7).map(|member| member.name), Some("ajax"));
+        assert_eq!(
+            api_member(line, 9).unwrap().signature(),
+            "htmx.ajax(verb, path, context)"
+        );
+        assert_eq!(api_member(line, 2), None);
+    }
+}
@@ -45,67 +45,230 @@ pub struct Tag {
     pub name: String,
     /// File id
     pub file: usize,
+    /// Function or struct that tag comment belongs to.
+    pub item: Option<Box<TagItem>>,
+}
+
+/// Code item of tag, captured as `@hx_item` by backend/javascript query.
+#[derive(Debug, Clone, Ord, Eq, PartialEq, PartialOrd)]
+pub struct TagItem {
+    /// Name of function, method or struct.
+    pub name: String,
+    /// Start of item, attributes and decorators are not included.
+    pub start: Point,
+    pub end: Point,
+    /// End of item name, goto definition selects signature up to it.
+    pub name_end: Point,
 }
 
 /// Check if client current position is in tag range.
 pub fn in_tag(line: &str, point: Point) -> Option<Tag> {
-    let tag = get_tag(line)?;
+    let tag = get_tag(line).or_else(|| get_jsdoc_tag(line)).or_else(|| {
+        let attribute = line.trim_start();
+        let mut tag = get_attribute_tag(attribute)?;
+        let indent = line.len() - attribute.len();
+        tag.start.column += indent;
+        tag.end.column += indent;
+        Some(tag)
+    })?;
     if point >= tag.start && point <= tag.end {
         return Some(tag);
     }
     None
 }
 
-/// Return tag from line if it exist. Start and end point,
-/// still have no information about line and file. Function get_tags solves this.
+/// Typed tag name when text before cursor ends with `hx@<name>`.
+pub fn tag_prefix(before: &str) -> Option<&str> {
+    let start = before.rfind("hx@")? + 3;
+    let name = &before[start..];
+    match name.contains(char::is_whitespace) {
+        true => None,
+        false => Some(name),
+    }
+}
+
+/// Return tag from line if it exist. Start and end point cover only tag name
+/// (without `hx@`), end is exclusive. They still have no information about line
+/// and file, function `query_tag` solves this.
 pub fn get_tag(line: &str) -> Option<Tag> {
-    let parts = line.split("hx@");
-    let mut first = parts.filter(|data| !data.contains(' '));
-    let first = first.next()?;
-    let mut parts = first.split(' ');
-    let first = parts.next()?;
-    let full = format!("hx@{}", &first);
-    let start = line.find(&full)?;
-    let end = start + 2 + first.len();
+    let start = line.find("hx@")? + 3;
+    let name = line[start..].split(char::is_whitespace).next()?;
+    // Comment can end right after name (`<!-- hx@cart-->`, `/* hx@cart*/`).
+    let name = name.strip_suffix("-->").unwrap_or(name);
+    let name = name.strip_suffix("*/").unwrap_or(name);
+    if name.is_empty() {
+        return None;
+    }
+    Some(Tag {
+        name: name.to_string(),
+        start: Point::new(0, start),
+        end: Point::new(0, start + name.len()),
+        file: 0,
+        item: None,
+    })
+}
+
+/// Tag from Rust attribute `#[htmx_tag("cart")]`, columns are relative to
+/// attribute text, same as in `get_tag`.
+pub fn get_attribute_tag(text: &str) -> Option<Tag> {
+    let arguments = text
+        .strip_prefix("#[")?
+        .trim_start()
+        .strip_prefix("htmx_tag")?
+        .trim_start()
+        .strip_prefix('(')?
+        .trim_start();
+    let name = arguments.strip_prefix('"')?;
+    let start = text.len() - name.len();
+    let name = &name[..name.find('"')?];
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        return None;
+    }
     Some(Tag {
-        name: first.to_string(),
+        name: name.to_string(),
         start: Point::new(0, start),
-        end: Point::new(0, end),
+        end: Point::new(0, start + name.len()),
         file: 0,
+        item: None,
     })
 }
 
-/// Get all tags from hx-lsp attribute.
-pub fn get_tags(value: &str, mut start_char: usize, line: usize) -> Option<Vec<Tag>> {
-    if value.starts_with(' ') || value.contains("  ") || value.is_empty() {
+/// Tag from JSDoc annotation `@hx cart`, columns are relative to text and text
+/// can be whole comment, same as in `get_tag`.
+pub fn get_jsdoc_tag(text: &str) -> Option<Tag> {
+    let (at, _) = text.match_indices("@hx").find(|(at, _)| {
+        let before = text[..*at].chars().next_back();
+        let after = text[at + 3..].chars().next();
+        before.is_none_or(|c| c.is_whitespace() || c == '*')
+            && after.is_some_and(|c| c == ' ' || c == '\t')
+    })?;
+    let value = &text[at + 3..];
+    let name = value.trim_start_matches([' ', '\t']);
+    let start = text.len() - name.len();
+    let name = name.split(char::is_whitespace).next()?;
+    let name = name.strip_suffix("*/").unwrap_or(name);
+    if name.is_empty() {
         return None;
     }
+    Some(Tag {
+        name: name.to_string(),
+        start: Point::new(0, start),
+        end: Point::new(0, start + name.len()),
+        file: 0,
+        item: None,
+    })
+}
+
+/// Get all tags from hx-lsp attribute. Tags are separated by any whitespace and
+/// value can span more lines. Like in `get_tag`, columns are in bytes and end is
+/// exclusive, so tag range is `start..end` on its own row.
+pub fn get_tags(value: &str, start_char: usize, line: usize) -> Option<Vec<Tag>> {
     let mut tags = vec![];
-    let parts = value.split(' ');
-    for part in parts {
-        let start = start_char;
-        let end = start + part.len() - 1;
-        start_char = end + 2;
-        let tag = Tag {
-            name: String::from(part),
-            start: Point::new(line, start),
-            end: Point::new(line, end),
-            file: 0,
-        };
-        tags.push(tag);
+    for (i, text) in value.split('\n').enumerate() {
+        let offset = if i == 0 { start_char } else { 0 };
+        let mut start = None;
+        for (j, c) in text.char_indices().chain([(text.len(), ' ')]) {
+            match (c.is_whitespace(), start) {
+                (false, None) => start = Some(j),
+                (true, Some(token)) => {
+                    tags.push(Tag {
+                        name: String::from(&text[token..j]),
+                        start: Point::new(line + i, offset + token),
+                        end: Point::new(line + i, offset + j),
+                        file: 0,
+                        item: None,
+                    });
+                    start = None;
+                }
+                _ => (),
+            }
+        }
     }
-    Some(tags)
+    (!tags.is_empty()).then_some(tags)
 }
 
 /// Checks if definition request position is between one of tags.
 pub fn in_tags(value: &str, definition: PositionDefinition) -> Option<Tag> {
-    let tags = get_tags(value, definition.start, definition.point.row)?;
-    for tag in tags {
-        let t = definition.point.row >= tag.start.row && definition.point.column <= tag.end.column;
-        if t {
-            return Some(tag);
-        }
+    let tags = get_tags(value, definition.start.column, definition.start.row)?;
+    let point = definition.point;
+    // Cursor right after name still belongs to it.
+    tags.into_iter()
+        .find(|tag| tag.start <= point && point <= tag.end)
+}
+
+#[cfg(test)]
+mod tests {
+    use tree_sitter::Point;
+
+    use super::{get_tag, get_tags, in_tags};
+    use crate::position::PositionDefinition;
+
+    #[test]
+    fn tags_in_value() {
+        let tags = get_tags("list  ćevapi\n\tedit", 12, 3).unwrap();
+        let ranges: Vec<_> = tags
+            .iter()
+            .map(|tag| (tag.name.as_str(), tag.start, tag.end))
+            .collect();
+        assert_eq!(
+            ranges,
+            [
+                ("list", Point::new(3, 12), Point::new(3, 16)),
+                ("ćevapi", Point::new(3, 18), Point::new(3, 25)),
+                ("edit", Point::new(4, 1), Point::new(4, 5))
+            ]
+        );
+        assert_eq!(get_tags(" \n ", 0, 0), None);
     }
 
-    None
+    #[test]
+    fn token_under_cursor() {
+        let value = "ćevapi list";
+        let tag = |column| {
+            let definition = PositionDefinition::new(Point::new(0, 10), Point::new(0, column));
+            in_tags(value, definition).map(|tag| tag.name)
+        };
+        assert_eq!(tag(10).as_deref(), Some("ćevapi"));
+        assert_eq!(tag(17).as_deref(), Some("ćevapi"));
+        assert_eq!(tag(18).as_deref(), Some("list"));
+        assert_eq!(tag(22).as_deref(), Some("list"));
+        assert_eq!(tag(23), None);
+    }
+
+    #[test]
+    fn tag_ranges_for_any_value() {
+        // Every value of up to four parts, parts are chosen for boundaries:
+        // multibyte characters, whitespace at start/end and empty lines.
+        let parts = ["a", "ć", "🦀", " ", "\t", "\n", "\r", "hx@"];
+        let mut values = vec![String::new()];
+        for _ in 0..4 {
+            let longer: Vec<String> = values
+                .iter()
+                .flat_map(|value| parts.iter().map(move |part| format!("{value}{part}")))
+                .collect();
+            values.extend(longer);
+        }
+        for value in &values {
+            let lines: Vec<&str> = value.split('\n').collect();
+            for tag in get_tags(value, 5, 2).into_iter().flatten() {
+                let row = tag.start.row - 2;
+                let offset = if row == 0 { 5 } else { 0 };
+                assert_eq!(tag.start.row, tag.end.row);
+                assert!(tag.start.column < tag.end.column, "{value:?}");
+                let text = &lines[row][tag.start.column - offset..tag.end.column - offset];
+                assert_eq!(text, tag.name);
+                assert!(!tag.name.contains(char::is_whitespace));
+            }
+            for row in 0..lines.len() + 1 {
+                for column in 0..value.len() + 8 {
+                    let definition =
+                        PositionDefinition::new(Point::new(2, 5), Point::new(2 + row, column));
+                    if let Some(tag) = in_tags(value, definition) {
+                        assert!(tag.start <= Point::new(2 + row, column));
+                    }
+                }
+            }
+            let _ = get_tag(value);
+        }
+    }
 }
@@ -1,6 +1,6 @@
 use std::{
-    cell::RefCell,
-    collections::HashMap,
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, BTreeSet, HashMap},
     path::Path,
     sync::{Arc, Mutex, RwLock},
 };
@@ -10,24 +10,50 @@ use dashmap::{
     DashMap,
 };
 use ropey::Rope;
+use serde::Serialize;
+use serde_json::Value;
 use tower_lsp::lsp_types::{
     request::{GotoImplementationParams, GotoImplementationResponse},
-    CodeActionParams, Diagnostic, DiagnosticSeverity, GotoDefinitionParams, GotoDefinitionResponse,
-    Location, Position, Range, ReferenceParams, Url,
+    CodeActionParams, CodeLens, Command, Diagnostic, DiagnosticSeverity, DiagnosticTag,
+    GotoDefinitionParams, GotoDefinitionResponse, Location, LocationLink, NumberOrString, Position,
+    Range, ReferenceParams, TextEdit, Url,
 };
-use tree_sitter::{InputEdit, Parser, Point, Query, Tree};
+use tower_lsp::lsp_types::{
+    CodeAction, CodeActionKind, CreateFile, DocumentChangeOperation, DocumentChanges, OneOf,
+    OptionalVersionedTextDocumentIdentifier, ResourceOp, TextDocumentEdit, WorkspaceEdit,
+};
+use tree_sitter::{InputEdit, Language, Parser, Point, Query, Tree};
 
 use crate::{
-    config::HtmxConfig,
+    ajax::{ajax_literal, string_literals},
+    code_lens::{verb_lenses, verb_stats},
+    config::{Feature, HtmxConfig, TagStub, MAX_FILE_SIZE, TS_EXTS},
+    extensions::{document_extensions, hx_ext_names, sse_event_names},
+    extract::Fragment,
+    find_value::{find_values, sort_matches, FindValueParams, ValueMatch},
+    fragment_tree::{fragment_tree, hx_lsp_tags, FragmentTree, Handler},
     htmx_tags::{in_tags, Tag},
+    include_graph::{resolve_include, template_includes, Include, IncludeGraph, TemplateIncludes},
+    incremental::TEMPLATE_RULES,
     init_hx::{LangType, LangTypes},
-    position::{query_position, Position as PositionType, PositionDefinition, QueryType},
-    queries::{HX_JS_TAGS, HX_RUST_TAGS},
+    metrics::{IndexSize, METRICS},
+    organize::{organize_attributes, ORGANIZE_HTMX_ATTRIBUTES},
+    position::{
+        boosted_elements, element_id, element_ids, error_near, error_nodes, find_element,
+        query_position, BoostedElement, ElementId, Position as PositionType, PositionDefinition,
+        QueryType,
+    },
     query_helper::{
-        find_hx_lsp, query_htmx_lsp, query_tag, HTMLQueries, HTMLQuery, HtmxQuery, Queries,
+        find_hx_lsp, query_attribute_values, query_has_match, query_htmx_lsp, query_missing_quotes,
+        query_tag, query_tag_item, HTMLQueries, HTMLQuery, HtmxQuery, Queries,
     },
+    selector::{selector_id, unknown_select_ids},
     server::{FileWriter, ServerTextDocumentItem},
-    to_input_edit::to_position,
+    state::{Recover, RecoverRw, State},
+    stylesheet::{unknown_classes, Stylesheet, HTMX_CLASSES},
+    suppress::{DUPLICATE_TAG, OOB_TARGET, SELECT_TARGET, UNKNOWN_CLASS, UNKNOWN_TAG, UNUSED_TAG},
+    to_input_edit::{strip_bom, to_position, to_position2},
+    uri::{canonicalize, normalize_uri},
 };
 
 type FileName = usize;
@@ -51,8 +77,24 @@ pub struct LspFiles {
     template: DashMap<FileName, Tree>,
     javascript: DashMap<FileName, Tree>,
     backend: DashMap<FileName, Tree>,
-    pub parsers: Arc<Mutex<Parsers>>,
-    pub tags: DashMap<String, Tag>,
+    pub parsers: Arc<Parsers>,
+    /// Tag definitions, same name can be defined in backend and javascript.
+    pub tags: DashMap<String, Vec<Tag>>,
+    /// Language of tags in file.
+    tag_scopes: DashMap<FileName, LangType>,
+    /// Extension of backend files that are parsed with grammar of their directory
+    /// (`backend_tags` with own `lang`).
+    backend_exts: DashMap<FileName, String>,
+    /// Extension of TypeScript files (`TS_EXTS`), they are parsed with TypeScript grammars.
+    typescript_exts: DashMap<FileName, String>,
+    /// Htmx attribute values used in templates, for value completion history.
+    attribute_values: DashMap<FileName, Vec<(String, String)>>,
+    /// Elements with id attribute in templates.
+    ids: DashMap<FileName, Vec<ElementId>>,
+    /// Classes from configured stylesheets, by uri.
+    stylesheets: DashMap<String, Stylesheet>,
+    /// Documents larger than this (in bytes) have no trees.
+    max_file_size: Cell<usize>,
 }
 
 impl Default for LspFiles {
@@ -60,11 +102,18 @@ impl Default for LspFiles {
         Self {
             current: RefCell::new(0),
             indexes: DashMap::new(),
-            parsers: Arc::new(Mutex::new(Parsers::default())),
+            parsers: Arc::new(Parsers::default()),
             tags: DashMap::new(),
+            tag_scopes: DashMap::new(),
+            backend_exts: DashMap::new(),
+            typescript_exts: DashMap::new(),
             template: DashMap::new(),
             javascript: DashMap::new(),
             backend: DashMap::new(),
+            attribute_values: DashMap::new(),
+            ids: DashMap::new(),
+            stylesheets: DashMap::new(),
+            max_file_size: Cell::new(MAX_FILE_SIZE as usize),
         }
     }
 }
@@ -77,34 +126,449 @@ impl LspFiles {
         self.javascript.clear();
         self.backend.clear();
         self.tags.clear();
+        self.tag_scopes.clear();
+        self.backend_exts.clear();
+        self.typescript_exts.clear();
+        self.attribute_values.clear();
+        self.ids.clear();
+        self.stylesheets.clear();
     }
 
-    /// After each save for backend/javascript, tags are deleted for that file.
-    pub fn delete_tags_by_index(&self, index: usize) {
-        let mut tags = vec![];
-        for i in &self.tags {
-            let file = i.value().file;
-            if file == index {
-                tags.push(String::from(i.key()));
+    pub fn set_max_file_size(&self, size: usize) {
+        self.max_file_size.set(size);
+    }
+
+    /// Document is too large for tree-based features.
+    pub fn too_large(&self, text: &str) -> bool {
+        text.len() > self.max_file_size.get()
+    }
+
+    pub fn add_stylesheet(&self, uri: String, content: &str) {
+        self.stylesheets.insert(uri, Stylesheet::parse(content));
+    }
+
+    /// Parse indexed stylesheet again, other files are ignored.
+    pub fn stylesheet_saved(&self, uri: &str, document_map: &DashMap<String, Rope>) {
+        if !self.stylesheets.contains_key(uri) {
+            return;
+        }
+        let content = match document_map.get(uri) {
+            Some(rope) => Some(rope.to_string()),
+            None => Url::parse(uri)
+                .ok()
+                .and_then(|uri| uri.to_file_path().ok())
+                .and_then(|path| std::fs::read_to_string(path).ok()),
+        };
+        if let Some(content) = content {
+            self.add_stylesheet(uri.to_string(), strip_bom(&content));
+        }
+    }
+
+    /// Ids for completion: ids of current document, then ids of other indexed
+    /// templates with their uri (if `project` is enabled). Every id is listed once.
+    pub fn id_names(
+        &self,
+        uri: &String,
+        document_map: &DashMap<String, Rope>,
+        project: bool,
+    ) -> Vec<(String, Option<String>)> {
+        let mut names: Vec<(String, Option<String>)> = vec![];
+        let index = self.get_index(uri);
+        let text = document_map.get(uri).map(|rope| rope.to_string());
+        if let (Some(index), Some(text)) = (index, text) {
+            if let Some(tree) = self.get_tree(LangType::Template, index) {
+                for element in element_ids(tree.root_node(), &text) {
+                    if !names.iter().any(|(name, _)| name == &element.id) {
+                        names.push((element.id, None));
+                    }
+                }
+            }
+        }
+        if !project {
+            return names;
+        }
+        let mut others: Vec<(String, Option<String>)> = vec![];
+        for file in self.ids.iter().filter(|file| Some(*file.key()) != index) {
+            let Some(other) = self.get_uri(*file.key()) else {
+                continue;
+            };
+            for element in file.value() {
+                others.push((element.id.clone(), Some(other.clone())));
+            }
+        }
+        others.sort();
+        for (id, other) in others {
+            if !names.iter().any(|(name, _)| name == &id) {
+                names.push((id, other));
+            }
+        }
+        names
+    }
+
+    /// Classes from every stylesheet, sorted.
+    pub fn class_names(&self) -> BTreeSet<String> {
+        self.stylesheets
+            .iter()
+            .flat_map(|stylesheet| stylesheet.classes.clone())
+            .collect()
+    }
+
+    /// Save all htmx attribute values and element ids from template.
+    pub fn index_template(&self, index: usize, text: &str, query: &HTMLQueries) {
+        if let Some(tree) = self.get_tree(LangType::Template, index) {
+            let values =
+                query_attribute_values(tree.root_node(), text, query.get(HTMLQuery::Values));
+            let ids = element_ids(tree.root_node(), text);
+            drop(tree);
+            self.attribute_values.insert(index, values);
+            self.ids.insert(index, ids);
+        }
+    }
+
+    /// Parse errors for every indexed tree, sorted by uri.
+    pub fn parse_stats(&self) -> Vec<ParseStats> {
+        let mut stats = vec![];
+        let trees = [
+            (LangType::Template, &self.template),
+            (LangType::JavaScript, &self.javascript),
+            (LangType::Backend, &self.backend),
+        ];
+        for (lang_type, trees) in trees {
+            for tree in trees.iter() {
+                let Some(uri) = self.get_uri(*tree.key()) else {
+                    continue;
+                };
+                let root = tree.root_node();
+                stats.push(ParseStats {
+                    uri,
+                    lang_type,
+                    errors: error_nodes(root),
+                    lines: root.end_position().row + 1,
+                });
+            }
+        }
+        stats.sort_by(|a, b| a.uri.cmp(&b.uri));
+        stats
+    }
+
+    /// Tag, out of band, unused tag and template diagnostics for whole project,
+    /// optional checks depend on `config`.
+    pub fn project_diagnostics(
+        &self,
+        tags: Vec<Tag>,
+        config: &HtmxConfig,
+        document_map: &DashMap<String, Rope>,
+    ) -> BTreeMap<String, Vec<Diagnostic>> {
+        let mut hm = HashMap::new();
+        self.publish_tag_diagnostics(tags, &mut hm);
+        if config.oob_check {
+            self.oob_diagnostics(&mut hm);
+        }
+        if config.unused_tags {
+            self.unused_tag_diagnostics(&mut hm);
+        }
+        self.class_diagnostics(document_map, &mut hm);
+        if config.enabled(Feature::Diagnostics) {
+            self.unknown_tag_diagnostics(document_map, &mut hm);
+            self.template_diagnostics(document_map, &mut hm);
+            self.select_diagnostics(document_map, &mut hm);
+        }
+        // Diagnostics come from maps, files and their diagnostics are sorted for stable output.
+        hm.into_iter()
+            .map(|(uri, mut diagnostics)| {
+                sort_diagnostics(&mut diagnostics);
+                (uri, diagnostics)
+            })
+            .collect()
+    }
+
+    /// Diagnostics from every template rule: extended selectors (`closest tr`, `find .item`)
+    /// that don't match any element and invalid `js:` values.
+    pub fn template_diagnostics(
+        &self,
+        document_map: &DashMap<String, Rope>,
+        hm: &mut HashMap<String, Vec<Diagnostic>>,
+    ) {
+        for tree in self.template.iter() {
+            let Some(uri) = self.get_uri(*tree.key()) else {
+                continue;
+            };
+            let Some(text) = document_map.get(&uri).map(|rope| rope.to_string()) else {
+                continue;
+            };
+            let Ok(url) = Url::parse(&uri) else {
+                continue;
+            };
+            let diagnostics = hm.entry(uri).or_default();
+            for rule in TEMPLATE_RULES {
+                diagnostics.extend(rule.check(&url, tree.root_node(), &text, None, &self.parsers));
+            }
+        }
+    }
+
+    /// Warn for every `hx-swap-oob` element whose id doesn't exist in other templates.
+    /// Every indexed template gets entry, so old diagnostics are cleared.
+    pub fn oob_diagnostics(&self, hm: &mut HashMap<String, Vec<Diagnostic>>) {
+        for file in self.ids.iter() {
+            let Some(uri) = self.get_uri(*file.key()) else {
+                continue;
+            };
+            let diagnostics = hm.entry(uri).or_default();
+            for element in file.value().iter().filter(|element| element.oob) {
+                if !self.oob_hosts(*file.key(), &element.id).is_empty() {
+                    continue;
+                }
+                diagnostics.push(Diagnostic {
+                    range: Range::new(to_position2(element.range.0), to_position2(element.range.1)),
+                    severity: Some(DiagnosticSeverity::WARNING),
+                    message: format!(
+                        "Out of band swap target #{} doesn't exist in other templates.",
+                        element.id
+                    ),
+                    code: Some(NumberOrString::String(String::from(OOB_TARGET))),
+                    source: Some(String::from("htmx-lsp")),
+                    ..Default::default()
+                });
+            }
+        }
+    }
+
+    /// Warn for ids in `hx-select` and `hx-select-oob` that don't exist in any template.
+    pub fn select_diagnostics(
+        &self,
+        document_map: &DashMap<String, Rope>,
+        hm: &mut HashMap<String, Vec<Diagnostic>>,
+    ) {
+        let ids: Vec<String> = self
+            .ids
+            .iter()
+            .flat_map(|file| {
+                file.value()
+                    .iter()
+                    .map(|element| element.id.clone())
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        let known = ids.iter().map(String::as_str).collect();
+        for tree in self.template.iter() {
+            let Some(uri) = self.get_uri(*tree.key()) else {
+                continue;
+            };
+            let Some(text) = document_map.get(&uri).map(|rope| rope.to_string()) else {
+                continue;
+            };
+            let diagnostics = hm.entry(uri).or_default();
+            for unknown in unknown_select_ids(tree.root_node(), &text, &known) {
+                diagnostics.push(Diagnostic {
+                    range: Range::new(to_position2(unknown.range.0), to_position2(unknown.range.1)),
+                    severity: Some(DiagnosticSeverity::WARNING),
+                    message: format!(
+                        "{} selects #{}, but no template has element with that id.",
+                        unknown.attribute, unknown.id
+                    ),
+                    code: Some(NumberOrString::String(String::from(SELECT_TARGET))),
+                    source: Some(String::from("htmx-lsp")),
+                    ..Default::default()
+                });
+            }
+        }
+    }
+
+    /// Warn for indicator classes that are not defined in stylesheets. Check is
+    /// skipped without stylesheets and when utility framework generates classes.
+    pub fn class_diagnostics(
+        &self,
+        document_map: &DashMap<String, Rope>,
+        hm: &mut HashMap<String, Vec<Diagnostic>>,
+    ) {
+        if self.stylesheets.is_empty()
+            || self
+                .stylesheets
+                .iter()
+                .any(|stylesheet| stylesheet.generated)
+        {
+            return;
+        }
+        let mut known = self.class_names();
+        known.extend(HTMX_CLASSES.map(String::from));
+        for tree in self.template.iter() {
+            let Some(uri) = self.get_uri(*tree.key()) else {
+                continue;
+            };
+            let Some(text) = document_map.get(&uri).map(|rope| rope.to_string()) else {
+                continue;
+            };
+            let diagnostics = hm.entry(uri).or_default();
+            for class in unknown_classes(tree.root_node(), &text, &known) {
+                diagnostics.push(Diagnostic {
+                    range: Range::new(to_position2(class.range.0), to_position2(class.range.1)),
+                    severity: Some(DiagnosticSeverity::WARNING),
+                    message: class.message,
+                    code: Some(NumberOrString::String(String::from(UNKNOWN_CLASS))),
+                    source: Some(String::from("htmx-lsp")),
+                    ..Default::default()
+                });
+            }
+        }
+    }
+
+    /// Locations of elements with same id as oob element, from other templates.
+    pub fn oob_hosts(&self, index: usize, id: &str) -> Vec<Location> {
+        let mut locations = vec![];
+        for file in self.ids.iter() {
+            if file.key() == &index {
+                continue;
+            }
+            let Some(uri) = self
+                .get_uri(*file.key())
+                .and_then(|uri| Url::parse(&uri).ok())
+            else {
+                continue;
+            };
+            for element in file.value().iter().filter(|element| element.id == id) {
+                let range =
+                    Range::new(to_position2(element.range.0), to_position2(element.range.1));
+                locations.push(Location::new(uri.clone(), range));
+            }
+        }
+        locations.sort_by(|a, b| a.uri.as_str().cmp(b.uri.as_str()));
+        locations
+    }
+
+    /// Goto from oob element to pages that contain element with same id.
+    pub fn goto_oob_hosts(&self, params: &GotoDefinitionParams) -> Option<GotoDefinitionResponse> {
+        let uri = normalize_uri(&params.text_document_position_params.text_document.uri);
+        let index = self.get_index(&uri)?;
+        let pos = params.text_document_position_params.position;
+        let point = Point::new(pos.line as usize, pos.character as usize);
+        let ids = self.ids.get(&index)?;
+        let element = ids
+            .iter()
+            .find(|element| element.oob && element.tag.0 <= point && point <= element.tag.1)?;
+        let locations = self.oob_hosts(index, &element.id);
+        drop(ids);
+        if locations.is_empty() {
+            return None;
+        }
+        Some(GotoDefinitionResponse::Array(locations))
+    }
+
+    /// Values used for attribute `name` in whole project, most used first.
+    pub fn used_values(&self, name: &str) -> Vec<(String, usize)> {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for file in self.attribute_values.iter() {
+            for (attribute, value) in file.value() {
+                if attribute == name && !value.trim().is_empty() {
+                    *counts.entry(value.to_string()).or_default() += 1;
+                }
+            }
+        }
+        let mut values: Vec<(String, usize)> = counts.into_iter().collect();
+        values.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        values
+    }
+
+    /// Number of `hx-lsp` references for every tag used in templates.
+    pub fn tag_references(&self) -> HashMap<String, usize> {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for file in self.attribute_values.iter() {
+            for (attribute, value) in file.value() {
+                if attribute == "hx-lsp" {
+                    for tag in value.split_whitespace() {
+                        *counts.entry(tag.to_string()).or_default() += 1;
+                    }
+                }
+            }
+        }
+        counts
+    }
+
+    /// Hint for every tag that is never referenced by template.
+    pub fn unused_tag_diagnostics(&self, hm: &mut HashMap<String, Vec<Diagnostic>>) {
+        let references = self.tag_references();
+        for tags in self.tags.iter() {
+            if references.contains_key(tags.key()) {
+                continue;
+            }
+            for tag in tags.value() {
+                let Some(uri) = self.get_uri(tag.file) else {
+                    continue;
+                };
+                let (start, end) = to_position(tag);
+                hm.entry(uri).or_default().push(Diagnostic {
+                    range: Range::new(start, end),
+                    severity: Some(DiagnosticSeverity::HINT),
+                    message: format!("Tag {} is not used in any template.", tag.name),
+                    code: Some(NumberOrString::String(String::from(UNUSED_TAG))),
+                    source: Some(String::from("htmx-lsp")),
+                    tags: Some(vec![DiagnosticTag::UNNECESSARY]),
+                    ..Default::default()
+                });
             }
         }
-        for i in tags {
-            self.tags.remove(&i);
+    }
+
+    /// Warn for every `hx-lsp` tag without `hx@` comment in backend, JavaScript or
+    /// template. Tag name is in diagnostic data, for "Create tag" quick fix.
+    pub fn unknown_tag_diagnostics(
+        &self,
+        document_map: &DashMap<String, Rope>,
+        hm: &mut HashMap<String, Vec<Diagnostic>>,
+    ) {
+        for template in self.fragment_tree(document_map).templates {
+            let diagnostics = hm.entry(template.uri).or_default();
+            for fragment in template.fragments {
+                if !fragment.handlers.is_empty() {
+                    continue;
+                }
+                diagnostics.push(Diagnostic {
+                    range: fragment.range,
+                    severity: Some(DiagnosticSeverity::WARNING),
+                    message: format!("Tag {} is not defined.", fragment.tag),
+                    code: Some(NumberOrString::String(String::from(UNKNOWN_TAG))),
+                    source: Some(String::from("htmx-lsp")),
+                    data: Some(Value::String(fragment.tag)),
+                    ..Default::default()
+                });
+            }
         }
     }
 
-    /// Errors if tag already exist.
-    pub fn add_tag(&self, tag: Tag) -> Result<(), Tag> {
-        if self.tags.contains_key(&tag.name) {
-            Err(tag)
-        } else {
-            self.tags.insert(String::from(&tag.name), tag);
-            Ok(())
+    /// After each save for backend/javascript, tags are deleted for that file.
+    pub fn delete_tags_by_index(&self, index: usize) {
+        self.tags.retain(|_, tags| {
+            tags.retain(|tag| tag.file != index);
+            !tags.is_empty()
+        });
+        self.tag_scopes.remove(&index);
+    }
+
+    /// Errors if tag already exist. With `multiple_definitions` tag can be defined
+    /// once per language, so backend and javascript can share tag.
+    pub fn add_tag(
+        &self,
+        tag: Tag,
+        lang_type: LangType,
+        multiple_definitions: bool,
+    ) -> Result<(), Tag> {
+        self.tag_scopes.insert(tag.file, lang_type);
+        let mut tags = self.tags.entry(String::from(&tag.name)).or_default();
+        let duplicate = tags.iter().any(|old| {
+            !multiple_definitions
+                || self
+                    .tag_scopes
+                    .get(&old.file)
+                    .is_none_or(|scope| *scope == lang_type)
+        });
+        if duplicate {
+            return Err(tag);
         }
+        tags.push(tag);
+        Ok(())
     }
 
-    /// Get reference to tag. Only used in definition request, deadlock can't happen here.
-    pub fn get_tag<'a>(&'a self, key: &String) -> Option<Ref<'a, std::string::String, Tag>> {
+    /// Get reference to tag definitions. Only used in definition request, deadlock can't happen here.
+    pub fn get_tag<'a>(&'a self, key: &String) -> Option<Ref<'a, std::string::String, Vec<Tag>>> {
         self.tags.get(key)
     }
 
@@ -114,6 +578,13 @@ impl LspFiles {
             Some(index) => Some(index),
             None => {
                 let old = self.current.replace_with(|&mut old| old + 1);
+                let ext = Path::new(&key).extension().and_then(|ext| ext.to_str());
+                if let Some(ext) = ext.filter(|ext| self.parsers.has_backend(ext)) {
+                    self.backend_exts.insert(old, ext.to_string());
+                }
+                if let Some(ext) = ext.filter(|ext| TS_EXTS.contains(ext)) {
+                    self.typescript_exts.insert(old, ext.to_string());
+                }
                 self.indexes.insert(key, old);
                 Some(old)
             }
@@ -128,6 +599,228 @@ impl LspFiles {
         None
     }
 
+    /// Number of indexed files and tags for `htmx/metrics`.
+    pub fn index_size(&self) -> IndexSize {
+        IndexSize {
+            files: self.indexes.len(),
+            templates: self.template.len(),
+            tags: self.tags.iter().map(|tags| tags.len()).sum(),
+        }
+    }
+
+    /// Attribute values in every indexed template that match `params`.
+    pub fn find_values(
+        &self,
+        params: &FindValueParams,
+        document_map: &DashMap<String, Rope>,
+    ) -> Vec<ValueMatch> {
+        let mut matches = vec![];
+        for tree in self.template.iter() {
+            let Some(uri) = self.get_uri(*tree.key()) else {
+                continue;
+            };
+            let Some(text) = document_map.get(&uri).map(|text| text.to_string()) else {
+                continue;
+            };
+            matches.extend(find_values(&uri, tree.root_node(), &text, params));
+        }
+        sort_matches(&mut matches);
+        matches
+    }
+
+    /// Templates with their `hx-lsp` tags and handlers where these tags are defined.
+    pub fn fragment_tree(&self, document_map: &DashMap<String, Rope>) -> FragmentTree {
+        let mut templates = vec![];
+        for tree in self.template.iter() {
+            let Some(uri) = self.get_uri(*tree.key()) else {
+                continue;
+            };
+            let Some(text) = document_map.get(&uri).map(|text| text.to_string()) else {
+                continue;
+            };
+            templates.push((uri, hx_lsp_tags(tree.root_node(), &text)));
+        }
+        let mut handlers: BTreeMap<String, Vec<Handler>> = BTreeMap::new();
+        for tags in self.tags.iter() {
+            let mut definitions: Vec<Handler> = tags
+                .value()
+                .iter()
+                .filter_map(|tag| {
+                    let (start, end) = to_position(tag);
+                    Some(Handler {
+                        uri: self.get_uri(tag.file)?,
+                        range: Range::new(start, end),
+                        lang_type: self.tag_scopes.get(&tag.file).map(|scope| *scope),
+                    })
+                })
+                .collect();
+            definitions.sort_by(|a, b| (&a.uri, a.range.start).cmp(&(&b.uri, b.range.start)));
+            handlers.insert(tags.key().to_string(), definitions);
+        }
+        fragment_tree(templates, handlers)
+    }
+
+    /// Tag names from `hx-lsp` attributes of all templates, with number of templates
+    /// that use them and whether any backend or JavaScript file defines them.
+    pub fn template_tags(
+        &self,
+        document_map: &DashMap<String, Rope>,
+    ) -> Vec<(String, usize, bool)> {
+        let mut tags: BTreeMap<String, (usize, bool)> = BTreeMap::new();
+        for template in self.fragment_tree(document_map).templates {
+            let mut names = BTreeSet::new();
+            for fragment in template.fragments {
+                let defined = !fragment.handlers.is_empty();
+                if names.insert(fragment.tag.clone()) {
+                    tags.entry(fragment.tag).or_insert((0, defined)).0 += 1;
+                }
+            }
+        }
+        tags.into_iter()
+            .map(|(name, (count, defined))| (name, count, defined))
+            .collect()
+    }
+
+    /// Checks if cursor at `point` is in comment of backend or JavaScript tree. Document that
+    /// is not indexed is parsed from `text`.
+    pub fn in_comment(&self, uri: &String, lang_type: LangType, text: &str, point: Point) -> bool {
+        let Some(index) = self
+            .get_index(uri)
+            .or_else(|| self.add_file(uri.to_string()))
+        else {
+            return false;
+        };
+        if self.get_tree(lang_type, index).is_none() {
+            self.add_tree(index, lang_type, text, None);
+        }
+        let Some(tree) = self.get_tree(lang_type, index) else {
+            return false;
+        };
+        // Character before cursor, line comment can end at cursor.
+        let point = Point::new(point.row, point.column.saturating_sub(1));
+        let node = tree.root_node().descendant_for_point_range(point, point);
+        node.is_some_and(|node| node.kind().contains("comment"))
+    }
+
+    /// Checks if character before cursor is in text content of template, outside of
+    /// any tag. Only stored tree is used, template without tree is never plain text.
+    pub fn in_plain_text(&self, uri: &String, point: Point) -> bool {
+        let Some(column) = point.column.checked_sub(1) else {
+            return false;
+        };
+        let Some(tree) = self
+            .get_index(uri)
+            .and_then(|index| self.get_tree(LangType::Template, index))
+        else {
+            return false;
+        };
+        let point = Point::new(point.row, column);
+        let root = tree.root_node();
+        let node = root.descendant_for_point_range(point, point);
+        node.is_some_and(|node| node.kind() == "text") && !error_near(root, point)
+    }
+
+    /// Code lenses with htmx verb statistics of template.
+    pub fn code_lens(
+        &self,
+        uri: &String,
+        document_map: &DashMap<String, Rope>,
+    ) -> Option<Vec<CodeLens>> {
+        let index = self.get_index(uri)?;
+        let tree = self.get_tree(LangType::Template, index)?;
+        let text = document_map.get(uri)?.to_string();
+        Some(verb_lenses(&verb_stats(tree.root_node(), &text)))
+    }
+
+    /// Include/extends directives of every template with resolved targets.
+    pub fn include_graph(&self, document_map: &DashMap<String, Rope>) -> IncludeGraph {
+        let uris: Vec<String> = self
+            .template
+            .iter()
+            .filter_map(|tree| self.get_uri(*tree.key()))
+            .collect();
+        let mut templates = vec![];
+        for uri in &uris {
+            let Some(includes) = self.template_includes(uri, document_map, &uris) else {
+                continue;
+            };
+            templates.push(TemplateIncludes {
+                uri: uri.to_string(),
+                includes,
+            });
+        }
+        templates.sort_by(|a, b| a.uri.cmp(&b.uri));
+        IncludeGraph { templates }
+    }
+
+    /// Directives in template with `uri`, targets are searched in `templates`.
+    fn template_includes(
+        &self,
+        uri: &String,
+        document_map: &DashMap<String, Rope>,
+        templates: &[String],
+    ) -> Option<Vec<Include>> {
+        let index = self.get_index(uri)?;
+        let text = document_map.get(uri)?.to_string();
+        let tree = self.get_tree(LangType::Template, index)?;
+        let mut includes = template_includes(tree.root_node(), &text);
+        for include in includes.iter_mut() {
+            let templates = templates.iter().map(String::as_str);
+            include.target = resolve_include(&include.name, uri, templates);
+        }
+        Some(includes)
+    }
+
+    /// From template name in include/extends directive to that template.
+    pub fn goto_include(
+        &self,
+        uri: &String,
+        position: Position,
+        document_map: &DashMap<String, Rope>,
+    ) -> Option<GotoDefinitionResponse> {
+        let templates: Vec<String> = self
+            .template
+            .iter()
+            .filter_map(|tree| self.get_uri(*tree.key()))
+            .collect();
+        let include = self
+            .template_includes(uri, document_map, &templates)?
+            .into_iter()
+            .find(|include| include.range.start <= position && position <= include.range.end)?;
+        let location = Location::new(Url::parse(&include.target?).ok()?, Range::default());
+        Some(GotoDefinitionResponse::Scalar(location))
+    }
+
+    /// SSE event names sent from indexed backend files.
+    pub fn sse_events(&self, document_map: &DashMap<String, Rope>) -> Vec<String> {
+        let mut events: Vec<String> = self
+            .backend
+            .iter()
+            .filter_map(|tree| self.get_uri(*tree.key()))
+            .filter_map(|uri| document_map.get(&uri).map(|rope| rope.to_string()))
+            .flat_map(|text| sse_event_names(&text))
+            .collect();
+        events.sort();
+        events.dedup();
+        events
+    }
+
+    /// Extensions enabled with `hx-ext` in document or in any indexed template.
+    pub fn extensions(&self, uri: &str, document_map: &DashMap<String, Rope>) -> Vec<String> {
+        let mut extensions = vec![];
+        let text = document_map.get(uri).map(|rope| rope.to_string());
+        let tree = self
+            .get_index(&uri.to_string())
+            .and_then(|index| self.get_tree(LangType::Template, index));
+        if let (Some(text), Some(tree)) = (text, tree) {
+            extensions = document_extensions(tree.root_node(), &text);
+        }
+        for (value, _) in self.used_values("hx-ext") {
+            extensions.extend(hx_ext_names(&value).map(String::from));
+        }
+        extensions
+    }
+
     /// Get file path for this index.
     pub fn get_uri(&self, index: usize) -> Option<String> {
         self.indexes.iter().find_map(|item| {
@@ -158,6 +851,7 @@ impl LspFiles {
                     range: Range::new(position.0, position.1),
                     severity: Some(DiagnosticSeverity::WARNING),
                     message: String::from("This tag already exist."),
+                    code: Some(NumberOrString::String(String::from(DUPLICATE_TAG))),
                     source: Some(String::from("htmx-lsp")),
                     ..Default::default()
                 };
@@ -182,18 +876,14 @@ impl LspFiles {
         query: &HTMLQueries,
     ) -> Option<PositionType> {
         let response = None;
-        let file = params
-            .text_document_position_params
-            .text_document
-            .uri
-            .to_string();
-        let ext = config.read().is_ok_and(|config| {
-            if !config.is_valid {
-                return false;
-            }
-            let ext = config.file_ext(Path::new(&file));
-            ext.is_some_and(|lang_types| lang_types.is_lang(LangType::Template))
-        });
+        let file = normalize_uri(&params.text_document_position_params.text_document.uri);
+        let ext = {
+            let config = config.recover_read(State::Config);
+            config.enabled(Feature::Definition)
+                && config
+                    .file_ext(Path::new(&file))
+                    .is_some_and(|lang_types| lang_types.is_lang(LangType::Template))
+        };
         if !ext {
             return None;
         }
@@ -221,90 +911,424 @@ impl LspFiles {
         response
     }
 
-    /// Prepare response for goto definition request.
+    /// Goto element with id from selector(`hx-target="#results"`). Elements from
+    /// same template are preferred, otherwise all indexed templates are searched.
+    pub fn goto_id(
+        &self,
+        uri: &String,
+        value: &str,
+        document_map: &DashMap<String, Rope>,
+    ) -> Option<GotoDefinitionResponse> {
+        let id = selector_id(value)?;
+        let index = self.get_index(uri)?;
+        let location = |uri: &str, element: &ElementId| {
+            let range = Range::new(to_position2(element.range.0), to_position2(element.range.1));
+            Some(Location::new(Url::parse(uri).ok()?, range))
+        };
+        let text = document_map.get(uri)?.to_string();
+        // Other files are searched for JavaScript file or when there is no local id.
+        let mut locations: Vec<Location> = match self.get_tree(LangType::Template, index) {
+            Some(tree) => element_ids(tree.root_node(), &text)
+                .iter()
+                .filter(|element| element.id == id)
+                .filter_map(|element| location(uri, element))
+                .collect(),
+            None => vec![],
+        };
+        if locations.is_empty() {
+            for file in self.ids.iter().filter(|file| file.key() != &index) {
+                let Some(other) = self.get_uri(*file.key()) else {
+                    continue;
+                };
+                locations.extend(
+                    file.value()
+                        .iter()
+                        .filter(|element| element.id == id)
+                        .filter_map(|element| location(&other, element)),
+                );
+            }
+            locations.sort_by(|a, b| a.uri.as_str().cmp(b.uri.as_str()));
+        }
+        definition_response(locations)
+    }
+
+    /// Prepare response for goto definition request. With `link` response has links
+    /// from tag in `hx-lsp` value.
     pub fn goto_definition_response(
         &self,
         definition: Option<PositionDefinition>,
         value: &str,
+        link: bool,
         def: &mut Option<GotoDefinitionResponse>,
     ) -> Option<()> {
         let tag = in_tags(value, definition?)?;
-        let tag = self.get_tag(&tag.name)?;
-        let file = self.get_uri(tag.file)?;
-        let (start, end) = to_position(&tag);
-        let range = Range::new(start, end);
-        *def = Some(GotoDefinitionResponse::Scalar(Location {
-            uri: Url::parse(&file).unwrap(),
-            range,
-        }));
+        *def = match link {
+            true => {
+                let (start, end) = to_position(&tag);
+                let links = self.tag_links(&tag.name, Range::new(start, end));
+                (!links.is_empty()).then_some(GotoDefinitionResponse::Link(links))
+            }
+            false => self.tag_definition(&tag.name),
+        };
         None
     }
 
-    /// Search and insert every tag, collect errors.
-    #[allow(clippy::result_unit_err)]
-    pub fn add_tags_from_file(
-        &self,
-        index: usize,
-        lang_type: LangType,
-        text: &str,
-        _overwrite: bool,
-        queries: &Queries,
-        diags: &mut Vec<Tag>,
-    ) -> Result<(), ()> {
-        let query = HtmxQuery::try_from(lang_type)?;
-        let query = queries.get(query);
-        if let Some(old_tree) = self.get_tree(lang_type, index) {
-            let tags = query_tag(
-                old_tree.root_node(),
-                text,
-                Point::new(0, 0),
-                &QueryType::Completion,
-                query,
-                true,
-            );
-            self.delete_tags_by_index(index);
-            for mut tag in tags {
-                tag.file = index;
-                if let Err(tag) = self.add_tag(tag) {
-                    diags.push(tag);
-                }
-            }
-            drop(old_tree);
+    /// Definitions of tag as links from `origin`. Target range covers tag comment
+    /// and its item, tag name in comment is selected.
+    fn tag_links(&self, name: &str, origin: Range) -> Vec<LocationLink> {
+        let Some(tags) = self.get_tag(&name.to_string()) else {
+            return vec![];
+        };
+        let mut links = vec![];
+        for tag in tags.iter() {
+            let Some(uri) = self.get_uri(tag.file).and_then(|uri| Url::parse(&uri).ok()) else {
+                continue;
+            };
+            let (start, end) = to_position(tag);
+            let target_range = match &tag.item {
+                Some(item) => Range::new(
+                    to_position2(item.start.min(tag.start)),
+                    to_position2(item.end.max(tag.end)),
+                ),
+                None => Range::new(start, end),
+            };
+            links.push(LocationLink {
+                origin_selection_range: Some(origin),
+                target_uri: uri,
+                target_range,
+                target_selection_range: Range::new(start, end),
+            });
         }
-        Ok(())
+        drop(tags);
+        links.sort_by(|a, b| {
+            (a.target_uri.as_str(), a.target_range.start)
+                .cmp(&(b.target_uri.as_str(), b.target_range.start))
+        });
+        links
     }
 
-    /// Called after didSave request. Returns tag errors.
-    pub fn saved(
+    /// Locations of every definition of tag.
+    pub fn tag_definition(&self, name: &str) -> Option<GotoDefinitionResponse> {
+        definition_response(self.tag_definitions(name))
+    }
+
+    /// Locations of tag definitions, sorted by uri and position.
+    fn tag_definitions(&self, name: &str) -> Vec<Location> {
+        let Some(tags) = self.get_tag(&name.to_string()) else {
+            return vec![];
+        };
+        let mut locations = vec![];
+        for tag in tags.iter() {
+            let Some(file) = self.get_uri(tag.file) else {
+                continue;
+            };
+            let Ok(uri) = Url::parse(&file) else {
+                continue;
+            };
+            // Signature of item up to its name, otherwise tag in comment.
+            let (start, end) = match &tag.item {
+                Some(item) => (to_position2(item.start), to_position2(item.name_end)),
+                None => to_position(tag),
+            };
+            locations.push(Location {
+                uri,
+                range: Range::new(start, end),
+            });
+        }
+        drop(tags);
+        locations
+            .sort_by(|a, b| (a.uri.as_str(), a.range.start).cmp(&(b.uri.as_str(), b.range.start)));
+        locations
+    }
+
+    /// Definition of string literal in `htmx.ajax` call from JavaScript file: element
+    /// with id for selector, same string literal in backend files for path
+    /// (route declaration), otherwise tag with that name.
+    pub fn goto_ajax(
         &self,
         uri: &String,
-        diagnostics: &mut Vec<Tag>,
-        config: &RwLock<HtmxConfig>,
+        point: Point,
         document_map: &DashMap<String, Rope>,
-        queries: &Arc<Mutex<Queries>>,
-    ) -> Option<Vec<Tag>> {
-        let path = Path::new(&uri);
-        let file = self.get_index(uri)?;
-        let mut lang_type = LangType::Template;
-        if let Ok(config) = config.read() {
-            let lang_types = config.file_ext(path)?;
-            lang_type = lang_types.get();
-            if lang_type == LangType::Template {
-                return None;
+    ) -> Option<GotoDefinitionResponse> {
+        let index = self.get_index(uri)?;
+        let text = document_map.get(uri)?.to_string();
+        let tree = self.get_tree(LangType::JavaScript, index)?;
+        let literal = ajax_literal(tree.root_node(), &text, point)?;
+        drop(tree);
+        if literal.value.starts_with('/') {
+            let path = literal.value.split(['?', '#']).next().unwrap_or_default();
+            return definition_response(self.route_locations(path, document_map));
+        }
+        if literal.value.contains('#') {
+            return self.goto_id(uri, &literal.value, document_map);
+        }
+        self.tag_definition(&literal.value)
+    }
+
+    /// String literals with `path` in backend files, sorted by uri.
+    fn route_locations(&self, path: &str, document_map: &DashMap<String, Rope>) -> Vec<Location> {
+        let mut locations = vec![];
+        for tree in self.backend.iter() {
+            let Some(uri) = self.get_uri(*tree.key()) else {
+                continue;
+            };
+            let (Some(text), Ok(url)) = (document_map.get(&uri), Url::parse(&uri)) else {
+                continue;
+            };
+            let text = text.to_string();
+            for literal in string_literals(tree.root_node(), &text, path) {
+                let range = Range::new(
+                    to_position2(literal.start_position()),
+                    to_position2(literal.end_position()),
+                );
+                locations.push(Location::new(url.clone(), range));
             }
         }
+        locations
+            .sort_by(|a, b| (a.uri.as_str(), a.range.start).cmp(&(b.uri.as_str(), b.range.start)));
+        locations
+    }
+
+    /// Type of handler that tag belongs to: return type of handler function,
+    /// otherwise type of its parameter (route struct), or struct with tag comment.
+    /// Only types defined in backend files are resolved.
+    pub fn tag_type_definition(
+        &self,
+        name: &String,
+        queries: &Queries,
+        document_map: &DashMap<String, Rope>,
+    ) -> Option<GotoDefinitionResponse> {
+        let tags = self.get_tag(name)?.clone();
+        for tag in tags {
+            let Some(uri) = self.get_uri(tag.file) else {
+                continue;
+            };
+            let Some(tree) = self.get_tree(LangType::Backend, tag.file) else {
+                continue;
+            };
+            let Some(text) = document_map.get(&uri).map(|rope| rope.to_string()) else {
+                continue;
+            };
+            let Ok(query) = self.tag_query(queries, LangType::Backend, tag.file) else {
+                continue;
+            };
+            let Some(item) = query_tag_item(tree.root_node(), &text, query, tag.start.row) else {
+                continue;
+            };
+            let mut names = vec![];
+            match item.kind() {
+                "function_item" => {
+                    for field in ["return_type", "parameters"] {
+                        if let Some(node) = item.child_by_field_name(field) {
+                            type_identifiers(node, &text, &mut names);
+                        }
+                    }
+                }
+                _ => {
+                    let range = item.child_by_field_name("name").unwrap_or(item);
+                    let range = Range::new(
+                        to_position2(range.start_position()),
+                        to_position2(range.end_position()),
+                    );
+                    let uri = Url::parse(&uri).ok()?;
+                    return Some(GotoDefinitionResponse::Scalar(Location::new(uri, range)));
+                }
+            }
+            drop(tree);
+            for name in names {
+                if let Some(location) = self.type_location(&name, document_map) {
+                    return Some(GotoDefinitionResponse::Scalar(location));
+                }
+            }
+        }
+        None
+    }
+
+    /// Struct, enum or type alias with `name` from backend files.
+    fn type_location(&self, name: &str, document_map: &DashMap<String, Rope>) -> Option<Location> {
+        let mut locations = vec![];
+        for tree in self.backend.iter() {
+            let Some(uri) = self.get_uri(*tree.key()) else {
+                continue;
+            };
+            let Some(text) = document_map.get(&uri).map(|rope| rope.to_string()) else {
+                continue;
+            };
+            let mut stack = vec![tree.root_node()];
+            while let Some(node) = stack.pop() {
+                let mut cursor = node.walk();
+                stack.extend(node.named_children(&mut cursor));
+                if !matches!(node.kind(), "struct_item" | "enum_item" | "type_item") {
+                    continue;
+                }
+                let Some(ident) = node.child_by_field_name("name") else {
+                    continue;
+                };
+                if ident.utf8_text(text.as_bytes()) == Ok(name) {
+                    let range = Range::new(
+                        to_position2(ident.start_position()),
+                        to_position2(ident.end_position()),
+                    );
+                    if let Ok(uri) = Url::parse(&uri) {
+                        locations.push(Location::new(uri, range));
+                    }
+                }
+            }
+        }
+        locations
+            .sort_by(|a, b| (a.uri.as_str(), a.range.start).cmp(&(b.uri.as_str(), b.range.start)));
+        locations.into_iter().next()
+    }
+
+    /// Markdown hover for tag in `hx-lsp` value, with item and file of every definition.
+    pub fn tag_hover(&self, value: &str, definition: Option<PositionDefinition>) -> Option<String> {
+        let tag = in_tags(value, definition?)?;
+        let tags = self.get_tag(&tag.name)?;
+        let mut lines = vec![];
+        for tag in tags.iter() {
+            let uri = self.get_uri(tag.file)?;
+            let file = uri.rsplit('/').next().unwrap_or(&uri);
+            let line = tag.start.row + 1;
+            lines.push(match &tag.item {
+                Some(item) => format!(
+                    "Tag `{}` is defined on handler `{}` (`{file}:{line}`).",
+                    tag.name, item.name
+                ),
+                None => format!("Tag `{}` is defined in `{file}:{line}`.", tag.name),
+            });
+        }
+        Some(lines.join("\n\n"))
+    }
+
+    /// Search and insert every tag, collect errors.
+    #[allow(clippy::result_unit_err)]
+    pub fn add_tags_from_file(
+        &self,
+        index: usize,
+        lang_type: LangType,
+        text: &str,
+        multiple_definitions: bool,
+        queries: &Queries,
+        diags: &mut Vec<Tag>,
+    ) -> Result<(), ()> {
+        let query = self.tag_query(queries, lang_type, index)?;
+        // Old tags are always removed, even if file lost all of them.
+        self.delete_tags_by_index(index);
+        self.insert_tags(index, lang_type, text, query, multiple_definitions, diags);
+        Ok(())
+    }
+
+    /// Same as `add_tags_from_file` for every language of file. Old tags are removed
+    /// only once, so file that is both backend and template keeps tags of both.
+    pub fn add_tags_from_langs(
+        &self,
+        index: usize,
+        lang_types: &LangTypes,
+        text: &str,
+        multiple_definitions: bool,
+        queries: &Queries,
+        diags: &mut Vec<Tag>,
+    ) {
+        self.delete_tags_by_index(index);
+        for lang_type in lang_types.langs() {
+            if let Ok(query) = self.tag_query(queries, lang_type, index) {
+                self.insert_tags(index, lang_type, text, query, multiple_definitions, diags);
+            }
+        }
+    }
+
+    fn insert_tags(
+        &self,
+        index: usize,
+        lang_type: LangType,
+        text: &str,
+        query: &Query,
+        multiple_definitions: bool,
+        diags: &mut Vec<Tag>,
+    ) {
+        if let Some(old_tree) = self.get_tree(lang_type, index) {
+            let tags = query_tag(
+                old_tree.root_node(),
+                text,
+                Point::new(0, 0),
+                &QueryType::Completion,
+                query,
+                true,
+            );
+            for mut tag in tags {
+                tag.file = index;
+                if let Err(tag) = self.add_tag(tag, lang_type, multiple_definitions) {
+                    diags.push(tag);
+                }
+            }
+            drop(old_tree);
+        }
+    }
+
+    /// File changed by workspace edit is parsed again from `document_map` and its
+    /// tags are indexed. File that isn't indexed yet is added.
+    pub fn reindex_file(
+        &self,
+        uri: &String,
+        diagnostics: &mut Vec<Tag>,
+        config: &RwLock<HtmxConfig>,
+        document_map: &DashMap<String, Rope>,
+        queries: &Arc<Mutex<Queries>>,
+    ) -> Option<Vec<Tag>> {
+        let index = self.add_file(uri.to_string())?;
+        let lang_types = config
+            .recover_read(State::Config)
+            .file_ext(Path::new(uri))?;
+        let text = document_map.get(uri)?.to_string();
+        for lang_type in lang_types.langs() {
+            self.add_tree(index, lang_type, &text, None);
+        }
+        self.saved(uri, diagnostics, config, document_map, queries)
+    }
+
+    /// Called after didSave request, every language of file runs its own save hook.
+    /// Returns tag errors.
+    pub fn saved(
+        &self,
+        uri: &String,
+        diagnostics: &mut Vec<Tag>,
+        config: &RwLock<HtmxConfig>,
+        document_map: &DashMap<String, Rope>,
+        queries: &Arc<Mutex<Queries>>,
+    ) -> Option<Vec<Tag>> {
+        let path = Path::new(&uri);
+        let file = self.get_index(uri)?;
+        let (lang_types, multiple_definitions) = {
+            let config = config.recover_read(State::Config);
+            (config.file_ext(path)?, config.multiple_definitions)
+        };
         let content = document_map.get(uri)?;
-        let content = content.value();
-        let mut a = FileWriter::default();
-        let _ = content.write_to(&mut a);
-        let content = a.content;
-        queries.lock().ok().and_then(|queries| {
-            self.add_tags_from_file(file, lang_type, &content, false, &queries, diagnostics)
-                .ok()
-        });
+        let mut w = FileWriter::default();
+        let _ = content.value().write_to(&mut w);
+        drop(content);
+        let queries = queries.recover(State::Queries);
+        if lang_types.is_lang(LangType::Template) {
+            self.template_saved(file, &w.content, &queries);
+        }
+        self.add_tags_from_langs(
+            file,
+            &lang_types,
+            &w.content,
+            multiple_definitions,
+            &queries,
+            diagnostics,
+        );
         Some(diagnostics.to_vec())
     }
 
+    /// Template is parsed again from saved text (incremental edits can't leave
+    /// stale tree behind) and its attribute values and ids are indexed again.
+    /// Validation runs when diagnostics are published.
+    fn template_saved(&self, index: usize, text: &str, queries: &Queries) {
+        self.add_tree(index, LangType::Template, text, None);
+        self.index_template(index, text, &queries.html);
+    }
+
     /// Can be called from backend/javascript file.
     pub fn references(
         &self,
@@ -314,15 +1338,14 @@ impl LspFiles {
         lang_type: LangType,
     ) -> Option<Vec<Location>> {
         let mut locations = None;
-        let uri = String::from(&params.text_document_position.text_document.uri.to_string());
+        let uri = normalize_uri(&params.text_document_position.text_document.uri);
         let point = Point::new(
             params.text_document_position.position.line as usize,
             params.text_document_position.position.character as usize,
         );
         let index = self.get_index(&uri)?;
         let tree = self.get_tree(lang_type, index)?;
-        if let Ok(c) = HtmxQuery::try_from(lang_type) {
-            let query = queries.get(c);
+        if let Ok(query) = self.tag_query(queries, lang_type, index) {
             let content = document_map.get(&uri)?;
             let mut w = FileWriter::default();
             let _ = content.value().write_to(&mut w);
@@ -336,39 +1359,53 @@ impl LspFiles {
                 false,
             );
             let tag = tags.first()?;
-            let mut references = vec![];
-            for tree in self.template.iter() {
-                let file = self.get_uri(*tree.key())?;
-                let mut w = FileWriter::default();
-                let content = document_map.get(&file)?;
-                let _ = content.value().write_to(&mut w);
-                query_htmx_lsp(
-                    tree.root_node(),
-                    &w.content,
-                    Point::new(0, 0),
-                    &QueryType::Hover,
-                    queries.html.get(HTMLQuery::Lsp),
-                    &tag.name,
-                    &mut references,
-                    *tree.key(),
-                );
-            }
-            references.sort();
-            let mut response = vec![];
-            for i in &references {
-                let index = self.get_uri(i.file)?;
-                let (start, mut end) = to_position(i);
-                end.character += 1;
-                let range = Range::new(start, end);
-                let location = Location::new(Url::parse(&index).unwrap(), range);
-                response.push(location);
-            }
+            let response = self.tag_locations(&tag.name, queries, document_map)?;
             locations = Some(response);
         }
         locations
     }
 
-    /// Goto first hx-lsp attribute.
+    /// Every `hx-lsp` token with tag `name` in templates, ranges cover only the token.
+    pub fn tag_locations(
+        &self,
+        name: &str,
+        queries: &Queries,
+        document_map: &DashMap<String, Rope>,
+    ) -> Option<Vec<Location>> {
+        let mut references = vec![];
+        for tree in self.template.iter() {
+            let file = self.get_uri(*tree.key())?;
+            let mut w = FileWriter::default();
+            let content = document_map.get(&file)?;
+            let _ = content.value().write_to(&mut w);
+            query_htmx_lsp(
+                tree.root_node(),
+                &w.content,
+                Point::new(0, 0),
+                &QueryType::Hover,
+                queries.html.get(HTMLQuery::Lsp),
+                name,
+                &mut references,
+                *tree.key(),
+            );
+        }
+        references.sort();
+        let mut response = vec![];
+        for i in &references {
+            let Some(uri) = self.get_uri(i.file).and_then(|uri| Url::parse(&uri).ok()) else {
+                continue;
+            };
+            let (start, end) = to_position(i);
+            response.push(Location::new(uri, Range::new(start, end)));
+        }
+        response
+            .sort_by(|a, b| (a.uri.as_str(), a.range.start).cmp(&(b.uri.as_str(), b.range.start)));
+        Some(response)
+    }
+
+    /// Goto implementation from tag in `hx-lsp` value returns every definition of tag,
+    /// handlers and template fragments with same `hx@` comment. Elsewhere in element it goes to
+    /// first hx-lsp attribute.
     pub fn goto_implementation(
         &self,
         params: GotoImplementationParams,
@@ -379,30 +1416,29 @@ impl LspFiles {
         if !lang_type.is_lang(LangType::Template) {
             return None;
         }
-        let uri = String::from(
-            &params
-                .text_document_position_params
-                .text_document
-                .uri
-                .to_string(),
-        );
+        let uri = normalize_uri(&params.text_document_position_params.text_document.uri);
         let point = Point::new(
             params.text_document_position_params.position.line as usize,
             params.text_document_position_params.position.character as usize,
         );
         let index = self.get_index(&uri)?;
         let tree = self.get_tree(LangType::Template, index)?;
+        let text = document_map.get(&uri)?.to_string();
+        // Cursor right after name still belongs to it.
+        let tag = hx_lsp_tags(tree.root_node(), &text)
+            .into_iter()
+            .find(|tag| tag.start <= point && point <= tag.end);
+        if let Some(tag) = tag {
+            drop(tree);
+            return definition_response(self.tag_definitions(&tag.name));
+        }
         let query = queries.html.get(HTMLQuery::Lsp);
-        let content = document_map.get(&uri)?;
-        let mut w = FileWriter::default();
-        let _ = content.value().write_to(&mut w);
-        drop(content);
         let uri = params
             .text_document_position_params
             .text_document
             .uri
             .clone();
-        let capture = find_hx_lsp(tree.root_node(), w.content, point, query)?;
+        let capture = find_hx_lsp(tree.root_node(), text, point, query)?;
         let start = Position {
             line: capture.start_position.row as u32,
             character: capture.start_position.column as u32,
@@ -423,14 +1459,14 @@ impl LspFiles {
         query: &HTMLQueries,
         document_map: &DashMap<String, Rope>,
     ) -> Option<()> {
-        let uri = String::from(&params.text_document.uri.to_string());
-        let ext = config.read().is_ok_and(|config| {
-            if !config.is_valid {
-                return false;
-            }
-            let ext = config.file_ext(Path::new(&uri));
-            ext.is_some_and(|lang_types| lang_types.is_lang(LangType::Template))
-        });
+        let uri = normalize_uri(&params.text_document.uri);
+        let ext = {
+            let config = config.recover_read(State::Config);
+            config.enabled(Feature::CodeActions)
+                && config
+                    .file_ext(Path::new(&uri))
+                    .is_some_and(|lang_types| lang_types.is_lang(LangType::Template))
+        };
         if !ext {
             return None;
         }
@@ -470,6 +1506,258 @@ impl LspFiles {
         }
     }
 
+    /// "Create tag" quick fix for every `unknown-tag` diagnostic in `params`. Comment is
+    /// added below handler of nearest tag in same template (`tag_stub: "handler"`), or on
+    /// top of `backend_stub`. Command of action indexes changed file after edit.
+    pub fn create_tag_actions(
+        &self,
+        params: &CodeActionParams,
+        config: &HtmxConfig,
+        document_map: &DashMap<String, Rope>,
+        create_file: bool,
+    ) -> Vec<CodeAction> {
+        let uri = normalize_uri(&params.text_document.uri);
+        let code = NumberOrString::String(String::from(UNKNOWN_TAG));
+        let mut actions = vec![];
+        for diagnostic in &params.context.diagnostics {
+            if diagnostic.code.as_ref() != Some(&code) {
+                continue;
+            }
+            let Some(name) = diagnostic.data.as_ref().and_then(Value::as_str) else {
+                continue;
+            };
+            let handler = match config.tag_stub {
+                TagStub::Handler => {
+                    self.nearest_handler(&uri, diagnostic.range.start, document_map)
+                }
+                TagStub::Top => None,
+            };
+            let edit = match handler {
+                Some(handler) => {
+                    let comment = config.tag_comment(name, Path::new(&handler.uri));
+                    handler_tag_edit(&handler, &comment, document_map)
+                }
+                None => {
+                    let stub = config.backend_stub.as_deref().unwrap_or_default();
+                    let comment = config.tag_comment(name, Path::new(stub));
+                    stub_tag_edit(config, &comment, create_file)
+                }
+            };
+            let Some((target, operations)) = edit else {
+                continue;
+            };
+            let file = target
+                .path_segments()
+                .and_then(|mut segments| segments.next_back());
+            actions.push(CodeAction {
+                title: format!("Create tag `{name}` in {}", file.unwrap_or_default()),
+                kind: Some(CodeActionKind::QUICKFIX),
+                diagnostics: Some(vec![diagnostic.clone()]),
+                edit: Some(WorkspaceEdit {
+                    document_changes: Some(DocumentChanges::Operations(operations)),
+                    ..Default::default()
+                }),
+                command: Some(Command {
+                    title: String::from("Index file"),
+                    command: String::from("htmx.indexFile"),
+                    arguments: Some(vec![Value::String(target.to_string())]),
+                }),
+                is_preferred: Some(true),
+                ..Default::default()
+            });
+        }
+        actions
+    }
+
+    /// Backend handler of tag in template `uri` that is closest to `position`.
+    fn nearest_handler(
+        &self,
+        uri: &str,
+        position: Position,
+        document_map: &DashMap<String, Rope>,
+    ) -> Option<Handler> {
+        let template = self
+            .fragment_tree(document_map)
+            .templates
+            .into_iter()
+            .find(|template| template.uri == uri)?;
+        template
+            .fragments
+            .into_iter()
+            .flat_map(|fragment| {
+                let line = fragment.range.start.line;
+                fragment
+                    .handlers
+                    .into_iter()
+                    .map(move |handler| (line, handler))
+            })
+            .filter(|(_, handler)| handler.lang_type == Some(LangType::Backend))
+            .min_by(|(a_line, a), (b_line, b)| {
+                (a_line.abs_diff(position.line), &a.uri, a.range.start).cmp(&(
+                    b_line.abs_diff(position.line),
+                    &b.uri,
+                    b.range.start,
+                ))
+            })
+            .map(|(_, handler)| handler)
+    }
+
+    /// Markdown report for `htmx.analyzeBoost` command. Returns `None` if document
+    /// has no `hx-boost="true"` element.
+    pub fn analyze_boost(&self, uri: &str, document_map: &DashMap<String, Rope>) -> Option<String> {
+        let text = document_map.get(uri)?.to_string();
+        let index = match self.get_index(&String::from(uri)) {
+            Some(index) => index,
+            None => self.add_file(String::from(uri))?,
+        };
+        if self.get_tree(LangType::Template, index).is_none() {
+            self.add_tree(index, LangType::Template, &text, None);
+        }
+        let tree = self.get_tree(LangType::Template, index)?;
+        let elements = boosted_elements(tree.root_node(), &text);
+        if elements.is_empty() && !text.contains("hx-boost=\"true\"") {
+            return None;
+        }
+        let mut report = format!("# hx-boost\n\n{uri}\n\n");
+        let (boosted, skipped): (Vec<_>, Vec<_>) = elements
+            .iter()
+            .partition(|element| element.skipped.is_none());
+        report.push_str(&format!("## Boosted ({})\n\n", boosted.len()));
+        for element in boosted {
+            report.push_str(&boost_line(element, uri));
+        }
+        report.push_str(&format!("\n## Skipped by htmx ({})\n\n", skipped.len()));
+        for element in skipped {
+            report.push_str(&boost_line(element, uri));
+        }
+        Some(report)
+    }
+
+    /// Element in selected range of template for "Extract htmx fragment" code
+    /// action. Action isn't offered without selection.
+    pub fn fragment_at(
+        &self,
+        params: &CodeActionParams,
+        document_map: &DashMap<String, Rope>,
+    ) -> Option<Fragment> {
+        let start = params.range.start;
+        let end = params.range.end;
+        if start == end {
+            return None;
+        }
+        let uri = normalize_uri(&params.text_document.uri);
+        let index = self.get_index(&uri)?;
+        let tree = self.get_tree(LangType::Template, index)?;
+        let text = document_map.get(&uri)?.to_string();
+        let element = find_element(
+            tree.root_node(),
+            Point::new(start.line as usize, start.character as usize),
+            Point::new(end.line as usize, end.character as usize),
+        )?;
+        let name = element_id(element, &text)
+            .unwrap_or_else(|| format!("fragment_{}", element.start_position().row + 1))
+            .replace(|c: char| !c.is_alphanumeric(), "_");
+        Some(Fragment {
+            name,
+            text: element.utf8_text(text.as_bytes()).ok()?.to_string(),
+            range: Range::new(
+                to_position2(element.start_position()),
+                to_position2(element.end_position()),
+            ),
+        })
+    }
+
+    /// Source action that sorts htmx attributes and removes repeated ones in whole template.
+    pub fn organize_attributes(
+        &self,
+        uri: &Url,
+        document_map: &DashMap<String, Rope>,
+    ) -> Option<CodeAction> {
+        let key = normalize_uri(uri);
+        let index = self.get_index(&key)?;
+        let tree = self.get_tree(LangType::Template, index)?;
+        let text = document_map.get(&key)?.to_string();
+        let edits: Vec<TextEdit> = organize_attributes(tree.root_node(), &text)
+            .into_iter()
+            .map(|edit| {
+                let range = Range::new(to_position2(edit.range.0), to_position2(edit.range.1));
+                TextEdit::new(range, edit.text)
+            })
+            .collect();
+        if edits.is_empty() {
+            return None;
+        }
+        Some(CodeAction {
+            title: String::from("Organize htmx attributes"),
+            kind: Some(CodeActionKind::new(ORGANIZE_HTMX_ATTRIBUTES)),
+            edit: Some(WorkspaceEdit {
+                changes: Some(HashMap::from([(uri.clone(), edits)])),
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+    }
+
+    /// Called before save. Returns edits that insert missing quotes for htmx attributes.
+    pub fn missing_quotes(
+        &self,
+        uri: &String,
+        document_map: &DashMap<String, Rope>,
+        query: &HTMLQueries,
+    ) -> Option<Vec<TextEdit>> {
+        let index = self.get_index(uri)?;
+        let tree = self.get_tree(LangType::Template, index)?;
+        let content = document_map.get(uri)?;
+        let mut w = FileWriter::default();
+        let _ = content.value().write_to(&mut w);
+        drop(content);
+        let points = query_missing_quotes(
+            tree.root_node(),
+            &w.content,
+            query.get(HTMLQuery::MissingQuotes),
+        );
+        if points.is_empty() {
+            return None;
+        }
+        let edits = points
+            .into_iter()
+            .map(|point| {
+                let position = to_position2(point);
+                TextEdit::new(Range::new(position, position), String::from("\"\""))
+            })
+            .collect();
+        Some(edits)
+    }
+
+    /// Checks all templates. Returns two values: are htmx attributes used and
+    /// is htmx script included.
+    pub fn htmx_usage(
+        &self,
+        document_map: &DashMap<String, Rope>,
+        query: &HTMLQueries,
+    ) -> (bool, bool) {
+        let mut used = false;
+        let mut loaded = false;
+        for tree in self.template.iter() {
+            let Some(file) = self.get_uri(*tree.key()) else {
+                continue;
+            };
+            let Some(content) = document_map.get(&file) else {
+                continue;
+            };
+            let mut w = FileWriter::default();
+            let _ = content.value().write_to(&mut w);
+            drop(content);
+            let root = tree.root_node();
+            used = used || query_has_match(root, &w.content, query.get(HTMLQuery::Usage));
+            loaded = loaded || query_has_match(root, &w.content, query.get(HTMLQuery::Script));
+            if loaded {
+                break;
+            }
+        }
+        (used, loaded)
+    }
+
     pub fn query_position(
         &self,
         index: usize,
@@ -505,6 +1793,54 @@ impl LspFiles {
         }
     }
 
+    /// Backend file in directory with own `lang` is parsed with grammar of that language.
+    fn parse(
+        &self,
+        lang_type: LangType,
+        index: usize,
+        text: &str,
+        old_tree: Option<&Tree>,
+    ) -> Option<Tree> {
+        if lang_type == LangType::Backend {
+            if let Some(ext) = self.backend_exts.get(&index) {
+                return self.parsers.parse_backend(&ext, text, old_tree);
+            }
+        }
+        if lang_type == LangType::JavaScript {
+            if let Some(ext) = self.typescript_exts.get(&index) {
+                return self.parsers.parse_typescript(&ext, text, old_tree);
+            }
+        }
+        self.parsers.parse(lang_type, text, old_tree)
+    }
+
+    /// Tag query for file, same as `parse`, backend file in directory with own `lang`
+    /// has query of that language and TypeScript file has TypeScript query.
+    #[allow(clippy::result_unit_err)]
+    pub fn tag_query<'a>(
+        &self,
+        queries: &'a Queries,
+        lang_type: LangType,
+        index: usize,
+    ) -> Result<&'a Query, ()> {
+        let query = queries.get(HtmxQuery::try_from(lang_type)?);
+        if lang_type == LangType::JavaScript {
+            return Ok(self
+                .typescript_exts
+                .get(&index)
+                .and_then(|ext| queries.typescript.get(ext.as_str()))
+                .unwrap_or(query));
+        }
+        if lang_type != LangType::Backend {
+            return Ok(query);
+        }
+        Ok(self
+            .backend_exts
+            .get(&index)
+            .and_then(|ext| queries.backends.get(ext.as_str()))
+            .unwrap_or(query))
+    }
+
     pub fn add_tree(
         &self,
         index: usize,
@@ -512,23 +1848,23 @@ impl LspFiles {
         text: &str,
         _range: Option<Range>,
     ) -> Option<()> {
-        self.parsers
-            .lock()
-            .ok()
-            .and_then(|mut parsers| -> Option<()> {
-                if let Some(old_tree) = self.get_mut_tree(lang_type, index) {
-                    if let Some(tree) = parsers.parse(lang_type, text, Some(&old_tree)) {
-                        drop(old_tree);
-                        self.insert_tree(lang_type, index, tree);
-                    }
-                } else {
-                    // tree doesn't exist, first insertion
-                    if let Some(tree) = parsers.parse(lang_type, text, None) {
-                        self.insert_tree(lang_type, index, tree);
-                    }
-                }
-                None
-            })
+        if self.too_large(text) {
+            self.remove_tree(lang_type, index);
+            return None;
+        }
+        // Old tree can't be reused here, it has no information about edits.
+        let tree = self.parse(lang_type, index, text, None)?;
+        self.insert_tree(lang_type, index, tree);
+        None
+    }
+
+    pub fn remove_tree(&self, lang_type: LangType, index: usize) -> Option<Tree> {
+        let trees = match lang_type {
+            LangType::Template => &self.template,
+            LangType::JavaScript => &self.javascript,
+            LangType::Backend => &self.backend,
+        };
+        trees.remove(&index).map(|(_, tree)| tree)
     }
 
     pub fn insert_tree(&self, lang_type: LangType, index: usize, tree: Tree) -> Option<Tree> {
@@ -548,77 +1884,1149 @@ impl LspFiles {
         lang_type: LangType,
     ) -> Option<()> {
         let file = self.get_index(file)?;
+        if self.too_large(&code) {
+            self.remove_tree(lang_type, file);
+            return None;
+        }
         let mut old_tree = self.get_mut_tree(lang_type, file)?;
-        let _ = self.parsers.lock().ok().and_then(|mut parsers| {
-            old_tree.edit(&input_edit);
-            let tree = parsers.parse(lang_type, &code, Some(&old_tree))?;
-            drop(old_tree);
-            self.insert_tree(lang_type, file, tree)
-        });
+        old_tree.edit(&input_edit);
+        let tree = self.parse(lang_type, file, &code, Some(&old_tree))?;
+        drop(old_tree);
+        self.insert_tree(lang_type, file, tree);
         None
     }
 }
 
-/// Parsers for HTML, JavaScript and backend language(Python, Rust, Go).
+/// Parse errors in one file.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ParseStats {
+    pub uri: String,
+    pub lang_type: LangType,
+    /// Number of ERROR and MISSING nodes.
+    pub errors: usize,
+    pub lines: usize,
+}
+
+impl ParseStats {
+    /// Warning is logged when file has more than one error per this number of lines.
+    pub const LINES_PER_ERROR: usize = 20;
+
+    pub fn is_degraded(&self) -> bool {
+        self.errors > 0 && self.errors * Self::LINES_PER_ERROR > self.lines
+    }
+}
+
+/// Names of types in Rust type or parameter list, in source order.
+fn type_identifiers(node: tree_sitter::Node<'_>, text: &str, names: &mut Vec<String>) {
+    if node.kind() == "type_identifier" {
+        if let Ok(name) = node.utf8_text(text.as_bytes()) {
+            names.push(name.to_string());
+        }
+    }
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        type_identifiers(child, text, names);
+    }
+}
+
+/// Insert tag comment on line below handler, with same indentation.
+fn handler_tag_edit(
+    handler: &Handler,
+    comment: &str,
+    document_map: &DashMap<String, Rope>,
+) -> Option<(Url, Vec<DocumentChangeOperation>)> {
+    let uri = Url::parse(&handler.uri).ok()?;
+    let line = handler.range.start.line as usize;
+    let text = match document_map.get(&handler.uri) {
+        Some(rope) => rope.get_line(line)?.to_string(),
+        None => std::fs::read_to_string(uri.to_file_path().ok()?)
+            .ok()?
+            .lines()
+            .nth(line)?
+            .to_string(),
+    };
+    let indent = &text[..text.len() - text.trim_start().len()];
+    let position = Position::new(line as u32 + 1, 0);
+    let edit = insert_edit(uri.clone(), position, format!("{indent}{comment}\n"));
+    Some((uri, vec![edit]))
+}
+
+/// Insert tag comment on top of `backend_stub`, missing file is created first.
+fn stub_tag_edit(
+    config: &HtmxConfig,
+    comment: &str,
+    create_file: bool,
+) -> Option<(Url, Vec<DocumentChangeOperation>)> {
+    let stub = config.backend_stub.as_ref()?;
+    let mut operations = vec![];
+    let path = match canonicalize(stub) {
+        Some(path) => path,
+        None if create_file => {
+            let path = Path::new(stub);
+            let parent = match path.parent().and_then(Path::to_str) {
+                Some("") | None => ".",
+                Some(parent) => parent,
+            };
+            let path = canonicalize(parent)?.join(path.file_name()?);
+            operations.push(DocumentChangeOperation::Op(ResourceOp::Create(
+                CreateFile {
+                    uri: Url::from_file_path(&path).ok()?,
+                    options: None,
+                    annotation_id: None,
+                },
+            )));
+            path
+        }
+        None => return None,
+    };
+    let uri = Url::from_file_path(&path).ok()?;
+    operations.push(insert_edit(
+        uri.clone(),
+        Position::new(0, 0),
+        format!("{comment}\n"),
+    ));
+    Some((uri, operations))
+}
+
+fn insert_edit(uri: Url, position: Position, text: String) -> DocumentChangeOperation {
+    DocumentChangeOperation::Edit(TextDocumentEdit {
+        text_document: OptionalVersionedTextDocumentIdentifier { uri, version: None },
+        edits: vec![OneOf::Left(TextEdit::new(
+            Range::new(position, position),
+            text,
+        ))],
+    })
+}
+
+/// Diagnostics of one file by position, then by code and message.
+pub fn sort_diagnostics(diagnostics: &mut [Diagnostic]) {
+    diagnostics.sort_by(|a, b| {
+        let code = |diagnostic: &Diagnostic| match &diagnostic.code {
+            Some(NumberOrString::String(code)) => code.clone(),
+            Some(NumberOrString::Number(code)) => code.to_string(),
+            None => String::new(),
+        };
+        (a.range.start, a.range.end, code(a), &a.message).cmp(&(
+            b.range.start,
+            b.range.end,
+            code(b),
+            &b.message,
+        ))
+    });
+}
+
+/// One location as scalar response, more locations as array.
+fn definition_response(mut locations: Vec<Location>) -> Option<GotoDefinitionResponse> {
+    match locations.len() {
+        0 => None,
+        1 => locations.pop().map(GotoDefinitionResponse::Scalar),
+        _ => Some(GotoDefinitionResponse::Array(locations)),
+    }
+}
+
+/// One line in hx-boost report, with link to element.
+fn boost_line(element: &BoostedElement, uri: &str) -> String {
+    let line = element.point.row + 1;
+    let url = element.url.as_deref().unwrap_or("-");
+    let method = &element.method;
+    match element.skipped {
+        Some(reason) => format!(
+            "* [`<{}>` line {line}]({uri}#L{line}) `{method} {url}` - {reason}\n",
+            element.tag
+        ),
+        None => format!(
+            "* [`<{}>` line {line}]({uri}#L{line}) `{method} {url}`\n",
+            element.tag
+        ),
+    }
+}
+
+/// Reusable parsers for one language.
+///
+/// Parser is taken from pool for every parse and returned after it, so concurrent
+/// requests don't wait for each other and no parser is configured twice.
+pub struct ParserPool {
+    inner: Mutex<PoolInner>,
+}
+
+struct PoolInner {
+    language: Language,
+    /// Increased on every language change, parsers from older generation are dropped.
+    generation: usize,
+    parsers: Vec<Parser>,
+    /// Number of parsers configured by this pool.
+    created: usize,
+    /// Grammar was built for other tree-sitter version, nothing is parsed.
+    error: Option<String>,
+}
+
+impl ParserPool {
+    pub fn new(language: Language) -> Self {
+        Self {
+            inner: Mutex::new(PoolInner {
+                language,
+                generation: 0,
+                parsers: vec![],
+                created: 0,
+                error: language_error(language),
+            }),
+        }
+    }
+
+    pub fn parse(&self, text: &str, old_tree: Option<&Tree>) -> Option<Tree> {
+        let (mut parser, generation) = {
+            let mut inner = self.inner.recover(State::Parsers);
+            if inner.error.is_some() {
+                return None;
+            }
+            let parser = match inner.parsers.pop() {
+                Some(parser) => parser,
+                None => {
+                    let mut parser = Parser::new();
+                    parser.set_language(inner.language).ok()?;
+                    inner.created += 1;
+                    parser
+                }
+            };
+            (parser, inner.generation)
+        };
+        let tree = parser.parse(text, old_tree);
+        let mut inner = self.inner.recover(State::Parsers);
+        if inner.generation == generation {
+            inner.parsers.push(parser);
+        }
+        tree
+    }
+
+    /// Parsers created so far, it stays at one without concurrent parsing.
+    pub fn created(&self) -> usize {
+        self.inner.recover(State::Parsers).created
+    }
+
+    pub fn set_language(&self, language: Language) {
+        let mut inner = self.inner.recover(State::Parsers);
+        inner.language = language;
+        inner.generation += 1;
+        inner.parsers.clear();
+        inner.error = language_error(language);
+    }
+
+    /// Why grammar can't be used, requests for its documents fail with this message.
+    pub fn error(&self) -> Option<String> {
+        self.inner.recover(State::Parsers).error.clone()
+    }
+
+    /// Number of idle parsers.
+    pub fn idle(&self) -> usize {
+        self.inner.recover(State::Parsers).parsers.len()
+    }
+}
+
+/// Grammar with incompatible ABI version is logged once, when it's set.
+fn language_error(language: Language) -> Option<String> {
+    let err = Parser::new().set_language(language).err()?;
+    let msg = format!("Can't use tree-sitter grammar: {err}");
+    log::error!("{msg}");
+    Some(msg)
+}
+
+/// Parser pools for HTML, JavaScript, TypeScript and backend language(Python, Rust, Go).
 pub struct Parsers {
-    html: Parser,
-    javascript: Parser,
-    backend: Parser,
+    html: ParserPool,
+    javascript: ParserPool,
+    /// TypeScript and TSX grammars, by file extension.
+    typescript: HashMap<&'static str, ParserPool>,
+    backend: ParserPool,
+    /// Grammars of `backend_tags` directories with own `lang`, by file extension.
+    backends: RwLock<HashMap<String, ParserPool>>,
 }
 
 impl Parsers {
     /// Get new tree after parsing.
-    pub fn parse(
-        &mut self,
-        lang_type: LangType,
-        text: &str,
-        old_tree: Option<&Tree>,
-    ) -> Option<Tree> {
+    pub fn parse(&self, lang_type: LangType, text: &str, old_tree: Option<&Tree>) -> Option<Tree> {
+        let start = METRICS.start();
+        let tree = self.pool(lang_type).parse(text, old_tree);
+        METRICS.record(METRICS.parse(lang_type), start);
+        tree
+    }
+
+    pub fn pool(&self, lang_type: LangType) -> &ParserPool {
         match lang_type {
-            LangType::Template => self.html.parse(text, old_tree),
-            LangType::JavaScript => self.javascript.parse(text, old_tree),
-            LangType::Backend => self.backend.parse(text, old_tree),
+            LangType::Template => &self.html,
+            LangType::JavaScript => &self.javascript,
+            LangType::Backend => &self.backend,
         }
     }
 
+    /// Backend file with extension of directory language is parsed with its grammar.
+    pub fn parse_backend(&self, ext: &str, text: &str, old_tree: Option<&Tree>) -> Option<Tree> {
+        let start = METRICS.start();
+        let tree = self
+            .backends
+            .recover_read(State::Parsers)
+            .get(ext)?
+            .parse(text, old_tree);
+        METRICS.record(METRICS.parse(LangType::Backend), start);
+        tree
+    }
+
+    /// TypeScript file is parsed with grammar of its extension (`TS_EXTS`).
+    pub fn parse_typescript(&self, ext: &str, text: &str, old_tree: Option<&Tree>) -> Option<Tree> {
+        let start = METRICS.start();
+        let tree = self.typescript.get(ext)?.parse(text, old_tree);
+        METRICS.record(METRICS.parse(LangType::JavaScript), start);
+        tree
+    }
+
     /// Change backend based on `lang_type` and `language`. It's called once, at reading config.
-    pub fn change_backend(&mut self, language: &str, lang_type: LangType) -> Option<()> {
+    pub fn change_backend(&self, language: &str, lang_type: LangType) -> Option<()> {
         if lang_type != LangType::Backend {
             return None;
         }
-        let language = match language {
-            "python" => Some(tree_sitter_python::language()),
-            "go" => Some(tree_sitter_go::language()),
-            _ => None,
-        };
-        let mut backend = Parser::new();
-        let _ = backend.set_language(language?);
-        self.backend = backend;
+        self.backend.set_language(backend_language(language)?);
         None
     }
+
+    /// Grammars for languages of `backend_tags` directories, `langs` are language
+    /// names with file extension.
+    pub fn set_backends(&self, langs: &[(&str, &str)]) {
+        let mut backends = self.backends.recover_write(State::Parsers);
+        backends.clear();
+        for (lang, ext) in langs {
+            if let Some(language) = backend_language(lang) {
+                backends.insert(ext.to_string(), ParserPool::new(language));
+            }
+        }
+    }
+
+    /// Grammar of `lang_type` can't be used (see `ParserPool::error`).
+    pub fn error(&self, lang_type: LangType) -> Option<String> {
+        self.pool(lang_type).error()
+    }
+
+    /// Files with `ext` have grammar of their directory.
+    pub fn has_backend(&self, ext: &str) -> bool {
+        self.backends.recover_read(State::Parsers).contains_key(ext)
+    }
+
+    /// Backend grammar loaded from shared library.
+    pub fn set_backend(&self, language: Language) {
+        self.backend.set_language(language);
+    }
 }
 
 impl Default for Parsers {
     fn default() -> Self {
-        let mut html = Parser::new();
-        let _ = html.set_language(tree_sitter_html::language());
-        let mut javascript = Parser::new();
-        let _query_javascript = Query::new(tree_sitter_javascript::language(), HX_JS_TAGS).unwrap();
-        let _ = javascript.set_language(tree_sitter_javascript::language());
-        let mut backend = Parser::new();
-        let _query_backend = Query::new(tree_sitter_rust::language(), HX_RUST_TAGS).unwrap();
-        let _ = backend.set_language(tree_sitter_rust::language());
-
         Self {
-            html,
-            javascript,
-            backend,
+            html: ParserPool::new(tree_sitter_html::language()),
+            javascript: ParserPool::new(tree_sitter_javascript::language()),
+            typescript: TS_EXTS
+                .into_iter()
+                .filter_map(|ext| Some((ext, ParserPool::new(typescript_language(ext)?))))
+                .collect(),
+            backend: ParserPool::new(tree_sitter_rust::language()),
+            backends: RwLock::new(HashMap::new()),
+        }
+    }
+}
+
+/// Bundled TypeScript grammar for file extension.
+pub fn typescript_language(ext: &str) -> Option<Language> {
+    match ext {
+        "ts" => Some(tree_sitter_typescript::language_typescript()),
+        "tsx" => Some(tree_sitter_typescript::language_tsx()),
+        _ => None,
+    }
+}
+
+/// Bundled grammar of backend language.
+pub fn backend_language(lang: &str) -> Option<Language> {
+    match lang {
+        "rust" => Some(tree_sitter_rust::language()),
+        "python" => Some(tree_sitter_python::language()),
+        "go" => Some(tree_sitter_go::language()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex, RwLock};
+
+    use dashmap::DashMap;
+    use ropey::Rope;
+    use tower_lsp::lsp_types::{
+        request::GotoImplementationParams, CodeActionContext, CodeActionParams,
+        DocumentChangeOperation, DocumentChanges, GotoDefinitionResponse, OneOf, Position, Range,
+        TextDocumentIdentifier, TextDocumentPositionParams, TextEdit, Url,
+    };
+    use tree_sitter::Point;
+
+    use crate::{
+        config::{HtmxConfig, TagStub},
+        init_hx::{LangType, LangTypes},
+        position::{
+            get_position_from_lsp_completion, Position as PositionType, PositionDefinition,
+            QueryType,
+        },
+        query_helper::Queries,
+        to_input_edit::ToInputEdit,
+    };
+
+    use super::{LspFiles, Parsers};
+
+    fn index_file(lsp_files: &LspFiles, queries: &Queries, text: &str) -> Vec<String> {
+        let index = lsp_files
+            .add_file(String::from("file:///src/main.rs"))
+            .unwrap();
+        lsp_files.add_tree(index, LangType::Backend, text, None);
+        let mut diags = vec![];
+        let _ = lsp_files.add_tags_from_file(
+            index,
+            LangType::Backend,
+            text,
+            false,
+            queries,
+            &mut diags,
+        );
+        let mut tags: Vec<String> = lsp_files.tags.iter().map(|t| t.key().clone()).collect();
+        tags.sort();
+        tags
+    }
+
+    #[test]
+    fn used_values_by_frequency() {
+        let lsp_files = LspFiles::default();
+        let queries = Queries::default();
+        let templates = [
+            r##"<tr hx-target="closest tr"></tr><div hx-target="#results"></div>"##,
+            r##"<div hx-target="#results" hx-swap="outerHTML"></div>"##,
+        ];
+        for (i, text) in templates.iter().enumerate() {
+            let index = lsp_files.add_file(format!("file:///{i}.html")).unwrap();
+            lsp_files.add_tree(index, LangType::Template, text, None);
+            lsp_files.index_template(index, text, &queries.html);
         }
+        assert_eq!(
+            lsp_files.used_values("hx-target"),
+            vec![
+                (String::from("#results"), 2),
+                (String::from("closest tr"), 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn space_in_text_content() {
+        let lsp_files = LspFiles::default();
+        let text = "<p>hello world</p>\n<div hx-get=\"/a\" hx-swap=\"innerHTML \">\n<span ";
+        let uri = String::from("file:///space.html");
+        let index = lsp_files.add_file(uri.clone()).unwrap();
+        lsp_files.add_tree(index, LangType::Template, text, None);
+        assert!(lsp_files.in_plain_text(&uri, Point::new(0, 9)));
+        assert!(!lsp_files.in_plain_text(&uri, Point::new(1, 17)));
+        assert!(!lsp_files.in_plain_text(&uri, Point::new(1, 37)));
+        assert!(!lsp_files.in_plain_text(&uri, Point::new(2, 6)));
+        assert!(!lsp_files.in_plain_text(&uri, Point::new(0, 0)));
+        assert!(!lsp_files.in_plain_text(&String::from("file:///other.html"), Point::new(0, 9)));
     }
+
+    #[test]
+    fn rust_doc_block_comment_and_attribute_tags() {
+        let lsp_files = LspFiles::default();
+        let queries = Queries::default();
+        let text = r#"/// Shows cart, hx@cart
+async fn cart() {}
+/*
+ * hx@rows
+ */
+struct Rows;
+#[htmx_tag("users")]
+#[debug_handler]
+async fn users() {}
+fn body() {
+    /* hx@body */
 }
+#[derive(Debug)]
+enum Plain {}
+"#;
+        let tags = index_file(&lsp_files, &queries, text);
+        assert_eq!(tags, vec!["body", "cart", "rows", "users"]);
+        let tag = |name: &str| {
+            let tags = lsp_files.tags.get(name).unwrap();
+            let tag = &tags[0];
+            let item = tag.item.as_ref().map(|item| item.name.clone());
+            (tag.start, tag.end, item)
+        };
+        let item = |name: &str| Some(String::from(name));
+        assert_eq!(
+            tag("cart"),
+            (Point::new(0, 19), Point::new(0, 23), item("cart"))
+        );
+        assert_eq!(
+            tag("rows"),
+            (Point::new(3, 6), Point::new(3, 10), item("Rows"))
+        );
+        assert_eq!(
+            tag("users"),
+            (Point::new(6, 12), Point::new(6, 17), item("users"))
+        );
+        assert_eq!(
+            tag("body"),
+            (Point::new(10, 10), Point::new(10, 14), item("body"))
+        );
+    }
 
-impl Clone for Parsers {
-    fn clone(&self) -> Self {
-        Self::default()
+    #[test]
+    fn tags_in_template_comments() {
+        let lsp_files = LspFiles::default();
+        let queries = Queries::default();
+        let text = "<!-- hx@cart-row -->\n<tr></tr>\n<!--\n  hx@cart-->\n<!--hx@total-->\n<!-- hx@ -->\n<!-- rowhx@no -->";
+        let index = lsp_files
+            .add_file(String::from("file:///cart.html"))
+            .unwrap();
+        lsp_files.add_tree(index, LangType::Template, text, None);
+        let mut diags = vec![];
+        let _ = lsp_files.add_tags_from_file(
+            index,
+            LangType::Template,
+            text,
+            false,
+            &queries,
+            &mut diags,
+        );
+        let mut tags: Vec<(String, Point, Point)> = lsp_files
+            .tags
+            .iter()
+            .flat_map(|tags| tags.value().clone())
+            .map(|tag| (tag.name, tag.start, tag.end))
+            .collect();
+        tags.sort_by_key(|tag| tag.1);
+        assert_eq!(
+            tags,
+            vec![
+                (
+                    String::from("cart-row"),
+                    Point::new(0, 8),
+                    Point::new(0, 16)
+                ),
+                (String::from("cart"), Point::new(3, 5), Point::new(3, 9)),
+                (String::from("total"), Point::new(4, 7), Point::new(4, 12)),
+            ]
+        );
+    }
+
+    #[test]
+    fn saved_template_is_indexed_again() {
+        let lsp_files = LspFiles::default();
+        let queries = Arc::new(Mutex::new(Queries::default()));
+        let config = RwLock::new(HtmxConfig {
+            lang: String::from("rust"),
+            template_ext: String::from("html"),
+            is_valid: true,
+            ..Default::default()
+        });
+        let uri = String::from("file:///templates/index.html");
+        let document_map = DashMap::new();
+        document_map.insert(
+            uri.clone(),
+            Rope::from_str(r##"<div hx-target="#a"></div>"##),
+        );
+        let index = lsp_files.add_file(uri.clone()).unwrap();
+        lsp_files.add_tree(index, LangType::Template, "", None);
+
+        let saved = lsp_files.saved(&uri, &mut vec![], &config, &document_map, &queries);
+        assert_eq!(saved, Some(vec![]));
+        assert_eq!(
+            lsp_files.used_values("hx-target"),
+            vec![(String::from("#a"), 1)]
+        );
+        let tree = lsp_files.get_tree(LangType::Template, index).unwrap();
+        assert_eq!(tree.root_node().end_byte(), 26);
+    }
+
+    #[test]
+    fn deleted_tags_are_removed() {
+        let lsp_files = LspFiles::default();
+        let queries = Queries::default();
+        let text = "fn a() {\n    // hx@first\n}\nfn b() {\n    // hx@second\n}\n";
+        assert_eq!(
+            index_file(&lsp_files, &queries, text),
+            vec!["first", "second"]
+        );
+
+        let text = "fn a() {\n}\nfn b() {\n    // hx@second\n}\n";
+        assert_eq!(index_file(&lsp_files, &queries, text), vec!["second"]);
+
+        let text = "fn a() {}\n";
+        assert!(index_file(&lsp_files, &queries, text).is_empty());
+
+        assert!(index_file(&lsp_files, &queries, "").is_empty());
+    }
+
+    #[test]
+    fn tag_type_definitions() {
+        let lsp_files = LspFiles::default();
+        let queries = Queries::default();
+        let text = r#"struct Page;
+struct UserRoute { id: u32 }
+// hx@page
+#[debug_handler]
+async fn page() -> Result<Page, Error> {
+}
+async fn user(Path(route): Path<UserRoute>) -> impl IntoResponse {
+    // hx@user
+}
+// hx@route
+struct Other;
+fn other() -> Missing {
+    // hx@missing
+}
+"#;
+        let tags = index_file(&lsp_files, &queries, text);
+        assert_eq!(tags, vec!["missing", "page", "route", "user"]);
+        let document_map = DashMap::new();
+        document_map.insert(String::from("file:///src/main.rs"), Rope::from_str(text));
+        let line = |name: &str| {
+            let response =
+                lsp_files.tag_type_definition(&String::from(name), &queries, &document_map);
+            match response {
+                Some(GotoDefinitionResponse::Scalar(location)) => Some(location.range.start),
+                _ => None,
+            }
+        };
+        assert_eq!(line("page"), Some(Position::new(0, 7)));
+        assert_eq!(line("user"), Some(Position::new(1, 7)));
+        assert_eq!(line("route"), Some(Position::new(10, 7)));
+        assert_eq!(line("missing"), None);
+    }
+
+    #[test]
+    fn tag_definition_and_hover_use_item() {
+        let lsp_files = LspFiles::default();
+        let queries = Queries::default();
+        let text = "// hx@cart\nasync fn get_cart() {}\nfn a() {\n    let b = 1;\n    {\n        // hx@nested\n    }\n}\n";
+        index_file(&lsp_files, &queries, text);
+        let definition = || Some(PositionDefinition::new(Point::new(0, 0), Point::new(0, 1)));
+        assert_eq!(
+            lsp_files.tag_hover("cart", definition()).as_deref(),
+            Some("Tag `cart` is defined on handler `get_cart` (`main.rs:1`).")
+        );
+        let mut def = None;
+        lsp_files.goto_definition_response(definition(), "cart", false, &mut def);
+        let Some(GotoDefinitionResponse::Scalar(location)) = def.take() else {
+            panic!("expected one location");
+        };
+        assert_eq!(
+            location.range,
+            Range::new(Position::new(1, 0), Position::new(1, 17))
+        );
+        lsp_files.goto_definition_response(definition(), "cart", true, &mut def);
+        let Some(GotoDefinitionResponse::Link(links)) = def else {
+            panic!("expected links");
+        };
+        assert_eq!(
+            links[0].origin_selection_range,
+            Some(Range::new(Position::new(0, 0), Position::new(0, 4)))
+        );
+        assert_eq!(
+            links[0].target_range,
+            Range::new(Position::new(0, 6), Position::new(1, 22))
+        );
+        assert_eq!(
+            links[0].target_selection_range,
+            Range::new(Position::new(0, 6), Position::new(0, 10))
+        );
+        assert_eq!(
+            lsp_files.tag_hover("nested", definition()).as_deref(),
+            Some("Tag `nested` is defined in `main.rs:6`.")
+        );
+    }
+
+    #[test]
+    fn create_unknown_tag_below_nearest_handler() {
+        let lsp_files = LspFiles::default();
+        let queries = Queries::default();
+        let document_map = DashMap::new();
+        let backend = "async fn page() {\n    // hx@page\n}\n";
+        index_file(&lsp_files, &queries, backend);
+        document_map.insert(String::from("file:///src/main.rs"), Rope::from_str(backend));
+        let text = "<div hx-lsp=\"page\"></div>\n<div hx-lsp=\"missing\"></div>";
+        let uri = String::from("file:///templates/index.html");
+        let index = lsp_files.add_file(uri.clone()).unwrap();
+        lsp_files.add_tree(index, LangType::Template, text, None);
+        document_map.insert(uri.clone(), Rope::from_str(text));
+
+        let mut hm = HashMap::new();
+        lsp_files.unknown_tag_diagnostics(&document_map, &mut hm);
+        let diagnostics = &hm[&uri];
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message, "Tag missing is not defined.");
+        assert_eq!(
+            diagnostics[0].range,
+            Range::new(Position::new(1, 13), Position::new(1, 20))
+        );
+
+        let params = CodeActionParams {
+            text_document: TextDocumentIdentifier::new(Url::parse(&uri).unwrap()),
+            range: diagnostics[0].range,
+            context: CodeActionContext {
+                diagnostics: diagnostics.clone(),
+                ..Default::default()
+            },
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        };
+        let config = HtmxConfig {
+            lang: String::from("rust"),
+            tag_stub: TagStub::Handler,
+            ..Default::default()
+        };
+        let actions = lsp_files.create_tag_actions(&params, &config, &document_map, false);
+        assert_eq!(actions.len(), 1);
+        assert_eq!(actions[0].title, "Create tag `missing` in main.rs");
+        let Some(DocumentChanges::Operations(operations)) = actions[0]
+            .edit
+            .as_ref()
+            .and_then(|edit| edit.document_changes.as_ref())
+        else {
+            panic!("expected document changes");
+        };
+        let DocumentChangeOperation::Edit(edit) = &operations[0] else {
+            panic!("expected text edit");
+        };
+        assert_eq!(edit.text_document.uri.as_str(), "file:///src/main.rs");
+        assert_eq!(
+            edit.edits,
+            vec![OneOf::Left(TextEdit::new(
+                Range::new(Position::new(2, 0), Position::new(2, 0)),
+                String::from("    // hx@missing\n")
+            ))]
+        );
+
+        // Without handler and backend_stub there is no place for tag.
+        let config = HtmxConfig::default();
+        assert!(lsp_files
+            .create_tag_actions(&params, &config, &document_map, true)
+            .is_empty());
+    }
+
+    #[test]
+    fn tags_in_backend_and_template_comments() {
+        let lsp_files = LspFiles::default();
+        let queries = Queries::default();
+        let document_map = DashMap::new();
+        index_file(&lsp_files, &queries, "// hx@cart\nasync fn get_cart() {}\n");
+        let templates = [
+            "<div hx-lsp=\"cart\"><p></p></div>",
+            "<!-- hx@cart -->\n<table></table>",
+            "<p></p>\n<!-- hx@cart-->",
+        ];
+        let mut duplicates = vec![];
+        for (i, text) in templates.iter().enumerate() {
+            let uri = format!("file:///{i}.html");
+            let index = lsp_files.add_file(uri.clone()).unwrap();
+            lsp_files.add_tree(index, LangType::Template, text, None);
+            // Template can share tag with backend, but not with other template.
+            let _ = lsp_files.add_tags_from_file(
+                index,
+                LangType::Template,
+                text,
+                true,
+                &queries,
+                &mut duplicates,
+            );
+            document_map.insert(uri, Rope::from_str(text));
+        }
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(duplicates[0].start, Point::new(1, 8));
+        let implementation = |character| {
+            let params = GotoImplementationParams {
+                text_document_position_params: TextDocumentPositionParams::new(
+                    TextDocumentIdentifier::new(Url::parse("file:///0.html").unwrap()),
+                    Position::new(0, character),
+                ),
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+            };
+            let lang_types = LangTypes::One(LangType::Template);
+            lsp_files.goto_implementation(params, &queries, &document_map, lang_types)
+        };
+        let Some(GotoDefinitionResponse::Array(locations)) = implementation(14) else {
+            panic!("expected definition and template fragment");
+        };
+        let ranges: Vec<_> = locations
+            .iter()
+            .map(|location| (location.uri.path(), location.range))
+            .collect();
+        assert_eq!(
+            ranges,
+            [
+                (
+                    "/1.html",
+                    Range::new(Position::new(0, 8), Position::new(0, 12))
+                ),
+                (
+                    "/src/main.rs",
+                    Range::new(Position::new(1, 0), Position::new(1, 17))
+                ),
+            ]
+        );
+        // Outside of tag, first hx-lsp attribute of element.
+        let Some(GotoDefinitionResponse::Scalar(location)) = implementation(20) else {
+            panic!("expected hx-lsp attribute");
+        };
+        assert_eq!(location.range.start, Position::new(0, 13));
+    }
+
+    #[test]
+    fn tag_locations_cover_token() {
+        let lsp_files = LspFiles::default();
+        let queries = Queries::default();
+        let document_map = DashMap::new();
+        let templates = [
+            "<p hx-lsp=\"ćevapi  cart\"></p>",
+            "<div hx-lsp=\"cart\">\n<a hx-lsp=\"list cart\"></a></div>",
+        ];
+        for (i, text) in templates.iter().enumerate() {
+            let uri = format!("file:///{i}.html");
+            let index = lsp_files.add_file(uri.clone()).unwrap();
+            lsp_files.add_tree(index, LangType::Template, text, None);
+            document_map.insert(uri, Rope::from_str(text));
+        }
+        let locations = lsp_files
+            .tag_locations("cart", &queries, &document_map)
+            .unwrap();
+        let ranges: Vec<_> = locations
+            .iter()
+            .map(|location| (location.uri.path(), location.range))
+            .collect();
+        assert_eq!(
+            ranges,
+            [
+                (
+                    "/0.html",
+                    Range::new(Position::new(0, 20), Position::new(0, 24))
+                ),
+                (
+                    "/1.html",
+                    Range::new(Position::new(0, 13), Position::new(0, 17))
+                ),
+                (
+                    "/1.html",
+                    Range::new(Position::new(1, 16), Position::new(1, 20))
+                )
+            ]
+        );
+    }
+
+    #[test]
+    fn tag_defined_in_backend_and_javascript() {
+        let queries = Queries::default();
+        let files = [
+            (
+                "file:///src/main.rs",
+                LangType::Backend,
+                "fn a() {\n    // hx@shared\n}\n",
+            ),
+            (
+                "file:///src/app.js",
+                LangType::JavaScript,
+                "function a() {\n  // hx@shared\n}\n",
+            ),
+            (
+                "file:///src/b.rs",
+                LangType::Backend,
+                "fn b() {\n    // hx@shared\n}\n",
+            ),
+        ];
+        for multiple_definitions in [false, true] {
+            let lsp_files = LspFiles::default();
+            let mut diags = vec![];
+            for (uri, lang_type, text) in files {
+                let index = lsp_files.add_file(String::from(uri)).unwrap();
+                lsp_files.add_tree(index, lang_type, text, None);
+                let _ = lsp_files.add_tags_from_file(
+                    index,
+                    lang_type,
+                    text,
+                    multiple_definitions,
+                    &queries,
+                    &mut diags,
+                );
+            }
+            let definitions = lsp_files.get_tag(&String::from("shared")).unwrap().len();
+            match multiple_definitions {
+                false => assert_eq!((definitions, diags.len()), (1, 2)),
+                true => assert_eq!((definitions, diags.len()), (2, 1)),
+            }
+        }
+    }
+
+    #[test]
+    fn ajax_literals_have_definitions() {
+        let lsp_files = LspFiles::default();
+        let queries = Queries::default();
+        let document_map = DashMap::new();
+        let files = [
+            (
+                "file:///src/main.rs",
+                LangType::Backend,
+                "fn users() {\n    // hx@users\n}\nfn app() {\n    route(\"/users\", users);\n}\n",
+            ),
+            (
+                "file:///index.html",
+                LangType::Template,
+                "<ul id=\"list\"></ul>",
+            ),
+            (
+                "file:///app.js",
+                LangType::JavaScript,
+                "htmx.ajax(\"GET\", \"/users?page=2\", \"#list\");\nhtmx.ajax(\"GET\", \"users\");\n",
+            ),
+        ];
+        for (uri, lang_type, text) in files {
+            let index = lsp_files.add_file(String::from(uri)).unwrap();
+            lsp_files.add_tree(index, lang_type, text, None);
+            document_map.insert(String::from(uri), Rope::from_str(text));
+            if lang_type == LangType::Template {
+                lsp_files.index_template(index, text, &queries.html);
+            } else {
+                let _ = lsp_files.add_tags_from_file(
+                    index,
+                    lang_type,
+                    text,
+                    false,
+                    &queries,
+                    &mut vec![],
+                );
+            }
+        }
+        let uri = String::from("file:///app.js");
+        let start =
+            |row, column| match lsp_files.goto_ajax(&uri, Point::new(row, column), &document_map) {
+                Some(GotoDefinitionResponse::Scalar(location)) => {
+                    Some((location.uri.path().to_string(), location.range.start))
+                }
+                _ => None,
+            };
+        assert_eq!(
+            start(0, 21),
+            Some((String::from("/src/main.rs"), Position::new(4, 10)))
+        );
+        assert_eq!(
+            start(0, 38),
+            Some((String::from("/index.html"), Position::new(0, 8)))
+        );
+        assert_eq!(
+            start(1, 20),
+            Some((String::from("/src/main.rs"), Position::new(0, 0)))
+        );
+        assert_eq!(start(0, 12), None);
+    }
+
+    #[test]
+    fn unused_tags_are_hinted() {
+        let lsp_files = LspFiles::default();
+        let queries = Queries::default();
+        let text = "fn a() {\n    // hx@used\n}\nfn b() {\n    // hx@unused\n}\n";
+        index_file(&lsp_files, &queries, text);
+        let template = r#"<div hx-lsp="used other"></div><p hx-lsp="used"></p>"#;
+        let index = lsp_files
+            .add_file(String::from("file:///index.html"))
+            .unwrap();
+        lsp_files.add_tree(index, LangType::Template, template, None);
+        lsp_files.index_template(index, template, &queries.html);
+        assert_eq!(lsp_files.tag_references().get("used"), Some(&2));
+
+        let mut hm = HashMap::new();
+        lsp_files.unused_tag_diagnostics(&mut hm);
+        let diagnostics = &hm["file:///src/main.rs"];
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].range.start, Position::new(4, 10));
+    }
+
+    #[test]
+    fn goto_id_in_same_and_other_templates() {
+        let queries = Queries::default();
+        let lsp_files = LspFiles::default();
+        let document_map = DashMap::new();
+        let templates = [
+            (
+                "file:///a.html",
+                r##"<div hx-target="#list"></div><ul id="list"></ul>"##,
+            ),
+            (
+                "file:///b.html",
+                r##"<div id="list"></div><p id="other"></p>"##,
+            ),
+        ];
+        for (uri, text) in templates {
+            let index = lsp_files.add_file(String::from(uri)).unwrap();
+            lsp_files.add_tree(index, LangType::Template, text, None);
+            lsp_files.index_template(index, text, &queries.html);
+            document_map.insert(String::from(uri), Rope::from_str(text));
+        }
+        let uri = String::from("file:///a.html");
+        let Some(GotoDefinitionResponse::Scalar(location)) =
+            lsp_files.goto_id(&uri, "#list", &document_map)
+        else {
+            panic!("expected one location");
+        };
+        assert_eq!(location.range.start, Position::new(0, 37));
+        let Some(GotoDefinitionResponse::Scalar(location)) =
+            lsp_files.goto_id(&uri, "closest #other", &document_map)
+        else {
+            panic!("expected one location");
+        };
+        assert_eq!(location.uri.as_str(), "file:///b.html");
+        assert!(lsp_files.goto_id(&uri, "#none", &document_map).is_none());
+    }
+
+    #[test]
+    fn parsers_are_reused() {
+        let parsers = Parsers::default();
+        for _ in 0..3 {
+            assert!(parsers
+                .parse(LangType::Template, "<div></div>", None)
+                .is_some());
+        }
+        assert_eq!(parsers.pool(LangType::Template).idle(), 1);
+        parsers.parse(LangType::Backend, "fn main() {}", None);
+        parsers.change_backend("python", LangType::Backend);
+        assert_eq!(parsers.pool(LangType::Backend).idle(), 0);
+        let tree = parsers
+            .parse(LangType::Backend, "def main(): pass", None)
+            .unwrap();
+        assert_eq!(tree.root_node().kind(), "module");
+    }
+
+    #[test]
+    fn completion_uses_edited_tree() {
+        let uri = String::from("file:///templates/index.html");
+        let mut rope = Rope::from_str("<div hx-></div>");
+        let lsp_files = LspFiles::default();
+        let index = lsp_files.add_file(uri.clone()).unwrap();
+        lsp_files.add_tree(index, LangType::Template, &rope.to_string(), None);
+
+        let range = Range::new(Position::new(0, 8), Position::new(0, 8));
+        let input_edit = rope.to_input_edit(range, "swap=\"\"");
+        rope.insert(8, "swap=\"\"");
+        lsp_files.input_edit(&uri, rope.to_string(), input_edit, LangType::Template);
+
+        let document_map = DashMap::new();
+        document_map.insert(uri.clone(), rope);
+        let params = TextDocumentPositionParams::new(
+            TextDocumentIdentifier::new(Url::parse(&uri).unwrap()),
+            Position::new(0, 14),
+        );
+        let position = get_position_from_lsp_completion(
+            &params,
+            &document_map,
+            uri,
+            QueryType::Completion,
+            &Arc::new(Mutex::new(lsp_files)),
+            &Arc::new(Mutex::new(Queries::default())),
+        );
+        assert!(matches!(
+            position,
+            Some(PositionType::AttributeValue { name, .. }) if name == "hx-swap"
+        ));
+    }
+
+    #[test]
+    fn completion_reuses_parser() {
+        let lsp_files = Arc::new(Mutex::new(LspFiles::default()));
+        let queries = Arc::new(Mutex::new(Queries::default()));
+        let document_map = DashMap::new();
+        for i in 0..10 {
+            let uri = format!("file:///templates/{i}.html");
+            document_map.insert(uri.clone(), Rope::from_str("<div hx-swap=\"\"></div>"));
+            let params = TextDocumentPositionParams::new(
+                TextDocumentIdentifier::new(Url::parse(&uri).unwrap()),
+                Position::new(0, 14),
+            );
+            let position = get_position_from_lsp_completion(
+                &params,
+                &document_map,
+                uri,
+                QueryType::Completion,
+                &lsp_files,
+                &queries,
+            );
+            assert!(position.is_some());
+        }
+        let lsp_files = lsp_files.lock().unwrap();
+        assert_eq!(lsp_files.parsers.pool(LangType::Template).created(), 1);
+    }
+
+    #[test]
+    fn typescript_files_have_typescript_grammar() {
+        let lsp_files = LspFiles::default();
+        let queries = Queries::default();
+        let text = "enum Status { Active }\nconst limits = { page: 10 } satisfies Record<string, number>;\n";
+        for (uri, ext) in [
+            ("file:///static/a.ts", Some("ts")),
+            ("file:///static/b.tsx", Some("tsx")),
+            ("file:///static/c.js", None),
+        ] {
+            let index = lsp_files.add_file(String::from(uri)).unwrap();
+            lsp_files.add_tree(index, LangType::JavaScript, text, None);
+            let tree = lsp_files.get_tree(LangType::JavaScript, index).unwrap();
+            // JavaScript grammar doesn't know `enum` and `satisfies`.
+            assert_eq!(tree.root_node().has_error(), ext.is_none());
+            let query = lsp_files
+                .tag_query(&queries, LangType::JavaScript, index)
+                .unwrap();
+            let expected = match ext {
+                Some(ext) => &queries.typescript[ext],
+                None => &queries.javascript,
+            };
+            assert!(std::ptr::eq(query, expected));
+        }
+        assert_eq!(lsp_files.parsers.pool(LangType::JavaScript).created(), 1);
+    }
+
+    #[test]
+    fn large_documents_have_no_tree() {
+        let uri = String::from("file:///templates/large.html");
+        let text = format!("<div hx-swap=\"\"></div>{}", "<p></p>".repeat(10));
+        let lsp_files = LspFiles::default();
+        lsp_files.set_max_file_size(50);
+        let index = lsp_files.add_file(uri.clone()).unwrap();
+        lsp_files.add_tree(index, LangType::Template, "<div></div>", None);
+        assert!(lsp_files.get_tree(LangType::Template, index).is_some());
+        let range = Range::new(Position::new(0, 11), Position::new(0, 11));
+        let input_edit = Rope::from_str("<div></div>").to_input_edit(range, "");
+        lsp_files.input_edit(&uri, text.clone(), input_edit, LangType::Template);
+        assert!(lsp_files.get_tree(LangType::Template, index).is_none());
+
+        let document_map = DashMap::new();
+        document_map.insert(uri.clone(), Rope::from_str(&text));
+        let lsp_files = Arc::new(Mutex::new(lsp_files));
+        let queries = Arc::new(Mutex::new(Queries::default()));
+        let params = TextDocumentPositionParams::new(
+            TextDocumentIdentifier::new(Url::parse(&uri).unwrap()),
+            Position::new(0, 14),
+        );
+        let position = |query_type| {
+            get_position_from_lsp_completion(
+                &params,
+                &document_map,
+                uri.clone(),
+                query_type,
+                &lsp_files,
+                &queries,
+            )
+        };
+        // Completion falls back to scanner, hover needs tree.
+        assert!(matches!(
+            position(QueryType::Completion),
+            Some(PositionType::AttributeValue { name, .. }) if name == "hx-swap"
+        ));
+        assert_eq!(position(QueryType::Hover), None);
+        let lsp_files = lsp_files.lock().unwrap();
+        assert!(lsp_files.get_tree(LangType::Template, index).is_none());
     }
 }
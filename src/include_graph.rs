@@ -0,0 +1,183 @@
+use serde::Serialize;
+use tower_lsp::lsp_types::{Position, Range, Url};
+use tree_sitter::{Node, Point};
+
+/// Template directives that reference other template by name.
+const DIRECTIVES: [(&str, IncludeKind); 5] = [
+    ("include", IncludeKind::Include),
+    ("extends", IncludeKind::Extends),
+    ("import", IncludeKind::Import),
+    ("from", IncludeKind::From),
+    ("embed", IncludeKind::Embed),
+];
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum IncludeKind {
+    Include,
+    Extends,
+    Import,
+    From,
+    Embed,
+}
+
+/// `{% include "name" %}` like directive, `range` is range of name without quotes.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Include {
+    pub kind: IncludeKind,
+    pub name: String,
+    pub range: Range,
+    /// Uri of included template, if it is indexed.
+    pub target: Option<String>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct TemplateIncludes {
+    pub uri: String,
+    pub includes: Vec<Include>,
+}
+
+/// Response for `htmx/includeGraph`, templates sorted by uri.
+#[derive(Debug, Default, Serialize, PartialEq)]
+pub struct IncludeGraph {
+    pub templates: Vec<TemplateIncludes>,
+}
+
+/// Jinja-like directives from text nodes of html tree, in document order.
+/// Targets are not resolved.
+pub fn template_includes(root: Node<'_>, source: &str) -> Vec<Include> {
+    let mut includes = vec![];
+    let mut stack = vec![root];
+    while let Some(node) = stack.pop() {
+        if node.kind() == "text" {
+            if let Ok(text) = node.utf8_text(source.as_bytes()) {
+                includes.extend(directives(text, node.start_position()));
+            }
+            continue;
+        }
+        let mut cursor = node.walk();
+        stack.extend(node.children(&mut cursor));
+    }
+    includes.sort_by_key(|include| include.range.start);
+    includes
+}
+
+/// Directives in text that starts at `start`.
+fn directives(text: &str, start: Point) -> Vec<Include> {
+    let mut includes = vec![];
+    let mut offset = 0;
+    while let Some(open) = text[offset..].find("{%") {
+        offset += open + 2;
+        let rest = &text[offset..];
+        let statement = rest.trim_start_matches(['-', '+']).trim_start();
+        let Some((keyword, kind)) = DIRECTIVES
+            .iter()
+            .find(|(keyword, _)| statement.starts_with(keyword))
+        else {
+            continue;
+        };
+        let argument = &statement[keyword.len()..];
+        let name = argument.trim_start();
+        if name.len() == argument.len() {
+            continue;
+        }
+        let Some(quote) = name.chars().next().filter(|c| matches!(c, '"' | '\'')) else {
+            continue;
+        };
+        let Some(end) = name[1..].find(quote) else {
+            continue;
+        };
+        let name_start = offset + (rest.len() - name.len()) + 1;
+        let name_end = name_start + end;
+        includes.push(Include {
+            kind: *kind,
+            name: text[name_start..name_end].to_string(),
+            range: Range::new(
+                position(text, start, name_start),
+                position(text, start, name_end),
+            ),
+            target: None,
+        });
+    }
+    includes
+}
+
+/// Position of byte `offset` in text that starts at `start`.
+fn position(text: &str, start: Point, offset: usize) -> Position {
+    let before = &text[..offset];
+    match before.rfind('\n') {
+        Some(newline) => Position::new(
+            (start.row + before.matches('\n').count()) as u32,
+            (offset - newline - 1) as u32,
+        ),
+        None => Position::new(start.row as u32, (start.column + offset) as u32),
+    }
+}
+
+/// Uri of template with `name`. Relative names are joined with uri of template
+/// that includes them, other names are searched from root of template directory,
+/// so shortest uri that ends with name is used.
+pub fn resolve_include<'a>(
+    name: &str,
+    from: &str,
+    mut templates: impl Iterator<Item = &'a str>,
+) -> Option<String> {
+    if name.starts_with("./") || name.starts_with("../") {
+        let uri = Url::parse(from).ok()?.join(name).ok()?.to_string();
+        return templates
+            .find(|template| *template == uri)
+            .map(String::from);
+    }
+    let suffix = format!("/{}", name.trim_start_matches('/'));
+    templates
+        .filter(|template| template.ends_with(&suffix))
+        .min_by_key(|template| (template.len(), *template))
+        .map(String::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp::lsp_types::{Position, Range};
+    use tree_sitter::Parser;
+
+    use super::{resolve_include, template_includes, IncludeKind};
+
+    #[test]
+    fn directives_and_targets() {
+        let text = "{% extends \"base.html\" %}\n<div>\n  {%- include 'partials/row.html' with x %}\n  {% from \"../macros.html\" import field %}\n  {% if x %}include \"no.html\"{% endif %}\n</div>";
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_html::language()).unwrap();
+        let tree = parser.parse(text, None).unwrap();
+        let includes = template_includes(tree.root_node(), text);
+        let names: Vec<(IncludeKind, &str)> = includes
+            .iter()
+            .map(|include| (include.kind, include.name.as_str()))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                (IncludeKind::Extends, "base.html"),
+                (IncludeKind::Include, "partials/row.html"),
+                (IncludeKind::From, "../macros.html"),
+            ]
+        );
+        assert_eq!(
+            includes[1].range,
+            Range::new(Position::new(2, 15), Position::new(2, 32))
+        );
+
+        let templates = [
+            "file:///app/templates/base.html",
+            "file:///app/templates/admin/base.html",
+            "file:///app/templates/macros.html",
+            "file:///app/templates/partials/row.html",
+        ];
+        let from = "file:///app/templates/admin/index.html";
+        let resolve = |name| resolve_include(name, from, templates.into_iter());
+        assert_eq!(resolve("base.html").as_deref(), Some(templates[0]));
+        assert_eq!(resolve("admin/base.html").as_deref(), Some(templates[1]));
+        assert_eq!(resolve("../macros.html").as_deref(), Some(templates[2]));
+        assert_eq!(resolve("./row.html"), None);
+        assert_eq!(resolve("missing.html"), None);
+    }
+}
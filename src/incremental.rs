@@ -0,0 +1,521 @@
+use tower_lsp::lsp_types::{
+    CodeDescription, Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, Location,
+    NumberOrString, Position, Range, Url,
+};
+use tree_sitter::{Node, Point};
+
+use crate::{
+    embedded::js_value_errors,
+    extensions::extension_value_errors,
+    htmx_tree_sitter::Parsers,
+    position::{duplicate_attributes, start_tags},
+    selector::unresolved_selectors_where,
+    suppress::{
+        Suppressions, DUPLICATE_ATTRIBUTE, EXTENSION_VALUE, JS_SYNTAX, SWAP_CONFLICT,
+        SWAP_MODIFIER, UNRESOLVED_SELECTOR, URL_INTERPOLATION,
+    },
+    swap::{swap_conflicts, swap_value_errors},
+    to_input_edit::to_position2,
+    url_params::{interpolations, url_value_errors},
+};
+
+/// One change from `didChange`, positions use same units as diagnostics.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Edit {
+    pub start: Position,
+    pub old_end: Position,
+    pub new_end: Position,
+}
+
+impl Edit {
+    pub fn new(range: Range, text: &str) -> Self {
+        let lines = text.matches('\n').count() as u32;
+        let new_end = match text.rfind('\n') {
+            Some(last) => Position::new(range.start.line + lines, (text.len() - last - 1) as u32),
+            None => Position::new(range.start.line, range.start.character + text.len() as u32),
+        };
+        Self {
+            start: range.start,
+            old_end: range.end,
+            new_end,
+        }
+    }
+
+    /// Position after edit, `None` if it was inside of replaced text.
+    pub fn shift(&self, position: Position) -> Option<Position> {
+        if position < self.start {
+            Some(position)
+        } else if position >= self.old_end {
+            if position.line == self.old_end.line {
+                let character =
+                    self.new_end.character + position.character - self.old_end.character;
+                Some(Position::new(self.new_end.line, character))
+            } else {
+                let line = position.line + self.new_end.line - self.old_end.line;
+                Some(Position::new(line, position.character))
+            }
+        } else {
+            None
+        }
+    }
+
+    /// Move diagnostics after edit, ones inside of replaced text are removed.
+    /// Related information is in same document, it's moved too.
+    pub fn shift_diagnostics(&self, diagnostics: &mut Vec<Diagnostic>) {
+        diagnostics.retain_mut(|diagnostic| {
+            let Some(range) = self.shift_range(diagnostic.range) else {
+                return false;
+            };
+            diagnostic.range = range;
+            for related in diagnostic.related_information.iter_mut().flatten() {
+                match self.shift_range(related.location.range) {
+                    Some(range) => related.location.range = range,
+                    None => return false,
+                }
+            }
+            true
+        });
+    }
+
+    fn shift_range(&self, range: Range) -> Option<Range> {
+        Some(Range::new(self.shift(range.start)?, self.shift(range.end)?))
+    }
+}
+
+/// Changed parts of document since last check.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct DirtyRanges(pub Vec<Range>);
+
+impl DirtyRanges {
+    /// Old ranges are moved, new text is added.
+    pub fn edit(&mut self, edit: &Edit) {
+        for range in &mut self.0 {
+            range.start = edit.shift(range.start).unwrap_or(edit.start);
+            range.end = edit.shift(range.end).unwrap_or(edit.new_end);
+        }
+        self.0.push(Range::new(edit.start, edit.new_end));
+    }
+
+    pub fn intersects(&self, range: Range) -> bool {
+        self.0
+            .iter()
+            .any(|dirty| dirty.start <= range.end && range.start <= dirty.end)
+    }
+}
+
+/// Template check that can run only on changed part of document.
+pub trait RangeRule {
+    /// Only diagnostics with this code are replaced by `check`.
+    fn code(&self) -> &'static str;
+    /// Diagnostics for elements in `dirty` ranges, or in whole document. Embedded
+    /// code is parsed with `parsers`.
+    fn check(
+        &self,
+        uri: &Url,
+        root: Node<'_>,
+        source: &str,
+        dirty: Option<&DirtyRanges>,
+        parsers: &Parsers,
+    ) -> Vec<Diagnostic>;
+}
+
+fn keep_dirty(dirty: Option<&DirtyRanges>) -> impl Fn(Point, Point) -> bool + '_ {
+    move |start, end| {
+        dirty.is_none_or(|dirty| {
+            dirty.intersects(Range::new(to_position2(start), to_position2(end)))
+        })
+    }
+}
+
+/// Extended selectors that don't match any element.
+pub struct SelectorRule;
+
+impl RangeRule for SelectorRule {
+    fn code(&self) -> &'static str {
+        UNRESOLVED_SELECTOR
+    }
+
+    fn check(
+        &self,
+        _uri: &Url,
+        root: Node<'_>,
+        source: &str,
+        dirty: Option<&DirtyRanges>,
+        _parsers: &Parsers,
+    ) -> Vec<Diagnostic> {
+        let keep = keep_dirty(dirty);
+        unresolved_selectors_where(root, source, keep)
+            .into_iter()
+            .map(|selector| Diagnostic {
+                range: Range::new(
+                    to_position2(selector.range.0),
+                    to_position2(selector.range.1),
+                ),
+                severity: Some(DiagnosticSeverity::WARNING),
+                message: format!(
+                    "{} \"{}\" doesn't match any element.",
+                    selector.attribute, selector.value
+                ),
+                code: Some(NumberOrString::String(String::from(UNRESOLVED_SELECTOR))),
+                source: Some(String::from("htmx-lsp")),
+                ..Default::default()
+            })
+            .collect()
+    }
+}
+
+/// `js:` values in `hx-vals`, `hx-headers` and `hx-request` that are not valid JavaScript.
+pub struct JsValueRule;
+
+impl RangeRule for JsValueRule {
+    fn code(&self) -> &'static str {
+        JS_SYNTAX
+    }
+
+    fn check(
+        &self,
+        _uri: &Url,
+        root: Node<'_>,
+        source: &str,
+        dirty: Option<&DirtyRanges>,
+        parsers: &Parsers,
+    ) -> Vec<Diagnostic> {
+        let keep = keep_dirty(dirty);
+        js_value_errors(root, source, parsers, keep)
+            .into_iter()
+            .map(|error| Diagnostic {
+                range: Range::new(to_position2(error.range.0), to_position2(error.range.1)),
+                severity: Some(DiagnosticSeverity::ERROR),
+                message: format!("Invalid JavaScript expression in {}.", error.attribute),
+                code: Some(NumberOrString::String(String::from(JS_SYNTAX))),
+                source: Some(String::from("htmx-lsp")),
+                ..Default::default()
+            })
+            .collect()
+    }
+}
+
+/// Unknown `hx-swap` modifier or invalid timing/scroll position.
+pub struct SwapModifierRule;
+
+impl RangeRule for SwapModifierRule {
+    fn code(&self) -> &'static str {
+        SWAP_MODIFIER
+    }
+
+    fn check(
+        &self,
+        _uri: &Url,
+        root: Node<'_>,
+        source: &str,
+        dirty: Option<&DirtyRanges>,
+        _parsers: &Parsers,
+    ) -> Vec<Diagnostic> {
+        let keep = keep_dirty(dirty);
+        swap_value_errors(root, source, keep)
+            .into_iter()
+            .map(|error| Diagnostic {
+                range: Range::new(to_position2(error.range.0), to_position2(error.range.1)),
+                severity: Some(DiagnosticSeverity::ERROR),
+                message: error.message,
+                code: Some(NumberOrString::String(String::from(SWAP_MODIFIER))),
+                source: Some(String::from("htmx-lsp")),
+                ..Default::default()
+            })
+            .collect()
+    }
+}
+
+/// Swap and target combination that does nothing (`hx-swap="delete"` with
+/// `hx-select`), diagnostic links to attribute documentation.
+pub struct SwapConflictRule;
+
+impl RangeRule for SwapConflictRule {
+    fn code(&self) -> &'static str {
+        SWAP_CONFLICT
+    }
+
+    fn check(
+        &self,
+        _uri: &Url,
+        root: Node<'_>,
+        source: &str,
+        dirty: Option<&DirtyRanges>,
+        _parsers: &Parsers,
+    ) -> Vec<Diagnostic> {
+        let keep = keep_dirty(dirty);
+        swap_conflicts(root, source, keep)
+            .into_iter()
+            .map(|conflict| Diagnostic {
+                range: Range::new(
+                    to_position2(conflict.range.0),
+                    to_position2(conflict.range.1),
+                ),
+                severity: Some(DiagnosticSeverity::WARNING),
+                message: conflict.message,
+                code: Some(NumberOrString::String(String::from(SWAP_CONFLICT))),
+                code_description: Url::parse(conflict.docs)
+                    .ok()
+                    .map(|href| CodeDescription { href }),
+                source: Some(String::from("htmx-lsp")),
+                ..Default::default()
+            })
+            .collect()
+    }
+}
+
+/// Invalid value of extension attribute (`ws-connect` with `http://` URL).
+pub struct ExtensionValueRule;
+
+impl RangeRule for ExtensionValueRule {
+    fn code(&self) -> &'static str {
+        EXTENSION_VALUE
+    }
+
+    fn check(
+        &self,
+        _uri: &Url,
+        root: Node<'_>,
+        source: &str,
+        dirty: Option<&DirtyRanges>,
+        _parsers: &Parsers,
+    ) -> Vec<Diagnostic> {
+        let keep = keep_dirty(dirty);
+        extension_value_errors(root, source, keep)
+            .into_iter()
+            .map(|error| Diagnostic {
+                range: Range::new(to_position2(error.range.0), to_position2(error.range.1)),
+                severity: Some(DiagnosticSeverity::ERROR),
+                message: error.message,
+                code: Some(NumberOrString::String(String::from(EXTENSION_VALUE))),
+                source: Some(String::from("htmx-lsp")),
+                ..Default::default()
+            })
+            .collect()
+    }
+}
+
+/// Malformed template expression (`{{ id }}` in Jinja) inside of request URL.
+/// Expression syntax is chosen by file extension.
+pub struct UrlInterpolationRule;
+
+impl RangeRule for UrlInterpolationRule {
+    fn code(&self) -> &'static str {
+        URL_INTERPOLATION
+    }
+
+    fn check(
+        &self,
+        uri: &Url,
+        root: Node<'_>,
+        source: &str,
+        dirty: Option<&DirtyRanges>,
+        _parsers: &Parsers,
+    ) -> Vec<Diagnostic> {
+        let ext = uri.path().rsplit_once('.').map(|(_, ext)| ext);
+        let delimiters = interpolations(ext.unwrap_or_default());
+        let keep = keep_dirty(dirty);
+        url_value_errors(root, source, delimiters, keep)
+            .into_iter()
+            .map(|error| Diagnostic {
+                range: Range::new(to_position2(error.range.0), to_position2(error.range.1)),
+                severity: Some(DiagnosticSeverity::ERROR),
+                message: error.message,
+                code: Some(NumberOrString::String(String::from(URL_INTERPOLATION))),
+                source: Some(String::from("htmx-lsp")),
+                ..Default::default()
+            })
+            .collect()
+    }
+}
+
+/// Attribute repeated on same element, browser uses only first one.
+pub struct DuplicateAttributeRule;
+
+impl RangeRule for DuplicateAttributeRule {
+    fn code(&self) -> &'static str {
+        DUPLICATE_ATTRIBUTE
+    }
+
+    fn check(
+        &self,
+        uri: &Url,
+        root: Node<'_>,
+        source: &str,
+        dirty: Option<&DirtyRanges>,
+        _parsers: &Parsers,
+    ) -> Vec<Diagnostic> {
+        let keep = keep_dirty(dirty);
+        let mut diagnostics = vec![];
+        for tag in start_tags(root) {
+            if !keep(tag.start_position(), tag.end_position()) {
+                continue;
+            }
+            for duplicate in duplicate_attributes(tag, source) {
+                let severity = match duplicate.name.starts_with("hx-")
+                    || duplicate.name.starts_with("data-hx-")
+                {
+                    true => DiagnosticSeverity::WARNING,
+                    false => DiagnosticSeverity::HINT,
+                };
+                let first = Range::new(
+                    to_position2(duplicate.first.0),
+                    to_position2(duplicate.first.1),
+                );
+                diagnostics.push(Diagnostic {
+                    range: Range::new(
+                        to_position2(duplicate.range.0),
+                        to_position2(duplicate.range.1),
+                    ),
+                    severity: Some(severity),
+                    message: format!(
+                        "Attribute {} is repeated, only first one is used.",
+                        duplicate.name
+                    ),
+                    code: Some(NumberOrString::String(String::from(DUPLICATE_ATTRIBUTE))),
+                    source: Some(String::from("htmx-lsp")),
+                    related_information: Some(vec![DiagnosticRelatedInformation {
+                        location: Location::new(uri.clone(), first),
+                        message: String::from("First occurrence"),
+                    }]),
+                    ..Default::default()
+                });
+            }
+        }
+        diagnostics
+    }
+}
+
+/// Rules checked after every template change.
+pub const TEMPLATE_RULES: [&dyn RangeRule; 7] = [
+    &SelectorRule,
+    &JsValueRule,
+    &SwapModifierRule,
+    &SwapConflictRule,
+    &UrlInterpolationRule,
+    &ExtensionValueRule,
+    &DuplicateAttributeRule,
+];
+
+/// Replace diagnostics of every rule in `dirty` ranges (`None` - whole document).
+/// Diagnostics from other sources are kept.
+pub fn recheck(
+    diagnostics: &mut Vec<Diagnostic>,
+    uri: &Url,
+    root: Node<'_>,
+    source: &str,
+    dirty: Option<&DirtyRanges>,
+    parsers: &Parsers,
+) {
+    let suppressions = Suppressions::new(source);
+    for rule in TEMPLATE_RULES {
+        diagnostics.retain(|diagnostic| {
+            let same_rule = matches!(&diagnostic.code, Some(NumberOrString::String(code)) if code == rule.code());
+            !same_rule || dirty.is_some_and(|dirty| !dirty.intersects(diagnostic.range))
+        });
+        let mut checked = rule.check(uri, root, source, dirty, parsers);
+        suppressions.filter(&mut checked);
+        diagnostics.extend(checked);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp::lsp_types::{DiagnosticSeverity, Position, Range, Url};
+    use tree_sitter::Parser;
+
+    use super::{recheck, DirtyRanges, DuplicateAttributeRule, Edit, RangeRule, SwapConflictRule};
+    use crate::htmx_tree_sitter::Parsers;
+
+    fn edit(start: (u32, u32), end: (u32, u32), text: &str) -> Edit {
+        Edit::new(
+            Range::new(Position::new(start.0, start.1), Position::new(end.0, end.1)),
+            text,
+        )
+    }
+
+    #[test]
+    fn positions_are_shifted() {
+        let insert = edit((1, 4), (1, 4), "ab\ncd");
+        assert_eq!(insert.new_end, Position::new(2, 2));
+        assert_eq!(insert.shift(Position::new(1, 2)), Some(Position::new(1, 2)));
+        assert_eq!(insert.shift(Position::new(1, 6)), Some(Position::new(2, 4)));
+        assert_eq!(insert.shift(Position::new(3, 1)), Some(Position::new(4, 1)));
+
+        let remove = edit((0, 2), (1, 3), "");
+        assert_eq!(remove.shift(Position::new(0, 5)), None);
+        assert_eq!(remove.shift(Position::new(1, 5)), Some(Position::new(0, 4)));
+
+        let mut dirty = DirtyRanges::default();
+        dirty.edit(&edit((0, 1), (0, 1), "x"));
+        dirty.edit(&edit((0, 0), (0, 0), "\n"));
+        assert_eq!(
+            dirty.0[0],
+            Range::new(Position::new(1, 1), Position::new(1, 2))
+        );
+    }
+
+    #[test]
+    fn only_dirty_selectors_are_checked() {
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_html::language()).unwrap();
+        let text = "<div hx-target=\"find .a\"></div>\n<div hx-target=\"find .b\"></div>\n";
+        let tree = parser.parse(text, None).unwrap();
+        let mut diagnostics = vec![];
+        let dirty = DirtyRanges(vec![Range::new(Position::new(1, 0), Position::new(1, 3))]);
+        let uri = Url::parse("file:///index.html").unwrap();
+        let parsers = Parsers::default();
+        recheck(
+            &mut diagnostics,
+            &uri,
+            tree.root_node(),
+            text,
+            Some(&dirty),
+            &parsers,
+        );
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].range.start.line, 1);
+
+        recheck(
+            &mut diagnostics,
+            &uri,
+            tree.root_node(),
+            text,
+            None,
+            &parsers,
+        );
+        assert_eq!(diagnostics.len(), 2);
+    }
+
+    #[test]
+    fn duplicate_attributes() {
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_html::language()).unwrap();
+        let text = r#"<div hx-swap="a" class="x" hx-swap="b" CLASS="y"></div>"#;
+        let tree = parser.parse(text, None).unwrap();
+        let uri = Url::parse("file:///index.html").unwrap();
+        let diagnostics =
+            DuplicateAttributeRule.check(&uri, tree.root_node(), text, None, &Parsers::default());
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::WARNING));
+        assert_eq!(diagnostics[0].range.start, Position::new(0, 27));
+        let related = diagnostics[0].related_information.as_ref().unwrap();
+        assert_eq!(related[0].location.range.start, Position::new(0, 5));
+        assert_eq!(diagnostics[1].severity, Some(DiagnosticSeverity::HINT));
+    }
+
+    #[test]
+    fn swap_conflicts_link_docs() {
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_html::language()).unwrap();
+        let text = r##"<li hx-swap="delete" hx-select="#row"></li>"##;
+        let tree = parser.parse(text, None).unwrap();
+        let uri = Url::parse("file:///index.html").unwrap();
+        let diagnostics =
+            SwapConflictRule.check(&uri, tree.root_node(), text, None, &Parsers::default());
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].range.start, Position::new(0, 21));
+        let docs = diagnostics[0].code_description.as_ref().unwrap();
+        assert_eq!(docs.href.as_str(), "https://htmx.org/attributes/hx-swap/");
+    }
+}
@@ -0,0 +1,213 @@
+use serde::Serialize;
+use std::sync::{
+    atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+    Arc,
+};
+
+use tokio::sync::{Mutex, MutexGuard};
+
+/// Error returned by workspace walk when newer index run was started.
+#[derive(Debug, thiserror::Error)]
+#[error("Indexing was canceled by newer run.")]
+pub struct IndexCanceled;
+
+/// Token of one index run, it is canceled as soon as next run starts.
+#[derive(Debug, Clone)]
+pub struct IndexToken {
+    generation: u64,
+    latest: Arc<AtomicU64>,
+    progress: Arc<IndexProgress>,
+}
+
+impl IndexToken {
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn is_canceled(&self) -> bool {
+        self.latest.load(Ordering::SeqCst) != self.generation
+    }
+
+    /// Stop walk early, checked between batches of files.
+    pub fn check(&self) -> Result<(), IndexCanceled> {
+        match self.is_canceled() {
+            true => Err(IndexCanceled),
+            false => Ok(()),
+        }
+    }
+
+    /// Number of files that walk will index.
+    pub fn set_total(&self, total: usize) {
+        if !self.is_canceled() {
+            self.progress.total.store(total, Ordering::SeqCst);
+        }
+    }
+
+    /// One more file is queryable.
+    pub fn file_indexed(&self) {
+        if !self.is_canceled() {
+            self.progress.indexed.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    /// Walk ended, with error or not. Newer run keeps its own progress.
+    pub fn finish(&self) {
+        if !self.is_canceled() {
+            self.progress.running.store(false, Ordering::SeqCst);
+        }
+    }
+}
+
+/// Progress of latest index run. Files are queryable as soon as they are
+/// indexed, so results are partial while run is in progress.
+#[derive(Debug, Default)]
+pub struct IndexProgress {
+    running: AtomicBool,
+    total: AtomicUsize,
+    indexed: AtomicUsize,
+}
+
+/// Snapshot of `IndexProgress` for `htmx/status`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct IndexStatus {
+    pub partial: bool,
+    pub indexed: usize,
+    pub total: usize,
+}
+
+/// Coordinates workspace index runs (initialization, config change, `reset_tags`).
+///
+/// Runs never overlap: new run cancels older one and waits until it stops, only
+/// then `LspFiles` is reset. Results of canceled run are never published.
+#[derive(Debug, Default)]
+pub struct IndexCoordinator {
+    latest: Arc<AtomicU64>,
+    running: Mutex<()>,
+    progress: Arc<IndexProgress>,
+    deferred: AtomicBool,
+}
+
+impl IndexCoordinator {
+    /// Start new generation, every older token is canceled.
+    pub fn start(&self) -> IndexToken {
+        let generation = self.latest.fetch_add(1, Ordering::SeqCst) + 1;
+        self.progress.running.store(true, Ordering::SeqCst);
+        self.progress.total.store(0, Ordering::SeqCst);
+        self.progress.indexed.store(0, Ordering::SeqCst);
+        IndexToken {
+            generation,
+            latest: self.latest.clone(),
+            progress: self.progress.clone(),
+        }
+    }
+
+    /// Wait until previous run stops. Guard must be held for whole walk.
+    pub async fn acquire(&self) -> MutexGuard<'_, ()> {
+        self.running.lock().await
+    }
+
+    /// Postpone first run until index is needed (`lazy_index`).
+    pub fn defer(&self) {
+        self.deferred.store(true, Ordering::SeqCst);
+    }
+
+    /// Cancel run in progress and deferred run, server is shutting down.
+    pub fn cancel(&self) {
+        self.latest.fetch_add(1, Ordering::SeqCst);
+        self.progress.running.store(false, Ordering::SeqCst);
+        self.deferred.store(false, Ordering::SeqCst);
+    }
+
+    /// `true` only for first caller after `defer`, that caller starts the run.
+    pub fn take_deferred(&self) -> bool {
+        self.deferred.swap(false, Ordering::SeqCst)
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.latest.load(Ordering::SeqCst)
+    }
+
+    /// Index run is in progress. Index lock is taken for each file, so it's
+    /// free between files.
+    pub fn is_running(&self) -> bool {
+        self.progress.running.load(Ordering::SeqCst)
+    }
+
+    pub fn status(&self) -> IndexStatus {
+        IndexStatus {
+            partial: self.is_running(),
+            indexed: self.progress.indexed.load(Ordering::SeqCst),
+            total: self.progress.total.load(Ordering::SeqCst),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use super::IndexCoordinator;
+
+    #[tokio::test]
+    async fn newer_run_cancels_older() {
+        let coordinator = Arc::new(IndexCoordinator::default());
+        let first = coordinator.start();
+        let guard = coordinator.acquire().await;
+        assert!(first.check().is_ok());
+
+        let second = coordinator.start();
+        assert!(first.is_canceled() && !second.is_canceled());
+        assert_eq!(coordinator.generation(), second.generation());
+
+        let waiting = {
+            let coordinator = coordinator.clone();
+            tokio::spawn(async move {
+                let _guard = coordinator.acquire().await;
+                second.is_canceled()
+            })
+        };
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(!waiting.is_finished());
+        drop(guard);
+        assert!(!waiting.await.unwrap());
+    }
+
+    #[test]
+    fn progress_of_latest_run() {
+        let coordinator = IndexCoordinator::default();
+        let first = coordinator.start();
+        first.set_total(3);
+        first.file_indexed();
+        let status = coordinator.status();
+        assert!(status.partial);
+        assert_eq!((status.indexed, status.total), (1, 3));
+
+        let second = coordinator.start();
+        first.file_indexed();
+        first.finish();
+        assert!(coordinator.status().partial);
+        assert_eq!(coordinator.status().indexed, 0);
+        second.finish();
+        assert!(!coordinator.status().partial);
+    }
+
+    #[test]
+    fn deferred_run_is_taken_once() {
+        let coordinator = IndexCoordinator::default();
+        assert!(!coordinator.take_deferred());
+        coordinator.defer();
+        assert!(coordinator.take_deferred());
+        assert!(!coordinator.take_deferred());
+    }
+
+    #[test]
+    fn cancel_stops_every_run() {
+        let coordinator = IndexCoordinator::default();
+        let run = coordinator.start();
+        coordinator.defer();
+        coordinator.cancel();
+        assert!(run.check().is_err());
+        assert!(!coordinator.is_running());
+        assert!(!coordinator.take_deferred());
+    }
+}
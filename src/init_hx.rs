@@ -1,10 +1,16 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct HxCompletion {
     pub name: String,
     pub desc: String,
+    /// Short category shown next to label ("AJAX verb", "swap modifier").
+    #[serde(default)]
+    pub detail: Option<String>,
+    #[serde(default)]
+    pub deprecated: bool,
 }
 
 impl From<&(&str, &str)> for HxCompletion {
@@ -12,6 +18,7 @@ impl From<&(&str, &str)> for HxCompletion {
         Self {
             name: name.to_string(),
             desc: desc.to_string(),
+            ..Default::default()
         }
     }
 }
@@ -20,191 +27,424 @@ fn to_hx_completion(values: Vec<(&str, &str)>) -> Vec<HxCompletion> {
     values.iter().map(|x| x.into()).collect()
 }
 
+/// Same as `to_hx_completion`, every item gets `detail` category.
+fn to_hx_detailed(values: Vec<(&str, &str)>, detail: &str) -> Vec<HxCompletion> {
+    let mut values = to_hx_completion(values);
+    for value in &mut values {
+        value.detail = Some(detail.to_string());
+    }
+    values
+}
+
+/// Category of htmx attribute (name without `hx-`).
+fn attribute_detail(name: &str) -> &'static str {
+    match name {
+        "get" | "post" | "put" | "patch" | "delete" => "AJAX verb",
+        "swap" | "swap-oob" | "select" | "select-oob" | "target" => "swapping",
+        "push-url" | "replace-url" | "history" | "history-elt" => "history",
+        "inherit" | "disinherit" => "inheritance",
+        "ext" | "sse" | "ws" => "extension",
+        "trigger" | "include" | "vals" | "headers" | "params" | "encoding" | "request" | "sync"
+        | "confirm" | "prompt" | "validate" | "indicator" => "request",
+        _ => "core",
+    }
+}
+
+/// Catalogue generated by `build.rs` from `src/md/htmx.json` snapshot.
+mod catalogue {
+    include!(concat!(env!("OUT_DIR"), "/catalogue.rs"));
+}
+
+pub use catalogue::HTMX_VERSION;
+
+/// Translated documentation bundled from `src/md/locales`, locale and JSON content.
+pub fn bundled_locales() -> Vec<(&'static str, &'static str)> {
+    catalogue::locales()
+}
+
 /// Initialize hx attributes.
 pub fn init_hx_tags() -> Vec<HxCompletion> {
-    let values = vec![
-        ("boost", include_str!("./md/attributes/hx-boost.md")),
-        ("delete", include_str!("./md/attributes/hx-delete.md")),
-        ("get", include_str!("./md/attributes/hx-get.md")),
-        ("include", include_str!("./md/attributes/hx-include.md")),
-        ("patch", include_str!("./md/attributes/hx-patch.md")),
-        ("post", include_str!("./md/attributes/hx-post.md")),
-        ("put", include_str!("./md/attributes/hx-put.md")),
-        ("swap", include_str!("./md/attributes/hx-swap.md")),
-        ("target", include_str!("./md/attributes/hx-target.md")),
-        ("trigger", include_str!("./md/attributes/hx-trigger.md")),
-        ("vals", include_str!("./md/attributes/hx-vals.md")),
-        ("push-url", include_str!("./md/attributes/hx-push-url.md")),
-        ("select", include_str!("./md/attributes/hx-select.md")),
-        ("ext", include_str!("./md/attributes/hx-ext.md")),
-        ("on", include_str!("./md/attributes/hx-on.md")),
-        (
-            "select-oob",
-            include_str!("./md/attributes/hx-select-oob.md"),
-        ),
-        ("swap-oob", include_str!("./md/attributes/hx-swap-oob.md")),
-        ("confirm", include_str!("./md/attributes/hx-confirm.md")),
-        ("disable", include_str!("./md/attributes/hx-disable.md")),
-        ("encoding", include_str!("./md/attributes/hx-encoding.md")),
-        ("headers", include_str!("./md/attributes/hx-headers.md")),
-        ("history", include_str!("./md/attributes/hx-history.md")),
-        (
-            "history-elt",
-            include_str!("./md/attributes/hx-history-elt.md"),
-        ),
-        ("indicator", include_str!("./md/attributes/hx-indicator.md")),
-        ("params", include_str!("./md/attributes/hx-params.md")),
-        ("preserve", include_str!("./md/attributes/hx-preserve.md")),
-        ("prompt", include_str!("./md/attributes/hx-prompt.md")),
-        (
-            "replace-url",
-            include_str!("./md/attributes/hx-replace-url.md"),
-        ),
-        ("request", include_str!("./md/attributes/hx-request.md")),
-        ("sync", include_str!("./md/attributes/hx-sync.md")),
-        ("validate", include_str!("./md/attributes/hx-validate.md")),
-    ];
-
-    to_hx_completion(values)
+    let mut attributes = to_hx_completion(catalogue::attributes());
+    for attribute in &mut attributes {
+        attribute.detail = Some(attribute_detail(&attribute.name).to_string());
+        attribute.deprecated = DEPRECATED_ATTRIBUTES.contains(&attribute.name.as_str());
+    }
+    attributes
 }
 
+/// Attributes replaced by extensions (`hx-ext="sse"`, `hx-ext="ws"`).
+const DEPRECATED_ATTRIBUTES: [&str; 2] = ["sse", "ws"];
+
 /// Init htmx values for attributes.
 pub fn init_hx_values() -> HashMap<String, Vec<HxCompletion>> {
-    let mut hm = HashMap::new();
-
-    let hx_swap = to_hx_completion(vec![
-        ("innerHTML", include_str!("./md/hx-swap/innerHTML.md")),
-        ("outerHTML", include_str!("./md/hx-swap/outerHTML.md")),
-        ("afterbegin", include_str!("./md/hx-swap/afterbegin.md")),
-        ("afterend", include_str!("./md/hx-swap/afterend.md")),
-        ("beforebegin", include_str!("./md/hx-swap/beforebegin.md")),
-        ("beforeend", include_str!("./md/hx-swap/beforeend.md")),
-        ("delete", include_str!("./md/hx-swap/delete.md")),
-        ("none", include_str!("./md/hx-swap/none.md")),
-    ]);
-    hm.insert(String::from("hx-swap"), hx_swap);
-
-    let hx_target = to_hx_completion(vec![
-        ("closest", include_str!("./md/hx-target/closest.md")),
-        ("find", include_str!("./md/hx-target/find.md")),
-        ("next", include_str!("./md/hx-target/next.md")),
-        ("prev", include_str!("./md/hx-target/prev.md")),
-        ("this", include_str!("./md/hx-target/this.md")),
-    ]);
-    hm.insert(String::from("hx-target"), hx_target);
-
-    let hx_boost = to_hx_completion(vec![
-        ("true", include_str!("./md/hx-boost/true.md")),
-        ("false", include_str!("./md/hx-boost/false.md")),
-    ]);
-    hm.insert(String::from("hx-boost"), hx_boost);
-
-    let hx_trigger = to_hx_completion(vec![
-        ("click", include_str!("./md/hx-trigger/click.md")),
-        ("once", include_str!("./md/hx-trigger/once.md")),
-        ("changed", include_str!("./md/hx-trigger/changed.md")),
-        ("delay:", include_str!("./md/hx-trigger/delay.md")),
-        ("throttle:", include_str!("./md/hx-trigger/throttle.md")),
-        ("from:", include_str!("./md/hx-trigger/from.md")),
-        ("target:", include_str!("./md/hx-trigger/target.md")),
-        ("consume", include_str!("./md/hx-trigger/consume.md")),
-        ("queue:", include_str!("./md/hx-trigger/queue.md")),
-        ("keyup", include_str!("./md/hx-trigger/keyup.md")),
-        ("load", include_str!("./md/hx-trigger/load.md")),
-        ("revealed", include_str!("./md/hx-trigger/revealed.md")),
-        ("intersect", include_str!("./md/hx-trigger/intersect.md")),
-        ("every", include_str!("./md/hx-trigger/every.md")),
-    ]);
-    hm.insert(String::from("hx-trigger"), hx_trigger);
-
-    let hx_ext = to_hx_completion(vec![
-        ("ajax-header", include_str!("./md/hx-ext/ajax-header.md")),
-        ("alpine-morph", include_str!("./md/hx-ext/alpine-morph.md")),
-        ("class-tools", include_str!("./md/hx-ext/class-tools.md")),
-        (
-            "client-side-templates",
-            include_str!("./md/hx-ext/client-side-templates.md"),
-        ),
-        ("debug", include_str!("./md/hx-ext/debug.md")),
-        (
-            "disable-element",
-            include_str!("./md/hx-ext/disable-element.md"),
+    let mut hm: HashMap<String, Vec<HxCompletion>> = catalogue::values()
+        .into_iter()
+        .map(|(name, values)| (name.to_string(), to_hx_completion(values)))
+        .collect();
+
+    for (name, detail) in [
+        ("hx-swap", "swap style"),
+        ("hx-swap-ob", "swap style"),
+        ("hx-target", "extended selector"),
+        ("hx-ext", "extension"),
+        ("hx-params", "parameter filter"),
+        ("hx-sync", "sync strategy"),
+    ] {
+        for value in hm.get_mut(name).into_iter().flatten() {
+            value.detail = Some(detail.to_string());
+        }
+    }
+    for value in hm.get_mut("hx-trigger").into_iter().flatten() {
+        let modifier = value.name.ends_with(':')
+            || ["once", "changed", "consume"].contains(&value.name.as_str());
+        let detail = match modifier {
+            true => "trigger modifier",
+            false => "event",
+        };
+        value.detail = Some(detail.to_string());
+    }
+
+    hm
+}
+
+/// Custom attribute from `extra_attributes` file.
+///
+/// ```json
+/// [
+///   {
+///     "name": "hx-analytics",
+///     "description": "Send analytics event.",
+///     "values": [{ "name": "click", "description": "On click." }]
+///   }
+/// ]
+/// ```
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ExtraAttribute {
+    /// Attribute name, it must start with `hx-`.
+    pub name: String,
+    /// Markdown documentation.
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub values: Vec<ExtraValue>,
+    /// Completion item is shown as deprecated.
+    #[serde(default)]
+    pub deprecated: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ExtraValue {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub deprecated: bool,
+}
+
+/// Parse and validate `extra_attributes` file.
+pub fn parse_extra_attributes(content: &str) -> anyhow::Result<Vec<ExtraAttribute>> {
+    let attributes: Vec<ExtraAttribute> = serde_json::from_str(content)?;
+    for attribute in &attributes {
+        if !attribute.name.starts_with("hx-") || attribute.name.len() == 3 {
+            anyhow::bail!("Attribute {:?} must start with hx-", attribute.name);
+        }
+        if attribute.values.iter().any(|value| value.name.is_empty()) {
+            anyhow::bail!("Attribute {} has value without name", attribute.name);
+        }
+    }
+    Ok(attributes)
+}
+
+/// Add extra attributes to built-in catalogue. Existing attributes and values
+/// are overridden.
+pub fn merge_extra_attributes(
+    extra: Vec<ExtraAttribute>,
+    attributes: &mut Vec<HxCompletion>,
+    values: &mut HashMap<String, Vec<HxCompletion>>,
+) {
+    for attribute in extra {
+        let name = attribute.name.replacen("hx-", "", 1);
+        match attributes.iter_mut().find(|item| item.name == name) {
+            Some(item) => {
+                if !attribute.description.is_empty() {
+                    item.desc = attribute.description;
+                }
+                item.deprecated |= attribute.deprecated;
+            }
+            None => attributes.push(HxCompletion {
+                name,
+                desc: attribute.description,
+                detail: Some(String::from("extra_attributes")),
+                deprecated: attribute.deprecated,
+            }),
+        }
+        let old = values.entry(attribute.name).or_default();
+        for value in attribute.values {
+            match old.iter_mut().find(|item| item.name == value.name) {
+                Some(item) => {
+                    item.desc = value.description;
+                    item.deprecated |= value.deprecated;
+                }
+                None => old.push(HxCompletion {
+                    name: value.name,
+                    desc: value.description,
+                    detail: Some(String::from("extra_attributes")),
+                    deprecated: value.deprecated,
+                }),
+            }
+        }
+    }
+}
+
+/// Standard HTML attributes, used for hover when `html_hover` is enabled.
+pub fn init_html_attributes() -> Vec<HxCompletion> {
+    let values = [
+        ("accesskey", "Keyboard shortcut to activate or focus the element.", "Global_attributes/accesskey"),
+        ("action", "The URL that processes the form submission.", "Element/form#action"),
+        ("alt", "Alternative text describing the image.", "Element/img#alt"),
+        ("aria-describedby", "Identifies the element (or elements) that describes the object.", "../Accessibility/ARIA/Attributes/aria-describedby"),
+        ("aria-hidden", "Indicates whether the element is exposed to an accessibility API.", "../Accessibility/ARIA/Attributes/aria-hidden"),
+        ("aria-label", "Defines a string value that labels an interactive element.", "../Accessibility/ARIA/Attributes/aria-label"),
+        ("aria-labelledby", "Identifies the element (or elements) that labels the element it is applied to.", "../Accessibility/ARIA/Attributes/aria-labelledby"),
+        ("autocomplete", "Hint for form autofill feature.", "Attributes/autocomplete"),
+        ("autofocus", "Indicates that an element should be focused on page load.", "Global_attributes/autofocus"),
+        ("checked", "Whether the checkbox or radio button is checked.", "Element/input#checked"),
+        ("class", "A space-separated list of the classes of the element. Classes allow CSS and JavaScript to select and access specific elements.", "Global_attributes/class"),
+        ("contenteditable", "Indicates if the element should be editable by the user.", "Global_attributes/contenteditable"),
+        ("data-*", "Custom data attributes, exchanged between HTML and its DOM representation by scripts.", "Global_attributes/data-*"),
+        ("dir", "Indicates the directionality of the element's text (`ltr`, `rtl`, `auto`).", "Global_attributes/dir"),
+        ("disabled", "Indicates whether the user can interact with the element.", "Attributes/disabled"),
+        ("draggable", "Indicates whether the element can be dragged, using the Drag and Drop API.", "Global_attributes/draggable"),
+        ("for", "Identifies the form control the label or output is associated with.", "Attributes/for"),
+        ("hidden", "Indicates that the element is not yet, or is no longer, relevant.", "Global_attributes/hidden"),
+        ("href", "The URL of a linked resource.", "Element/a#href"),
+        ("id", "Defines a unique identifier which must be unique in the whole document.", "Global_attributes/id"),
+        ("lang", "Defines the language of the element.", "Global_attributes/lang"),
+        ("method", "The HTTP method to submit the form with (`get`, `post`, `dialog`).", "Element/form#method"),
+        ("name", "Name of the element, submitted with the form data.", "Element/input#name"),
+        ("placeholder", "Text that appears in the form control when it has no value set.", "Attributes/placeholder"),
+        ("readonly", "Indicates that the value of the control is not editable.", "Attributes/readonly"),
+        ("rel", "Relationship of the linked resource to the current document.", "Attributes/rel"),
+        ("required", "Indicates that the value is required before the form can be submitted.", "Attributes/required"),
+        ("role", "Defines the semantic meaning of the element for assistive technologies.", "../Accessibility/ARIA/Roles"),
+        ("spellcheck", "Defines whether the element may be checked for spelling errors.", "Global_attributes/spellcheck"),
+        ("src", "The URL of the embeddable content.", "Element/img#src"),
+        ("style", "Contains CSS styling declarations to be applied to the element.", "Global_attributes/style"),
+        ("tabindex", "Indicates if the element can take input focus and in which order.", "Global_attributes/tabindex"),
+        ("target", "Where to display the linked URL or the form response (`_self`, `_blank`, `_parent`, `_top`).", "Element/a#target"),
+        ("title", "Contains a text representing advisory information related to the element.", "Global_attributes/title"),
+        ("type", "Type of the element, for example input or button type.", "Element/input#type"),
+        ("value", "The value of the control, submitted with the form data.", "Element/input#value"),
+    ];
+    values
+        .iter()
+        .map(|(name, desc, path)| HxCompletion {
+            name: name.to_string(),
+            desc: format!(
+                "{desc}\n\n[MDN Reference](https://developer.mozilla.org/en-US/docs/Web/HTML/{path})"
+            ),
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// Values for hx-sync depend on already typed part of value(`prefix`).
+///
+/// `closest form:queue last` - selector, strategy and queue modifier.
+pub fn hx_sync_values(prefix: &str) -> Vec<HxCompletion> {
+    let strategy = prefix.rsplit_once(':').map(|(_, strategy)| strategy);
+    match strategy {
+        Some(strategy) if strategy.trim_start().starts_with("queue ") => to_hx_detailed(
+            vec![
+                ("first", include_str!("./md/hx-sync/first.md")),
+                ("last", include_str!("./md/hx-sync/last.md")),
+                ("all", include_str!("./md/hx-sync/all.md")),
+            ],
+            "queue modifier",
         ),
-        ("event-header", include_str!("./md/hx-ext/event-header.md")),
-        ("head-support", include_str!("./md/hx-ext/head-support.md")),
-        ("include-vals", include_str!("./md/hx-ext/include-vals.md")),
-        ("json-enc", include_str!("./md/hx-ext/json-enc.md")),
-        ("morph", include_str!("./md/hx-ext/morph.md")),
-        (
-            "loading-states",
-            include_str!("./md/hx-ext/loading-states.md"),
+        Some(_) => to_hx_detailed(
+            vec![
+                ("drop", include_str!("./md/hx-sync/drop.md")),
+                ("abort", include_str!("./md/hx-sync/abort.md")),
+                ("replace", include_str!("./md/hx-sync/replace.md")),
+                ("queue", include_str!("./md/hx-sync/queue.md")),
+            ],
+            "sync strategy",
         ),
-        (
-            "method-override",
-            include_str!("./md/hx-ext/method-override.md"),
+        None => to_hx_detailed(
+            vec![
+                ("this", include_str!("./md/hx-target/this.md")),
+                ("closest", include_str!("./md/hx-target/closest.md")),
+                ("find", include_str!("./md/hx-target/find.md")),
+                ("next", include_str!("./md/hx-target/next.md")),
+                ("previous", include_str!("./md/hx-target/prev.md")),
+            ],
+            "extended selector",
         ),
+    }
+}
+
+/// Values for hx-request are JSON object with `timeout`, `credentials` and `noHeaders`
+/// keys. `prefix` decides if key or value is completed.
+///
+/// `{"timeout": 100, "credentials": ` - boolean value is next.
+pub fn hx_request_values(prefix: &str) -> Vec<HxCompletion> {
+    let mut keys = vec![
+        ("\"timeout\"", include_str!("./md/hx-request/timeout.md")),
         (
-            "morphdom-swap",
-            include_str!("./md/hx-ext/morphdom-swap.md"),
+            "\"credentials\"",
+            include_str!("./md/hx-request/credentials.md"),
         ),
-        ("multi-swap", include_str!("./md/hx-ext/multi-swap.md")),
-        ("path-deps", include_str!("./md/hx-ext/path-deps.md")),
-        ("preload", include_str!("./md/hx-ext/preload.md")),
-        ("remove-me", include_str!("./md/hx-ext/remove-me.md")),
         (
-            "response-targets",
-            include_str!("./md/hx-ext/response-targets.md"),
+            "\"noHeaders\"",
+            include_str!("./md/hx-request/noHeaders.md"),
         ),
-        ("restored", include_str!("./md/hx-ext/restored.md")),
-        ("sse", include_str!("./md/hx-ext/sse.md")),
-        ("ws", include_str!("./md/hx-ext/ws.md")),
-    ]);
-    hm.insert(String::from("hx-ext"), hx_ext);
-
-    let hx_push_ul = to_hx_completion(vec![
-        ("true", include_str!("./md/hx-push-url/true.md")),
-        ("false", include_str!("./md/hx-push-url/false.md")),
-    ]);
-    hm.insert(String::from("hx-push-ul"), hx_push_ul);
-
-    let hx_swap_ob = to_hx_completion(vec![
-        ("true", include_str!("./md/hx-swap-oob/true.md")),
-        ("innerHTML", include_str!("./md/hx-swap/innerHTML.md")),
-        ("outerHTML", include_str!("./md/hx-swap/outerHTML.md")),
-        ("afterbegin", include_str!("./md/hx-swap/afterbegin.md")),
-        ("afterend", include_str!("./md/hx-swap/afterend.md")),
-        ("beforebegin", include_str!("./md/hx-swap/beforebegin.md")),
-        ("beforeend", include_str!("./md/hx-swap/beforeend.md")),
-        ("delete", include_str!("./md/hx-swap/delete.md")),
-        ("none", include_str!("./md/hx-swap/none.md")),
-    ]);
-    hm.insert(String::from("hx-swap-ob"), hx_swap_ob);
-
-    let hx_history = to_hx_completion(vec![("false", include_str!("./md/hx-history/false.md"))]);
-    hm.insert(String::from("hx-history"), hx_history);
-
-    let hx_params = to_hx_completion(vec![
-        ("*", include_str!("./md/hx-params/star.md")),
-        ("none", include_str!("./md/hx-params/none.md")),
-        ("not", include_str!("./md/hx-params/not.md")),
-    ]);
-    hm.insert(String::from("hx-params"), hx_params);
-
-    let hx_replace_ul = to_hx_completion(vec![
-        ("true", include_str!("./md/hx-replace-url/true.md")),
-        ("false", include_str!("./md/hx-replace-url/false.md")),
-    ]);
-    hm.insert(String::from("hx-replace-ul"), hx_replace_ul);
-
-    let hx_sync = to_hx_completion(vec![
-        ("drop", include_str!("./md/hx-sync/drop.md")),
-        ("abort", include_str!("./md/hx-sync/abort.md")),
-        ("replace", include_str!("./md/hx-sync/replace.md")),
-        ("queue", include_str!("./md/hx-sync/queue.md")),
-    ]);
-    hm.insert(String::from("hx-sync"), hx_sync);
+    ];
+    let prefix = prefix.trim_start();
+    if prefix.is_empty() {
+        let mut values = vec![("{", "JSON object with request configuration")];
+        values.push(("js:", include_str!("./md/hx-request/js.md")));
+        return to_hx_completion(values);
+    }
+    if prefix.starts_with("js:") || prefix.starts_with("javascript:") {
+        return vec![];
+    }
+    let last = prefix.rsplit(['{', ',']).next().unwrap_or_default();
+    match last.split_once(':') {
+        Some((key, value)) if value.trim().is_empty() => {
+            let key = key.trim().trim_matches('"');
+            if key == "credentials" || key == "noHeaders" {
+                to_hx_completion(vec![("true", ""), ("false", "")])
+            } else {
+                vec![]
+            }
+        }
+        Some(_) => vec![],
+        None => {
+            let used: Vec<&str> = prefix
+                .split(['{', ','])
+                .filter_map(|pair| pair.split_once(':'))
+                .map(|(key, _)| key.trim())
+                .collect();
+            keys.retain(|(key, _)| !used.contains(key));
+            to_hx_completion(keys)
+        }
+    }
+}
 
-    hm
+/// Values for hx-headers are JSON object with header names as keys. Common request
+/// headers and headers that htmx itself sends are completed, only at key position.
+pub fn hx_headers_values(prefix: &str) -> Vec<HxCompletion> {
+    let prefix = prefix.trim_start();
+    if prefix.is_empty() {
+        return to_hx_completion(vec![
+            ("{", "JSON object with request headers"),
+            ("js:", include_str!("./md/hx-headers/js.md")),
+        ]);
+    }
+    let Some((_, used)) = json_key_prefix(prefix) else {
+        return vec![];
+    };
+    let headers = [
+        ("HX-Boosted", "Indicates that the request is via an element using hx-boost."),
+        ("HX-Current-URL", "The current URL of the browser."),
+        ("HX-History-Restore-Request", "`true` if the request is for history restoration after a miss in the local history cache."),
+        ("HX-Prompt", "The user response to an hx-prompt."),
+        ("HX-Request", "Always `true` for requests made by htmx."),
+        ("HX-Target", "The id of the target element if it exists."),
+        ("HX-Trigger-Name", "The name of the triggered element if it exists."),
+        ("HX-Trigger", "The id of the triggered element if it exists."),
+        ("Accept", "Media types that are acceptable for the response."),
+        ("Accept-Language", "Natural languages that are preferred for the response."),
+        ("Authorization", "Credentials for authenticating the client with the server."),
+        ("Cache-Control", "Caching directives for the request."),
+        ("Content-Type", "Media type of the request body."),
+        ("X-CSRF-Token", "Token that protects against cross-site request forgery."),
+        ("X-CSRFToken", "Token that protects against cross-site request forgery (Django)."),
+        ("X-Requested-With", "Marks request as AJAX request (`XMLHttpRequest`)."),
+    ];
+    headers
+        .iter()
+        .filter(|(name, _)| !used.iter().any(|key| key.eq_ignore_ascii_case(name)))
+        .map(|(name, desc)| HxCompletion {
+            name: format!("\"{name}\""),
+            desc: desc.to_string(),
+            detail: Some(String::from("request header")),
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// Typed part of JSON object key before cursor, with opening quote, and keys that
+/// object already has. `None` if cursor is not at key position.
+///
+/// `{"a": "b, c", "X-Re` - `"X-Re` is typed and `a` is used.
+pub fn json_key_prefix(prefix: &str) -> Option<(&str, Vec<&str>)> {
+    if !prefix.trim_start().starts_with('{') {
+        return None;
+    }
+    // Next string is key, `closed` is set when key string ends before `:`.
+    let mut key = false;
+    let mut closed = false;
+    let mut string = None;
+    let mut escaped = false;
+    let mut used = vec![];
+    for (i, c) in prefix.char_indices() {
+        match string {
+            Some(_) if escaped => escaped = false,
+            Some(_) if c == '\\' => escaped = true,
+            Some(start) if c == '"' => {
+                if key {
+                    used.push(&prefix[start + 1..i]);
+                    closed = true;
+                }
+                string = None;
+            }
+            Some(_) => (),
+            None => match c {
+                '"' => string = Some(i),
+                '{' | ',' => (key, closed) = (true, false),
+                ':' => key = false,
+                _ => (),
+            },
+        }
+    }
+    match (key, string) {
+        (true, Some(start)) => Some((&prefix[start..], used)),
+        (true, None) if !closed => Some((&prefix[prefix.len()..], used)),
+        _ => None,
+    }
+}
+
+/// Values for hx-inherit and hx-disinherit are htmx attributes found on
+/// current element or its ancestors.
+pub fn hx_inherit_values(
+    attributes: &[String],
+    hx_attributes: &[HxCompletion],
+) -> Vec<HxCompletion> {
+    let mut values = vec![HxCompletion::from(&(
+        "*",
+        include_str!("./md/hx-disinherit/star.md"),
+    ))];
+    for attribute in attributes {
+        if attribute == "hx-inherit" || attribute == "hx-disinherit" {
+            continue;
+        }
+        if let Some(hx) = hx_attributes
+            .iter()
+            .find(|hx| hx.name == attribute.replace("hx-", ""))
+        {
+            values.push(HxCompletion {
+                name: attribute.to_string(),
+                desc: hx.desc.to_string(),
+                detail: Some(String::from("in scope")),
+                ..Default::default()
+            });
+        }
+    }
+    values
 }
 
 /// In every language server request, backend has to check for file extension
@@ -224,7 +464,7 @@ pub fn init_hx_values() -> HashMap<String, Vec<HxCompletion>> {
 ///
 /// Code actions - Template
 ///
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
 pub enum LangType {
     Template,
     JavaScript,
@@ -259,6 +499,15 @@ impl LangTypes {
         }
     }
 
+    /// All languages of this file. For files that are both backend and template
+    /// (single-file components), backend comes first.
+    pub fn langs(&self) -> Vec<LangType> {
+        match self {
+            LangTypes::One(lang) => vec![*lang],
+            LangTypes::Two { first, second } => vec![*first, *second],
+        }
+    }
+
     pub fn get(&self) -> LangType {
         match self {
             LangTypes::One(lang) => *lang,
@@ -277,3 +526,126 @@ impl From<usize> for LangType {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        hx_headers_values, hx_request_values, init_hx_tags, init_hx_values, json_key_prefix,
+        merge_extra_attributes, parse_extra_attributes,
+    };
+
+    fn names(prefix: &str) -> Vec<String> {
+        hx_request_values(prefix)
+            .into_iter()
+            .map(|item| item.name)
+            .collect()
+    }
+
+    #[test]
+    fn hx_request_keys_and_values() {
+        assert_eq!(names(""), vec!["{", "js:"]);
+        assert_eq!(
+            names("{"),
+            vec!["\"timeout\"", "\"credentials\"", "\"noHeaders\""]
+        );
+        assert_eq!(
+            names(r#"{"timeout": 100, "#),
+            vec!["\"credentials\"", "\"noHeaders\""]
+        );
+        assert_eq!(names(r#"{"noHeaders": "#), vec!["true", "false"]);
+        assert!(names(r#"{"timeout": "#).is_empty());
+        assert!(names("js: timeout").is_empty());
+    }
+
+    #[test]
+    fn hx_headers_keys() {
+        let headers = |prefix: &str| -> Vec<String> {
+            hx_headers_values(prefix)
+                .into_iter()
+                .map(|item| item.name)
+                .collect()
+        };
+        assert_eq!(headers(""), vec!["{", "js:"]);
+        assert!(headers("{").contains(&String::from("\"HX-Prompt\"")));
+        let rest = headers(r#"{"hx-prompt": "a, b: c", "#);
+        assert!(!rest.contains(&String::from("\"HX-Prompt\"")));
+        assert!(rest.contains(&String::from("\"Accept\"")));
+        assert!(headers(r#"{"Accept": "#).is_empty());
+        assert!(headers(r#"{"Accept": "text/html, "#).is_empty());
+        assert!(headers(r#"{"Accept" "#).is_empty());
+        assert!(headers("js:{").is_empty());
+
+        assert_eq!(json_key_prefix("{"), Some(("", vec![])));
+        assert_eq!(
+            json_key_prefix(r#"{"a": "b\", c", "X-Re"#),
+            Some((r#""X-Re"#, vec!["a"]))
+        );
+        assert_eq!(json_key_prefix(r#"{"a": "b"#), None);
+    }
+
+    #[test]
+    fn extra_attributes_are_merged() {
+        let content = r#"[
+            {"name": "hx-analytics", "description": "Analytics", "values": [{"name": "click"}]},
+            {"name": "hx-swap", "values": [{"name": "morph", "description": "Morph swap"}]}
+        ]"#;
+        let extra = parse_extra_attributes(content).unwrap();
+        let mut attributes = init_hx_tags();
+        let mut values = init_hx_values();
+        let swap_values = values["hx-swap"].len();
+        merge_extra_attributes(extra, &mut attributes, &mut values);
+        assert!(attributes.iter().any(|item| item.name == "analytics"));
+        assert_eq!(values["hx-analytics"][0].name, "click");
+        assert_eq!(values["hx-swap"].len(), swap_values + 1);
+
+        assert!(parse_extra_attributes(r#"[{"name": "analytics"}]"#).is_err());
+        assert!(parse_extra_attributes(r#"[{"name": "hx-a", "docs": ""}]"#).is_err());
+    }
+
+    #[test]
+    fn completion_metadata() {
+        let attributes = init_hx_tags();
+        let get = attributes.iter().find(|item| item.name == "get").unwrap();
+        assert_eq!(get.detail.as_deref(), Some("AJAX verb"));
+        assert!(!get.deprecated);
+        let sse = attributes.iter().find(|item| item.name == "sse").unwrap();
+        assert!(sse.deprecated);
+
+        let values = init_hx_values();
+        let detail = |name: &str, value: &str| {
+            values[name]
+                .iter()
+                .find(|item| item.name == value)
+                .and_then(|item| item.detail.clone())
+        };
+        assert_eq!(
+            detail("hx-trigger", "delay:").as_deref(),
+            Some("trigger modifier")
+        );
+        assert_eq!(detail("hx-trigger", "click").as_deref(), Some("event"));
+        assert_eq!(
+            detail("hx-swap", "innerHTML").as_deref(),
+            Some("swap style")
+        );
+
+        let extra = parse_extra_attributes(r#"[{"name": "hx-old", "deprecated": true}]"#).unwrap();
+        let mut attributes = attributes;
+        merge_extra_attributes(extra, &mut attributes, &mut init_hx_values());
+        let old = attributes.iter().find(|item| item.name == "old").unwrap();
+        assert!(old.deprecated);
+        assert_eq!(old.detail.as_deref(), Some("extra_attributes"));
+    }
+
+    #[test]
+    fn catalogue_keeps_reference_order() {
+        let attributes = init_hx_tags();
+        assert_eq!(attributes.len(), 35);
+        assert_eq!(attributes[0].name, "boost");
+        assert!(attributes[0].desc.contains("hx-boost"));
+        let values = init_hx_values();
+        let swap: Vec<&str> = values["hx-swap"].iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(&swap[..2], ["innerHTML", "outerHTML"]);
+        assert_eq!(values["hx-params"][0].name, "*");
+        assert_eq!(values.len(), 13);
+    }
+}
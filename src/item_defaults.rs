@@ -0,0 +1,197 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, RwLock},
+    task::{Context, Poll},
+};
+
+use serde_json::{json, Map, Value};
+use tower_lsp::jsonrpc::{Request, Response};
+use tower_service::Service;
+
+use crate::state::{RecoverRw, State};
+
+/// Fields of `CompletionList.itemDefaults` that can be shared by items.
+const ITEM_DEFAULTS: [&str; 5] = [
+    "commitCharacters",
+    "editRange",
+    "insertTextFormat",
+    "insertTextMode",
+    "data",
+];
+
+/// Service that moves fields with same value in every completion item to
+/// `itemDefaults`, for clients that support them. `lsp-types` has no `itemDefaults`
+/// in `CompletionList`, so JSON response of `textDocument/completion` is rewritten.
+pub struct ItemDefaults<S> {
+    inner: S,
+    supported: Arc<RwLock<Vec<String>>>,
+}
+
+impl<S> ItemDefaults<S> {
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            supported: Arc::new(RwLock::new(vec![])),
+        }
+    }
+}
+
+impl<S> Service<Request> for ItemDefaults<S>
+where
+    S: Service<Request, Response = Option<Response>>,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+{
+    type Response = Option<Response>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        if request.method() == "initialize" {
+            *self.supported.recover_write(State::Client) = supported_defaults(request.params());
+        }
+        let completion = request.method() == "textDocument/completion";
+        let supported = self.supported.clone();
+        let response = self.inner.call(request);
+        Box::pin(async move {
+            let response = response.await?;
+            let supported = supported.recover_read(State::Client).clone();
+            if !completion || supported.is_empty() {
+                return Ok(response);
+            }
+            Ok(response.map(|response| {
+                let (id, body) = response.into_parts();
+                let body = body.map(|list| item_defaults(list, &supported));
+                Response::from_parts(id, body)
+            }))
+        })
+    }
+}
+
+/// Fields from `completionList.itemDefaults` client capability that server can share.
+fn supported_defaults(params: Option<&Value>) -> Vec<String> {
+    let capability = "/capabilities/textDocument/completion/completionList/itemDefaults";
+    params
+        .and_then(|params| params.pointer(capability))
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .filter(|field| ITEM_DEFAULTS.contains(field))
+        .map(String::from)
+        .collect()
+}
+
+/// Value of default field in completion item. Only plain text edit has `editRange`.
+fn item_field<'a>(item: &'a Value, field: &str) -> Option<&'a Value> {
+    match field {
+        "editRange" => item.get("textEdit")?.get("range"),
+        _ => item.get(field),
+    }
+}
+
+/// Completion list where fields that are same in every item are in `itemDefaults`.
+/// Response without shared fields is not changed.
+pub fn item_defaults(response: Value, supported: &[String]) -> Value {
+    let items = match &response {
+        Value::Array(items) => items,
+        Value::Object(list) => match list.get("items").and_then(Value::as_array) {
+            Some(items) => items,
+            None => return response,
+        },
+        _ => return response,
+    };
+    let mut defaults = Map::new();
+    for field in supported {
+        let Some(first) = items.first().and_then(|item| item_field(item, field)) else {
+            continue;
+        };
+        if items.len() > 1
+            && items
+                .iter()
+                .all(|item| item_field(item, field) == Some(first))
+        {
+            defaults.insert(field.to_string(), first.clone());
+        }
+    }
+    if defaults.is_empty() {
+        return response;
+    }
+    let (is_incomplete, mut items) = match response {
+        Value::Array(items) => (false, items),
+        Value::Object(mut list) => (
+            list.get("isIncomplete") == Some(&Value::Bool(true)),
+            match list.remove("items") {
+                Some(Value::Array(items)) => items,
+                _ => vec![],
+            },
+        ),
+        _ => unreachable!(),
+    };
+    for item in items.iter_mut().filter_map(Value::as_object_mut) {
+        for field in defaults.keys() {
+            if field != "editRange" {
+                item.remove(field);
+                continue;
+            }
+            // Label is default text of edit.
+            let text = item
+                .remove("textEdit")
+                .and_then(|edit| edit.get("newText").cloned());
+            if text.is_some() && text.as_ref() != item.get("label") {
+                item.insert(String::from("textEditText"), text.unwrap_or_default());
+            }
+        }
+    }
+    json!({
+        "isIncomplete": is_incomplete,
+        "itemDefaults": defaults,
+        "items": items,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{item_defaults, supported_defaults};
+
+    #[test]
+    fn shared_fields_are_defaults() {
+        let params = json!({"capabilities": {"textDocument": {"completion": {"completionList": {
+            "itemDefaults": ["commitCharacters", "editRange", "insertTextFormat", "unknown"]
+        }}}}});
+        let supported = supported_defaults(Some(&params));
+        assert_eq!(
+            supported,
+            ["commitCharacters", "editRange", "insertTextFormat"]
+        );
+        let range =
+            json!({"start": {"line": 0, "character": 14}, "end": {"line": 0, "character": 16}});
+        let items = json!([
+            {"label": "innerHTML", "commitCharacters": ["\""], "textEdit": {"range": range, "newText": "innerHTML"}, "insertTextFormat": 1},
+            {"label": "outerHTML", "commitCharacters": ["\""], "textEdit": {"range": range, "newText": "outerHTML swap:1s"}, "insertTextFormat": 2},
+        ]);
+        let list = item_defaults(items, &supported);
+        assert_eq!(
+            list["itemDefaults"],
+            json!({"commitCharacters": ["\""], "editRange": range})
+        );
+        assert_eq!(
+            list["items"],
+            json!([
+                {"label": "innerHTML", "insertTextFormat": 1},
+                {"label": "outerHTML", "textEditText": "outerHTML swap:1s", "insertTextFormat": 2},
+            ])
+        );
+        assert_eq!(list["isIncomplete"], json!(false));
+
+        let single = json!([{"label": "a", "commitCharacters": []}]);
+        assert_eq!(item_defaults(single.clone(), &supported), single);
+    }
+}
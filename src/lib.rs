@@ -1,9 +1,43 @@
+pub mod ajax;
+pub mod check;
+pub mod cli;
+pub mod code_lens;
+pub mod completion_cache;
 pub mod config;
+pub mod config_file;
+pub mod daemon;
+pub mod docs;
+pub mod embedded;
+pub mod extensions;
+pub mod extract;
+pub mod find_value;
+pub mod fragment_tree;
+pub mod grammar;
+pub mod htmx_api;
 pub mod htmx_tags;
 pub mod htmx_tree_sitter;
+pub mod include_graph;
+pub mod incremental;
+pub mod indexing;
 pub mod init_hx;
+pub mod item_defaults;
+pub mod locale;
+pub mod markup;
+pub mod metrics;
+pub mod organize;
 pub mod position;
 pub mod queries;
 pub mod query_helper;
+pub mod request_log;
+pub mod scaffold;
+pub mod selector;
 pub mod server;
+pub mod state;
+pub mod stylesheet;
+pub mod suppress;
+pub mod swap;
+#[cfg(test)]
+mod test_dir;
 pub mod to_input_edit;
+pub mod uri;
+pub mod url_params;
@@ -0,0 +1,108 @@
+use std::{collections::HashMap, path::Path};
+
+use crate::init_hx::{bundled_locales, parse_extra_attributes, ExtraAttribute, HxCompletion};
+
+/// Locales to try, most specific first: `de-AT` gives `de-at` and `de`. English
+/// documentation is built-in, so it has no candidates.
+pub fn locale_candidates(locale: &str) -> Vec<String> {
+    let locale = locale.trim().to_lowercase().replace('_', "-");
+    let language = locale.split('-').next().unwrap_or_default().to_string();
+    if language.is_empty() || language == "en" {
+        return vec![];
+    }
+    let mut candidates = vec![locale];
+    if candidates[0] != language {
+        candidates.push(language);
+    }
+    candidates
+}
+
+/// Translated documentation for `locale`. `<locale>.json` from `user_dir` is
+/// preferred over bundled one, file has same format as `extra_attributes`.
+/// Returns locale that was found.
+pub fn locale_docs(
+    locale: &str,
+    user_dir: Option<&Path>,
+) -> anyhow::Result<Option<(String, Vec<ExtraAttribute>)>> {
+    let bundled = bundled_locales();
+    for candidate in locale_candidates(locale) {
+        let path = user_dir.map(|dir| dir.join(format!("{candidate}.json")));
+        if let Some(path) = path.filter(|path| path.is_file()) {
+            let content = std::fs::read_to_string(&path)?;
+            return Ok(Some((candidate, parse_extra_attributes(&content)?)));
+        }
+        if let Some((_, content)) = bundled.iter().find(|(name, _)| *name == candidate) {
+            return Ok(Some((candidate, parse_extra_attributes(content)?)));
+        }
+    }
+    Ok(None)
+}
+
+/// Replace documentation of attributes and values with translated one. Only
+/// existing items are translated, missing translations stay in English.
+pub fn translate(
+    docs: Vec<ExtraAttribute>,
+    attributes: &mut [HxCompletion],
+    values: &mut HashMap<String, Vec<HxCompletion>>,
+) {
+    for attribute in docs {
+        let name = attribute.name.replacen("hx-", "", 1);
+        if let Some(item) = attributes.iter_mut().find(|item| item.name == name) {
+            if !attribute.description.is_empty() {
+                item.desc = attribute.description;
+            }
+        }
+        let Some(old) = values.get_mut(&attribute.name) else {
+            continue;
+        };
+        for value in attribute.values {
+            let item = old.iter_mut().find(|item| item.name == value.name);
+            if let Some(item) = item.filter(|_| !value.description.is_empty()) {
+                item.desc = value.description;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        init_hx::{init_hx_tags, init_hx_values},
+        test_dir::TestDir,
+    };
+
+    use super::{locale_candidates, locale_docs, translate};
+
+    #[test]
+    fn candidates_and_fallback() {
+        assert_eq!(locale_candidates("de_AT"), ["de-at", "de"]);
+        assert_eq!(locale_candidates("de"), ["de"]);
+        assert!(locale_candidates("en-US").is_empty());
+        assert!(locale_candidates("").is_empty());
+
+        let (locale, docs) = locale_docs("de-CH", None).unwrap().unwrap();
+        assert_eq!(locale, "de");
+        let mut attributes = init_hx_tags();
+        let mut values = init_hx_values();
+        translate(docs, &mut attributes, &mut values);
+        let desc = |name: &str| {
+            let item = attributes.iter().find(|item| item.name == name).unwrap();
+            item.desc.clone()
+        };
+        assert!(desc("get").contains("Das Attribut hx-get"));
+        // Not translated, English is kept.
+        assert!(desc("vals").contains("The hx-vals attribute"));
+        assert!(values["hx-swap"][0].desc.starts_with("Standard"));
+        assert!(locale_docs("fr", None).unwrap().is_none());
+
+        let dir = TestDir::new();
+        dir.write(
+            "de.json",
+            r#"[{"name": "hx-get", "description": "Eigene Doku"}]"#,
+        );
+        let (_, docs) = locale_docs("de", Some(&dir)).unwrap().unwrap();
+        assert_eq!(docs[0].description, "Eigene Doku");
+        dir.write("de.json", "[");
+        assert!(locale_docs("de", Some(&dir)).is_err());
+    }
+}
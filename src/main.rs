@@ -1,10 +1,61 @@
-use htmx_lsp2::server::BackendHtmx;
-use tower_lsp::{LspService, Server};
+use std::{
+    io::Write,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use htmx_lsp2::{
+    cli::{handle_args, mode, Mode},
+    daemon,
+    request_log::current_request,
+    server::{build_service, BackendHtmx},
+};
+use tower_lsp::Server;
 #[tokio::main]
 async fn main() {
-    env_logger::init();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if handle_args(&args).await {
+        return;
+    }
+    // Lines logged while request is handled have its id (`req=12`).
+    env_logger::Builder::from_default_env()
+        .format(|buf, record| {
+            let request = current_request()
+                .map(|id| format!(" req={id}"))
+                .unwrap_or_default();
+            writeln!(
+                buf,
+                "[{} {:<5} {}{request}] {}",
+                buf.timestamp(),
+                record.level(),
+                record.target(),
+                record.args()
+            )
+        })
+        .init();
+    let result = match mode(&args) {
+        Mode::Stdio => None,
+        Mode::Listen(addr) => Some(daemon::listen(&addr).await),
+        Mode::Connect(addr) => Some(daemon::connect(&addr).await),
+    };
+    match result {
+        None => (),
+        Some(Ok(())) => return,
+        Some(Err(err)) => {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+    }
     let stdin = tokio::io::stdin();
     let stdout = tokio::io::stdout();
-    let (service, socket) = LspService::build(BackendHtmx::new).finish();
+    let shutdown = Arc::new(AtomicBool::new(false));
+    let (service, socket) =
+        build_service(|client| BackendHtmx::with_shutdown(client, shutdown.clone()));
     Server::new(stdin, stdout, socket).serve(service).await;
+    // Exit without shutdown request is an error.
+    if !shutdown.load(Ordering::SeqCst) {
+        std::process::exit(1);
+    }
 }
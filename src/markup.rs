@@ -0,0 +1,83 @@
+use tower_lsp::lsp_types::{MarkupContent, MarkupKind};
+
+/// Documentation in format that client renders. Markdown is stripped for
+/// clients that support only plain text.
+pub fn markup_content(markdown: bool, value: &str) -> MarkupContent {
+    match markdown {
+        true => MarkupContent {
+            kind: MarkupKind::Markdown,
+            value: value.to_string(),
+        },
+        false => MarkupContent {
+            kind: MarkupKind::PlainText,
+            value: strip_markdown(value),
+        },
+    }
+}
+
+/// Client supports Markdown if it is listed, or if it sends no formats.
+pub fn supports_markdown(formats: Option<&Vec<MarkupKind>>) -> bool {
+    formats.is_none_or(|formats| formats.contains(&MarkupKind::Markdown))
+}
+
+/// Markdown as plain text: code fences, inline code, bold text and headings
+/// lose their markers, links keep text and url.
+pub fn strip_markdown(text: &str) -> String {
+    let mut lines = vec![];
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") {
+            continue;
+        }
+        let line = match trimmed.starts_with('#') {
+            true => trimmed.trim_start_matches('#').trim_start(),
+            false => line,
+        };
+        lines.push(strip_links(&line.replace("**", "").replace('`', "")));
+    }
+    lines.join("\n").trim().to_string()
+}
+
+/// `[text](url)` becomes `text (url)`.
+fn strip_links(line: &str) -> String {
+    let mut stripped = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(open) = rest.find('[') {
+        let link = rest[open..].find("](").and_then(|middle| {
+            let close = rest[open + middle..].find(')')?;
+            Some((open + middle, open + middle + close))
+        });
+        let Some((middle, close)) = link else {
+            break;
+        };
+        stripped.push_str(&rest[..open]);
+        stripped.push_str(&rest[open + 1..middle]);
+        stripped.push_str(" (");
+        stripped.push_str(&rest[middle + 2..close]);
+        stripped.push(')');
+        rest = &rest[close + 1..];
+    }
+    stripped.push_str(rest);
+    stripped
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp::lsp_types::MarkupKind;
+
+    use super::{markup_content, strip_markdown, supports_markdown};
+
+    #[test]
+    fn markdown_is_stripped() {
+        let text = "**hx-get**\n\nIssues `GET`:\n\n```html\n<div hx-get=\"/a\"></div>\n```\n\n## Notes\n[HTMX Reference](https://htmx.org/attributes/hx-get/)";
+        assert_eq!(
+            strip_markdown(text),
+            "hx-get\n\nIssues GET:\n\n<div hx-get=\"/a\"></div>\n\nNotes\nHTMX Reference (https://htmx.org/attributes/hx-get/)"
+        );
+        assert_eq!(strip_markdown("[a] b"), "[a] b");
+        assert_eq!(markup_content(true, "`a`").value, "`a`");
+        assert_eq!(markup_content(false, "`a`").kind, MarkupKind::PlainText);
+        assert!(supports_markdown(None));
+        assert!(!supports_markdown(Some(&vec![MarkupKind::PlainText])));
+    }
+}
@@ -0,0 +1,211 @@
+use std::{
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::init_hx::LangType;
+
+/// Upper bounds of histogram buckets, in microseconds.
+const BUCKETS: [u64; 14] = [
+    50,
+    100,
+    250,
+    500,
+    1_000,
+    2_500,
+    5_000,
+    10_000,
+    25_000,
+    50_000,
+    100_000,
+    250_000,
+    500_000,
+    u64::MAX,
+];
+
+/// Durations counted in fixed buckets, percentiles are upper bounds of bucket.
+pub struct Histogram {
+    counts: [AtomicU64; BUCKETS.len()],
+    total: AtomicU64,
+}
+
+#[derive(Debug, Default, Serialize, PartialEq)]
+pub struct Summary {
+    pub count: u64,
+    pub mean_us: u64,
+    pub p50_us: u64,
+    pub p95_us: u64,
+}
+
+impl Histogram {
+    pub const fn new() -> Self {
+        Self {
+            counts: [const { AtomicU64::new(0) }; BUCKETS.len()],
+            total: AtomicU64::new(0),
+        }
+    }
+
+    pub fn record(&self, duration: Duration) {
+        let micros = duration.as_micros() as u64;
+        let bucket = BUCKETS.iter().position(|bound| micros <= *bound);
+        self.counts[bucket.unwrap_or(BUCKETS.len() - 1)].fetch_add(1, Ordering::Relaxed);
+        self.total.fetch_add(micros, Ordering::Relaxed);
+    }
+
+    pub fn summary(&self) -> Summary {
+        let counts: Vec<u64> = self
+            .counts
+            .iter()
+            .map(|count| count.load(Ordering::Relaxed))
+            .collect();
+        let count: u64 = counts.iter().sum();
+        if count == 0 {
+            return Summary::default();
+        }
+        let percentile = |p: u64| {
+            let rank = (count * p).div_ceil(100);
+            let mut seen = 0;
+            for (i, bucket) in counts.iter().enumerate() {
+                seen += bucket;
+                if seen >= rank {
+                    // Last bucket has no upper bound, previous one is reported.
+                    return BUCKETS[i.min(BUCKETS.len() - 2)];
+                }
+            }
+            BUCKETS[BUCKETS.len() - 2]
+        };
+        Summary {
+            count,
+            mean_us: self.total.load(Ordering::Relaxed) / count,
+            p50_us: percentile(50),
+            p95_us: percentile(95),
+        }
+    }
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Number of indexed files and tags.
+#[derive(Debug, Default, Serialize, PartialEq)]
+pub struct IndexSize {
+    pub files: usize,
+    pub templates: usize,
+    pub tags: usize,
+}
+
+/// Counters for `htmx/metrics` request. Nothing is recorded until `metrics`
+/// is enabled in config, and nothing leaves the process.
+pub struct Metrics {
+    enabled: AtomicBool,
+    pub completion: Histogram,
+    pub template_parse: Histogram,
+    pub javascript_parse: Histogram,
+    pub backend_parse: Histogram,
+}
+
+/// Metrics are shared by all sessions in daemon mode.
+pub static METRICS: Metrics = Metrics::new();
+
+impl Metrics {
+    pub const fn new() -> Self {
+        Self {
+            enabled: AtomicBool::new(false),
+            completion: Histogram::new(),
+            template_parse: Histogram::new(),
+            javascript_parse: Histogram::new(),
+            backend_parse: Histogram::new(),
+        }
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Start of measurement, `None` when metrics are disabled.
+    pub fn start(&self) -> Option<Instant> {
+        self.is_enabled().then(Instant::now)
+    }
+
+    pub fn record(&self, histogram: &Histogram, start: Option<Instant>) {
+        if let Some(start) = start {
+            histogram.record(start.elapsed());
+        }
+    }
+
+    pub fn parse(&self, lang_type: LangType) -> &Histogram {
+        match lang_type {
+            LangType::Template => &self.template_parse,
+            LangType::JavaScript => &self.javascript_parse,
+            LangType::Backend => &self.backend_parse,
+        }
+    }
+
+    pub fn report(&self, index: IndexSize) -> Value {
+        if !self.is_enabled() {
+            return serde_json::json!({ "enabled": false });
+        }
+        serde_json::json!({
+            "enabled": true,
+            "completion": self.completion.summary(),
+            "parse": {
+                "template": self.template_parse.summary(),
+                "javascript": self.javascript_parse.summary(),
+                "backend": self.backend_parse.summary(),
+            },
+            "index": index,
+        })
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Histogram, Metrics};
+
+    #[test]
+    fn histogram_percentiles() {
+        let histogram = Histogram::new();
+        for _ in 0..90 {
+            histogram.record(Duration::from_micros(80));
+        }
+        for _ in 0..10 {
+            histogram.record(Duration::from_millis(20));
+        }
+        let summary = histogram.summary();
+        assert_eq!(summary.count, 100);
+        assert_eq!(summary.p50_us, 100);
+        assert_eq!(summary.p95_us, 25_000);
+        assert_eq!(summary.mean_us, (90 * 80 + 10 * 20_000) / 100);
+
+        assert_eq!(Histogram::new().summary().count, 0);
+    }
+
+    #[test]
+    fn disabled_metrics_are_not_recorded() {
+        let metrics = Metrics::new();
+        assert!(metrics.start().is_none());
+        assert_eq!(metrics.report(Default::default())["enabled"], false);
+        metrics.set_enabled(true);
+        let start = metrics.start();
+        metrics.record(&metrics.completion, start);
+        assert_eq!(metrics.completion.summary().count, 1);
+    }
+}
@@ -0,0 +1,171 @@
+use std::collections::HashSet;
+
+use tree_sitter::{Node, Point};
+
+use crate::position::start_tags;
+
+/// Code action kind for "Organize htmx attributes".
+pub const ORGANIZE_HTMX_ATTRIBUTES: &str = "source.organizeHtmxAttributes";
+
+/// Canonical order: request, trigger, target and swap, request details,
+/// history, inheritance and rest. Unknown htmx attributes keep their order
+/// after known ones, `hx-lsp` is always last.
+const ORDER: [&str; 34] = [
+    "hx-get",
+    "hx-post",
+    "hx-put",
+    "hx-patch",
+    "hx-delete",
+    "hx-trigger",
+    "hx-target",
+    "hx-swap",
+    "hx-select",
+    "hx-select-oob",
+    "hx-swap-oob",
+    "hx-include",
+    "hx-vals",
+    "hx-headers",
+    "hx-params",
+    "hx-encoding",
+    "hx-request",
+    "hx-sync",
+    "hx-indicator",
+    "hx-disabled-elt",
+    "hx-confirm",
+    "hx-prompt",
+    "hx-validate",
+    "hx-push-url",
+    "hx-replace-url",
+    "hx-history",
+    "hx-history-elt",
+    "hx-boost",
+    "hx-preserve",
+    "hx-ext",
+    "hx-inherit",
+    "hx-disinherit",
+    "hx-disable",
+    "hx-on",
+];
+
+fn is_htmx(name: &str) -> bool {
+    name.starts_with("hx-") || name.starts_with("data-hx-")
+}
+
+fn rank(name: &str) -> usize {
+    let name = name.strip_prefix("data-").unwrap_or(name);
+    if name == "hx-lsp" {
+        return ORDER.len() + 1;
+    }
+    let name = match name.starts_with("hx-on:") {
+        true => "hx-on",
+        false => name,
+    };
+    ORDER
+        .iter()
+        .position(|known| *known == name)
+        .unwrap_or(ORDER.len())
+}
+
+/// Replacement for attributes of one start tag.
+#[derive(Debug, PartialEq)]
+pub struct AttributeEdit {
+    pub range: (Point, Point),
+    pub text: String,
+}
+
+/// Edits for every start tag where htmx attributes are not in canonical order
+/// or some attribute is repeated(last one is kept). Other attributes stay in place.
+pub fn organize_attributes(root: Node<'_>, source: &str) -> Vec<AttributeEdit> {
+    start_tags(root)
+        .into_iter()
+        .filter_map(|tag| organize_tag(tag, source))
+        .collect()
+}
+
+fn organize_tag(start_tag: Node<'_>, source: &str) -> Option<AttributeEdit> {
+    if start_tag.has_error() {
+        return None;
+    }
+    let mut cursor = start_tag.walk();
+    let attributes: Vec<Node<'_>> = start_tag
+        .children(&mut cursor)
+        .filter(|node| node.kind() == "attribute")
+        .collect();
+    let first = attributes.first()?;
+    let last = attributes.last()?;
+    let mut items = vec![];
+    for attribute in &attributes {
+        let name = attribute.child(0)?.utf8_text(source.as_bytes()).ok()?;
+        let text = attribute.utf8_text(source.as_bytes()).ok()?;
+        items.push((name, text));
+    }
+    // Separators between attributes, they are reused for new order.
+    let gaps: Vec<&str> = attributes
+        .windows(2)
+        .map(|pair| source.get(pair[0].end_byte()..pair[1].start_byte()))
+        .collect::<Option<_>>()?;
+
+    let mut seen = HashSet::new();
+    let mut kept: Vec<(&str, &str)> = items
+        .iter()
+        .rev()
+        .filter(|(name, _)| seen.insert(name.to_ascii_lowercase()))
+        .copied()
+        .collect();
+    kept.reverse();
+    let slots: Vec<usize> = (0..kept.len()).filter(|i| is_htmx(kept[*i].0)).collect();
+    let mut htmx: Vec<(&str, &str)> = slots.iter().map(|i| kept[*i]).collect();
+    htmx.sort_by_key(|(name, _)| rank(name));
+    for (slot, attribute) in slots.into_iter().zip(htmx) {
+        kept[slot] = attribute;
+    }
+    if kept == items {
+        return None;
+    }
+    let mut text = String::new();
+    for (i, (_, attribute)) in kept.iter().enumerate() {
+        if i > 0 {
+            text.push_str(gaps.get(i - 1).copied().unwrap_or(" "));
+        }
+        text.push_str(attribute);
+    }
+    Some(AttributeEdit {
+        range: (first.start_position(), last.end_position()),
+        text,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use tree_sitter::Parser;
+
+    use super::organize_attributes;
+
+    fn organize(text: &str) -> Vec<String> {
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_html::language()).unwrap();
+        let tree = parser.parse(text, None).unwrap();
+        organize_attributes(tree.root_node(), text)
+            .into_iter()
+            .map(|edit| edit.text)
+            .collect()
+    }
+
+    #[test]
+    fn htmx_attributes_are_sorted() {
+        assert_eq!(
+            organize(r#"<div hx-lsp="a" class="row" hx-swap="outerHTML" hx-get="/a"></div>"#),
+            vec![r#"hx-get="/a" class="row" hx-swap="outerHTML" hx-lsp="a""#]
+        );
+        assert!(organize(r#"<div hx-get="/a" id="x" hx-target="this"></div>"#).is_empty());
+    }
+
+    #[test]
+    fn duplicates_are_merged() {
+        assert_eq!(
+            organize("<div hx-swap=\"a\"\n     hx-get=\"/a\"\n     hx-swap=\"b\"></div>"),
+            vec!["hx-get=\"/a\"\n     hx-swap=\"b\""]
+        );
+        assert_eq!(organize(r#"<input id="a" id="b">"#), vec![r#"id="b""#]);
+    }
+}
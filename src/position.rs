@@ -11,7 +11,8 @@ use tree_sitter::{Node, Point};
 use crate::{
     htmx_tree_sitter::LspFiles,
     init_hx::LangType,
-    query_helper::{query_name, query_value, HTMLQueries, HTMLQuery},
+    query_helper::{query_name, query_value, HTMLQueries, HTMLQuery, Queries},
+    state::{Recover, State},
 };
 
 /// Helpful enum when making TreeSitter queries.
@@ -48,12 +49,14 @@ pub enum Position {
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct PositionDefinition {
+    /// Cursor.
     pub point: Point,
-    pub start: usize,
+    /// Start of attribute value, value can span more lines.
+    pub start: Point,
 }
 
 impl PositionDefinition {
-    pub fn new(start: usize, point: Point) -> Self {
+    pub fn new(start: Point, point: Point) -> Self {
         Self { point, start }
     }
 }
@@ -65,32 +68,501 @@ pub fn get_position_from_lsp_completion(
     uri: String,
     query_type: QueryType,
     lsp_files: &Arc<Mutex<LspFiles>>,
-    query: &HTMLQueries,
+    queries: &Arc<Mutex<Queries>>,
 ) -> Option<Position> {
-    let text = text.get(&uri)?;
-    let text = text.to_string();
+    let rope = text.get(&uri)?;
+    let text = rope.to_string();
     let pos = text_params.position;
+    let trigger_point = clamp_to_line(&text, Point::new(pos.line as usize, pos.character as usize));
+    let offset = rope
+        .try_line_to_byte(pos.line as usize)
+        .ok()
+        .map(|line| line + trigger_point.column);
+    drop(rope);
+
+    let completion = query_type == QueryType::Completion;
+    // Completion doesn't wait for index lock (workspace indexing or other request),
+    // static catalogue is used for text before cursor.
+    let lsp_files = match completion {
+        true => match lsp_files.try_recover(State::Index) {
+            Some(lsp_files) => lsp_files,
+            None => return scan_position(text.get(..offset?)?),
+        },
+        false => lsp_files.recover(State::Index),
+    };
+    // Same lock order as in indexing, index before queries.
+    let queries = queries.recover(State::Queries);
+    let query = &queries.html;
+    let index = match lsp_files.get_index(&uri) {
+        Some(index) => index,
+        None => lsp_files.add_file(String::from(&uri))?,
+    };
+    // Stored tree is kept up to date by `did_change`. Backend file can also contain
+    // template part, in that case it has no html tree until first completion.
+    if lsp_files.get_tree(LangType::Template, index).is_none() {
+        lsp_files.add_tree(index, LangType::Template, &text, None);
+    }
+    let position = lsp_files.query_position(index, &text, query_type, pos, query);
+    if position.is_some() || !completion {
+        return position;
+    }
+    // Templating syntax can confuse html parser, fallback to simple scanner. It's
+    // also used for documents over size limit, they have no tree.
+    if let Some(tree) = lsp_files.get_tree(LangType::Template, index) {
+        if !error_near(tree.root_node(), trigger_point) {
+            return None;
+        }
+    }
+    scan_position(text.get(..offset?)?)
+}
+
+/// Get name of any html attribute under cursor.
+pub fn get_attribute_name(
+    text_params: &TextDocumentPositionParams,
+    text: &DashMap<String, Rope>,
+    uri: String,
+    lsp_files: &Arc<Mutex<LspFiles>>,
+) -> Option<String> {
+    let text = text.get(&uri)?.to_string();
+    let pos = text_params.position;
+    let lsp_files = lsp_files.recover(State::Index);
+    let index = lsp_files.get_index(&uri)?;
+    let tree = lsp_files.get_tree(LangType::Template, index)?;
+    let trigger_point = Point::new(pos.line as usize, pos.character as usize);
+    let node = tree
+        .root_node()
+        .descendant_for_point_range(trigger_point, trigger_point)?;
+    if node.kind() != "attribute_name" {
+        return None;
+    }
+    node.utf8_text(text.as_bytes()).ok().map(String::from)
+}
+
+/// Smallest element that contains whole range.
+pub fn find_element(root: Node<'_>, start: Point, end: Point) -> Option<Node<'_>> {
+    let mut node = root.descendant_for_point_range(start, end);
+    while let Some(current) = node {
+        if current.kind() == "element" {
+            return Some(current);
+        }
+        node = current.parent();
+    }
+    None
+}
+
+/// Attribute names and value nodes from start tag. Value is `None` for attribute
+/// without value.
+pub fn tag_attributes<'a>(start_tag: Node<'a>, source: &str) -> Vec<(String, Option<Node<'a>>)> {
+    let mut cursor = start_tag.walk();
+    start_tag
+        .children(&mut cursor)
+        .filter(|node| node.kind() == "attribute")
+        .filter_map(|attribute| {
+            let name = attribute.child(0)?.utf8_text(source.as_bytes()).ok()?;
+            // Children are name, `=` and value, quoted value has one more level.
+            let value = attribute
+                .child(2)
+                .and_then(|value| value.child(1).or(Some(value)))
+                .filter(|value| value.kind() == "attribute_value");
+            Some((name.to_string(), value))
+        })
+        .collect()
+}
+
+/// Every start tag (and self closing tag) in document order.
+pub fn start_tags(root: Node<'_>) -> Vec<Node<'_>> {
+    let mut tags = vec![];
+    let mut stack = vec![root];
+    while let Some(node) = stack.pop() {
+        if matches!(node.kind(), "start_tag" | "self_closing_tag") {
+            tags.push(node);
+            continue;
+        }
+        let mut cursor = node.walk();
+        let children: Vec<Node<'_>> = node.children(&mut cursor).collect();
+        stack.extend(children.into_iter().rev());
+    }
+    tags
+}
+
+/// Attribute that is repeated on same element, `first` is range of first occurrence.
+#[derive(Debug, PartialEq)]
+pub struct DuplicateAttribute {
+    pub name: String,
+    pub range: (Point, Point),
+    pub first: (Point, Point),
+}
+
+/// Repeated attribute names(case insensitive) in start tag.
+pub fn duplicate_attributes(start_tag: Node<'_>, source: &str) -> Vec<DuplicateAttribute> {
+    let mut seen: HashMap<String, (Point, Point)> = HashMap::new();
+    let mut duplicates = vec![];
+    let mut cursor = start_tag.walk();
+    for attribute in start_tag
+        .children(&mut cursor)
+        .filter(|node| node.kind() == "attribute")
+    {
+        let Some(name) = attribute.child(0) else {
+            continue;
+        };
+        let Ok(text) = name.utf8_text(source.as_bytes()) else {
+            continue;
+        };
+        let range = (name.start_position(), name.end_position());
+        match seen.get(&text.to_ascii_lowercase()) {
+            Some(first) => duplicates.push(DuplicateAttribute {
+                name: text.to_string(),
+                range,
+                first: *first,
+            }),
+            None => {
+                seen.insert(text.to_ascii_lowercase(), range);
+            }
+        }
+    }
+    duplicates
+}
+
+/// Value of `id` attribute for element.
+pub fn element_id(element: Node<'_>, source: &str) -> Option<String> {
+    let start_tag = element.child(0)?;
+    tag_attributes(start_tag, source)
+        .into_iter()
+        .find(|(name, _)| name == "id")?
+        .1?
+        .utf8_text(source.as_bytes())
+        .ok()
+        .map(String::from)
+}
 
-    if let Ok(lsp_files) = lsp_files.lock() {
-        if let Some(index) = lsp_files.get_index(&uri) {
-            lsp_files.query_position(index, &text, query_type, pos, query)
-        } else if let Some(index) = lsp_files.add_file(String::from(&uri)) {
-            lsp_files.add_tree(index, LangType::Template, &text, None);
-            lsp_files.query_position(index, &text, query_type, pos, query)
-        } else {
-            None
+/// Element with `id` attribute.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ElementId {
+    /// Value of `id` attribute.
+    pub id: String,
+    /// Start and end of `id` value.
+    pub range: (Point, Point),
+    /// Start and end of element start tag.
+    pub tag: (Point, Point),
+    /// Element has `hx-swap-oob` attribute.
+    pub oob: bool,
+}
+
+/// All elements with `id` attribute in document.
+pub fn element_ids(root: Node<'_>, source: &str) -> Vec<ElementId> {
+    let mut ids = vec![];
+    let mut stack = vec![root];
+    while let Some(node) = stack.pop() {
+        let mut cursor = node.walk();
+        stack.extend(node.children(&mut cursor));
+        if node.kind() != "start_tag" && node.kind() != "self_closing_tag" {
+            continue;
         }
+        let attributes = tag_attributes(node, source);
+        let oob = attributes.iter().any(|(name, _)| name == "hx-swap-oob");
+        let id = attributes
+            .into_iter()
+            .find_map(|(name, value)| (name == "id").then_some(value).flatten());
+        if let Some(value) = id {
+            if let Ok(text) = value.utf8_text(source.as_bytes()) {
+                ids.push(ElementId {
+                    id: text.to_string(),
+                    range: (value.start_position(), value.end_position()),
+                    tag: (node.start_position(), node.end_position()),
+                    oob,
+                });
+            }
+        }
+    }
+    ids.sort_by_key(|element| element.range.0);
+    ids
+}
+
+/// `<a>` or `<form>` element inside of `hx-boost="true"` element.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoostedElement {
+    pub tag: String,
+    pub point: Point,
+    /// `href` for link, `action` for form.
+    pub url: Option<String>,
+    /// Uppercase HTTP method, `GET` for link, form `method` (`GET` by default).
+    pub method: String,
+    /// Reason why htmx won't boost this element.
+    pub skipped: Option<&'static str>,
+}
+
+/// All links and forms affected by `hx-boost`. Boost is inherited, `hx-boost="false"`
+/// turns it off for descendants.
+pub fn boosted_elements(root: Node<'_>, source: &str) -> Vec<BoostedElement> {
+    let mut elements = vec![];
+    let mut stack = vec![(root, false)];
+    while let Some((node, boosted)) = stack.pop() {
+        let mut boosted = boosted;
+        if node.kind() == "element" {
+            if let Some(start_tag) = node.child(0) {
+                boosted = boost_element(start_tag, source, boosted, &mut elements);
+            }
+        }
+        let mut cursor = node.walk();
+        let children: Vec<Node<'_>> = node.children(&mut cursor).collect();
+        stack.extend(children.into_iter().rev().map(|child| (child, boosted)));
+    }
+    elements
+}
+
+fn boost_element(
+    start_tag: Node<'_>,
+    source: &str,
+    boosted: bool,
+    elements: &mut Vec<BoostedElement>,
+) -> bool {
+    let text = |value: Option<Node<'_>>| {
+        value
+            .and_then(|value| value.utf8_text(source.as_bytes()).ok())
+            .map(String::from)
+    };
+    let attributes = tag_attributes(start_tag, source);
+    let get = |key: &str| attributes.iter().find(|(name, _)| name == key);
+    let boosted = match get("hx-boost")
+        .and_then(|(_, value)| text(*value))
+        .as_deref()
+    {
+        Some("true") => true,
+        Some("false") => false,
+        _ => boosted,
+    };
+    let tag = start_tag
+        .child(1)
+        .and_then(|name| name.utf8_text(source.as_bytes()).ok())
+        .unwrap_or_default()
+        .to_lowercase();
+    if !boosted || (tag != "a" && tag != "form") {
+        return boosted;
+    }
+    let url = get(if tag == "a" { "href" } else { "action" }).and_then(|(_, value)| text(*value));
+    let method = match tag.as_str() {
+        "form" => get("method").and_then(|(_, value)| text(*value)),
+        _ => None,
+    };
+    let method = method.map_or(String::from("GET"), |method| method.to_uppercase());
+    let skipped = if get("target").and_then(|(_, value)| text(*value)).as_deref() == Some("_blank")
+    {
+        Some("target=\"_blank\"")
+    } else if get("download").is_some() {
+        Some("download")
     } else {
         None
+    };
+    elements.push(BoostedElement {
+        tag,
+        point: start_tag.start_position(),
+        url,
+        method,
+        skipped,
+    });
+    boosted
+}
+
+/// Number of ERROR and MISSING nodes in tree.
+pub fn error_nodes(root: Node<'_>) -> usize {
+    if !root.has_error() {
+        return 0;
     }
+    let mut count = 0;
+    let mut stack = vec![root];
+    while let Some(node) = stack.pop() {
+        if node.is_error() || node.is_missing() {
+            count += 1;
+        }
+        if node.has_error() {
+            let mut cursor = node.walk();
+            stack.extend(node.children(&mut cursor));
+        }
+    }
+    count
 }
 
-fn find_element_referent_to_current_node(node: Node<'_>) -> Option<Node<'_>> {
-    if node.kind() == "element" || node.kind() == "fragment" {
-        return Some(node);
+/// Editors can send column past the last character of line, it is moved to line end.
+pub fn clamp_to_line(source: &str, point: Point) -> Point {
+    match source.lines().nth(point.row) {
+        Some(line) => Point::new(point.row, point.column.min(line.len())),
+        None => point,
     }
+}
+
+/// Checks if `point` is right after the last character of its line.
+pub fn at_line_end(source: &str, point: Point) -> bool {
+    source
+        .lines()
+        .nth(point.row)
+        .is_some_and(|line| line.len() == point.column)
+}
+
+/// Checks if node at `trigger_point` or one of its ancestors is ERROR node.
+pub fn error_near(root: Node<'_>, trigger_point: Point) -> bool {
+    let mut node = root.descendant_for_point_range(trigger_point, trigger_point);
+    while let Some(current) = node {
+        if current.is_error() || current.is_missing() {
+            return true;
+        }
+        node = current.parent();
+    }
+    false
+}
+
+/// Find `Position` without TreeSitter, only from text before cursor.
+/// Used for completion when tree around cursor contains errors.
+pub fn scan_position(before: &str) -> Option<Position> {
+    let tag = &before[before.rfind('<')?..];
+    if tag.contains('>') {
+        return None;
+    }
+    let is_separator = |c: char| c.is_whitespace() || matches!(c, '<' | '}' | ')');
+    if tag.matches('"').count() % 2 == 1 {
+        let name = tag[..tag.rfind('"')?].strip_suffix('=')?;
+        let name = name.rsplit(is_separator).next()?;
+        return name.starts_with("hx-").then(|| Position::AttributeValue {
+            name: name.to_string(),
+            value: String::new(),
+            definition: None,
+        });
+    }
+    let name = tag.rsplit(is_separator).next()?;
+    is_hx_name_prefix(name).then(|| Position::AttributeName(name.to_string()))
+}
+
+/// Attribute name of completion position after attributes of unfinished start tag
+/// (`<div hx-boost |`), new attribute is completed there.
+pub const NEW_ATTRIBUTE: &str = "--";
+
+/// Attribute name that can be completed to htmx attribute: `hx-...`, or
+/// `data-hx-...` and its start (`data-`, `data-h`).
+pub fn is_hx_name_prefix(name: &str) -> bool {
+    name.starts_with("hx-")
+        || name
+            .strip_prefix("data-")
+            .is_some_and(|rest| "hx-".starts_with(rest) || rest.starts_with("hx-"))
+}
 
-    return find_element_referent_to_current_node(node.parent()?);
+/// Attribute name before cursor with its start (byte offset in `line`).
+pub fn attribute_name_prefix(line: &str, column: usize) -> Option<(usize, &str)> {
+    let line = line.get(..column)?;
+    let start = line
+        .rfind(|c: char| c.is_whitespace() || c == '<')
+        .map_or(0, |i| i + 1);
+    Some((start, &line[start..]))
+}
+
+/// Get all htmx attributes that are in scope of current position.
+pub fn get_hx_attributes_in_scope(
+    text_params: &TextDocumentPositionParams,
+    text: &DashMap<String, Rope>,
+    uri: String,
+    lsp_files: &Arc<Mutex<LspFiles>>,
+) -> Option<Vec<String>> {
+    let text = text.get(&uri)?.to_string();
+    let pos = text_params.position;
+    // Used for completion, which doesn't wait for index lock.
+    let lsp_files = lsp_files.try_recover(State::Index)?;
+    let index = lsp_files.get_index(&uri)?;
+    let tree = lsp_files.get_tree(LangType::Template, index)?;
+    let trigger_point = Point::new(pos.line as usize, pos.character as usize);
+    Some(hx_attributes_in_scope(
+        tree.root_node(),
+        &text,
+        trigger_point,
+    ))
+}
+
+/// Names of htmx attributes on current element and all of its ancestors.
+/// Closest attributes come first.
+pub fn hx_attributes_in_scope(root: Node<'_>, source: &str, trigger_point: Point) -> Vec<String> {
+    let mut names: Vec<String> = vec![];
+    let mut node = root.descendant_for_point_range(trigger_point, trigger_point);
+    while let Some(current) = node {
+        let mut cursor = current.walk();
+        for child in current.children(&mut cursor) {
+            let mut tag_cursor = child.walk();
+            let attributes: Vec<Node<'_>> = match child.kind() {
+                "start_tag" | "self_closing_tag" => child
+                    .children(&mut tag_cursor)
+                    .filter(|node| node.kind() == "attribute")
+                    .filter_map(|node| node.child(0))
+                    .collect(),
+                "attribute" => child.child(0).into_iter().collect(),
+                "attribute_name" => vec![child],
+                _ => vec![],
+            };
+            for attribute in attributes {
+                if let Ok(name) = attribute.utf8_text(source.as_bytes()) {
+                    if name.starts_with("hx-") && !names.iter().any(|n| n == name) {
+                        names.push(name.to_string());
+                    }
+                }
+            }
+        }
+        node = current.parent();
+    }
+    names
+}
+
+/// Returns htmx attribute name if text before cursor ends with `hx-name=`.
+pub fn attribute_before_equal(before: &str) -> Option<&str> {
+    let name = before.strip_suffix('=')?;
+    let name = name
+        .rsplit(|c: char| c.is_whitespace() || c == '<')
+        .next()?;
+    name.starts_with("hx-").then_some(name)
+}
+
+/// Part of attribute value before cursor. `column` is byte offset in `line`.
+pub fn value_prefix(line: &str, column: usize) -> Option<&str> {
+    let line = line.get(..column)?;
+    let start = line.rfind(['"', '\''])?;
+    Some(&line[start + 1..])
+}
+
+/// Word of value prefix that completion item replaces. Values are separated with
+/// spaces and commas, `hx-swap` and `hx-sync` modifier values and `hx-select-oob`
+/// swap styles also with `:`.
+pub fn typed_word<'a>(attribute: &str, prefix: &'a str) -> &'a str {
+    let attribute = attribute.strip_prefix("data-").unwrap_or(attribute);
+    let colon = matches!(attribute, "hx-swap" | "hx-sync" | "hx-select-oob");
+    let start = prefix
+        .char_indices()
+        .rev()
+        .find(|(_, c)| c.is_whitespace() || *c == ',' || (colon && *c == ':'))
+        .map_or(0, |(i, c)| i + c.len_utf8());
+    &prefix[start..]
+}
+
+/// Id (`#`) or class (`.`) part of selector word that is being typed, `div#mo` gives `#mo`.
+pub fn selector_token(word: &str) -> Option<&str> {
+    let start = word.rfind(['#', '.'])?;
+    let token = &word[start..];
+    token[1..]
+        .chars()
+        .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+        .then_some(token)
+}
+
+/// Part of `name` attribute value before cursor, value can contain other quotes
+/// (`hx-request='{"timeout": 100}'`).
+pub fn attribute_value_prefix<'a>(line: &'a str, column: usize, name: &str) -> Option<&'a str> {
+    let line = line.get(..column)?;
+    let start = line.rfind(&format!("{name}="))? + name.len() + 1;
+    let value = &line[start..];
+    Some(value.strip_prefix(['"', '\'']).unwrap_or(value))
+}
+
+/// Closest element (or fragment) around node. Generated templates can be nested
+/// very deep, so parents are walked in loop.
+fn find_element_referent_to_current_node(node: Node<'_>) -> Option<Node<'_>> {
+    let mut node = node;
+    while node.kind() != "element" && node.kind() != "fragment" {
+        node = node.parent()?;
+    }
+    Some(node)
 }
 
 /// Main function for querying HTML TreeSitter. It can be used for testing.
@@ -101,6 +573,7 @@ pub fn query_position(
     query_type: QueryType,
     query: &HTMLQueries,
 ) -> Option<Position> {
+    let trigger_point = clamp_to_line(source, trigger_point);
     let closest_node = root.descendant_for_point_range(trigger_point, trigger_point)?;
     let element = find_element_referent_to_current_node(closest_node)?;
 
@@ -209,7 +682,12 @@ mod tests1 {
     use tree_sitter::{Parser, Point};
 
     use crate::{
-        position::{query_position, Position, QueryType},
+        position::{
+            attribute_before_equal, attribute_name_prefix, attribute_value_prefix,
+            boosted_elements, element_ids, error_nodes, hx_attributes_in_scope, is_hx_name_prefix,
+            query_position, scan_position, selector_token, typed_word, value_prefix, Position,
+            QueryType, NEW_ATTRIBUTE,
+        },
         query_helper::{query_props, HTMLQueries, Queries},
     };
 
@@ -252,6 +730,81 @@ mod tests1 {
         assert_eq!(matches, Some(Position::AttributeName("hx-".to_string())));
     }
 
+    #[test]
+    fn deeply_nested_document() {
+        let depth = 10_000;
+        let mut text = String::new();
+        for _ in 0..depth {
+            text.push_str("<div hx-get=\"/a\">\n");
+        }
+        text.push_str("<p hx-swap=\"\"></p>\n");
+        for _ in 0..depth {
+            text.push_str("</div>\n");
+        }
+        let tree = prepare_tree(&text);
+        let query = HTMLQueries::default();
+        let position =
+            |point, query_type| query_position(tree.root_node(), &text, point, query_type, &query);
+        assert_eq!(
+            position(Point::new(depth, 12), QueryType::Completion),
+            Some(Position::AttributeValue {
+                name: String::from("hx-swap"),
+                value: String::new(),
+                definition: None,
+            })
+        );
+        assert_eq!(
+            position(Point::new(5, 6), QueryType::Hover),
+            Some(Position::AttributeName(String::from("hx-get")))
+        );
+        // Grammar serializes limited number of open tags, deep part has errors.
+        assert!(error_nodes(tree.root_node()) > 0);
+        assert_eq!(element_ids(tree.root_node(), &text).len(), 0);
+        assert_eq!(boosted_elements(tree.root_node(), &text).len(), 0);
+        assert!(hx_attributes_in_scope(tree.root_node(), &text, Point::new(depth, 12)).len() > 1);
+    }
+
+    #[test]
+    fn end_of_line_positions() {
+        let query = HTMLQueries::default();
+        let position = |text: &str, column, query_type| {
+            let tree = prepare_tree(text);
+            let point = Point::new(0, column);
+            query_position(tree.root_node(), text, point, query_type, &query)
+        };
+        let value = |name: &str, value: &str| {
+            Some(Position::AttributeValue {
+                name: name.to_string(),
+                value: value.to_string(),
+                definition: None,
+            })
+        };
+        let name = |name: &str| Some(Position::AttributeName(name.to_string()));
+
+        let open = "<div hx-get=\"\n></div>";
+        assert_eq!(
+            position(open, 13, QueryType::Completion),
+            value("hx-get", "")
+        );
+        let prefix = "<div hx-\n></div>";
+        assert_eq!(position(prefix, 8, QueryType::Completion), name("hx-"));
+        let bare = "<div hx-swap\n></div>";
+        assert_eq!(position(bare, 12, QueryType::Hover), name("hx-swap"));
+
+        let closed = "<div hx-swap=\"innerHTML\"\n></div>";
+        assert_eq!(
+            position(closed, 24, QueryType::Hover),
+            value("hx-swap", "innerHTML")
+        );
+        assert_eq!(
+            position(closed, 40, QueryType::Hover),
+            value("hx-swap", "innerHTML")
+        );
+        assert_eq!(position(closed, 24, QueryType::Completion), None);
+        let inline = "<div hx-swap=\"innerHTML\"></div>";
+        assert_eq!(position(inline, 24, QueryType::Hover), None);
+    }
+
     #[test]
     fn does_not_suggest_when_quote_not_initiated() {
         let text = r##"<div hx-swap= ></div>"##;
@@ -295,6 +848,31 @@ mod tests1 {
         );
     }
 
+    #[test]
+    fn suggests_extension_attr_values() {
+        let text = r#"<div hx-ext="sse" sse-swap=""></div>"#;
+
+        let tree = prepare_tree(text);
+
+        let query = HTMLQueries::default();
+        let matches = query_position(
+            tree.root_node(),
+            text,
+            Point::new(0, 28),
+            QueryType::Completion,
+            &query,
+        );
+
+        assert_eq!(
+            matches,
+            Some(Position::AttributeValue {
+                name: "sse-swap".to_string(),
+                value: "".to_string(),
+                definition: None
+            })
+        );
+    }
+
     #[test]
     fn suggests_attr_values_when_open_and_closed_quotes() {
         let text = r#"<div hx-swap=""></div>"#;
@@ -571,7 +1149,271 @@ mod tests1 {
             let tree = prepare_tree(text);
             let query = HTMLQueries::default();
             let matches = query_position(tree.root_node(), text, case.1, case.2, &query);
-            assert_eq!(matches, Some(Position::AttributeName(String::from("--"))));
+            assert_eq!(
+                matches,
+                Some(Position::AttributeName(String::from(NEW_ATTRIBUTE)))
+            );
+        }
+    }
+
+    #[test]
+    fn custom_elements_and_svg() {
+        let value = |name: &str, value: &str| {
+            Some(Position::AttributeValue {
+                name: name.to_string(),
+                value: value.to_string(),
+                definition: None,
+            })
+        };
+        let cases = [
+            (
+                r#"<my-component hx- ></my-component>"#,
+                17,
+                QueryType::Completion,
+                Some(Position::AttributeName(String::from("hx-"))),
+            ),
+            (
+                r#"<my-el @click="go" hx-swap=""></my-el>"#,
+                28,
+                QueryType::Completion,
+                value("hx-swap", ""),
+            ),
+            (
+                r#"<sl-button hx-get="/a" />"#,
+                20,
+                QueryType::Hover,
+                value("hx-get", "/a"),
+            ),
+            (
+                r#"<svg><g hx-swap=""></g></svg>"#,
+                17,
+                QueryType::Completion,
+                value("hx-swap", ""),
+            ),
+            (
+                r#"<svg><circle hx-get="/a" /></svg>"#,
+                22,
+                QueryType::Hover,
+                value("hx-get", "/a"),
+            ),
+            (
+                r#"<svg><foreignObject><div hx-swap=""></div></foreignObject></svg>"#,
+                34,
+                QueryType::Completion,
+                value("hx-swap", ""),
+            ),
+        ];
+        let query = HTMLQueries::default();
+        for (text, column, query_type, expected) in cases {
+            let tree = prepare_tree(text);
+            let point = Point::new(0, column);
+            let matches = query_position(tree.root_node(), text, point, query_type, &query);
+            assert_eq!(matches, expected, "{text}");
+        }
+    }
+
+    #[test]
+    fn unfinished_nested_tag_at_end() {
+        let cases = [
+            (
+                r#"<svg><g hx-"#,
+                11,
+                Some(Position::AttributeName(String::from("hx-"))),
+            ),
+            (
+                r#"<svg><g hx-swap=""#,
+                17,
+                Some(Position::AttributeValue {
+                    name: String::from("hx-swap"),
+                    value: String::new(),
+                    definition: None,
+                }),
+            ),
+            (r#"<svg><g hx-swap "#, 16, None),
+        ];
+        let query = HTMLQueries::default();
+        for (text, column, expected) in cases {
+            let tree = prepare_tree(text);
+            let point = Point::new(0, column);
+            let matches =
+                query_position(tree.root_node(), text, point, QueryType::Completion, &query);
+            assert_eq!(matches, expected, "{text}");
+        }
+    }
+
+    #[test]
+    fn hx_attributes_from_ancestors() {
+        let text = r##"<div hx-target="#list" hx-swap="outerHTML">
+      <ul>
+        <li hx-get="/item" hx-disinherit=""></li>
+      </ul>
+    </div>"##;
+        let tree = prepare_tree(text);
+        let names = hx_attributes_in_scope(tree.root_node(), text, Point::new(2, 42));
+        assert_eq!(names[2..], ["hx-target", "hx-swap"]);
+        assert!(names[..2].contains(&String::from("hx-get")));
+    }
+
+    #[test]
+    fn attribute_name_before_equal() {
+        assert_eq!(attribute_before_equal("<div hx-swap="), Some("hx-swap"));
+        assert_eq!(attribute_before_equal("  hx-get="), Some("hx-get"));
+        assert_eq!(attribute_before_equal("<div class="), None);
+        assert_eq!(attribute_before_equal("<div hx-swap=\""), None);
+    }
+
+    #[test]
+    fn ids_and_oob_elements() {
+        let text =
+            r#"<div id="main"><p hx-swap-oob="true" id="alerts"></p><span id=x></span></div>"#;
+        let tree = prepare_tree(text);
+        let ids: Vec<(String, bool)> = element_ids(tree.root_node(), text)
+            .into_iter()
+            .map(|element| (element.id, element.oob))
+            .collect();
+        assert_eq!(
+            ids,
+            vec![
+                (String::from("main"), false),
+                (String::from("alerts"), true),
+                (String::from("x"), false)
+            ]
+        );
+    }
+
+    #[test]
+    fn boosted_links_and_forms() {
+        let text = r#"<a href="/out">out</a>
+<body hx-boost="true">
+  <a href="/in">in</a>
+  <a href="/new" target="_blank">new</a>
+  <div hx-boost="false"><form action="/no"></form></div>
+  <form action="/save" method="post"><a href="/f.pdf" download>file</a></form>
+  <form action="/search"></form>
+</body>"#;
+        let tree = prepare_tree(text);
+        let elements: Vec<(String, String, Option<&str>)> =
+            boosted_elements(tree.root_node(), text)
+                .into_iter()
+                .map(|element| {
+                    let url = element.url.unwrap_or_default();
+                    (element.method, url, element.skipped)
+                })
+                .collect();
+        let element =
+            |method: &str, url: &str, skipped| (String::from(method), String::from(url), skipped);
+        assert_eq!(
+            elements,
+            vec![
+                element("GET", "/in", None),
+                element("GET", "/new", Some("target=\"_blank\"")),
+                element("POST", "/save", None),
+                element("GET", "/f.pdf", Some("download")),
+                element("GET", "/search", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn counts_error_nodes() {
+        assert_eq!(error_nodes(prepare_tree("<div></div>").root_node()), 0);
+        let tree = prepare_tree("<div hx-get=\"/\" <p></div>\n<span =>");
+        assert!(error_nodes(tree.root_node()) > 0);
+    }
+
+    #[test]
+    fn data_prefix_names() {
+        assert!(is_hx_name_prefix("data-"));
+        assert!(is_hx_name_prefix("data-h"));
+        assert!(is_hx_name_prefix("data-hx-sw"));
+        assert!(!is_hx_name_prefix("data-id"));
+        assert_eq!(
+            attribute_name_prefix("<div class=\"a\" data-h", 21),
+            Some((15, "data-h"))
+        );
+        assert_eq!(
+            scan_position("<div {{ x }} data-h"),
+            Some(Position::AttributeName(String::from("data-h")))
+        );
+
+        let text = r#"<div data-h></div>"#;
+        let tree = prepare_tree(text);
+        let query = HTMLQueries::default();
+        let matches = query_position(
+            tree.root_node(),
+            text,
+            Point::new(0, 11),
+            QueryType::Completion,
+            &query,
+        );
+        assert_eq!(matches, Some(Position::AttributeName("data-h".to_string())));
+    }
+
+    #[test]
+    fn value_prefix_before_cursor() {
+        let line = r#"<input hx-sync="closest form:queue " />"#;
+        assert_eq!(value_prefix(line, 35), Some("closest form:queue "));
+        assert_eq!(value_prefix(line, 16), Some(""));
+        assert_eq!(value_prefix(line, 3), None);
+    }
+
+    #[test]
+    fn typed_word_of_value() {
+        assert_eq!(typed_word("hx-target", "#mod"), "#mod");
+        assert_eq!(typed_word("hx-swap", "innerHTML swap:1"), "1");
+        assert_eq!(typed_word("data-hx-sync", "closest form:ab"), "ab");
+        assert_eq!(typed_word("hx-trigger", "click del"), "del");
+        assert_eq!(typed_word("hx-ext", "sse,pre"), "pre");
+        assert_eq!(typed_word("preload", "preload:i"), "preload:i");
+        assert_eq!(typed_word("hx-swap", "innerHTML "), "");
+        assert_eq!(typed_word("hx-select-oob", "#a, #alert:after"), "after");
+    }
+
+    #[test]
+    fn selector_token_of_word() {
+        assert_eq!(selector_token("#mod"), Some("#mod"));
+        assert_eq!(selector_token("div#mo"), Some("#mo"));
+        assert_eq!(selector_token("form.btn-"), Some(".btn-"));
+        assert_eq!(selector_token("."), Some("."));
+        assert_eq!(selector_token("closest"), None);
+        assert_eq!(selector_token("#a:hover"), None);
+    }
+
+    #[test]
+    fn attribute_value_prefix_with_quotes() {
+        let line = r#"<div hx-request='{"timeout": 100, "cre' hx-get="/">"#;
+        assert_eq!(
+            attribute_value_prefix(line, 38, "hx-request"),
+            Some(r#"{"timeout": 100, "cre"#)
+        );
+        assert_eq!(attribute_value_prefix(line, 3, "hx-request"), None);
+    }
+
+    #[test]
+    fn scan_position_in_templated_regions() {
+        let cases = [
+            (
+                r#"<div {% if user %}hx-"#,
+                Some(Position::AttributeName(String::from("hx-"))),
+            ),
+            (
+                "<button\n  class=\"btn\"\n  hx-sw",
+                Some(Position::AttributeName(String::from("hx-sw"))),
+            ),
+            (
+                r#"<div {% if user %}hx-swap=""#,
+                Some(Position::AttributeValue {
+                    name: String::from("hx-swap"),
+                    value: String::new(),
+                    definition: None,
+                }),
+            ),
+            (r#"<div class="p-4" hx-get="/a"> hx-"#, None),
+            (r#"<div hx-swap= "#, None),
+            (r#"<div class=""#, None),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(scan_position(text), expected);
         }
     }
 
@@ -591,6 +1433,7 @@ def a():
         query.change_backend("python");
         let query = &query.backend;
         let props = query_props(closest_node, case, trigger_point, query, true);
-        assert_eq!(props.len(), 3);
+        let comments = props.keys().filter(|key| key.starts_with("hx_comment"));
+        assert_eq!(comments.count(), 3);
     }
 }
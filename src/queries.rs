@@ -1,10 +1,13 @@
 /// Search for htmx attributes, annotated by @attr_name
-/// `@attr_name` must start with `hx-`.
+/// `@attr_name` must start with `hx-`, partial `data-hx-` (`data-h`) is also matched.
 ///
 /// `@complete_match` is @attr_name
 ///
 /// `@unfinished_tag` contains error in query but it can still recommend
 /// htmx attribute.
+///
+/// `@error_tag` - tag that is not finished at end of nested element
+/// (`<svg><g hx-`), only attribute name itself is completed.
 pub static HX_NAME: &str = r#"
 (
         [
@@ -25,9 +28,15 @@ pub static HX_NAME: &str = r#"
 
              (ERROR)? @equal_error
             ) @unfinished_tag
+
+            (ERROR
+              (tag_name)
+
+              (attribute_name) @attr_name
+            ) @error_tag
         ]
 
-        (#match? @attr_name "hx-.*")
+        (#match? @attr_name "hx-.*|^data-(h|hx)?$")
 )
     
 "#;
@@ -38,7 +47,8 @@ pub static HX_NAME: &str = r#"
 ///
 /// `@attr_value` - attribute value.
 ///
-/// `@open_quote_error` - started typing but second quote is missing.
+/// `@open_quote_error` - started typing but second quote is missing, also
+/// at end of unfinished nested tag.
 ///
 /// `@error_char` - means missing '=' or badly positioned '='.
 ///
@@ -46,6 +56,8 @@ pub static HX_NAME: &str = r#"
 ///
 /// `@non_empty_attribute` - attribute value isn't empty, usually used for hover,
 /// goto implementation and goto definition request.
+///
+/// Extension attributes (`sse-swap`, `ws-connect`, `preload`) are also matched.
 pub static HX_VALUE: &str = r#"
 (
         [
@@ -56,6 +68,16 @@ pub static HX_VALUE: &str = r#"
             (_)
           ) @open_quote_error
 
+          (ERROR
+            (tag_name)
+
+            (attribute_name) @attr_name
+            .
+            "="
+            .
+            ["\"" "'"]
+          ) @open_quote_error
+
           (_ 
             (tag_name)
 
@@ -89,22 +111,56 @@ pub static HX_VALUE: &str = r#"
           )
         ]
 
-        (#match? @attr_name "hx-.*")
+        (#match? @attr_name "hx-.*|^(data-)?(sse-|ws-|preload$)")
 )"#;
 
-/// Rust comments should be inside of function, closure or block.
+/// Rust comments should be inside of function, closure or block, or right above
+/// handler function or route struct.
 /// `@hx_comment` - comment. Tag starts after '@'.
+/// `@hx_item` - function or struct that tag belongs to.
+/// `@hx_item_comment` - comment inside `@hx_item`, same comment is also `@hx_comment`.
 pub static HX_RUST_TAGS: &str = r#"
 (
     (block
-    	(line_comment) @hx_comment
+    	[(line_comment) (block_comment)] @hx_comment
     )
-	(#match? @hx_comment " hx@.*")
+	(#match? @hx_comment "(^|[^a-zA-Z0-9_])hx@")
+)
+
+(
+    [(line_comment) (block_comment)] @hx_comment
+    .
+    (attribute_item)*
+    .
+    [(function_item) (struct_item) (enum_item)] @hx_item
+	(#match? @hx_comment "(^|[^a-zA-Z0-9_])hx@")
+)
+
+(
+    (attribute_item
+        (attribute (identifier) @_attribute)
+    ) @hx_comment
+    .
+    (attribute_item)*
+    .
+    [(function_item) (struct_item) (enum_item)] @hx_item
+	(#eq? @_attribute "htmx_tag")
+)
+
+(
+    (function_item
+        body: (block
+            [(line_comment) (block_comment)] @hx_item_comment
+        )
+    ) @hx_item
+	(#match? @hx_item_comment "(^|[^a-zA-Z0-9_])hx@")
 )
 "#;
 
-/// JavaScript/TypeScript comments are inside any type of function.
-/// `@hx_comment` - comment. Tag starts after '@'.
+/// JavaScript/TypeScript comments are inside any type of function, or JSDoc
+/// comment above function has `@hx cart` annotation.
+/// `@hx_comment` - comment. Tag starts after '@' (or after `@hx ` in JSDoc).
+/// `@hx_item` - function that tag belongs to.
 pub static HX_JS_TAGS: &str = r#"
 (
 	[
@@ -112,22 +168,78 @@ pub static HX_JS_TAGS: &str = r#"
           (statement_block
           	(comment) @hx_comment
           )
-      ) 
+      ) @hx_item
       
       (arrow_function
         (statement_block
           (comment) @hx_comment
         )
-      ) 
+      ) @hx_item
 
       (method_definition
         (statement_block
             (comment) @hx_comment
         )
-      )
+      ) @hx_item
     ]
     
-    (#match? @hx_comment " hx@")
+    (#match? @hx_comment "(^|[^a-zA-Z0-9_])hx@")
+)
+
+(
+    (comment) @hx_comment
+    .
+    [
+      (function_declaration) @hx_item
+      (method_definition) @hx_item
+      (lexical_declaration
+        (variable_declarator value: [(arrow_function) (function)] @hx_item)
+      )
+      (export_statement
+        declaration: [
+          (function_declaration) @hx_item
+          (lexical_declaration
+            (variable_declarator value: [(arrow_function) (function)] @hx_item)
+          )
+        ]
+      )
+    ]
+    (#match? @hx_comment "^/[*][*]")
+    (#match? @hx_comment "@hx[ \t]")
+)
+"#;
+
+/// TypeScript patterns, added to `HX_JS_TAGS`. JSDoc comment can be above
+/// decorators of method or above abstract method.
+pub static HX_TS_TAGS: &str = r#"
+(
+    (comment) @hx_comment
+    .
+    (decorator)+
+    .
+    (method_definition) @hx_item
+
+    (#match? @hx_comment "^/[*][*]")
+    (#match? @hx_comment "@hx[ \t]")
+)
+
+(
+    (comment) @hx_comment
+    .
+    (abstract_method_signature) @hx_item
+
+    (#match? @hx_comment "^/[*][*]")
+    (#match? @hx_comment "@hx[ \t]")
+)
+"#;
+
+/// Tag comments in templates (`<!-- hx@cart-row -->`).
+/// `@hx_comment` - comment. Tag starts after '@'.
+pub static HX_HTML_TAGS: &str = r#"
+(
+    (comment) @hx_comment
+
+    (#match? @hx_comment "(^|[^a-zA-Z0-9_])hx@")
 )
 "#;
 
@@ -145,6 +257,64 @@ pub static HX_HTML: &str = r#"
 )
 "#;
 
+/// Find htmx attributes with '=' but without quotes(`hx-get= `).
+/// `@equal_error` - error that starts with '='.
+pub static HX_MISSING_QUOTES: &str = r#"
+(
+    (attribute
+        (attribute_name) @attr_name
+        .
+    )
+    .
+    (ERROR) @equal_error
+
+    (#match? @attr_name "hx-.*")
+    (#match? @equal_error "^=")
+)
+"#;
+
+/// Find script element that loads htmx.
+pub static HX_SCRIPT: &str = r#"
+(
+    (script_element
+        (start_tag
+            (attribute
+                (attribute_name) @attr_name
+                (quoted_attribute_value
+                    (attribute_value) @attr_value
+                )
+            )
+        )
+    )
+
+    (#eq? @attr_name "src")
+    (#match? @attr_value "htmx")
+)
+"#;
+
+/// Find any htmx attribute.
+pub static HX_USAGE: &str = r#"
+(
+    (attribute_name) @attr_name
+
+    (#match? @attr_name "^hx-")
+)
+"#;
+
+/// All htmx attributes with values. Used for value history.
+pub static HX_VALUES: &str = r#"
+(
+    (attribute
+        (attribute_name) @attr_name
+        (quoted_attribute_value
+            (attribute_value) @attr_value
+        )
+    )
+
+    (#match? @attr_name "^hx-")
+)
+"#;
+
 /// Replace "NAME" with any html attribute. At the moment not used query.
 pub static HX_ANY_HTML: &str = r#"
 (
@@ -163,14 +333,37 @@ pub static HX_ANY_HTML: &str = r#"
 /// They can be everywhere. Because of identation it has no information about
 /// scope.
 /// `@hx_comment` - comment. Tag starts after '@'.
+/// `@hx_item` - function or class right below comment, or function around it.
+/// `@hx_item_comment` - comment of `@hx_item`.
 pub static HX_PYTHON_TAGS: &str = r#"
 (
 	(comment) @hx_comment
 )
+
+(
+    (comment) @hx_item_comment
+    .
+    [(function_definition) (decorated_definition) (class_definition)] @hx_item
+)
+
+(
+    (function_definition
+        body: (block
+            (comment) @hx_item_comment
+        )
+    ) @hx_item
+)
+
+(
+    (function_definition
+        (comment) @hx_item_comment
+    ) @hx_item
+)
 "#;
 
 /// Go comments are inside of function.
 /// `@hx_comment` - comment. Tag starts after '@'.
+/// `@hx_item` - function that tag belongs to.
 pub static HX_GO_TAGS: &str = r#"
 
 (
@@ -179,10 +372,10 @@ pub static HX_GO_TAGS: &str = r#"
         	(block
             	(comment) @hx_comment
             )
-        )
+        ) @hx_item
     ]
     
-	(#match? @hx_comment " hx@")
+	(#match? @hx_comment "(^|[^a-zA-Z0-9_])hx@")
 )
 	    
 "#;
@@ -3,12 +3,16 @@ use std::collections::HashMap;
 use tree_sitter::{Node, Point, Query, QueryCursor};
 
 use crate::{
-    htmx_tags::{get_tag, get_tags, Tag},
+    config::TS_EXTS,
+    htmx_tags::{get_attribute_tag, get_jsdoc_tag, get_tag, get_tags, Tag, TagItem},
+    htmx_tree_sitter::typescript_language,
     init_hx::LangType,
-    position::{CaptureDetails, Position, PositionDefinition, QueryType},
+    position::{
+        at_line_end, CaptureDetails, Position, PositionDefinition, QueryType, NEW_ATTRIBUTE,
+    },
     queries::{
-        HX_ANY_HTML, HX_GO_TAGS, HX_HTML, HX_JS_TAGS, HX_NAME, HX_PYTHON_TAGS, HX_RUST_TAGS,
-        HX_VALUE,
+        HX_ANY_HTML, HX_GO_TAGS, HX_HTML, HX_HTML_TAGS, HX_JS_TAGS, HX_MISSING_QUOTES, HX_NAME,
+        HX_PYTHON_TAGS, HX_RUST_TAGS, HX_SCRIPT, HX_TS_TAGS, HX_USAGE, HX_VALUE, HX_VALUES,
     },
 };
 
@@ -17,10 +21,14 @@ use crate::{
 pub struct Queries {
     /// Check `HTMLQueries` for more info.
     pub html: HTMLQueries,
-    /// JavaScript/TypeScript query. TreeSitter query for both languages is same.
+    /// JavaScript query.
     pub javascript: Query,
+    /// TypeScript and TSX queries, by file extension.
+    pub typescript: HashMap<&'static str, Query>,
     /// Backend tags query. Can be in Python, Rust, Go.
     pub backend: Query,
+    /// Tag queries of `backend_tags` directories with own `lang`, by file extension.
+    pub backends: HashMap<String, Query>,
 }
 
 impl Clone for Queries {
@@ -34,7 +42,12 @@ impl Default for Queries {
         Self {
             html: HTMLQueries::default(),
             javascript: Query::new(tree_sitter_javascript::language(), HX_JS_TAGS).unwrap(),
+            typescript: TS_EXTS
+                .into_iter()
+                .filter_map(|ext| Some((ext, typescript_query(ext)?)))
+                .collect(),
             backend: Query::new(tree_sitter_rust::language(), HX_RUST_TAGS).unwrap(),
+            backends: HashMap::new(),
         }
     }
 }
@@ -51,26 +64,60 @@ impl Queries {
 
     /// Default backend language is Rust. Change at the beginning to other.
     pub fn change_backend(&mut self, lang: &str) -> Option<()> {
-        let lang = match lang {
-            "python" => Some((tree_sitter_python::language(), HX_PYTHON_TAGS)),
-            "go" => Some((tree_sitter_go::language(), HX_GO_TAGS)),
-            _ => None,
-        };
-        if let Some(lang) = lang {
-            self.backend = Query::new(lang.0, lang.1).unwrap();
+        if let Some(query) = backend_query(lang) {
+            self.backend = query;
         }
         None
     }
+
+    /// Queries for languages of `backend_tags` directories, `langs` are language
+    /// names with file extension.
+    pub fn set_backends(&mut self, langs: &[(&str, &str)]) {
+        self.backends = langs
+            .iter()
+            .filter_map(|(lang, ext)| Some((ext.to_string(), backend_query(lang)?)))
+            .collect();
+    }
+}
+
+/// Tag query of TypeScript grammar, JavaScript tags and TypeScript only ones.
+fn typescript_query(ext: &str) -> Option<Query> {
+    Query::new(
+        typescript_language(ext)?,
+        &format!("{HX_JS_TAGS}{HX_TS_TAGS}"),
+    )
+    .ok()
+}
+
+/// Tag query of bundled backend language.
+fn backend_query(lang: &str) -> Option<Query> {
+    let (language, query) = match lang {
+        "rust" => (tree_sitter_rust::language(), HX_RUST_TAGS),
+        "python" => (tree_sitter_python::language(), HX_PYTHON_TAGS),
+        "go" => (tree_sitter_go::language(), HX_GO_TAGS),
+        _ => return None,
+    };
+    Query::new(language, query).ok()
 }
 
-/// HTMLQueries has three queries:
+/// HTMLQueries has eight queries:
 /// * lsp `HX_HTML`
 /// * name `HX_NAME`
-/// * value `HX_VALUE`   
+/// * value `HX_VALUE`
+/// * missing quotes `HX_MISSING_QUOTES`
+/// * script `HX_SCRIPT`
+/// * usage `HX_USAGE`
+/// * values `HX_VALUES`
+/// * tags `HX_HTML_TAGS`
 pub struct HTMLQueries {
     lsp: Query,
     name: Query,
     value: Query,
+    missing_quotes: Query,
+    script: Query,
+    usage: Query,
+    values: Query,
+    tags: Query,
 }
 
 impl Default for HTMLQueries {
@@ -78,7 +125,21 @@ impl Default for HTMLQueries {
         let lsp = Query::new(tree_sitter_html::language(), HX_HTML).unwrap();
         let name = Query::new(tree_sitter_html::language(), HX_NAME).unwrap();
         let value = Query::new(tree_sitter_html::language(), HX_VALUE).unwrap();
-        Self { lsp, name, value }
+        let missing_quotes = Query::new(tree_sitter_html::language(), HX_MISSING_QUOTES).unwrap();
+        let script = Query::new(tree_sitter_html::language(), HX_SCRIPT).unwrap();
+        let usage = Query::new(tree_sitter_html::language(), HX_USAGE).unwrap();
+        let values = Query::new(tree_sitter_html::language(), HX_VALUES).unwrap();
+        let tags = Query::new(tree_sitter_html::language(), HX_HTML_TAGS).unwrap();
+        Self {
+            lsp,
+            name,
+            value,
+            missing_quotes,
+            script,
+            usage,
+            values,
+            tags,
+        }
     }
 }
 
@@ -94,16 +155,22 @@ impl HTMLQueries {
             HTMLQuery::Lsp => &self.lsp,
             HTMLQuery::Name => &self.name,
             HTMLQuery::Value => &self.value,
+            HTMLQuery::MissingQuotes => &self.missing_quotes,
+            HTMLQuery::Script => &self.script,
+            HTMLQuery::Usage => &self.usage,
+            HTMLQuery::Values => &self.values,
+            HTMLQuery::Tags => &self.tags,
         }
     }
 
-    /// Generate new query, for some random, non-htmx attribute.
-    pub fn get_by_attribute_name(name: &str) -> Query {
+    /// Generate new query, for some random, non-htmx attribute. Name that breaks
+    /// query (`"`) has no query.
+    pub fn get_by_attribute_name(name: &str) -> Option<Query> {
         Query::new(
             tree_sitter_html::language(),
             &HX_ANY_HTML.replace("NAME", name),
         )
-        .unwrap()
+        .ok()
     }
 }
 
@@ -112,6 +179,11 @@ pub enum HTMLQuery {
     Lsp,
     Name,
     Value,
+    MissingQuotes,
+    Script,
+    Usage,
+    Values,
+    Tags,
 }
 
 /// HtmxQuery
@@ -126,13 +198,16 @@ impl TryFrom<LangType> for HtmxQuery {
 
     fn try_from(value: LangType) -> Result<Self, Self::Error> {
         match value {
-            LangType::Template => Err(()),
+            LangType::Template => Ok(HtmxQuery::Html(HTMLQuery::Tags)),
             LangType::JavaScript => Ok(HtmxQuery::JavaScript),
             LangType::Backend => Ok(HtmxQuery::Backend),
         }
     }
 }
 
+/// Rows before and after trigger point that are searched for position.
+const QUERY_WINDOW: usize = 200;
+
 /// Capture all query results. No duplicates, except when searching for hx_comment.
 pub fn query_props(
     node: Node<'_>,
@@ -142,6 +217,13 @@ pub fn query_props(
     all: bool,
 ) -> HashMap<String, CaptureDetails> {
     let mut cursor_qry = QueryCursor::new();
+    if !all {
+        // Element around trigger point can contain whole document, only matches
+        // near trigger point are needed.
+        let start = Point::new(trigger_point.row.saturating_sub(QUERY_WINDOW), 0);
+        let end = Point::new(trigger_point.row + QUERY_WINDOW, 0);
+        cursor_qry.set_point_range(start..end);
+    }
     let capture_names = query.capture_names();
     let matches = cursor_qry.matches(query, node, source.as_bytes());
 
@@ -194,6 +276,9 @@ pub fn query_name(
 ) -> Option<Position> {
     let props = query_props(element, source, trigger_point, query, false);
     let attr_name = props.get("attr_name")?;
+    if props.contains_key("error_tag") && trigger_point > attr_name.end_position {
+        return None;
+    }
     if let Some(unfinished_tag) = props.get("unfinished_tag") {
         if query_type == &QueryType::Hover {
             let complete_match = props.get("complete_match");
@@ -204,7 +289,7 @@ pub fn query_name(
         } else if query_type == &QueryType::Completion
             && trigger_point > unfinished_tag.end_position
         {
-            return Some(Position::AttributeName(String::from("--")));
+            return Some(Position::AttributeName(String::from(NEW_ATTRIBUTE)));
         } else if let Some(_capture) = props.get("equal_error") {
             if query_type == &QueryType::Completion {
                 return None;
@@ -253,18 +338,22 @@ pub fn query_value(
     };
 
     if let Some(capture) = props.get("non_empty_attribute") {
-        if trigger_point >= capture.end_position {
+        // Cursor after closing quote at line end still points to value, except in completion.
+        let inclusive = query_type != &QueryType::Completion && at_line_end(source, trigger_point);
+        if trigger_point > capture.end_position
+            || (trigger_point == capture.end_position && !inclusive)
+        {
             return None;
         }
         if query_type == &QueryType::Hover || query_type == &QueryType::Definition {
-            let mut start = 0;
+            let mut start = Point::new(0, 0);
             let _ = props.get("attr_value").is_some_and(|s| {
                 value = s.value.to_string();
-                start = s.start_position.column;
+                start = s.start_position;
                 true
             });
-            if query_type == &QueryType::Definition {
-                //
+            // Hover on tag needs its position too.
+            if query_type == &QueryType::Definition || attr_name.value == "hx-lsp" {
                 definition = Some(PositionDefinition::new(start, trigger_point));
             }
         }
@@ -286,12 +375,40 @@ pub fn query_tag(
     query: &Query,
     full: bool,
 ) -> Vec<Tag> {
-    let comments = query_props(element, source, trigger_point, query, full);
-    let mut tags = vec![];
+    let mut comments: Vec<(String, CaptureDetails)> =
+        query_props(element, source, trigger_point, query, full)
+            .into_iter()
+            .filter(|(key, _)| key.starts_with("hx_comment"))
+            .collect();
+    // Captures come from map, tags must be in document order (first one is definition).
+    comments.sort_by_key(|(_, capture)| capture.start_position);
+    let items = query_tag_items(element, source, query);
+    let mut tags: Vec<Tag> = vec![];
+    // Other captures describe item around comment, same comment can match twice.
     for comment in comments {
-        if let Some(mut tag) = get_tag(&comment.1.value) {
-            tag.start.row = comment.1.start_position.row;
-            tag.end.row = comment.1.start_position.row;
+        let value = &comment.1.value;
+        let tag = get_tag(value)
+            .or_else(|| get_jsdoc_tag(value))
+            .or_else(|| get_attribute_tag(value));
+        if let Some(mut tag) = tag {
+            let start = comment.1.start_position;
+            // Block comment can span more lines.
+            let before = &value[..tag.start.column];
+            let point = match before.rfind('\n') {
+                Some(newline) => Point::new(
+                    start.row + before.matches('\n').count(),
+                    before.len() - newline - 1,
+                ),
+                None => Point::new(start.row, start.column + before.len()),
+            };
+            if tags.iter().any(|tag| tag.start == point) {
+                continue;
+            }
+            tag.start = point;
+            tag.end = Point::new(point.row, point.column + tag.name.len());
+            tag.item = smallest_item(&items, start.row)
+                .and_then(|item| tag_item(item, source))
+                .map(Box::new);
             tags.push(tag);
         }
     }
@@ -319,10 +436,8 @@ pub fn query_htmx_lsp(
                 capture.1.start_position.column,
                 capture.1.start_position.row,
             );
-            if let Some(tags) = tags {
-                let tag = tags.iter().find(|item| item.name == tag_name);
-                if let Some(tag) = tag {
-                    let mut tag = tag.clone();
+            for mut tag in tags.into_iter().flatten() {
+                if tag.name == tag_name {
                     tag.file = file;
                     references.push(tag);
                 }
@@ -333,6 +448,118 @@ pub fn query_htmx_lsp(
     }
 }
 
+/// Positions right after '=' for every htmx attribute without quotes.
+pub fn query_missing_quotes(element: Node<'_>, source: &str, query: &Query) -> Vec<Point> {
+    let mut cursor_qry = QueryCursor::new();
+    let capture_names = query.capture_names();
+    let matches = cursor_qry.matches(query, element, source.as_bytes());
+    let mut points = vec![];
+    for m in matches {
+        for capture in m.captures {
+            if capture_names[capture.index as usize] == "equal_error" {
+                let start = capture.node.start_position();
+                points.push(Point::new(start.row, start.column + 1));
+            }
+        }
+    }
+    points
+}
+
+/// Every (attribute name, attribute value) pair from `HX_VALUES` query.
+pub fn query_attribute_values(
+    element: Node<'_>,
+    source: &str,
+    query: &Query,
+) -> Vec<(String, String)> {
+    let mut cursor_qry = QueryCursor::new();
+    let capture_names = query.capture_names();
+    let matches = cursor_qry.matches(query, element, source.as_bytes());
+    let mut values = vec![];
+    for m in matches {
+        let mut name = None;
+        let mut value = None;
+        for capture in m.captures {
+            let text = capture.node.utf8_text(source.as_bytes()).ok();
+            match capture_names[capture.index as usize].as_str() {
+                "attr_name" => name = text,
+                "attr_value" => value = text,
+                _ => (),
+            }
+        }
+        if let (Some(name), Some(value)) = (name, value) {
+            values.push((name.to_string(), value.to_string()));
+        }
+    }
+    values
+}
+
+/// Checks if query has at least one match.
+pub fn query_has_match(element: Node<'_>, source: &str, query: &Query) -> bool {
+    let mut cursor_qry = QueryCursor::new();
+    let mut matches = cursor_qry.matches(query, element, source.as_bytes());
+    matches.next().is_some()
+}
+
+/// Item (`@hx_item`) that tag comment on `row` belongs to. When comment is in
+/// more items (comment above nested function), smallest one is returned.
+pub fn query_tag_item<'a>(
+    element: Node<'a>,
+    source: &str,
+    query: &Query,
+    row: usize,
+) -> Option<Node<'a>> {
+    smallest_item(&query_tag_items(element, source, query), row)
+}
+
+fn smallest_item<'a>(items: &[(usize, Node<'a>)], row: usize) -> Option<Node<'a>> {
+    items
+        .iter()
+        .filter(|(comment, _)| *comment == row)
+        .map(|(_, item)| *item)
+        .min_by_key(|item| item.byte_range().len())
+}
+
+/// Row of every tag comment with its item.
+fn query_tag_items<'a>(element: Node<'a>, source: &str, query: &Query) -> Vec<(usize, Node<'a>)> {
+    let mut cursor_qry = QueryCursor::new();
+    let capture_names = query.capture_names();
+    let matches = cursor_qry.matches(query, element, source.as_bytes());
+    let mut items = vec![];
+    for m in matches {
+        let mut comment = None;
+        let mut item = None;
+        for capture in m.captures {
+            match capture_names[capture.index as usize].as_str() {
+                "hx_comment" | "hx_item_comment" => comment = Some(capture.node),
+                "hx_item" => item = Some(capture.node),
+                _ => (),
+            }
+        }
+        if let (Some(comment), Some(item)) = (comment, item) {
+            items.push((comment.start_position().row, item));
+        }
+    }
+    items
+}
+
+/// Name and range of tag item. Anonymous functions get name from variable
+/// (`const load = () => {}`), decorators are skipped.
+pub fn tag_item(item: Node<'_>, source: &str) -> Option<TagItem> {
+    let item = match item.kind() {
+        "decorated_definition" => item.child_by_field_name("definition")?,
+        _ => item,
+    };
+    let name = item
+        .child_by_field_name("name")
+        .or_else(|| item.parent()?.child_by_field_name("name"))?;
+    Some(TagItem {
+        name: name.utf8_text(source.as_bytes()).ok()?.to_string(),
+        start: item.start_position(),
+        end: item.end_position(),
+        name_end: name.end_position(),
+    })
+}
+
 /// `HX_HTML`
 pub fn find_hx_lsp(
     element: Node<'_>,
@@ -347,3 +574,312 @@ pub fn find_hx_lsp(
     }
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use tree_sitter::{Language, Parser, Point, Query};
+
+    use crate::{
+        htmx_tree_sitter::typescript_language,
+        position::QueryType,
+        queries::{HX_GO_TAGS, HX_JS_TAGS, HX_PYTHON_TAGS, HX_RUST_TAGS},
+    };
+
+    use super::{
+        query_has_match, query_missing_quotes, query_tag, typescript_query, HTMLQueries, HTMLQuery,
+    };
+
+    fn tag_position(language: Language, query: &str, text: &str) -> (String, Point, Point) {
+        let mut parser = Parser::new();
+        parser
+            .set_language(language)
+            .expect("could not load grammar");
+        let tree = parser.parse(text, None).expect("not to fail");
+        let query = Query::new(language, query).unwrap();
+        let tags = query_tag(
+            tree.root_node(),
+            text,
+            Point::new(0, 0),
+            &QueryType::Completion,
+            &query,
+            true,
+        );
+        assert_eq!(tags.len(), 1);
+        let tag = &tags[0];
+        (tag.name.clone(), tag.start, tag.end)
+    }
+
+    #[test]
+    fn missing_quotes() {
+        let text = "<div class=\"p-4\" hx-swap=>\n<span hx-target= ></span></div>";
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_html::language()).unwrap();
+        let tree = parser.parse(text, None).unwrap();
+        let queries = HTMLQueries::default();
+        let points = query_missing_quotes(
+            tree.root_node(),
+            text,
+            queries.get(HTMLQuery::MissingQuotes),
+        );
+        assert_eq!(points, vec![Point::new(0, 25), Point::new(1, 16)]);
+    }
+
+    #[test]
+    fn htmx_script_and_usage() {
+        let queries = HTMLQueries::default();
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_html::language()).unwrap();
+        let cases = [
+            (
+                r#"<script defer src="/static/htmx.min.js"></script>"#,
+                false,
+                true,
+            ),
+            (
+                r#"<script src="/app.js"></script><a hx-get="/a"></a>"#,
+                true,
+                false,
+            ),
+            (r#"<div class="hx-get"></div>"#, false, false),
+        ];
+        for (text, used, loaded) in cases {
+            let tree = parser.parse(text, None).unwrap();
+            let root = tree.root_node();
+            assert_eq!(
+                query_has_match(root, text, queries.get(HTMLQuery::Usage)),
+                used
+            );
+            assert_eq!(
+                query_has_match(root, text, queries.get(HTMLQuery::Script)),
+                loaded
+            );
+        }
+    }
+
+    #[test]
+    fn tags_carry_their_items() {
+        let cases = [
+            (
+                tree_sitter_rust::language(),
+                HX_RUST_TAGS,
+                "// hx@above\n#[get(\"/\")]\nasync fn get_cart() {}\nfn list() {\n    // hx@inside\n}\n",
+            ),
+            (
+                tree_sitter_javascript::language(),
+                HX_JS_TAGS,
+                "const get_cart = () => {\n    // hx@above\n};\nfunction list() {\n    // hx@inside\n}\n",
+            ),
+            (
+                tree_sitter_python::language(),
+                HX_PYTHON_TAGS,
+                "# hx@above\n@app.get(\"/\")\ndef get_cart():\n    pass\ndef list():\n    # hx@inside\n    pass\n",
+            ),
+            (
+                tree_sitter_go::language(),
+                HX_GO_TAGS,
+                "package main\nfunc get_cart() {\n    // hx@above\n}\nfunc list() {\n    // hx@inside\n}\n",
+            ),
+        ];
+        for (language, query, text) in cases {
+            let mut parser = Parser::new();
+            parser.set_language(language).unwrap();
+            let tree = parser.parse(text, None).unwrap();
+            let query = Query::new(language, query).unwrap();
+            let mut tags = query_tag(
+                tree.root_node(),
+                text,
+                Point::new(0, 0),
+                &QueryType::Completion,
+                &query,
+                true,
+            );
+            tags.sort_by(|a, b| a.name.cmp(&b.name));
+            let items: Vec<_> = tags
+                .iter()
+                .map(|tag| {
+                    (
+                        tag.name.as_str(),
+                        tag.item.as_ref().map(|item| item.name.as_str()),
+                    )
+                })
+                .collect();
+            assert_eq!(
+                items,
+                [("above", Some("get_cart")), ("inside", Some("list"))],
+                "{text}"
+            );
+        }
+    }
+
+    #[test]
+    fn jsdoc_and_typescript_tags() {
+        let language = tree_sitter_javascript::language();
+        let text = r#"interface Cart { id: number }
+/**
+ * Loads cart.
+ * @hx cart
+ */
+export async function cart<T>(id: T): Promise<void> {}
+/** @hx rows */
+export const rows = (page: number) => {};
+class Users {
+    /**
+     * @hx users
+     */
+    load(id: number): void {}
+    save(user: User): void {
+        // hx@save
+    }
+}
+/** @hxcart is not annotation */
+function plain() {}
+"#;
+        let mut parser = Parser::new();
+        parser.set_language(language).unwrap();
+        let tree = parser.parse(text, None).unwrap();
+        let query = Query::new(language, HX_JS_TAGS).unwrap();
+        let tags = query_tag(
+            tree.root_node(),
+            text,
+            Point::new(0, 0),
+            &QueryType::Completion,
+            &query,
+            true,
+        );
+        let tags: Vec<_> = tags
+            .iter()
+            .map(|tag| {
+                (
+                    tag.name.as_str(),
+                    tag.start,
+                    tag.item.as_ref().map(|item| item.name.as_str()),
+                )
+            })
+            .collect();
+        assert_eq!(
+            tags,
+            [
+                ("cart", Point::new(3, 7), Some("cart")),
+                ("rows", Point::new(6, 8), Some("rows")),
+                ("users", Point::new(10, 11), Some("load")),
+                ("save", Point::new(14, 14), Some("save")),
+            ]
+        );
+    }
+
+    #[test]
+    fn typescript_grammar_tags() {
+        let text = r#"enum Status { Active = "active", Archived = "archived" }
+function first<T extends { id: number }>(items: T[]): T | undefined {
+    // hx@first
+    return items[0];
+}
+abstract class Repository<T> {
+    /** @hx find */
+    abstract find(id: number): Promise<T>;
+    /**
+     * @hx list
+     */
+    @Get("/users")
+    @Cache()
+    list(): T[] {
+        return [];
+    }
+}
+export const routes = {
+    load: () => {
+        // hx@load
+    },
+} satisfies Record<string, () => void>;
+"#;
+        let language = typescript_language("ts").unwrap();
+        let mut parser = Parser::new();
+        parser.set_language(language).unwrap();
+        let tree = parser.parse(text, None).unwrap();
+        assert!(!tree.root_node().has_error());
+        let query = typescript_query("ts").unwrap();
+        let tags = query_tag(
+            tree.root_node(),
+            text,
+            Point::new(0, 0),
+            &QueryType::Completion,
+            &query,
+            true,
+        );
+        let tags: Vec<_> = tags
+            .iter()
+            .map(|tag| {
+                (
+                    tag.name.as_str(),
+                    tag.start,
+                    tag.item.as_ref().map(|item| item.name.as_str()),
+                )
+            })
+            .collect();
+        assert_eq!(
+            tags,
+            [
+                ("first", Point::new(2, 10), Some("first")),
+                ("find", Point::new(6, 12), Some("find")),
+                ("list", Point::new(9, 11), Some("list")),
+                ("load", Point::new(19, 14), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn tsx_grammar_tags() {
+        let text = r#"export function Cart({ items }: { items: string[] }) {
+    // hx@cart
+    return <ul hx-get="/cart">{items.map((item) => <li>{item}</li>)}</ul>;
+}
+"#;
+        let mut parser = Parser::new();
+        parser
+            .set_language(typescript_language("tsx").unwrap())
+            .unwrap();
+        let tree = parser.parse(text, None).unwrap();
+        assert!(!tree.root_node().has_error());
+        let query = typescript_query("tsx").unwrap();
+        let tags = query_tag(
+            tree.root_node(),
+            text,
+            Point::new(0, 0),
+            &QueryType::Completion,
+            &query,
+            true,
+        );
+        assert_eq!(tags.len(), 1);
+        assert_eq!(tags[0].start, Point::new(1, 10));
+        assert_eq!(tags[0].item.as_ref().unwrap().name, "Cart");
+        assert!(typescript_query("js").is_none());
+    }
+
+    #[test]
+    fn tag_range_covers_only_name() {
+        let cases = [
+            (
+                tree_sitter_rust::language(),
+                HX_RUST_TAGS,
+                "fn a() {\n    // hx@counter\n}\n",
+            ),
+            (
+                tree_sitter_javascript::language(),
+                HX_JS_TAGS,
+                "function a() {\n    // hx@counter\n}\n",
+            ),
+            (
+                tree_sitter_python::language(),
+                HX_PYTHON_TAGS,
+                "def a():\n    # hx@counter\n    pass\n",
+            ),
+        ];
+        for (language, query, text) in cases {
+            let (name, start, end) = tag_position(language, query, text);
+            let line = text.lines().nth(1).unwrap();
+            assert_eq!(name, "counter");
+            assert_eq!(start, Point::new(1, line.find("counter").unwrap()));
+            assert_eq!(&line[start.column..end.column], "counter");
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::atomic::{AtomicU64, Ordering},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use serde_json::Value;
+use tower_lsp::jsonrpc::{Request, Response};
+use tower_service::Service;
+
+/// Id of next request, ids are unique in process (also in daemon mode).
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Requests slower than this (in milliseconds) are logged as warning, 0 - never.
+static SLOW_REQUEST_MS: AtomicU64 = AtomicU64::new(crate::config::SLOW_REQUEST_MS);
+
+tokio::task_local! {
+    static REQUEST_ID: u64;
+}
+
+pub fn set_slow_request_ms(ms: u64) {
+    SLOW_REQUEST_MS.store(ms, Ordering::Relaxed);
+}
+
+/// Id of request that is handled by current task. Log lines written by handler
+/// have it, see `main`.
+pub fn current_request() -> Option<u64> {
+    REQUEST_ID.try_with(|id| *id).ok()
+}
+
+/// Request or notification from client, as it's written in log.
+#[derive(Debug, PartialEq)]
+pub struct RequestSpan {
+    pub id: u64,
+    pub method: String,
+    /// Document uri with position (`file:///index.html:3:14`), position is
+    /// zero based, same as in LSP.
+    pub target: Option<String>,
+}
+
+impl RequestSpan {
+    pub fn new(id: u64, request: &Request) -> Self {
+        let params = request.params();
+        let uri = params
+            .and_then(|params| params.pointer("/textDocument/uri"))
+            .and_then(Value::as_str);
+        let position = params.and_then(|params| params.get("position"));
+        let position = position.and_then(|position| {
+            let line = position.get("line")?.as_u64()?;
+            let character = position.get("character")?.as_u64()?;
+            Some(format!(":{line}:{character}"))
+        });
+        Self {
+            id,
+            method: request.method().to_string(),
+            target: uri.map(|uri| format!("{uri}{}", position.unwrap_or_default())),
+        }
+    }
+
+    /// Log end of request, slow request is warning.
+    pub fn finish(&self, elapsed: Duration) {
+        let target = self.target.as_deref().unwrap_or_default();
+        let msg = format!("{} {target} took {elapsed:.1?}", self.method);
+        match is_slow(elapsed, SLOW_REQUEST_MS.load(Ordering::Relaxed)) {
+            true => log::warn!("slow request: {msg}"),
+            false => log::debug!("{msg}"),
+        }
+    }
+}
+
+fn is_slow(elapsed: Duration, threshold_ms: u64) -> bool {
+    threshold_ms != 0 && elapsed >= Duration::from_millis(threshold_ms)
+}
+
+/// Service that gives every request and notification an id and logs its method,
+/// document, position and duration. Handler runs with id in task local, so every
+/// line it logs belongs to request.
+pub struct RequestLog<S> {
+    inner: S,
+}
+
+impl<S> RequestLog<S> {
+    pub fn new(inner: S) -> Self {
+        Self { inner }
+    }
+}
+
+impl<S> Service<Request> for RequestLog<S>
+where
+    S: Service<Request, Response = Option<Response>>,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+{
+    type Response = Option<Response>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let span = RequestSpan::new(NEXT_ID.fetch_add(1, Ordering::Relaxed), &request);
+        let response = self.inner.call(request);
+        Box::pin(REQUEST_ID.scope(span.id, async move {
+            let start = Instant::now();
+            let response = response.await;
+            span.finish(start.elapsed());
+            response
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serde_json::json;
+    use tower_lsp::jsonrpc::Request;
+
+    use super::{current_request, is_slow, RequestSpan, REQUEST_ID};
+
+    #[tokio::test]
+    async fn spans_of_requests() {
+        let request = Request::build("textDocument/completion")
+            .id(4)
+            .params(json!({
+                "textDocument": { "uri": "file:///index.html" },
+                "position": { "line": 3, "character": 14 }
+            }))
+            .finish();
+        assert_eq!(
+            RequestSpan::new(7, &request),
+            RequestSpan {
+                id: 7,
+                method: String::from("textDocument/completion"),
+                target: Some(String::from("file:///index.html:3:14")),
+            }
+        );
+        let request = Request::build("initialized").params(json!({})).finish();
+        assert_eq!(RequestSpan::new(8, &request).target, None);
+
+        assert_eq!(current_request(), None);
+        assert_eq!(
+            REQUEST_ID.scope(7, async { current_request() }).await,
+            Some(7)
+        );
+
+        assert!(is_slow(Duration::from_millis(600), 500));
+        assert!(!is_slow(Duration::from_millis(400), 500));
+        assert!(!is_slow(Duration::from_secs(60), 0));
+    }
+}
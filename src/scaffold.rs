@@ -0,0 +1,324 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    config::{BackendDir, HtmxConfig, JS_EXTS},
+    config_file::{to_toml, CONFIG_FILE},
+};
+
+/// Editors supported by `init` command.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Editor {
+    Helix,
+    Kakoune,
+    Neovim,
+    VsCode,
+}
+
+impl Editor {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "helix" | "hx" => Some(Editor::Helix),
+            "kak" | "kakoune" => Some(Editor::Kakoune),
+            "nvim" | "neovim" => Some(Editor::Neovim),
+            "vscode" | "code" => Some(Editor::VsCode),
+            _ => None,
+        }
+    }
+
+    /// Project file with language server configuration.
+    pub fn config_path(&self) -> &'static str {
+        match self {
+            Editor::Helix => ".helix/languages.toml",
+            Editor::Kakoune => "kak-lsp.toml",
+            Editor::Neovim => ".nvim.lua",
+            Editor::VsCode => ".vscode/settings.json",
+        }
+    }
+}
+
+/// Directories that are never indexed.
+const SKIPPED_DIRS: [&str; 7] = [
+    "target",
+    "node_modules",
+    "venv",
+    "__pycache__",
+    "dist",
+    "build",
+    "vendor",
+];
+
+fn is_skipped(name: &str) -> bool {
+    name.starts_with('.') || SKIPPED_DIRS.contains(&name)
+}
+
+/// Backend language from project manifest.
+pub fn detect_lang(root: &Path) -> Option<&'static str> {
+    let has = |file: &str| root.join(file).exists();
+    if has("Cargo.toml") {
+        Some("rust")
+    } else if has("go.mod") {
+        Some("go")
+    } else if [
+        "pyproject.toml",
+        "requirements.txt",
+        "manage.py",
+        "setup.py",
+    ]
+    .iter()
+    .any(|file| has(file))
+    {
+        Some("python")
+    } else {
+        None
+    }
+}
+
+/// Files in project, hidden and build directories are skipped.
+fn project_files(dir: &Path) -> impl Iterator<Item = PathBuf> {
+    walkdir::WalkDir::new(dir)
+        .into_iter()
+        .filter_entry(|entry| {
+            entry.depth() == 0 || !is_skipped(&entry.file_name().to_string_lossy())
+        })
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| entry.into_path())
+}
+
+fn has_ext(path: &Path, exts: &[&str]) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| exts.contains(&ext))
+}
+
+/// Relative path with `./` prefix, as in README examples.
+fn relative(root: &Path, path: &Path) -> String {
+    let path = path.strip_prefix(root).unwrap_or(path);
+    format!("./{}", path.to_string_lossy().replace('\\', "/"))
+}
+
+/// Top level directories (and files) that contain files with `exts`.
+fn dirs_with(root: &Path, exts: &[&str]) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(root) else {
+        return vec![];
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(Result::ok)
+        .filter(|entry| !is_skipped(&entry.file_name().to_string_lossy()))
+        .map(|entry| entry.path())
+        .filter(|path| match path.is_dir() {
+            true => project_files(path).any(|file| has_ext(&file, exts)),
+            false => has_ext(path, exts),
+        })
+        .collect();
+    paths.sort();
+    paths.iter().map(|path| relative(root, path)).collect()
+}
+
+/// Directories named `templates`, nested ones are covered by parent.
+fn template_dirs(root: &Path) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = walkdir::WalkDir::new(root)
+        .max_depth(4)
+        .into_iter()
+        .filter_entry(|entry| {
+            entry.depth() == 0 || !is_skipped(&entry.file_name().to_string_lossy())
+        })
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_dir() && entry.file_name() == "templates")
+        .map(|entry| entry.into_path())
+        .collect();
+    dirs.sort();
+    let mut top: Vec<PathBuf> = vec![];
+    for dir in dirs {
+        if !top.iter().any(|parent| dir.starts_with(parent)) {
+            top.push(dir);
+        }
+    }
+    top
+}
+
+/// Most common file extension in template directories.
+fn template_ext(dirs: &[PathBuf]) -> String {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for file in dirs.iter().flat_map(|dir| project_files(dir)) {
+        if let Some(ext) = file.extension().and_then(|ext| ext.to_str()) {
+            *counts.entry(ext.to_string()).or_default() += 1;
+        }
+    }
+    counts
+        .into_iter()
+        .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(&a.0)))
+        .map(|(ext, _)| ext)
+        .unwrap_or_else(|| String::from("html"))
+}
+
+/// Config guessed from project layout. Without known backend language
+/// template-only config is returned.
+pub fn detect_config(root: &Path) -> HtmxConfig {
+    let Some(lang) = detect_lang(root) else {
+        return HtmxConfig {
+            template_only: true,
+            ..Default::default()
+        };
+    };
+    let backend_ext = match lang {
+        "rust" => "rs",
+        "go" => "go",
+        _ => "py",
+    };
+    let templates = template_dirs(root);
+    HtmxConfig {
+        lang: lang.to_string(),
+        template_ext: template_ext(&templates),
+        templates: templates.iter().map(|dir| relative(root, dir)).collect(),
+        js_tags: dirs_with(root, &JS_EXTS),
+        backend_tags: dirs_with(root, &[backend_ext])
+            .into_iter()
+            .map(BackendDir::from)
+            .collect(),
+        ..Default::default()
+    }
+}
+
+/// Content of `htmx-lsp.toml`.
+pub fn config_file(config: &HtmxConfig) -> String {
+    let value = match config.template_only {
+        true => serde_json::json!({ "template_only": true }),
+        false => serde_json::json!({
+            "lang": config.lang,
+            "template_ext": config.template_ext,
+            "templates": config.templates,
+            "js_tags": config.js_tags,
+            "backend_tags": config.backend_tags,
+        }),
+    };
+    format!(
+        "# htmx-lsp2 config, used when editor sends no initialization options.\n{}",
+        to_toml(&value)
+    )
+}
+
+/// Language server configuration for editor. Config itself is read from
+/// `htmx-lsp.toml`, so snippets only start server for templates.
+pub fn editor_config(editor: Editor, config: &HtmxConfig) -> String {
+    let mut filetypes = vec![String::from("html")];
+    if !config.template_only && !filetypes.contains(&config.template_ext) {
+        filetypes.push(config.template_ext.clone());
+    }
+    let quoted = filetypes
+        .iter()
+        .map(|filetype| format!("\"{filetype}\""))
+        .collect::<Vec<_>>()
+        .join(", ");
+    match editor {
+        Editor::Helix => {
+            let mut snippet = String::from(
+                "[language-server.htmx-lsp2]\ncommand = \"htmx-lsp2\"\n",
+            );
+            for filetype in &filetypes {
+                snippet.push_str(&format!(
+                    "\n[[language]]\nname = \"{filetype}\"\nlanguage-servers = [\"vscode-html-language-server\", \"htmx-lsp2\"]\n"
+                ));
+            }
+            snippet
+        }
+        Editor::Kakoune => format!(
+            "[language_server.htmx-lsp2]\nfiletypes = [{}]\nroots = [\"{CONFIG_FILE}\", \".git\"]\ncommand = \"htmx-lsp2\"\n",
+            quoted
+        ),
+        Editor::Neovim => format!(
+            "vim.api.nvim_create_autocmd(\"FileType\", {{\n  pattern = {{ {} }},\n  callback = function()\n    vim.lsp.start({{\n      name = \"htmx-lsp2\",\n      cmd = {{ \"htmx-lsp2\" }},\n      root_dir = vim.fs.dirname(vim.fs.find({{ \"{CONFIG_FILE}\", \".git\" }}, {{ upward = true }})[1]),\n    }})\n  end,\n}})\n",
+            quoted
+        ),
+        // Generic LSP client extension (`vscode-glspc`) starts one server for html.
+        Editor::VsCode => String::from(
+            "{\n  \"glspc.serverPath\": \"htmx-lsp2\",\n  \"glspc.languageId\": \"html\"\n}\n",
+        ),
+    }
+}
+
+/// Result of `init` for one file.
+pub enum Written {
+    Created(PathBuf),
+    /// File already exists, content has to be added by hand.
+    Exists(PathBuf, String),
+}
+
+/// Write `htmx-lsp.toml` and editor config to `root`. Existing files are never
+/// overwritten.
+pub fn init(root: &Path, editor: Editor) -> std::io::Result<Vec<Written>> {
+    let config = detect_config(root);
+    let files = [
+        (PathBuf::from(CONFIG_FILE), config_file(&config)),
+        (
+            PathBuf::from(editor.config_path()),
+            editor_config(editor, &config),
+        ),
+    ];
+    let mut written = vec![];
+    for (path, content) in files {
+        let full = root.join(&path);
+        if full.exists() {
+            written.push(Written::Exists(path, content));
+            continue;
+        }
+        if let Some(parent) = full.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&full, content)?;
+        written.push(Written::Created(path));
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{detect_config, editor_config, init, Editor, Written};
+    use crate::{config::BackendDir, config_file::read_config_file, test_dir::TestDir};
+
+    #[test]
+    fn detects_project_and_writes_configs() {
+        let root = TestDir::new();
+        for file in [
+            "Cargo.toml",
+            "src/main.rs",
+            "templates/index.jinja",
+            "templates/parts/row.jinja",
+            "templates/base.html",
+            "static/app.js",
+            "target/debug/build.rs",
+            "node_modules/x/index.js",
+        ] {
+            root.write(file, "");
+        }
+
+        let config = detect_config(&root);
+        assert_eq!(config.lang, "rust");
+        assert_eq!(config.template_ext, "jinja");
+        assert_eq!(config.templates, vec!["./templates"]);
+        assert_eq!(config.js_tags, vec!["./static"]);
+        assert_eq!(
+            config.backend_tags,
+            vec![BackendDir::from(String::from("./src"))]
+        );
+        assert!(
+            editor_config(Editor::Neovim, &config).contains("pattern = { \"html\", \"jinja\" }")
+        );
+
+        root.write(".nvim.lua", "-- user config");
+        let written = init(&root, Editor::Neovim).unwrap();
+        assert!(matches!(&written[0], Written::Created(path) if path.ends_with("htmx-lsp.toml")));
+        assert!(
+            matches!(&written[1], Written::Exists(_, snippet) if snippet.contains("vim.lsp.start"))
+        );
+        let saved = read_config_file(&root).unwrap();
+        assert_eq!(saved["templates"][0], "./templates");
+        assert_eq!(
+            std::fs::read_to_string(root.join(".nvim.lua")).unwrap(),
+            "-- user config"
+        );
+    }
+}
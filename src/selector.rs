@@ -0,0 +1,473 @@
+use std::collections::HashSet;
+
+use tree_sitter::{Node, Point};
+
+use crate::{
+    embedded::advance,
+    position::{start_tags, tag_attributes},
+};
+
+/// htmx extended selector, used in `hx-target`, `hx-include` and `hx-indicator`.
+#[derive(Debug, PartialEq)]
+pub enum ExtendedSelector<'a> {
+    This,
+    Closest(&'a str),
+    Find(&'a str),
+    Next(Option<&'a str>),
+    Previous(Option<&'a str>),
+    /// Plain CSS selector, it's resolved in whole page(not only in this template).
+    Css(&'a str),
+}
+
+impl<'a> ExtendedSelector<'a> {
+    pub fn parse(value: &'a str) -> Self {
+        let value = value.trim();
+        let (prefix, rest) = value.split_once(char::is_whitespace).unwrap_or((value, ""));
+        let rest = rest.trim();
+        let optional = |rest: &'a str| (!rest.is_empty()).then_some(rest);
+        match prefix {
+            "this" if rest.is_empty() => Self::This,
+            "closest" => Self::Closest(rest),
+            "find" => Self::Find(rest),
+            "next" => Self::Next(optional(rest)),
+            "previous" => Self::Previous(optional(rest)),
+            _ => Self::Css(value),
+        }
+    }
+}
+
+/// Selector with only tag name, id and classes (`tr`, `#foo`, `div.item.active`).
+/// Anything else can't be checked without real DOM.
+#[derive(Debug, Default, PartialEq)]
+pub struct SimpleSelector<'a> {
+    tag: Option<&'a str>,
+    id: Option<&'a str>,
+    classes: Vec<&'a str>,
+}
+
+impl<'a> SimpleSelector<'a> {
+    pub fn parse(selector: &'a str) -> Option<Self> {
+        let valid = |part: &str| {
+            !part.is_empty()
+                && part
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        };
+        let mut simple = SimpleSelector::default();
+        let first = selector.find(['#', '.']).unwrap_or(selector.len());
+        if first > 0 {
+            let tag = &selector[..first];
+            valid(tag).then_some(())?;
+            simple.tag = Some(tag);
+        }
+        let mut rest = &selector[first..];
+        while let Some(kind) = rest.chars().next() {
+            let end = rest[1..].find(['#', '.']).map_or(rest.len(), |end| end + 1);
+            let part = &rest[1..end];
+            valid(part).then_some(())?;
+            match kind {
+                '#' if simple.id.is_none() => simple.id = Some(part),
+                '.' => simple.classes.push(part),
+                _ => return None,
+            }
+            rest = &rest[end..];
+        }
+        Some(simple)
+    }
+
+    /// Check if element matches selector.
+    pub fn matches(&self, element: Node<'_>, source: &str) -> bool {
+        let Some(start_tag) = element.child(0) else {
+            return false;
+        };
+        if let Some(tag) = self.tag {
+            let name = start_tag
+                .child(1)
+                .and_then(|name| name.utf8_text(source.as_bytes()).ok());
+            if !name.is_some_and(|name| name.eq_ignore_ascii_case(tag)) {
+                return false;
+            }
+        }
+        let attributes = tag_attributes(start_tag, source);
+        let value = |key: &str| {
+            attributes
+                .iter()
+                .find(|(name, _)| name == key)
+                .and_then(|(_, value)| value.as_ref())
+                .and_then(|value| value.utf8_text(source.as_bytes()).ok())
+        };
+        if let Some(id) = self.id {
+            if value("id") != Some(id) {
+                return false;
+            }
+        }
+        if !self.classes.is_empty() {
+            let class = value("class").unwrap_or_default();
+            let class: Vec<&str> = class.split_whitespace().collect();
+            return self.classes.iter().all(|name| class.contains(name));
+        }
+        true
+    }
+}
+
+/// Resolve extended selector relative to `element`. `None` if selector can't be
+/// checked (plain CSS, templating syntax, complex selectors).
+pub fn resolve(element: Node<'_>, value: &str, source: &str) -> Option<bool> {
+    match ExtendedSelector::parse(value) {
+        ExtendedSelector::This => Some(true),
+        ExtendedSelector::Css(_) => None,
+        ExtendedSelector::Closest(selector) => {
+            let selector = SimpleSelector::parse(selector)?;
+            let mut node = Some(element);
+            while let Some(current) = node {
+                if current.kind() == "element" && selector.matches(current, source) {
+                    return Some(true);
+                }
+                node = current.parent();
+            }
+            full_page(element, source).then_some(false)
+        }
+        ExtendedSelector::Find(selector) => {
+            let selector = SimpleSelector::parse(selector)?;
+            let found = elements(element)
+                .into_iter()
+                .skip(1)
+                .any(|node| selector.matches(node, source));
+            Some(found)
+        }
+        ExtendedSelector::Next(None) | ExtendedSelector::Previous(None) => {
+            let next = matches!(ExtendedSelector::parse(value), ExtendedSelector::Next(_));
+            let found = sibling_element(element, next).is_some();
+            let top_level = element
+                .parent()
+                .is_some_and(|parent| parent.parent().is_none());
+            (found || !top_level || full_page(element, source)).then_some(found)
+        }
+        ExtendedSelector::Next(Some(selector)) => scan(element, selector, source, true),
+        ExtendedSelector::Previous(Some(selector)) => scan(element, selector, source, false),
+    }
+}
+
+/// Search whole document forward or backward from `element`.
+fn scan(element: Node<'_>, selector: &str, source: &str, next: bool) -> Option<bool> {
+    let selector = SimpleSelector::parse(selector)?;
+    let mut root = element;
+    while let Some(parent) = root.parent() {
+        root = parent;
+    }
+    let start = element.start_byte();
+    let found = elements(root).into_iter().any(|node| {
+        let order = match next {
+            true => node.start_byte() > start,
+            false => node.start_byte() < start,
+        };
+        order && selector.matches(node, source)
+    });
+    (found || full_page(element, source)).then_some(found)
+}
+
+/// Partial template can be included in other page, so selectors that leave
+/// element can be resolved only in full page(with `html` or `body` element).
+fn full_page(element: Node<'_>, source: &str) -> bool {
+    let mut root = element;
+    while let Some(parent) = root.parent() {
+        root = parent;
+    }
+    let page = SimpleSelector {
+        tag: Some("body"),
+        ..Default::default()
+    };
+    let html = SimpleSelector {
+        tag: Some("html"),
+        ..Default::default()
+    };
+    elements(root)
+        .into_iter()
+        .any(|node| page.matches(node, source) || html.matches(node, source))
+}
+
+/// Element and all of its descendant elements in document order.
+fn elements(node: Node<'_>) -> Vec<Node<'_>> {
+    let mut elements = vec![];
+    let mut stack = vec![node];
+    while let Some(node) = stack.pop() {
+        if node.kind() == "element" {
+            elements.push(node);
+        }
+        let mut cursor = node.walk();
+        let children: Vec<Node<'_>> = node.children(&mut cursor).collect();
+        stack.extend(children.into_iter().rev());
+    }
+    elements
+}
+
+fn sibling_element(element: Node<'_>, next: bool) -> Option<Node<'_>> {
+    let mut node = match next {
+        true => element.next_named_sibling(),
+        false => element.prev_named_sibling(),
+    };
+    while let Some(current) = node {
+        if current.kind() == "element" {
+            return Some(current);
+        }
+        node = match next {
+            true => current.next_named_sibling(),
+            false => current.prev_named_sibling(),
+        };
+    }
+    None
+}
+
+/// Id from selector, `closest #results` -> `results`.
+pub fn selector_id(value: &str) -> Option<&str> {
+    let start = value.find('#')? + 1;
+    let rest = &value[start..];
+    let end = rest
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+        .unwrap_or(rest.len());
+    (end > 0).then(|| &rest[..end])
+}
+
+/// Attributes that accept extended selectors.
+pub const SELECTOR_ATTRIBUTES: [&str; 3] = ["hx-target", "hx-include", "hx-indicator"];
+
+/// Attributes with selector of elements from response.
+pub const SELECT_ATTRIBUTES: [&str; 2] = ["hx-select", "hx-select-oob"];
+
+/// One selector from `hx-select` or `hx-select-oob` value, `offset` is byte offset
+/// of selector in value.
+#[derive(Debug, PartialEq)]
+pub struct SelectPart<'a> {
+    pub offset: usize,
+    pub selector: &'a str,
+    /// Swap style after `:` in `hx-select-oob` (`#alert:afterbegin`).
+    pub style: Option<&'a str>,
+}
+
+/// Comma separated selectors of `hx-select` or `hx-select-oob` value. Only
+/// `hx-select-oob` selectors can have swap style.
+pub fn select_parts<'a>(attribute: &str, value: &'a str) -> Vec<SelectPart<'a>> {
+    let attribute = attribute.strip_prefix("data-").unwrap_or(attribute);
+    let mut parts = vec![];
+    let mut offset = 0;
+    for part in value.split(',') {
+        let start = offset + part.len() - part.trim_start().len();
+        offset += part.len() + 1;
+        let part = part.trim();
+        let (selector, style) = match part.split_once(':') {
+            Some((selector, style)) if attribute == "hx-select-oob" => {
+                (selector.trim_end(), Some(style.trim()))
+            }
+            _ => (part, None),
+        };
+        parts.push(SelectPart {
+            offset: start,
+            selector,
+            style,
+        });
+    }
+    parts
+}
+
+/// Id from `hx-select`/`hx-select-oob` selector that doesn't exist in any template.
+#[derive(Debug, PartialEq)]
+pub struct UnknownSelectId {
+    pub attribute: String,
+    pub id: String,
+    /// Range of `#id` in template.
+    pub range: (Point, Point),
+}
+
+/// Ids selected from response that are not in `known` ids of project.
+pub fn unknown_select_ids(
+    root: Node<'_>,
+    source: &str,
+    known: &HashSet<&str>,
+) -> Vec<UnknownSelectId> {
+    let mut unknown = vec![];
+    for tag in start_tags(root) {
+        for (name, value) in tag_attributes(tag, source) {
+            if !SELECT_ATTRIBUTES.contains(&name.strip_prefix("data-").unwrap_or(&name)) {
+                continue;
+            }
+            let Some(value) = value else {
+                continue;
+            };
+            let Ok(text) = value.utf8_text(source.as_bytes()) else {
+                continue;
+            };
+            for part in select_parts(&name, text) {
+                let Some(id) = selector_id(part.selector) else {
+                    continue;
+                };
+                if known.contains(id) {
+                    continue;
+                }
+                let start = part.offset + part.selector.find(&format!("#{id}")).unwrap_or(0);
+                let start_point = advance(value.start_position(), &text[..start]);
+                unknown.push(UnknownSelectId {
+                    attribute: name.clone(),
+                    id: id.to_string(),
+                    range: (
+                        start_point,
+                        advance(start_point, &text[start..=start + id.len()]),
+                    ),
+                });
+            }
+        }
+    }
+    unknown
+}
+
+/// Extended selector that doesn't match any element, with range of attribute value.
+#[derive(Debug, PartialEq)]
+pub struct UnresolvedSelector {
+    pub attribute: String,
+    pub value: String,
+    pub range: (Point, Point),
+}
+
+/// Check every extended selector in template.
+pub fn unresolved_selectors(root: Node<'_>, source: &str) -> Vec<UnresolvedSelector> {
+    unresolved_selectors_where(root, source, |_, _| true)
+}
+
+/// Check extended selectors only in start tags accepted by `keep`(start and end of tag).
+pub fn unresolved_selectors_where(
+    root: Node<'_>,
+    source: &str,
+    keep: impl Fn(Point, Point) -> bool,
+) -> Vec<UnresolvedSelector> {
+    let mut unresolved = vec![];
+    for element in elements(root) {
+        let Some(start_tag) = element.child(0) else {
+            continue;
+        };
+        if !keep(start_tag.start_position(), start_tag.end_position()) {
+            continue;
+        }
+        for (name, value) in tag_attributes(start_tag, source) {
+            if !SELECTOR_ATTRIBUTES.contains(&name.as_str()) {
+                continue;
+            }
+            let Some(value) = value else {
+                continue;
+            };
+            let Ok(text) = value.utf8_text(source.as_bytes()) else {
+                continue;
+            };
+            if resolve(element, text, source) == Some(false) {
+                unresolved.push(UnresolvedSelector {
+                    attribute: name,
+                    value: text.to_string(),
+                    range: (value.start_position(), value.end_position()),
+                });
+            }
+        }
+    }
+    unresolved
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use tree_sitter::{Parser, Point};
+
+    use super::{
+        select_parts, selector_id, unknown_select_ids, unresolved_selectors, ExtendedSelector,
+        SelectPart, SimpleSelector,
+    };
+
+    fn unresolved(text: &str) -> Vec<String> {
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_html::language()).unwrap();
+        let tree = parser.parse(text, None).unwrap();
+        unresolved_selectors(tree.root_node(), text)
+            .into_iter()
+            .map(|selector| selector.value)
+            .collect()
+    }
+
+    #[test]
+    fn parse_selectors() {
+        assert_eq!(ExtendedSelector::parse("this"), ExtendedSelector::This);
+        assert_eq!(
+            ExtendedSelector::parse("closest tr"),
+            ExtendedSelector::Closest("tr")
+        );
+        assert_eq!(
+            ExtendedSelector::parse("next"),
+            ExtendedSelector::Next(None)
+        );
+        assert_eq!(
+            ExtendedSelector::parse("#results"),
+            ExtendedSelector::Css("#results")
+        );
+        assert!(SimpleSelector::parse("div.item#a").is_some());
+        assert!(SimpleSelector::parse("div > p").is_none());
+        assert!(SimpleSelector::parse("{{ target }}").is_none());
+    }
+
+    #[test]
+    fn id_from_selector() {
+        assert_eq!(selector_id("#results"), Some("results"));
+        assert_eq!(selector_id("closest #row-1 td"), Some("row-1"));
+        assert_eq!(selector_id("closest tr"), None);
+        assert_eq!(selector_id("#"), None);
+    }
+
+    #[test]
+    fn partial_template_can_be_included() {
+        let text = r#"<td><button hx-target="closest tr">x</button></td>
+<p hx-target="previous"></p><p hx-target="find tr"></p>"#;
+        assert_eq!(unresolved(text), vec!["find tr"]);
+    }
+
+    #[test]
+    fn resolve_relative_to_element() {
+        let text = r##"<body><table><tr class="row">
+  <td><button hx-target="closest tr" hx-include="closest .row">x</button></td>
+  <td><button hx-target="closest form">y</button></td>
+</tr></table>
+<div hx-target="find .item"><p class="item"></p></div>
+<div hx-target="find .missing"><p></p></div>
+<p hx-target="next p"></p><p hx-target="previous #nope"></p>
+<span hx-target="#elsewhere" hx-indicator="closest {{ x }}"></span></body>"##;
+        assert_eq!(
+            unresolved(text),
+            vec!["closest form", "find .missing", "previous #nope"]
+        );
+    }
+
+    #[test]
+    fn select_and_select_oob_ids() {
+        assert_eq!(
+            select_parts("hx-select-oob", "#alert:afterbegin, #info"),
+            vec![
+                SelectPart {
+                    offset: 0,
+                    selector: "#alert",
+                    style: Some("afterbegin")
+                },
+                SelectPart {
+                    offset: 19,
+                    selector: "#info",
+                    style: None
+                },
+            ]
+        );
+        assert_eq!(select_parts("hx-select", "#a:first-child")[0].style, None);
+
+        let text = r##"<div hx-select="#content" hx-select-oob="#alert:afterbegin, #nope"></div>
+<p data-hx-select="{{ selector }}"></p>"##;
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_html::language()).unwrap();
+        let tree = parser.parse(text, None).unwrap();
+        let known = HashSet::from(["content", "alert"]);
+        let unknown = unknown_select_ids(tree.root_node(), text, &known);
+        assert_eq!(unknown.len(), 1);
+        assert_eq!(unknown[0].id, "nope");
+        assert_eq!(unknown[0].range, (Point::new(0, 60), Point::new(0, 65)));
+    }
+}
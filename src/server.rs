@@ -1,50 +1,138 @@
-use crate::config::{read_config, validate_config, HtmxConfig};
-use crate::htmx_tags::Tag;
+use crate::completion_cache::{CacheKey, CompletionCache};
+use crate::config::{
+    check_htmx_script, config_schema, read_config, validate_config, Feature, HtmxConfig, JS_EXTS,
+};
+use crate::config_file::read_config_file;
+use crate::daemon::SharedIndex;
+use crate::docs::{render_docs, Docs, DocsParams};
+use crate::extensions::extension_attribute;
+use crate::extract::extract_fragment;
+use crate::find_value::{fuzzy_score, FindValueParams, ValueMatch};
+use crate::htmx_api::{api_member, api_prefix, HTMX_API};
+use crate::htmx_tags::{in_tag, in_tags, tag_prefix, Tag};
+use crate::incremental::{recheck, DirtyRanges, Edit};
+use crate::indexing::{IndexCanceled, IndexCoordinator, IndexToken};
+use crate::item_defaults::ItemDefaults;
+use crate::locale::{locale_docs, translate};
+use crate::markup::{markup_content, supports_markdown};
+use crate::metrics::METRICS;
+use crate::organize::ORGANIZE_HTMX_ATTRIBUTES;
 use crate::query_helper::Queries;
-use crate::to_input_edit::ToInputEdit;
-use std::collections::HashMap;
+use crate::request_log::{set_slow_request_ms, RequestLog};
+use crate::selector::SELECT_ATTRIBUTES;
+use crate::stylesheet::SELECTOR_ATTRIBUTES;
+use crate::suppress::Suppressions;
+use crate::to_input_edit::{strip_bom, ToInputEdit};
+use crate::uri::{
+    canonicalize, is_file_uri, is_local_file, normalize_uri, path_to_uri, NormalizedPath,
+};
+use std::collections::{BTreeMap, HashMap};
 
 use std::path::Path;
-use std::sync::{Arc, Mutex, RwLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
+use std::time::Duration;
 
-use dashmap::DashMap;
+use dashmap::{mapref::entry::Entry, DashMap, DashSet};
 use ropey::Rope;
+use tokio::task::JoinHandle;
+use tree_sitter::Point;
 
 use serde_json::Value;
-use tower_lsp::jsonrpc::Result;
-use tower_lsp::lsp_types::request::{GotoImplementationParams, GotoImplementationResponse};
+use tower_lsp::jsonrpc::{Error, ErrorCode, Result};
+use tower_lsp::lsp_types::notification::Progress;
+use tower_lsp::lsp_types::request::{
+    GotoImplementationParams, GotoImplementationResponse, GotoTypeDefinitionParams,
+    GotoTypeDefinitionResponse, WorkDoneProgressCreate,
+};
 use tower_lsp::lsp_types::{
-    CodeAction, CodeActionKind, CodeActionOrCommand, CodeActionParams,
-    CodeActionProviderCapability, CodeActionResponse, Command, CompletionContext, CompletionItem,
-    CompletionItemKind, CompletionOptions, CompletionParams, CompletionResponse,
-    CompletionTriggerKind, Diagnostic, DidChangeTextDocumentParams, DidCloseTextDocumentParams,
+    CodeAction, CodeActionKind, CodeActionOptions, CodeActionOrCommand, CodeActionParams,
+    CodeActionProviderCapability, CodeActionResponse, CodeLens, CodeLensOptions, CodeLensParams,
+    Command, CompletionContext, CompletionItem, CompletionItemKind, CompletionOptions,
+    CompletionParams, CompletionResponse, CompletionTriggerKind, Diagnostic,
+    DidChangeConfigurationParams, DidChangeTextDocumentParams, DidCloseTextDocumentParams,
     DidOpenTextDocumentParams, DidSaveTextDocumentParams, Documentation, ExecuteCommandOptions,
     ExecuteCommandParams, GotoDefinitionParams, GotoDefinitionResponse, Hover, HoverContents,
     HoverParams, HoverProviderCapability, ImplementationProviderCapability, InitializedParams,
-    Location, MarkupContent, MarkupKind, MessageType, OneOf, ReferenceParams, ServerCapabilities,
-    TextDocumentSyncCapability, TextDocumentSyncKind, TextDocumentSyncOptions,
-    TextDocumentSyncSaveOptions, Url,
+    Location, MessageType, OneOf, ReferenceParams, ServerCapabilities, TextDocumentSyncCapability,
+    TextDocumentSyncKind, TextDocumentSyncOptions, TextDocumentSyncSaveOptions, TextEdit, Url,
+    WillSaveTextDocumentParams,
+};
+use tower_lsp::lsp_types::{
+    CompletionItemLabelDetails, CompletionItemTag, CompletionRegistrationOptions,
+    CompletionTextEdit, DocumentFilter, InsertTextFormat, Position as LspPosition, Range,
+    Registration, ResourceOperationKind, TextDocumentPositionParams,
+    TextDocumentRegistrationOptions, TypeDefinitionProviderCapability,
 };
 use tower_lsp::lsp_types::{InitializeParams, ServerInfo};
-use tower_lsp::{lsp_types::InitializeResult, Client, LanguageServer};
+use tower_lsp::lsp_types::{
+    NumberOrString, ProgressParams, ProgressParamsValue, WorkDoneProgress, WorkDoneProgressBegin,
+    WorkDoneProgressCreateParams, WorkDoneProgressEnd,
+};
+use tower_lsp::{lsp_types::InitializeResult, Client, ClientSocket, LanguageServer, LspService};
 
-use crate::htmx_tree_sitter::LspFiles;
-use crate::init_hx::{init_hx_tags, init_hx_values, HxCompletion, LangType, LangTypes};
-use crate::position::{get_position_from_lsp_completion, Position, QueryType};
+use crate::htmx_tree_sitter::{sort_diagnostics, LspFiles};
+use crate::init_hx::{
+    hx_headers_values, hx_inherit_values, hx_request_values, hx_sync_values, init_html_attributes,
+    init_hx_tags, init_hx_values, json_key_prefix, merge_extra_attributes, parse_extra_attributes,
+    HxCompletion, LangType,
+};
+use crate::position::{
+    attribute_before_equal, attribute_name_prefix, attribute_value_prefix, get_attribute_name,
+    get_hx_attributes_in_scope, get_position_from_lsp_completion, is_hx_name_prefix,
+    selector_token, typed_word, value_prefix, Position, QueryType, NEW_ATTRIBUTE,
+};
+use crate::state::{degraded_states, take_degraded_report, Recover, RecoverRw, State};
+use crate::swap::{hx_select_oob_values, hx_swap_values, swap_hover};
+use crate::url_params::{interpolations, url_hover, URL_ATTRIBUTES};
 
 /// BackendHtmx - contains all important parts for htmx-lsp
 pub struct BackendHtmx {
     pub client: Client,
     /// Every document is represented as Rope data structure. This lsp support only incremental changes.
-    pub document_map: DashMap<String, Rope>,
+    pub document_map: Arc<DashMap<String, Rope>>,
+    /// Last published diagnostics for every document. On template change they are
+    /// moved and only changed ranges are checked again.
+    pub published: Arc<DashMap<String, Vec<Diagnostic>>>,
+    /// Change counter for every document. Whole template is checked after it
+    /// stops changing for `IDLE_RECHECK`, completion cache is also keyed by it.
+    pub revisions: Arc<DashMap<String, u64>>,
+    /// Pending whole template check of every document, newer change replaces it.
+    /// Checks are aborted on shutdown.
+    pub rechecks: Arc<DashMap<String, JoinHandle<()>>>,
+    /// Latest version of every open document from client. Some proxies resend or
+    /// reorder notifications, changes that are not newer are ignored.
+    pub versions: Arc<DashMap<String, i32>>,
+    /// Open file documents that can't be canonicalized to local path (remote mount,
+    /// path from container). Only completion and hover work for them.
+    pub read_only: Arc<DashSet<String>>,
+    /// Completion positions for recent cursor positions.
+    pub completion_cache: CompletionCache,
     /// All htmx attributes used for completion and hover.
-    pub hx_attributes: Vec<HxCompletion>,
+    pub hx_attributes: RwLock<Vec<HxCompletion>>,
     /// All htmx attribute values used for completion and hover.
-    pub hx_attribute_values: HashMap<String, Vec<HxCompletion>>,
-    /// Some clients have no context information about completion request.
-    /// This can help server to suggest htmx values.  
-    /// Client completion starts after trigger character.
+    pub hx_attribute_values: RwLock<HashMap<String, Vec<HxCompletion>>>,
+    /// Standard HTML attributes, used for hover fallback.
+    pub html_attributes: Vec<HxCompletion>,
+    /// Compatibility mode, completion is also returned for
+    /// `TriggerForIncompleteCompletions` requests(helix).
     pub can_complete: RwLock<bool>,
+    /// Client supports snippets in completion items.
+    pub snippet_support: RwLock<bool>,
+    /// Client renders Markdown in hover, otherwise plain text is sent.
+    pub markdown_hover: RwLock<bool>,
+    /// Client renders Markdown in completion documentation.
+    pub markdown_completion: RwLock<bool>,
+    /// Completion is registered with `client/registerCapability` after initialization.
+    pub dynamic_completion: RwLock<bool>,
+    /// Client can create files with `WorkspaceEdit`.
+    pub create_file_support: RwLock<bool>,
+    /// Client accepts `LocationLink` in definition response.
+    pub link_support: RwLock<bool>,
+    /// Client shows `$/progress` notifications.
+    pub work_done_progress: RwLock<bool>,
+    /// Locale from `initialize` request, documentation is translated to it.
+    pub client_locale: RwLock<Option<String>>,
     /// Configuration for htmx-lsp. Hover and completion can work without it.
     pub htmx_config: RwLock<HtmxConfig>,
     /// Main field, responsible for all htmx actions.
@@ -52,58 +140,605 @@ pub struct BackendHtmx {
     pub lsp_files: Arc<Mutex<LspFiles>>,
     /// All tree sitter queries.
     pub queries: Arc<Mutex<Queries>>,
+    /// Set after shutdown request. Process exit code depends on it,
+    /// 0 if shutdown was received before exit, otherwise 1.
+    pub shutdown: Arc<AtomicBool>,
+    /// Index shared with other editor sessions in daemon mode.
+    pub shared_index: Option<Arc<SharedIndex>>,
+    /// Only latest workspace index run can publish results.
+    pub indexing: Arc<IndexCoordinator>,
 }
 
 impl BackendHtmx {
     /// Initialization of server. `BackendServer` fields are cloneable.
     pub fn new(client: Client) -> Self {
+        Self::with_shutdown(client, Arc::new(AtomicBool::new(false)))
+    }
+
+    /// Same as `new`, but shutdown flag is shared with caller.
+    pub fn with_shutdown(client: Client, shutdown: Arc<AtomicBool>) -> Self {
         Self {
             client,
-            document_map: DashMap::new(),
-            hx_attributes: init_hx_tags(),
-            hx_attribute_values: init_hx_values(),
+            document_map: Arc::new(DashMap::new()),
+            published: Arc::new(DashMap::new()),
+            revisions: Arc::new(DashMap::new()),
+            rechecks: Arc::new(DashMap::new()),
+            versions: Arc::new(DashMap::new()),
+            read_only: Arc::new(DashSet::new()),
+            completion_cache: CompletionCache::default(),
+            hx_attributes: RwLock::new(init_hx_tags()),
+            hx_attribute_values: RwLock::new(init_hx_values()),
+            html_attributes: init_html_attributes(),
             can_complete: RwLock::new(false),
+            snippet_support: RwLock::new(false),
+            markdown_hover: RwLock::new(true),
+            markdown_completion: RwLock::new(true),
+            client_locale: RwLock::new(None),
+            work_done_progress: RwLock::new(false),
+            link_support: RwLock::new(false),
+            dynamic_completion: RwLock::new(false),
+            create_file_support: RwLock::new(false),
             htmx_config: RwLock::new(HtmxConfig::default()),
             lsp_files: Arc::new(Mutex::new(LspFiles::default())),
             queries: Arc::new(Mutex::new(Queries::default())),
+            shutdown,
+            shared_index: None,
+            indexing: Arc::new(IndexCoordinator::default()),
+        }
+    }
+
+    /// Session in daemon mode, index and documents are taken from shared index.
+    pub fn with_index(client: Client, shutdown: Arc<AtomicBool>, index: Arc<SharedIndex>) -> Self {
+        Self {
+            lsp_files: index.lsp_files.clone(),
+            queries: index.queries.clone(),
+            document_map: index.document_map.clone(),
+            versions: index.versions.clone(),
+            revisions: index.revisions.clone(),
+            published: index.published.clone(),
+            indexing: index.indexing.clone(),
+            shared_index: Some(index),
+            ..Self::with_shutdown(client, shutdown)
+        }
+    }
+
+    /// Reset attribute catalogue, translate its documentation to `docs_locale` (or
+    /// client locale) and merge `extra_attributes` file into it.
+    async fn load_attributes(&self) {
+        let (path, locale, docs_dir) = {
+            let config = self.htmx_config.recover_read(State::Config);
+            (
+                config.extra_attributes.clone(),
+                config.docs_locale.clone(),
+                config.docs_dir.clone(),
+            )
+        };
+        let locale = locale.or_else(|| self.client_locale.recover_read(State::Client).clone());
+        let mut attributes = init_hx_tags();
+        let mut values = init_hx_values();
+        if let Some(locale) = locale {
+            let docs_dir = docs_dir.map(|dir| NormalizedPath::new(&dir).to_path_buf());
+            match locale_docs(&locale, docs_dir.as_deref()) {
+                Ok(Some((_, docs))) => translate(docs, &mut attributes, &mut values),
+                Ok(None) => (),
+                Err(err) => {
+                    self.client
+                        .show_message(MessageType::WARNING, format!("docs_locale {locale}: {err}"))
+                        .await;
+                }
+            }
+        }
+        if let Some(path) = path {
+            let extra = std::fs::read_to_string(NormalizedPath::new(&path).to_path_buf())
+                .map_err(anyhow::Error::from)
+                .and_then(|content| parse_extra_attributes(&content));
+            match extra {
+                Ok(extra) => merge_extra_attributes(extra, &mut attributes, &mut values),
+                Err(err) => {
+                    self.client
+                        .show_message(MessageType::WARNING, format!("{path}: {err}"))
+                        .await;
+                    // Old catalogue is kept until file is fixed.
+                    return;
+                }
+            }
+        }
+        *self.hx_attributes.recover_write(State::Attributes) = attributes;
+        *self.hx_attribute_values.recover_write(State::Attributes) = values;
+    }
+
+    /// Documentation for swap style or modifier under cursor in `hx-swap` value.
+    fn swap_hover(&self, params: &TextDocumentPositionParams, value: &str) -> Option<String> {
+        let position = params.position;
+        let line = self
+            .document_map
+            .get(&normalize_uri(&params.text_document.uri))
+            .and_then(|rope| Some(rope.get_line(position.line as usize)?.to_string()))?;
+        let prefix = attribute_value_prefix(&line, position.character as usize, "hx-swap")?;
+        swap_hover(value, prefix.len()).map(String::from)
+    }
+
+    /// Index for completion. Completion doesn't wait for index lock, it's
+    /// answered without index while lock is held by indexing or other request.
+    fn completion_index(&self) -> Option<MutexGuard<'_, LspFiles>> {
+        self.lsp_files.try_recover(State::Index)
+    }
+
+    /// Ids (for `#` token) or stylesheet classes (for `.` token) in selector value.
+    /// `hx-select` and `hx-select-oob` select from response, so ids of every template
+    /// are completed for them.
+    fn selector_items(&self, uri: &String, name: &str, token: &str) -> Vec<CompletionItem> {
+        let Some(lsp_files) = self.completion_index() else {
+            return vec![];
+        };
+        if token.starts_with('.') {
+            return lsp_files
+                .class_names()
+                .into_iter()
+                .map(|class| CompletionItem {
+                    label: format!(".{class}"),
+                    detail: Some(String::from("CSS class")),
+                    kind: Some(CompletionItemKind::CLASS),
+                    commit_characters: Some(value_commit_characters(name, &class)),
+                    ..Default::default()
+                })
+                .collect();
+        }
+        let project = self.htmx_config.recover_read(State::Config).project_ids
+            || SELECT_ATTRIBUTES.contains(&name.strip_prefix("data-").unwrap_or(name));
+        lsp_files
+            .id_names(uri, &self.document_map, project)
+            .into_iter()
+            .enumerate()
+            .map(|(rank, (id, other))| CompletionItem {
+                label: format!("#{id}"),
+                detail: Some(String::from("Element id")),
+                label_details: other.map(|other| CompletionItemLabelDetails {
+                    detail: None,
+                    description: other.rsplit('/').next().map(String::from),
+                }),
+                kind: Some(CompletionItemKind::REFERENCE),
+                sort_text: Some(format!("{rank:04}")),
+                commit_characters: Some(value_commit_characters(name, &id)),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    /// Values of extension attribute, only when extension is enabled in document
+    /// or in project. `sse-swap` also gets event names from backend files.
+    fn extension_values(&self, uri: &str, name: &str) -> Option<Vec<HxCompletion>> {
+        let attribute = extension_attribute(name)?;
+        let lsp_files = self.completion_index()?;
+        let extensions = lsp_files.extensions(uri, &self.document_map);
+        if !extensions.iter().any(|name| name == attribute.extension) {
+            return None;
+        }
+        let mut values = attribute.completions();
+        if attribute.name == "sse-swap" {
+            for event in lsp_files.sse_events(&self.document_map) {
+                if values.iter().any(|value| value.name == event) {
+                    continue;
+                }
+                values.push(HxCompletion {
+                    name: event,
+                    desc: String::from("Event sent by backend."),
+                    detail: Some(String::from("SSE event")),
+                    deprecated: false,
+                });
+            }
+        }
+        Some(values)
+    }
+
+    /// Range of attribute name before cursor.
+    fn attribute_name_range(&self, params: &TextDocumentPositionParams) -> Option<Range> {
+        let position = params.position;
+        let line = self
+            .document_map
+            .get(&normalize_uri(&params.text_document.uri))
+            .and_then(|rope| Some(rope.get_line(position.line as usize)?.to_string()))?;
+        let (start, _) = attribute_name_prefix(&line, position.character as usize)?;
+        Some(Range::new(
+            LspPosition::new(position.line, start as u32),
+            position,
+        ))
+    }
+
+    /// Check if saved document is `extra_attributes` file.
+    fn is_extra_attributes(&self, uri: &Url) -> bool {
+        let path = self
+            .htmx_config
+            .recover_read(State::Config)
+            .extra_attributes
+            .clone();
+        let extra = path
+            .and_then(|path| canonicalize(&path))
+            .and_then(|path| path_to_uri(&path));
+        extra.is_some_and(|extra| extra == normalize_uri(uri))
+    }
+
+    /// Walk workspace, or reuse result from other session with same config.
+    /// With `force` workspace is always walked again.
+    ///
+    /// Newer run cancels this one, `None` is returned for canceled run and its
+    /// results must not be published.
+    async fn index_workspace(&self, force: bool) -> anyhow::Result<Option<Vec<Tag>>> {
+        let token = self.indexing.start();
+        let _running = self.indexing.acquire().await;
+        self.index_run(force, token).await
+    }
+
+    /// Same as `index_workspace`, caller holds `IndexCoordinator::acquire` guard.
+    async fn index_run(&self, force: bool, token: IndexToken) -> anyhow::Result<Option<Vec<Tag>>> {
+        // Indexing replaces documents and trees without new revision. Completion
+        // during indexing is answered without trees, so it's not cached after it.
+        self.completion_cache.clear();
+        let result = self.walk_workspace(force, &token).await;
+        token.finish();
+        self.completion_cache.clear();
+        match result {
+            Err(err) if err.is::<IndexCanceled>() => Ok(None),
+            _ if token.is_canceled() => Ok(None),
+            result => result.map(Some),
+        }
+    }
+
+    async fn walk_workspace(&self, force: bool, token: &IndexToken) -> anyhow::Result<Vec<Tag>> {
+        let Some(index) = &self.shared_index else {
+            return read_config(
+                &self.htmx_config,
+                &self.lsp_files,
+                &self.queries,
+                &self.document_map,
+                &self.versions,
+                token,
+            )
+            .await;
+        };
+        let key = serde_json::to_string(&*self.htmx_config.recover_read(State::Config))
+            .unwrap_or_default();
+        if let Some(diagnostics) = index.indexed(&key).filter(|_| !force) {
+            return Ok(diagnostics);
+        }
+        let diagnostics = read_config(
+            &self.htmx_config,
+            &self.lsp_files,
+            &self.queries,
+            &self.document_map,
+            &self.versions,
+            token,
+        )
+        .await?;
+        if !token.is_canceled() {
+            index.set_indexed(key, &diagnostics);
+        }
+        Ok(diagnostics)
+    }
+
+    /// Index file changed by workspace edit. File that isn't opened by client is
+    /// read from disk, edit was applied there.
+    async fn index_file(&self, uri: &Url) {
+        let key = normalize_uri(uri);
+        if !self.versions.contains_key(&key) {
+            let content = uri
+                .to_file_path()
+                .ok()
+                .and_then(|path| std::fs::read_to_string(path).ok());
+            if let Some(content) = content {
+                self.document_map
+                    .insert(key.clone(), Rope::from_str(strip_bom(&content)));
+            }
+        }
+        let mut diags = vec![];
+        if let Some(diagnostics) = self.lsp_files.recover(State::Index).reindex_file(
+            &key,
+            &mut diags,
+            &self.htmx_config,
+            &self.document_map,
+            &self.queries,
+        ) {
+            diags = diagnostics;
+        }
+        self.publish_tag_diagnostics(diags, Some(key)).await;
+    }
+
+    /// Index workspace again and publish tag diagnostics, if no newer run was started.
+    async fn reindex(&self) {
+        match self.index_workspace(true).await {
+            Ok(Some(diagnostics)) => self.publish_tag_diagnostics(diagnostics, None).await,
+            Ok(None) => {}
+            Err(err) => {
+                self.client
+                    .log_message(MessageType::WARNING, format!("Indexing failed: {err}"))
+                    .await;
+            }
+        }
+    }
+
+    /// Publish diagnostics of first index run, or mark config as invalid.
+    async fn publish_index(&self, result: anyhow::Result<Option<Vec<Tag>>>) {
+        match result {
+            Ok(None) => {}
+            Ok(Some(diagnostics)) => {
+                self.warn_parse_errors(None).await;
+                self.publish_tag_diagnostics(diagnostics, None).await;
+                if let Some(msg) = check_htmx_script(
+                    &self.htmx_config,
+                    &self.lsp_files,
+                    &self.queries,
+                    &self.document_map,
+                ) {
+                    self.client.show_message(MessageType::WARNING, msg).await;
+                }
+            }
+            Err(err) => {
+                let features = {
+                    let mut config = self.htmx_config.recover_write(State::Config);
+                    config.is_valid = false;
+                    config.features_message()
+                };
+                let msg = format!("{err} Features {features}");
+                self.client.log_message(MessageType::WARNING, msg).await;
+            }
+        };
+    }
+
+    /// Run deferred first index (`lazy_index`), with progress reported to client.
+    /// Requests that come while it runs wait for it, their results aren't empty.
+    async fn ensure_indexed(&self) {
+        if !self.htmx_config.recover_read(State::Config).lazy_index {
+            return;
+        }
+        // Deferred run is taken while index lock is held, so other callers wait
+        // until it ends.
+        let running = self.indexing.acquire().await;
+        if !self.indexing.take_deferred() {
+            return;
+        }
+        let token = self.indexing.start();
+        let progress = self.begin_progress("Indexing htmx workspace").await;
+        let result = self.index_run(false, token).await;
+        drop(running);
+        let status = self.indexing.status();
+        self.publish_index(result).await;
+        if let Some(token) = progress {
+            let end = WorkDoneProgress::End(WorkDoneProgressEnd {
+                message: Some(format!("{} files", status.indexed)),
+            });
+            self.client
+                .send_notification::<Progress>(ProgressParams {
+                    token,
+                    value: ProgressParamsValue::WorkDone(end),
+                })
+                .await;
         }
     }
 
+    /// Create progress token and send begin notification, `None` if client
+    /// doesn't support work done progress.
+    async fn begin_progress(&self, title: &str) -> Option<NumberOrString> {
+        if !*self.work_done_progress.recover_read(State::Client) {
+            return None;
+        }
+        let token = NumberOrString::String(String::from("htmx-lsp/index"));
+        self.client
+            .send_request::<WorkDoneProgressCreate>(WorkDoneProgressCreateParams {
+                token: token.clone(),
+            })
+            .await
+            .ok()?;
+        let begin = WorkDoneProgress::Begin(WorkDoneProgressBegin {
+            title: title.to_string(),
+            cancellable: Some(false),
+            message: None,
+            percentage: None,
+        });
+        self.client
+            .send_notification::<Progress>(ProgressParams {
+                token: token.clone(),
+                value: ProgressParamsValue::WorkDone(begin),
+            })
+            .await;
+        Some(token)
+    }
+
     /// Used after didOpen request.
     fn after_open(&self, params: ServerTextDocumentItem) {
-        let rope = ropey::Rope::from_str(&params.text);
-        self.document_map
-            .insert(params.uri.to_string(), rope.clone());
+        let rope = ropey::Rope::from_str(strip_bom(&params.text));
+        let uri = normalize_uri(&params.uri);
+        self.bump_revision(&uri);
+        self.document_map.insert(uri, rope.clone());
+    }
+
+    /// Document text is changed, returns new revision.
+    fn bump_revision(&self, uri: &str) -> u64 {
+        let mut revision = self.revisions.entry(uri.to_string()).or_default();
+        *revision += 1;
+        *revision
+    }
+
+    /// Save version of change. Latest version is returned if change is not newer.
+    fn stale_version(&self, uri: &str, version: i32) -> Option<i32> {
+        match self.versions.entry(uri.to_string()) {
+            Entry::Occupied(latest) if *latest.get() >= version => Some(*latest.get()),
+            Entry::Occupied(mut latest) => {
+                latest.insert(version);
+                None
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(version);
+                None
+            }
+        }
+    }
+
+    fn revision(&self, uri: &str) -> u64 {
+        self.revisions
+            .get(uri)
+            .map(|revision| *revision)
+            .unwrap_or_default()
+    }
+
+    /// Some clients send requests before didOpen, or for documents that server
+    /// never saw. In that case document is read from disk.
+    ///
+    /// Request fails with `InternalError` when no grammar of document can be used,
+    /// other documents are still served.
+    async fn ensure_document(&self, uri: &Url) -> Result<()> {
+        self.report_degraded().await;
+        self.grammar_error(uri)?;
+        let key = normalize_uri(uri);
+        if self.document_map.contains_key(&key) || !is_file_uri(uri) {
+            return Ok(());
+        }
+        let content = uri
+            .to_file_path()
+            .ok()
+            .and_then(|path| std::fs::read_to_string(path).ok());
+        let msg = match content {
+            Some(content) => {
+                self.document_map
+                    .insert(key, Rope::from_str(strip_bom(&content)));
+                format!("Document {uri} is not opened, reading from disk.")
+            }
+            None => format!("Document {uri} is not opened and can't be read from disk."),
+        };
+        self.client.log_message(MessageType::WARNING, msg).await;
+        Ok(())
+    }
+
+    /// Every grammar that handles document is incompatible (custom grammar built
+    /// for other tree-sitter version).
+    fn grammar_error(&self, uri: &Url) -> Result<()> {
+        let routes = self.routes(uri);
+        // Index is locked by indexing, grammar is checked in next request.
+        let Some(lsp_files) = self.lsp_files.try_recover(State::Index) else {
+            return Ok(());
+        };
+        let errors: Vec<String> = routes
+            .iter()
+            .filter_map(|lang_type| lsp_files.parsers.error(*lang_type))
+            .collect();
+        if errors.is_empty() || errors.len() < routes.len() {
+            return Ok(());
+        }
+        log::error!("{uri}: {}", errors[0]);
+        Err(Error {
+            code: ErrorCode::InternalError,
+            message: errors[0].clone(),
+            data: None,
+        })
+    }
+
+    /// Show message once for every feature whose state was recovered after panic.
+    async fn report_degraded(&self) {
+        if let Some(msg) = take_degraded_report() {
+            self.client.show_message(MessageType::WARNING, msg).await;
+        }
     }
 
     /// Client notification for `Tag` errors.
     ///
     /// Called after:
     ///  * successful initialization
-    ///  * document save for backend/frontend(tags are saved)
+    ///  * document save (backend/frontend tags are saved, templates are validated again)
     ///  * code action  - `reset_tag`.
     async fn publish_tag_diagnostics(&self, diagnostics: Vec<Tag>, file: Option<String>) {
-        let mut hm: HashMap<String, Vec<Diagnostic>> = HashMap::new();
-        let len = diagnostics.len();
-        self.lsp_files
-            .lock()
-            .ok()
-            .and_then(|lsp_files| -> Option<()> {
-                lsp_files.publish_tag_diagnostics(diagnostics, &mut hm);
-                None
-            });
-        for (url, diagnostics) in hm {
+        let mut hm = {
+            let config = self.htmx_config.recover_read(State::Config).clone();
+            self.lsp_files.recover(State::Index).project_diagnostics(
+                diagnostics,
+                &config,
+                &self.document_map,
+            )
+        };
+        // Saved file can lose all of its tags, old diagnostics must be cleared.
+        if let Some(uri) = file {
+            hm.entry(uri).or_default();
+        }
+        let clients = self.clients();
+        for (url, mut diagnostics) in hm {
             if let Ok(uri) = Url::parse(&url) {
-                self.client
-                    .publish_diagnostics(uri, diagnostics, None)
-                    .await;
+                if !diagnostics.is_empty() {
+                    let text = match self.document_map.get(&url) {
+                        Some(rope) => Some(rope.to_string()),
+                        None => uri
+                            .to_file_path()
+                            .ok()
+                            .and_then(|path| std::fs::read_to_string(path).ok())
+                            .map(|text| strip_bom(&text).to_string()),
+                    };
+                    if let Some(text) = text {
+                        Suppressions::new(&text).filter(&mut diagnostics);
+                    }
+                }
+                self.published.insert(url, diagnostics.clone());
+                for client in &clients {
+                    client
+                        .publish_diagnostics(uri.clone(), diagnostics.clone(), None)
+                        .await;
+                }
             }
         }
-        if let Some(uri) = file {
-            if len == 0 {
-                let uri = Url::parse(&uri).unwrap();
-                self.client.publish_diagnostics(uri, vec![], None).await;
+    }
+
+    /// Every editor that receives diagnostics.
+    fn clients(&self) -> Vec<Client> {
+        match &self.shared_index {
+            Some(index) => index.clients(),
+            None => vec![self.client.clone()],
+        }
+    }
+
+    /// Diagnostics after template change. Only `edits` are checked now (`None` - whole
+    /// template), whole template is checked later if there are no other changes.
+    async fn recheck_changed(&self, uri: &str, edits: Option<Vec<Edit>>, revision: u64) {
+        let dirty = edits.map(|edits| {
+            let mut dirty = DirtyRanges::default();
+            let mut published = self.published.entry(uri.to_string()).or_default();
+            for edit in &edits {
+                edit.shift_diagnostics(&mut published);
+                dirty.edit(edit);
             }
+            dirty
+        });
+        let clients = self.clients();
+        let Some(diagnostics) = recheck_document(
+            &self.lsp_files,
+            &self.document_map,
+            &self.published,
+            uri,
+            dirty.as_ref(),
+        ) else {
+            return;
+        };
+        publish(&clients, uri, diagnostics).await;
+        if dirty.is_none() {
+            return;
+        }
+        let lsp_files = self.lsp_files.clone();
+        let document_map = self.document_map.clone();
+        let published = self.published.clone();
+        let revisions = self.revisions.clone();
+        let key = uri.to_string();
+        let uri = uri.to_string();
+        let recheck = tokio::spawn(async move {
+            tokio::time::sleep(IDLE_RECHECK).await;
+            if revisions
+                .get(&uri)
+                .is_none_or(|current| *current != revision)
+            {
+                return;
+            }
+            if let Some(diagnostics) =
+                recheck_document(&lsp_files, &document_map, &published, &uri, None)
+            {
+                publish(&clients, &uri, diagnostics).await;
+            }
+        });
+        if let Some(previous) = self.rechecks.insert(key, recheck) {
+            previous.abort();
         }
     }
 
@@ -118,9 +753,10 @@ impl BackendHtmx {
             } = position
             {
                 if &name == "hx-lsp" {
-                    self.lsp_files.lock().ok().and_then(|lsp_files| {
-                        lsp_files.goto_definition_response(definition, &value, &mut def)
-                    });
+                    let link = *self.link_support.recover_read(State::Client);
+                    self.lsp_files
+                        .recover(State::Index)
+                        .goto_definition_response(definition, &value, link, &mut def);
                 }
             }
             true
@@ -128,6 +764,744 @@ impl BackendHtmx {
 
         def
     }
+
+    /// References of tag under cursor in `hx-lsp` value.
+    fn template_references(&self, params: ReferenceParams) -> Option<Vec<Location>> {
+        let lsp_files = self.lsp_files.recover(State::Index);
+        let queries = self.queries.recover(State::Queries);
+        let params = GotoDefinitionParams {
+            text_document_position_params: params.text_document_position,
+            work_done_progress_params: params.work_done_progress_params,
+            partial_result_params: params.partial_result_params,
+        };
+        let position =
+            lsp_files.goto_definition(params, &self.htmx_config, &self.document_map, &queries.html);
+        let Some(Position::AttributeValue {
+            name,
+            value,
+            definition: Some(definition),
+        }) = position
+        else {
+            return None;
+        };
+        if name != "hx-lsp" {
+            return None;
+        }
+        let tag = in_tags(&value, definition)?;
+        lsp_files.tag_locations(&tag.name, &queries, &self.document_map)
+    }
+
+    /// Completion right after `hx-swap=`. Items insert quoted value, so user
+    /// doesn't have to type quotes first. Empty quotes are inserted only as snippet.
+    fn equal_completion(
+        &self,
+        text_params: &TextDocumentPositionParams,
+        uri: &str,
+    ) -> Option<Vec<CompletionItem>> {
+        let pos = text_params.position;
+        let line = self
+            .document_map
+            .get(uri)?
+            .get_line(pos.line as usize)?
+            .to_string();
+        let name = attribute_before_equal(line.get(..pos.character as usize)?)?;
+        let snippet = *self.snippet_support.recover_read(State::Client);
+        let range = Range::new(
+            LspPosition::new(pos.line, pos.character - 1),
+            LspPosition::new(pos.line, pos.character),
+        );
+        let mut ret = vec![];
+        if snippet {
+            ret.push(CompletionItem {
+                label: String::from("\"\""),
+                kind: Some(CompletionItemKind::SNIPPET),
+                insert_text_format: Some(InsertTextFormat::SNIPPET),
+                text_edit: Some(CompletionTextEdit::Edit(TextEdit::new(
+                    range,
+                    String::from("=\"$1\""),
+                ))),
+                commit_characters: Some(vec![]),
+                ..Default::default()
+            });
+        }
+        let values = self.hx_attribute_values.recover_read(State::Attributes);
+        for item in values.get(name).into_iter().flatten() {
+            ret.push(with_metadata(
+                item,
+                CompletionItem {
+                    label: item.name.to_string(),
+                    detail: Some(item.desc.to_string()),
+                    kind: Some(CompletionItemKind::TEXT),
+                    filter_text: Some(format!("={}", item.name)),
+                    text_edit: Some(CompletionTextEdit::Edit(TextEdit::new(
+                        range,
+                        format!("=\"{}\"", item.name),
+                    ))),
+                    // Quotes are already inserted.
+                    commit_characters: Some(vec![]),
+                    ..Default::default()
+                },
+            ));
+        }
+        Some(ret)
+    }
+
+    /// Register completion only for template, backend and JavaScript files, with all
+    /// trigger characters.
+    async fn register_completion(&self) {
+        if !*self.dynamic_completion.recover_read(State::Client) {
+            return;
+        }
+        let config = self.htmx_config.recover_read(State::Config).clone();
+        let document_selector = config.is_valid.then(|| {
+            let mut exts = vec![config.template_ext.as_str()];
+            exts.extend(JS_EXTS);
+            exts.extend(config.backend_exts());
+            exts.sort_unstable();
+            exts.dedup();
+            exts.into_iter()
+                .map(|ext| DocumentFilter {
+                    language: None,
+                    scheme: None,
+                    pattern: Some(format!("**/*.{ext}")),
+                })
+                .collect()
+        });
+        let options = CompletionRegistrationOptions {
+            text_document_registration_options: TextDocumentRegistrationOptions {
+                document_selector,
+            },
+            completion_options: completion_options(),
+        };
+        let registration = Registration {
+            id: String::from("htmx-completion"),
+            method: String::from("textDocument/completion"),
+            register_options: serde_json::to_value(options).ok(),
+        };
+        if let Err(err) = self.client.register_capability(vec![registration]).await {
+            self.client
+                .log_message(MessageType::ERROR, err.to_string())
+                .await;
+        }
+    }
+
+    /// "Extract htmx fragment" action for selected element. Index is locked only
+    /// while element is found, files are checked and read on blocking thread.
+    async fn extract_fragment(&self, params: &CodeActionParams) -> Option<CodeAction> {
+        let fragment = self
+            .lsp_files
+            .recover(State::Index)
+            .fragment_at(params, &self.document_map)?;
+        let config = self.htmx_config.recover_read(State::Config).clone();
+        let document_map = self.document_map.clone();
+        let uri = params.text_document.uri.clone();
+        tokio::task::spawn_blocking(move || {
+            extract_fragment(fragment, &uri, &config, &document_map)
+        })
+        .await
+        .ok()?
+    }
+
+    /// Custom request `htmx/configSchema`. Returns JSON Schema for `HtmxConfig`.
+    pub async fn config_schema(&self) -> Result<Value> {
+        Ok(config_schema())
+    }
+
+    /// Custom request `htmx/status`, parse errors for every indexed file and
+    /// read-only documents.
+    pub async fn status(&self) -> Result<Value> {
+        let files = self.lsp_files.recover(State::Index).parse_stats();
+        let degraded: Vec<&str> = degraded_states().iter().map(|state| state.name()).collect();
+        let versions: BTreeMap<String, i32> = self
+            .versions
+            .iter()
+            .map(|version| (version.key().to_string(), *version.value()))
+            .collect();
+        let mut read_only: Vec<String> = self.read_only.iter().map(|uri| uri.to_string()).collect();
+        read_only.sort();
+        Ok(serde_json::json!({
+            "files": files,
+            "versions": versions,
+            "read_only": read_only,
+            "completion_cache": self.completion_cache.stats(),
+            "degraded": degraded,
+            "index": self.indexing.status(),
+        }))
+    }
+
+    /// Custom request `htmx/metrics`. Latency and parse times, only if `metrics` is enabled.
+    pub async fn metrics(&self) -> Result<Value> {
+        let index = self.lsp_files.recover(State::Index).index_size();
+        Ok(METRICS.report(index))
+    }
+
+    /// Custom request `htmx/fragmentTree`. Which templates use which tags and
+    /// which backend/JavaScript handlers define them.
+    pub async fn fragment_tree(&self) -> Result<Value> {
+        self.ensure_indexed().await;
+        let tree = self
+            .lsp_files
+            .recover(State::Index)
+            .fragment_tree(&self.document_map);
+        Ok(serde_json::to_value(tree).unwrap_or_default())
+    }
+
+    /// Custom request `htmx/includeGraph`. Include/extends directives of every
+    /// template and templates they point to.
+    pub async fn include_graph(&self) -> Result<Value> {
+        self.ensure_indexed().await;
+        let graph = self
+            .lsp_files
+            .recover(State::Index)
+            .include_graph(&self.document_map);
+        Ok(serde_json::to_value(graph).unwrap_or_default())
+    }
+
+    /// Custom request `htmx/docs`. Markdown reference of all attributes or of one
+    /// attribute, rendered from completion data.
+    pub async fn docs(&self, params: DocsParams) -> Result<Docs> {
+        let attributes = self.hx_attributes.recover_read(State::Attributes);
+        let values = self.hx_attribute_values.recover_read(State::Attributes);
+        let attribute = params.attribute.as_deref();
+        render_docs(&attributes, &values, attribute).ok_or_else(|| {
+            Error::invalid_params(format!(
+                "Unknown attribute {}",
+                attribute.unwrap_or_default()
+            ))
+        })
+    }
+
+    /// Custom request `htmx/findValue`. Attribute values in all templates that
+    /// match pattern, best matches first.
+    pub async fn find_value(&self, params: FindValueParams) -> Result<Vec<ValueMatch>> {
+        self.ensure_indexed().await;
+        Ok(self
+            .lsp_files
+            .recover(State::Index)
+            .find_values(&params, &self.document_map))
+    }
+
+    /// Log warning for files with many parse errors, they degrade completion and
+    /// diagnostics. Only `file` is checked if it's set.
+    async fn warn_parse_errors(&self, file: Option<&str>) {
+        let stats = self.lsp_files.recover(State::Index).parse_stats();
+        for stats in stats {
+            if !stats.is_degraded() || file.is_some_and(|file| file != stats.uri) {
+                continue;
+            }
+            let msg = format!(
+                "{} has {} parse errors in {} lines, htmx features can be degraded.",
+                stats.uri, stats.errors, stats.lines
+            );
+            self.client.log_message(MessageType::WARNING, msg).await;
+        }
+    }
+
+    /// Completion for htmx attribute names and values.
+    async fn complete(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
+        self.ensure_document(&params.text_document_position.text_document.uri)
+            .await?;
+        let compat = *self.can_complete.recover_read(State::Client);
+        if !completion_allowed(params.context.as_ref(), compat) {
+            return Ok(None);
+        }
+
+        let uri = &params.text_document_position.text_document.uri;
+        let space = params
+            .context
+            .as_ref()
+            .is_some_and(|context| context.trigger_character.as_deref() == Some(" "));
+        for lang_type in self.routes(uri) {
+            let position = &params.text_document_position;
+            let response = match lang_type {
+                // Space in text content, there is nothing to complete.
+                LangType::Template if space && self.in_plain_text(position) => None,
+                LangType::Template => self.template_completion(&params),
+                LangType::Backend => self.tag_completion(position, lang_type),
+                LangType::JavaScript => self
+                    .tag_completion(position, lang_type)
+                    .or_else(|| self.api_completion(position)),
+            };
+            if response.is_some() {
+                return Ok(response);
+            }
+        }
+        Ok(None)
+    }
+
+    /// Cheap check before completion query, see `LspFiles::in_plain_text`.
+    fn in_plain_text(&self, position: &TextDocumentPositionParams) -> bool {
+        let Some(lsp_files) = self.completion_index() else {
+            return false;
+        };
+        let point = Point::new(
+            position.position.line as usize,
+            position.position.character as usize,
+        );
+        lsp_files.in_plain_text(&normalize_uri(&position.text_document.uri), point)
+    }
+
+    /// Languages that handle requests for document, see `HtmxConfig::routes`.
+    /// Document that isn't a file (or is read-only) is always template, backend and
+    /// JavaScript features need index.
+    fn routes(&self, uri: &Url) -> Vec<LangType> {
+        if !self.has_index(uri) {
+            return vec![LangType::Template];
+        }
+        let config = self.htmx_config.recover_read(State::Config);
+        config.routes(Path::new(uri.path()))
+    }
+
+    /// Index features (definition, references, code actions) work only for local files.
+    fn has_index(&self, uri: &Url) -> bool {
+        is_file_uri(uri) && !self.read_only.contains(&normalize_uri(uri))
+    }
+
+    /// Remember if opened or saved file is read-only, `true` when it's newly marked.
+    fn mark_read_only(&self, uri: &Url) -> bool {
+        let key = normalize_uri(uri);
+        match is_file_uri(uri) && !is_local_file(uri) {
+            true => self.read_only.insert(key),
+            false => {
+                self.read_only.remove(&key);
+                false
+            }
+        }
+    }
+
+    /// Completion of htmx attribute names and values in template.
+    fn template_completion(&self, params: &CompletionParams) -> Option<CompletionResponse> {
+        let key = normalize_uri(&params.text_document_position.text_document.uri);
+        if let Some(items) = self.equal_completion(&params.text_document_position, &key) {
+            return Some(CompletionResponse::Array(items));
+        }
+        let cache_key = CacheKey {
+            uri: key.to_string(),
+            revision: self.revision(&key),
+            line: params.text_document_position.position.line,
+            column: params.text_document_position.position.character,
+        };
+        let result = self.completion_cache.get_or_query(cache_key, || {
+            get_position_from_lsp_completion(
+                &params.text_document_position,
+                &self.document_map,
+                key.to_string(),
+                QueryType::Completion,
+                &self.lsp_files,
+                &self.queries,
+            )
+        });
+
+        if let Some(result) = result {
+            match result {
+                Position::AttributeName(name) => {
+                    // Nothing is typed after unfinished tag, every attribute is offered.
+                    if name == NEW_ATTRIBUTE || is_hx_name_prefix(&name) {
+                        // Partial `data-` prefix is replaced with full `data-hx-name`.
+                        let data_edit = match name.starts_with("data-") {
+                            true => self.attribute_name_range(&params.text_document_position),
+                            false => None,
+                        };
+                        let completions =
+                            self.hx_attributes.recover_read(State::Attributes).clone();
+                        let markdown = *self.markdown_completion.recover_read(State::Client);
+                        let mut ret = Vec::with_capacity(completions.len());
+                        for item in completions {
+                            let mut completion = CompletionItem {
+                                label: item.name.to_string(),
+                                kind: Some(CompletionItemKind::TEXT),
+                                documentation: Some(Documentation::MarkupContent(markup_content(
+                                    markdown, &item.desc,
+                                ))),
+                                commit_characters: Some(name_commit_characters()),
+                                ..Default::default()
+                            };
+                            if let Some(range) = data_edit {
+                                let label = format!("data-hx-{}", item.name);
+                                completion.filter_text = Some(label.clone());
+                                completion.text_edit = Some(CompletionTextEdit::Edit(
+                                    TextEdit::new(range, label.clone()),
+                                ));
+                                completion.label = label;
+                            }
+                            ret.push(with_metadata(&item, completion));
+                        }
+                        return Some(CompletionResponse::Array(ret));
+                    }
+                }
+                Position::AttributeValue { name, .. } => {
+                    let position = params.text_document_position.position;
+                    let line = self
+                        .document_map
+                        .get(&key)
+                        .and_then(|rope| Some(rope.get_line(position.line as usize)?.to_string()))
+                        .unwrap_or_default();
+                    let column = position.character as usize;
+                    let completions = match name.as_str() {
+                        "hx-sync" => Some(hx_sync_values(
+                            value_prefix(&line, column).unwrap_or_default(),
+                        )),
+                        "hx-swap" => Some(hx_swap_values(
+                            value_prefix(&line, column).unwrap_or_default(),
+                        )),
+                        "hx-select-oob" => Some(hx_select_oob_values(
+                            value_prefix(&line, column).unwrap_or_default(),
+                        )),
+                        "hx-request" => Some(hx_request_values(
+                            attribute_value_prefix(&line, column, &name).unwrap_or_default(),
+                        )),
+                        "hx-headers" => Some(hx_headers_values(
+                            attribute_value_prefix(&line, column, &name).unwrap_or_default(),
+                        )),
+                        "hx-inherit" | "hx-disinherit" => get_hx_attributes_in_scope(
+                            &params.text_document_position,
+                            &self.document_map,
+                            key.to_string(),
+                            &self.lsp_files,
+                        )
+                        .map(|attributes| {
+                            let hx_attributes = self.hx_attributes.recover_read(State::Attributes);
+                            hx_inherit_values(&attributes, &hx_attributes)
+                        }),
+                        extension if extension_attribute(extension).is_some() => {
+                            self.extension_values(&key, extension)
+                        }
+                        _ => self
+                            .hx_attribute_values
+                            .recover_read(State::Attributes)
+                            .get(&name)
+                            .cloned(),
+                    };
+                    let completions = completions.unwrap_or_default();
+                    // Typed part of value, items are filtered with it and replace it.
+                    // `hx-request` completion already depends on JSON context.
+                    // Header name replaces typed key together with its quote.
+                    let json_key = match name.as_str() {
+                        "hx-headers" => attribute_value_prefix(&line, column, &name)
+                            .and_then(json_key_prefix)
+                            .map(|(typed, _)| typed),
+                        _ => None,
+                    };
+                    let prefix = match name.as_str() {
+                        "hx-request" => None,
+                        "hx-headers" if json_key.is_some() => json_key,
+                        _ => value_prefix(&line, column),
+                    };
+                    let typed = |text: &str| {
+                        let start = LspPosition::new(position.line, (column - text.len()) as u32);
+                        (text.to_string(), Range::new(start, position))
+                    };
+                    let word = prefix.map(|prefix| typed(typed_word(&name, prefix)));
+                    let whole = prefix.map(typed);
+                    let mut ret = Vec::with_capacity(completions.len());
+                    for item in &completions {
+                        let completion = CompletionItem {
+                            label: item.name.to_string(),
+                            detail: Some(item.desc.to_string()),
+                            kind: Some(CompletionItemKind::TEXT),
+                            commit_characters: Some(value_commit_characters(&name, &item.name)),
+                            ..Default::default()
+                        };
+                        ret.push(with_metadata(item, completion));
+                    }
+                    // Id or class of selector is being typed, ids come from templates and
+                    // classes from stylesheets. Only that token is replaced.
+                    let selector = word
+                        .as_ref()
+                        .filter(|_| SELECTOR_ATTRIBUTES.contains(&name.as_str()))
+                        .and_then(|(word, _)| selector_token(word))
+                        .map(typed);
+                    if let Some(selector) = selector {
+                        let items = self.selector_items(&key, &name, &selector.0);
+                        let items = filter_typed(items, Some(&selector));
+                        if items.is_empty() {
+                            return None;
+                        }
+                        return Some(CompletionResponse::Array(items));
+                    }
+                    let mut ret = filter_typed(ret, word.as_ref());
+                    let mut used = vec![];
+                    // Whole values from project don't fit in place of JSON key.
+                    let used_values = self
+                        .completion_index()
+                        .filter(|_| json_key.is_none())
+                        .map(|lsp_files| lsp_files.used_values(&name))
+                        .unwrap_or_default();
+                    for (rank, (value, count)) in used_values.into_iter().enumerate() {
+                        if completions.iter().any(|item| item.name == value) {
+                            continue;
+                        }
+                        used.push(CompletionItem {
+                            commit_characters: Some(value_commit_characters(&name, &value)),
+                            label: value,
+                            detail: Some(format!("Used {count} time(s) in project")),
+                            label_details: Some(CompletionItemLabelDetails {
+                                detail: None,
+                                description: Some(String::from("project")),
+                            }),
+                            kind: Some(CompletionItemKind::VALUE),
+                            sort_text: Some(format!("~{rank:04}")),
+                            ..Default::default()
+                        });
+                    }
+                    // Project values are whole values, they replace whole prefix.
+                    ret.extend(filter_typed(used, whole.as_ref()));
+                    if ret.is_empty() {
+                        return None;
+                    }
+                    return Some(CompletionResponse::Array(ret));
+                }
+            }
+        }
+        None
+    }
+
+    /// Tag names from templates after `hx@` in comment of backend or JavaScript file.
+    /// Tags without definition come first.
+    fn tag_completion(
+        &self,
+        position: &TextDocumentPositionParams,
+        lang_type: LangType,
+    ) -> Option<CompletionResponse> {
+        let key = normalize_uri(&position.text_document.uri);
+        let (line, column) = self.cursor_line(&key, position.position)?;
+        let typed = tag_prefix(&line[..column])?.to_string();
+        let point = Point::new(position.position.line as usize, column);
+        let text = self.document_map.get(&key)?.to_string();
+        let lsp_files = self.completion_index()?;
+        if !lsp_files.in_comment(&key, lang_type, &text, point) {
+            return None;
+        }
+        let tags = lsp_files.template_tags(&self.document_map);
+        drop(lsp_files);
+        let start = LspPosition::new(position.position.line, (column - typed.len()) as u32);
+        let range = Range::new(
+            start,
+            LspPosition::new(position.position.line, column as u32),
+        );
+        let items = tags
+            .into_iter()
+            .map(|(name, count, defined)| CompletionItem {
+                detail: Some(match defined {
+                    true => format!("Used in {count} template(s)"),
+                    false => format!("Used in {count} template(s), not defined"),
+                }),
+                sort_text: Some(format!("{}{name}", defined as u8)),
+                kind: Some(CompletionItemKind::REFERENCE),
+                label: name,
+                ..Default::default()
+            })
+            .collect();
+        let items = filter_typed(items, Some(&(typed, range)));
+        (!items.is_empty()).then_some(CompletionResponse::Array(items))
+    }
+
+    /// Members of htmx JavaScript API after `htmx.`.
+    fn api_completion(&self, position: &TextDocumentPositionParams) -> Option<CompletionResponse> {
+        let key = normalize_uri(&position.text_document.uri);
+        let (line, column) = self.cursor_line(&key, position.position)?;
+        let typed = api_prefix(&line[..column])?.to_string();
+        let markdown = *self.markdown_completion.recover_read(State::Client);
+        let start = LspPosition::new(position.position.line, (column - typed.len()) as u32);
+        let range = Range::new(
+            start,
+            LspPosition::new(position.position.line, column as u32),
+        );
+        let items = HTMX_API
+            .iter()
+            .map(|member| CompletionItem {
+                label: member.name.to_string(),
+                detail: Some(member.signature()),
+                documentation: Some(Documentation::MarkupContent(markup_content(
+                    markdown,
+                    member.desc,
+                ))),
+                kind: Some(match member.args {
+                    Some(_) => CompletionItemKind::METHOD,
+                    None => CompletionItemKind::PROPERTY,
+                }),
+                ..Default::default()
+            })
+            .collect();
+        let items = filter_typed(items, Some(&(typed, range)));
+        (!items.is_empty()).then_some(CompletionResponse::Array(items))
+    }
+
+    /// Hover for htmx attributes and their values in template.
+    fn template_hover(&self, params: &HoverParams) -> Option<Hover> {
+        let uri = &params.text_document_position_params.text_document.uri;
+        let result = get_position_from_lsp_completion(
+            &params.text_document_position_params,
+            &self.document_map,
+            normalize_uri(uri),
+            QueryType::Hover,
+            &self.lsp_files,
+            &self.queries,
+        );
+        let markdown = *self.markdown_hover.recover_read(State::Client);
+
+        if let Some(result) = result {
+            match result {
+                Position::AttributeName(name) => {
+                    let res = self
+                        .hx_attributes
+                        .recover_read(State::Attributes)
+                        .iter()
+                        .find(|x| x.name == name.replace("hx-", ""))
+                        .cloned();
+                    if let Some(res) = res {
+                        let hover_contents =
+                            HoverContents::Markup(markup_content(markdown, &res.desc));
+                        let hover = Hover {
+                            contents: hover_contents,
+                            range: None,
+                        };
+                        return Some(hover);
+                    }
+                }
+                Position::AttributeValue {
+                    name,
+                    value,
+                    definition,
+                } => {
+                    let res = match name.as_str() {
+                        "hx-lsp" => self
+                            .lsp_files
+                            .recover(State::Index)
+                            .tag_hover(&value, definition),
+                        "hx-swap" => self.swap_hover(&params.text_document_position_params, &value),
+                        url if URL_ATTRIBUTES.contains(&url) => {
+                            let template_ext = self
+                                .htmx_config
+                                .recover_read(State::Config)
+                                .template_ext
+                                .clone();
+                            url_hover(&value, interpolations(&template_ext))
+                        }
+                        extension if extension_attribute(extension).is_some() => {
+                            extension_attribute(extension).map(|attribute| {
+                                let value =
+                                    attribute.values.iter().find(|(name, _)| *name == value);
+                                value.map_or(attribute.desc, |(_, desc)| *desc).to_string()
+                            })
+                        }
+                        _ => self
+                            .hx_attribute_values
+                            .recover_read(State::Attributes)
+                            .get(&name)
+                            .and_then(|values| values.iter().find(|x| x.name == value))
+                            .map(|value| value.desc.clone()),
+                    };
+                    if let Some(res) = res {
+                        let hover_contents = HoverContents::Markup(markup_content(markdown, &res));
+                        let hover = Hover {
+                            contents: hover_contents,
+                            range: None,
+                        };
+                        return Some(hover);
+                    }
+                }
+            }
+        }
+
+        if self.htmx_config.recover_read(State::Config).html_hover {
+            let name = get_attribute_name(
+                &params.text_document_position_params,
+                &self.document_map,
+                normalize_uri(uri),
+                &self.lsp_files,
+            );
+            if let Some(res) = name.and_then(|name| {
+                self.html_attributes
+                    .iter()
+                    .find(|x| x.name == name || (x.name == "data-*" && name.starts_with("data-")))
+            }) {
+                let hover = Hover {
+                    contents: HoverContents::Markup(markup_content(markdown, &res.desc)),
+                    range: None,
+                };
+                return Some(hover);
+            }
+        }
+
+        None
+    }
+
+    /// Signature and description of htmx JavaScript API member under cursor.
+    fn api_hover(&self, position: &TextDocumentPositionParams) -> Option<Hover> {
+        let key = normalize_uri(&position.text_document.uri);
+        let (line, column) = self.cursor_line(&key, position.position)?;
+        let member = api_member(&line, column)?;
+        let markdown = *self.markdown_hover.recover_read(State::Client);
+        let text = format!("```js\n{}\n```\n{}", member.signature(), member.desc);
+        Some(Hover {
+            contents: HoverContents::Markup(markup_content(markdown, &text)),
+            range: None,
+        })
+    }
+
+    /// From htmx attribute value in template to tag definition, element with id,
+    /// hosts of oob element or included template.
+    fn template_definition(&self, params: &GotoDefinitionParams) -> Option<GotoDefinitionResponse> {
+        let uri = normalize_uri(&params.text_document_position_params.text_document.uri);
+        let pos = params.text_document_position_params.position;
+        let lsp_files = self.lsp_files.recover(State::Index);
+        let queries = self.queries.recover(State::Queries);
+        let oob_hosts = lsp_files.goto_oob_hosts(params);
+        let include = self
+            .htmx_config
+            .recover_read(State::Config)
+            .enabled(Feature::Definition)
+            .then(|| lsp_files.goto_include(&uri, pos, &self.document_map))
+            .flatten();
+        let position = lsp_files.goto_definition(
+            params.clone(),
+            &self.htmx_config,
+            &self.document_map,
+            &queries.html,
+        );
+        let id_definition = match &position {
+            Some(Position::AttributeValue { name, value, .. }) if name != "hx-lsp" => {
+                lsp_files.goto_id(&uri, value, &self.document_map)
+            }
+            _ => None,
+        };
+        drop(queries);
+        drop(lsp_files);
+        self.check_definition(position)
+            .or(id_definition)
+            .or(oob_hosts)
+            .or(include)
+    }
+
+    /// From string literal in `htmx.ajax` call in JavaScript file.
+    fn ajax_definition(
+        &self,
+        position: &TextDocumentPositionParams,
+    ) -> Option<GotoDefinitionResponse> {
+        if !self
+            .htmx_config
+            .recover_read(State::Config)
+            .enabled(Feature::Definition)
+        {
+            return None;
+        }
+        let uri = normalize_uri(&position.text_document.uri);
+        let pos = position.position;
+        let point = Point::new(pos.line as usize, pos.character as usize);
+        self.lsp_files
+            .recover(State::Index)
+            .goto_ajax(&uri, point, &self.document_map)
+    }
+
+    /// Line of document and cursor column in it, column is clamped to line end.
+    fn cursor_line(&self, key: &str, position: LspPosition) -> Option<(String, usize)> {
+        let rope = self.document_map.get(key)?;
+        let line = rope.get_line(position.line as usize)?.to_string();
+        let line = line.trim_end_matches(['\n', '\r']).to_string();
+        let column = (position.character as usize).min(line.len());
+        line.is_char_boundary(column).then_some((line, column))
+    }
 }
 
 #[tower_lsp::async_trait]
@@ -137,40 +1511,129 @@ impl LanguageServer for BackendHtmx {
         let mut references_provider = None;
         let mut code_action_provider = None;
         let mut implementation_provider = None;
+        let mut type_definition_provider = None;
         let mut execute_command_provider = None;
+        let mut code_lens_provider = None;
 
+        let snippet_support = params
+            .capabilities
+            .text_document
+            .as_ref()
+            .and_then(|text_document| text_document.completion.as_ref())
+            .and_then(|completion| completion.completion_item.as_ref())
+            .and_then(|completion_item| completion_item.snippet_support)
+            .unwrap_or(false);
+        *self.snippet_support.recover_write(State::Client) = snippet_support;
+        let text_document = params.capabilities.text_document.as_ref();
+        *self.markdown_hover.recover_write(State::Client) = supports_markdown(
+            text_document
+                .and_then(|text_document| text_document.hover.as_ref())
+                .and_then(|hover| hover.content_format.as_ref()),
+        );
+        *self.markdown_completion.recover_write(State::Client) = supports_markdown(
+            text_document
+                .and_then(|text_document| text_document.completion.as_ref())
+                .and_then(|completion| completion.completion_item.as_ref())
+                .and_then(|completion_item| completion_item.documentation_format.as_ref()),
+        );
+        *self.client_locale.recover_write(State::Client) = params.locale.clone();
+        *self.link_support.recover_write(State::Client) = text_document
+            .and_then(|text_document| text_document.definition.as_ref())
+            .and_then(|definition| definition.link_support)
+            .unwrap_or(false);
+        *self.work_done_progress.recover_write(State::Client) = params
+            .capabilities
+            .window
+            .as_ref()
+            .and_then(|window| window.work_done_progress)
+            .unwrap_or(false);
+        let dynamic_completion = params
+            .capabilities
+            .text_document
+            .as_ref()
+            .and_then(|text_document| text_document.completion.as_ref())
+            .and_then(|completion| completion.dynamic_registration)
+            .unwrap_or(false);
+        *self.dynamic_completion.recover_write(State::Client) = dynamic_completion;
+        let create_file_support = params
+            .capabilities
+            .workspace
+            .as_ref()
+            .and_then(|workspace| workspace.workspace_edit.as_ref())
+            .and_then(|workspace_edit| workspace_edit.resource_operations.as_ref())
+            .is_some_and(|operations| operations.contains(&ResourceOperationKind::Create));
+        *self.create_file_support.recover_write(State::Client) = create_file_support;
+        // Completion is registered after config is loaded.
+        let completion_provider = match dynamic_completion {
+            true => None,
+            false => Some(completion_options()),
+        };
         if let Some(client_info) = params.client_info {
             if client_info.name == "helix" {
-                if let Ok(mut can_complete) = self.can_complete.write() {
-                    *can_complete = true;
-                }
+                *self.can_complete.recover_write(State::Client) = true;
             }
         }
-        match validate_config(params.initialization_options) {
-            Some(htmx_config) => {
-                self.htmx_config
-                    .try_write()
-                    .ok()
-                    .and_then(|mut config| -> Option<()> {
-                        definition_provider = Some(OneOf::Left(true));
-                        references_provider = Some(OneOf::Left(true));
-                        code_action_provider = Some(CodeActionProviderCapability::Simple(true));
-                        implementation_provider =
-                            Some(ImplementationProviderCapability::Simple(true));
-                        execute_command_provider = Some(ExecuteCommandOptions {
-                            commands: vec!["reset_tags".to_string()],
-                            ..Default::default()
-                        });
-                        *config = htmx_config;
-                        None
-                    });
-            }
+        // Project config file is used when editor sends no options.
+        let root = params
+            .root_uri
+            .as_ref()
+            .and_then(|uri| uri.to_file_path().ok());
+        let options = params
+            .initialization_options
+            .filter(|options| !options.is_null())
+            .or_else(|| read_config_file(root.as_deref().unwrap_or(Path::new("."))));
+        let mut htmx_config = match validate_config(options) {
+            Some(htmx_config) => htmx_config,
             None => {
                 self.client
-                    .log_message(MessageType::INFO, "Config not found")
+                    .log_message(
+                        MessageType::WARNING,
+                        "Config is not valid, running in template-only mode",
+                    )
                     .await;
+                HtmxConfig::template_only()
             }
+        };
+        htmx_config.root = root;
+        if htmx_config.enabled(Feature::Definition) {
+            definition_provider = Some(OneOf::Left(true));
+            type_definition_provider = Some(TypeDefinitionProviderCapability::Simple(true));
+        }
+        if htmx_config.enabled(Feature::References) {
+            references_provider = Some(OneOf::Left(true));
+        }
+        if htmx_config.enabled(Feature::Implementation) {
+            implementation_provider = Some(ImplementationProviderCapability::Simple(true));
+        }
+        if htmx_config.enabled(Feature::CodeActions) {
+            code_action_provider = Some(CodeActionProviderCapability::Options(CodeActionOptions {
+                code_action_kinds: Some(vec![
+                    CodeActionKind::EMPTY,
+                    CodeActionKind::QUICKFIX,
+                    CodeActionKind::REFACTOR_EXTRACT,
+                    CodeActionKind::new(ORGANIZE_HTMX_ATTRIBUTES),
+                ]),
+                work_done_progress_options: Default::default(),
+                resolve_provider: None,
+            }));
+            execute_command_provider = Some(ExecuteCommandOptions {
+                commands: vec![
+                    "reset_tags".to_string(),
+                    "htmx.reindex".to_string(),
+                    "htmx.analyzeBoost".to_string(),
+                    "htmx.indexFile".to_string(),
+                ],
+                ..Default::default()
+            });
+        }
+        if htmx_config.code_lens {
+            code_lens_provider = Some(CodeLensOptions {
+                resolve_provider: Some(false),
+            });
         }
+        METRICS.set_enabled(htmx_config.metrics);
+        set_slow_request_ms(htmx_config.slow_request_ms());
+        *self.htmx_config.recover_write(State::Config) = htmx_config;
 
         Ok(InitializeResult {
             capabilities: ServerCapabilities {
@@ -178,91 +1641,180 @@ impl LanguageServer for BackendHtmx {
                     TextDocumentSyncOptions {
                         change: Some(TextDocumentSyncKind::INCREMENTAL),
                         will_save: Some(true),
+                        will_save_wait_until: Some(true),
                         save: Some(TextDocumentSyncSaveOptions::Supported(true)),
                         ..Default::default()
                     },
                 )),
-                completion_provider: Some(CompletionOptions {
-                    resolve_provider: Some(false),
-                    trigger_characters: Some(vec![
-                        "-".to_string(),
-                        "\"".to_string(),
-                        " ".to_string(),
-                    ]),
-                    all_commit_characters: None,
-                    work_done_progress_options: Default::default(),
-                    completion_item: None,
-                }),
+                completion_provider,
                 hover_provider: Some(HoverProviderCapability::Simple(true)),
                 definition_provider,
+                type_definition_provider,
                 references_provider,
                 code_action_provider,
                 implementation_provider,
                 execute_command_provider,
+                code_lens_provider,
                 ..ServerCapabilities::default()
             },
             server_info: Some(ServerInfo {
-                name: String::from("htmx-lsp"),
-                version: Some(String::from("0.1.3")),
+                name: String::from(env!("CARGO_PKG_NAME")),
+                version: Some(String::from(env!("CARGO_PKG_VERSION"))),
             }),
             offset_encoding: None,
         })
     }
 
     async fn initialized(&self, _params: InitializedParams) {
+        let features = self
+            .htmx_config
+            .recover_read(State::Config)
+            .features_message();
         self.client
-            .log_message(MessageType::INFO, "initialized!")
+            .log_message(
+                MessageType::INFO,
+                format!(
+                    "{} {} initialized! {features}",
+                    env!("CARGO_PKG_NAME"),
+                    env!("CARGO_PKG_VERSION")
+                ),
+            )
             .await;
 
-        match read_config(
-            &self.htmx_config,
-            &self.lsp_files,
-            &self.queries,
-            &self.document_map,
-        ) {
-            Ok(diagnostics) => {
-                self.publish_tag_diagnostics(diagnostics, None).await;
-            }
-            Err(err) => {
-                let _ = self
-                    .htmx_config
-                    .write()
-                    .ok()
-                    .and_then(|mut config| -> Option<()> {
-                        config.is_valid = false;
-                        None
-                    });
-                let msg = err.to_string();
-                self.client.log_message(MessageType::INFO, msg).await;
+        // Completion works without index, it's registered before workspace walk.
+        self.load_attributes().await;
+        self.register_completion().await;
+        let lazy = self.htmx_config.recover_read(State::Config).lazy_index;
+        let result = match lazy {
+            true => {
+                self.indexing.defer();
+                None
             }
+            false => Some(self.index_workspace(false).await),
         };
+        if let Some(result) = result {
+            self.publish_index(result).await;
+        }
+    }
+
+    /// Settings can be sent as `{"htmx": {..}}` or as config itself. Workspace is
+    /// indexed again with new config, index run that is still in progress is canceled.
+    async fn did_change_configuration(&self, params: DidChangeConfigurationParams) {
+        let settings = match params.settings {
+            Value::Object(mut settings) if settings.contains_key("htmx") => settings.remove("htmx"),
+            Value::Null => None,
+            settings => Some(settings),
+        };
+        let Some(settings) = settings.filter(|settings| !settings.is_null()) else {
+            return;
+        };
+        let Some(config) = validate_config(Some(settings)) else {
+            self.client
+                .log_message(
+                    MessageType::WARNING,
+                    "Changed config is not valid, it is ignored",
+                )
+                .await;
+            return;
+        };
+        let lazy = config.lazy_index;
+        set_slow_request_ms(config.slow_request_ms());
+        *self.htmx_config.recover_write(State::Config) = config;
+        self.load_attributes().await;
+        match lazy {
+            true => self.indexing.defer(),
+            false => self.reindex().await,
+        }
     }
 
     async fn did_open(&self, params: DidOpenTextDocumentParams) {
         let _temp_uri = params.text_document.uri.clone();
+        self.versions.insert(
+            normalize_uri(&params.text_document.uri),
+            params.text_document.version,
+        );
+        let limit = self
+            .htmx_config
+            .recover_read(State::Config)
+            .file_size_limit();
+        if params.text_document.text.len() as u64 > limit {
+            let msg = format!(
+                "{} is larger than max_file_size, only completion without syntax tree works.",
+                params.text_document.uri
+            );
+            self.client.log_message(MessageType::WARNING, msg).await;
+        }
+        if self.mark_read_only(&params.text_document.uri) {
+            let msg = format!(
+                "{} can't be resolved to local file, it's read-only: only completion and hover work, definition, references and code actions are unavailable.",
+                params.text_document.uri
+            );
+            self.client.show_message(MessageType::WARNING, msg).await;
+        }
         self.after_open(ServerTextDocumentItem {
             uri: params.text_document.uri,
             text: params.text_document.text,
         });
     }
 
-    async fn did_close(&self, _: DidCloseTextDocumentParams) {}
+    /// Versions start again after document is opened.
+    async fn did_close(&self, params: DidCloseTextDocumentParams) {
+        let uri = normalize_uri(&params.text_document.uri);
+        self.versions.remove(&uri);
+        self.read_only.remove(&uri);
+        if let Some((_, recheck)) = self.rechecks.remove(&uri) {
+            recheck.abort();
+        }
+    }
+
+    async fn will_save_wait_until(
+        &self,
+        params: WillSaveTextDocumentParams,
+    ) -> Result<Option<Vec<TextEdit>>> {
+        let uri = normalize_uri(&params.text_document.uri);
+        let is_template = {
+            let config = self.htmx_config.recover_read(State::Config);
+            config.is_valid
+                && config
+                    .file_ext(Path::new(&uri))
+                    .is_some_and(|lang_types| lang_types.is_lang(LangType::Template))
+        };
+        if !is_template {
+            return Ok(None);
+        }
+        let edits = self.lsp_files.recover(State::Index).missing_quotes(
+            &uri,
+            &self.document_map,
+            &self.queries.recover(State::Queries).html,
+        );
+        Ok(edits)
+    }
 
     async fn did_save(&self, params: DidSaveTextDocumentParams) {
-        let uri = params.text_document.uri.to_string();
-        let _path = Path::new(&uri);
+        // New file is local after first save.
+        self.mark_read_only(&params.text_document.uri);
+        if !self.has_index(&params.text_document.uri) {
+            return;
+        }
+        if self.is_extra_attributes(&params.text_document.uri) {
+            self.load_attributes().await;
+            return;
+        }
+        let uri = normalize_uri(&params.text_document.uri);
         let mut diags = vec![];
-        if let Ok(lsp_files) = self.lsp_files.lock() {
-            if let Some(diagnostics) = lsp_files.saved(
-                &uri,
-                &mut diags,
-                &self.htmx_config,
-                &self.document_map,
-                &self.queries,
-            ) {
-                diags = diagnostics;
-            }
+        self.lsp_files
+            .recover(State::Index)
+            .stylesheet_saved(&uri, &self.document_map);
+        if let Some(diagnostics) = self.lsp_files.recover(State::Index).saved(
+            &uri,
+            &mut diags,
+            &self.htmx_config,
+            &self.document_map,
+            &self.queries,
+        ) {
+            diags = diagnostics;
         }
+        self.warn_parse_errors(Some(&uri)).await;
         self.publish_tag_diagnostics(diags, Some(uri)).await;
     }
 
@@ -330,23 +1882,46 @@ impl LanguageServer for BackendHtmx {
     // }
 
     async fn did_change(&self, params: DidChangeTextDocumentParams) {
-        let uri = &params.text_document.uri.to_string();
+        let uri = &normalize_uri(&params.text_document.uri);
+        let version = params.text_document.version;
+        if let Some(latest) = self.stale_version(uri, version) {
+            let msg =
+                format!("Ignoring change of {uri} with version {version}, latest is {latest}.");
+            self.client.log_message(MessageType::WARNING, msg).await;
+            return;
+        }
+        let revision = self.bump_revision(uri);
         let rope = self.document_map.get_mut(uri);
-        let lang_types = self
+        let mut langs = self
             .htmx_config
-            .read()
-            .ok()
-            .and_then(|lang| lang.file_ext(Path::new(uri)));
-        if lang_types.is_none() {
-            return;
+            .recover_read(State::Config)
+            .file_ext(Path::new(uri))
+            .map(|lang_types| lang_types.langs())
+            .unwrap_or_default();
+        // Template tree can also be created by completion, it must stay in sync with text.
+        if !langs.contains(&LangType::Template) {
+            let has_template = {
+                let lsp_files = self.lsp_files.recover(State::Index);
+                let tree = lsp_files
+                    .get_index(uri)
+                    .and_then(|index| lsp_files.get_tree(LangType::Template, index));
+                tree.is_some()
+            };
+            if has_template {
+                langs.push(LangType::Template);
+            }
         }
-        let lang_types = lang_types.unwrap();
+        let mut edits = Some(vec![]);
         if let Some(mut rope) = rope {
             for change in params.content_changes {
                 if let Some(range) = &change.range {
+                    if let Some(edits) = &mut edits {
+                        edits.push(Edit::new(*range, &change.text));
+                    }
                     let input_edit = rope.to_input_edit(*range, &change.text);
-                    let start = rope.to_byte(range.start);
-                    let end = rope.to_byte(range.end);
+                    // Rope is edited with char indices.
+                    let start = rope.byte_to_char(rope.to_byte(range.start));
+                    let end = rope.byte_to_char(rope.to_byte(range.end));
                     if start <= end {
                         rope.remove(start..end);
                     } else {
@@ -355,184 +1930,74 @@ impl LanguageServer for BackendHtmx {
                     if !change.text.is_empty() {
                         rope.insert(start, &change.text);
                     }
+                    if langs.is_empty() {
+                        continue;
+                    }
                     let mut w = FileWriter::default();
                     let _ = rope.write_to(&mut w);
-                    self.lsp_files
-                        .lock()
-                        .ok()
-                        .and_then(|lsp_files| match lang_types {
-                            LangTypes::One(lang) => {
-                                lsp_files.input_edit(uri, w.content, input_edit, lang)
-                            }
-                            LangTypes::Two { first, second } => {
-                                lsp_files.input_edit(uri, w.content.to_string(), input_edit, first);
-                                lsp_files.input_edit(uri, w.content, input_edit, second)
-                            }
-                        });
+                    let lsp_files = self.lsp_files.recover(State::Index);
+                    for lang in &langs {
+                        lsp_files.input_edit(uri, w.content.to_string(), input_edit, *lang);
+                    }
                 } else {
-                    let new_rope = Rope::from_str(&change.text);
+                    edits = None;
+                    let new_rope = Rope::from_str(strip_bom(&change.text));
                     *rope = new_rope;
+                    if langs.is_empty() {
+                        continue;
+                    }
 
                     let mut w = FileWriter::default();
                     let _ = rope.write_to(&mut w);
 
-                    self.lsp_files
-                        .lock()
-                        .ok()
-                        .and_then(|lsp_files| match lang_types {
-                            LangTypes::One(lang) => lsp_files.add_tree(
-                                lsp_files.get_index(uri)?,
-                                lang,
-                                &w.content,
-                                None,
-                            ),
-                            LangTypes::Two { first, second } => {
-                                let index = lsp_files.get_index(uri)?;
-                                lsp_files.add_tree(index, first, &w.content, None);
-                                lsp_files.add_tree(index, second, &w.content, None)
-                            }
-                        });
-                }
-            }
-        }
-    }
-
-    async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
-        let can_complete = {
-            matches!(
-                params.context,
-                Some(CompletionContext {
-                    trigger_kind: CompletionTriggerKind::TRIGGER_CHARACTER,
-                    ..
-                }) | Some(CompletionContext {
-                    trigger_kind: CompletionTriggerKind::INVOKED,
-                    ..
-                })
-            )
-        };
-        if !can_complete {
-            let can_complete = self.can_complete.read().is_ok_and(|d| *d);
-            if !can_complete {
-                return Ok(None);
-            }
-        }
-
-        let uri = &params.text_document_position.text_document.uri;
-        match uri.to_file_path().unwrap().extension().is_some_and(|ext| {
-            self.htmx_config.read().is_ok_and(|config| {
-                if !config.is_valid {
-                    return false;
-                }
-                return ext.to_str().unwrap() != config.template_ext;
-            })
-        }) {
-            true => return Ok(None),
-            false => (),
-        }
-        let result = self.queries.lock().ok().and_then(|queries| {
-            get_position_from_lsp_completion(
-                &params.text_document_position,
-                &self.document_map,
-                uri.to_string(),
-                QueryType::Completion,
-                &self.lsp_files,
-                &queries.html,
-            )
-        });
-
-        if let Some(result) = result {
-            match result {
-                Position::AttributeName(name) => {
-                    if name.starts_with("hx-") {
-                        let completions = self.hx_attributes.clone();
-                        let mut ret = Vec::with_capacity(completions.len());
-                        for item in completions {
-                            ret.push(CompletionItem {
-                                label: item.name.to_string(),
-                                kind: Some(CompletionItemKind::TEXT),
-                                documentation: Some(Documentation::MarkupContent(MarkupContent {
-                                    kind: MarkupKind::Markdown,
-                                    value: item.desc.to_string(),
-                                })),
-                                ..Default::default()
-                            });
+                    let lsp_files = self.lsp_files.recover(State::Index);
+                    if let Some(index) = lsp_files.get_index(uri) {
+                        for lang in &langs {
+                            lsp_files.add_tree(index, *lang, &w.content, None);
                         }
-                        return Ok(Some(CompletionResponse::Array(ret)));
                     }
                 }
-                Position::AttributeValue { name, .. } => {
-                    if let Some(completions) = self.hx_attribute_values.get(&name) {
-                        let mut ret = Vec::with_capacity(completions.len());
-                        for item in completions {
-                            ret.push(CompletionItem {
-                                label: item.name.to_string(),
-                                detail: Some(item.desc.to_string()),
-                                kind: Some(CompletionItemKind::TEXT),
-                                ..Default::default()
-                            });
-                        }
-                        return Ok(Some(CompletionResponse::Array(ret)));
-                    }
-                    return Ok(None);
+            }
+            // Value history follows unsaved edits of template.
+            if langs.contains(&LangType::Template) {
+                let mut w = FileWriter::default();
+                let _ = rope.write_to(&mut w);
+                let lsp_files = self.lsp_files.recover(State::Index);
+                if let Some(index) = lsp_files.get_index(uri) {
+                    let queries = self.queries.recover(State::Queries);
+                    lsp_files.index_template(index, &w.content, &queries.html);
                 }
             }
         }
-        Ok(None)
+        let check = self
+            .htmx_config
+            .recover_read(State::Config)
+            .enabled(Feature::Diagnostics);
+        if check && langs.contains(&LangType::Template) {
+            self.recheck_changed(uri, edits, revision).await;
+        }
     }
 
-    async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
-        let uri = &params.text_document_position_params.text_document.uri;
-        let result = self.queries.lock().ok().and_then(|queries| {
-            get_position_from_lsp_completion(
-                &params.text_document_position_params,
-                &self.document_map,
-                uri.to_string(),
-                QueryType::Hover,
-                &self.lsp_files,
-                &queries.html,
-            )
-        });
-
-        if let Some(result) = result {
-            match result {
-                Position::AttributeName(name) => {
-                    if let Some(res) = self
-                        .hx_attributes
-                        .iter()
-                        .find(|x| x.name == name.replace("hx-", ""))
-                        .cloned()
-                    {
-                        let markup_content = MarkupContent {
-                            kind: MarkupKind::Markdown,
-                            value: res.desc,
-                        };
-                        let hover_contents = HoverContents::Markup(markup_content);
-                        let hover = Hover {
-                            contents: hover_contents,
-                            range: None,
-                        };
-                        return Ok(Some(hover));
-                    }
-                }
-                Position::AttributeValue { name, value, .. } => {
-                    if let Some(res) = self.hx_attribute_values.get(&name) {
-                        if let Some(res) = res.iter().find(|x| x.name == value).cloned() {
-                            let markup_content = MarkupContent {
-                                kind: MarkupKind::Markdown,
-                                value: res.desc,
-                            };
-                            let hover_contents = HoverContents::Markup(markup_content);
-                            let hover = Hover {
-                                contents: hover_contents,
-                                range: None,
-                            };
-                            return Ok(Some(hover));
-                        }
-                    }
-                }
+    async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
+        let start = METRICS.start();
+        let response = self.complete(params).await;
+        METRICS.record(&METRICS.completion, start);
+        response
+    }
+
+    async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
+        let uri = &params.text_document_position_params.text_document.uri;
+        self.ensure_document(uri).await?;
+        for lang_type in self.routes(uri) {
+            let hover = match lang_type {
+                LangType::Template => self.template_hover(&params),
+                LangType::JavaScript => self.api_hover(&params.text_document_position_params),
+                LangType::Backend => None,
+            };
+            if hover.is_some() {
+                return Ok(hover);
             }
         }
-
         Ok(None)
     }
 
@@ -540,27 +2005,86 @@ impl LanguageServer for BackendHtmx {
         &self,
         params: GotoDefinitionParams,
     ) -> Result<Option<GotoDefinitionResponse>> {
-        let res = self.lsp_files.lock().ok().and_then(|lsp_files| {
-            self.queries.lock().ok().and_then(|queries| {
-                let position = lsp_files.goto_definition(
-                    params,
-                    &self.htmx_config,
-                    &self.document_map,
-                    &queries.html,
-                );
-                drop(queries);
-                drop(lsp_files);
-                self.check_definition(position)
-            })
-        });
-        Ok(res)
+        if !self.has_index(&params.text_document_position_params.text_document.uri) {
+            return Ok(None);
+        }
+        self.ensure_indexed().await;
+        let uri = &params.text_document_position_params.text_document.uri;
+        self.ensure_document(uri).await?;
+        for lang_type in self.routes(uri) {
+            let definition = match lang_type {
+                LangType::Template => self.template_definition(&params),
+                LangType::JavaScript => self.ajax_definition(&params.text_document_position_params),
+                LangType::Backend => None,
+            };
+            if definition.is_some() {
+                return Ok(definition);
+            }
+        }
+        Ok(None)
+    }
+
+    /// From `hx-lsp` value or tag comment to type of Rust handler.
+    async fn goto_type_definition(
+        &self,
+        params: GotoTypeDefinitionParams,
+    ) -> Result<Option<GotoTypeDefinitionResponse>> {
+        let text_document = &params.text_document_position_params.text_document;
+        if !self.has_index(&text_document.uri) {
+            return Ok(None);
+        }
+        self.ensure_indexed().await;
+        self.ensure_document(&text_document.uri).await?;
+        let uri = normalize_uri(&text_document.uri);
+        let pos = params.text_document_position_params.position;
+        let lsp_files = self.lsp_files.recover(State::Index);
+        let queries = self.queries.recover(State::Queries);
+        let position = lsp_files.goto_definition(
+            params.clone(),
+            &self.htmx_config,
+            &self.document_map,
+            &queries.html,
+        );
+        let tag = match position {
+            Some(Position::AttributeValue {
+                name,
+                value,
+                definition: Some(definition),
+            }) if name == "hx-lsp" => in_tags(&value, definition),
+            _ if self
+                .htmx_config
+                .recover_read(State::Config)
+                .enabled(Feature::Definition) =>
+            {
+                self.document_map
+                    .get(&uri)
+                    .and_then(|rope| {
+                        rope.get_line(pos.line as usize)
+                            .map(|line| line.to_string())
+                    })
+                    // Tag from line has no row.
+                    .and_then(|line| in_tag(&line, Point::new(0, pos.character as usize)))
+            }
+            _ => None,
+        };
+        let Some(tag) = tag else {
+            return Ok(None);
+        };
+        Ok(lsp_files.tag_type_definition(&tag.name, &queries, &self.document_map))
     }
 
     async fn references(&self, params: ReferenceParams) -> Result<Option<Vec<Location>>> {
+        if !self.has_index(&params.text_document_position.text_document.uri) {
+            return Ok(None);
+        }
+        self.ensure_indexed().await;
+        self.ensure_document(&params.text_document_position.text_document.uri)
+            .await?;
         let mut locations = None;
         let mut lang_type = LangType::Template;
-        if let Ok(config) = self.htmx_config.read() {
-            if !config.is_valid {
+        {
+            let config = self.htmx_config.recover_read(State::Config);
+            if !config.enabled(Feature::References) {
                 return Ok(locations);
             }
             let ext = config.file_ext(Path::new(
@@ -569,7 +2093,8 @@ impl LanguageServer for BackendHtmx {
             if let Some(lang_types) = ext {
                 let lang_type2 = lang_types.get();
                 if lang_type2 == LangType::Template {
-                    return Ok(locations);
+                    drop(config);
+                    return Ok(self.template_references(params));
                 } else {
                     lang_type = lang_type2;
                 }
@@ -589,11 +2114,12 @@ impl LanguageServer for BackendHtmx {
             //     false => (),
             // }
         }
-        locations = self.lsp_files.lock().ok().and_then(|lsp_files| {
-            self.queries.lock().ok().and_then(|queries| {
-                lsp_files.references(params, &queries, &self.document_map, lang_type)
-            })
-        });
+        locations = self.lsp_files.recover(State::Index).references(
+            params,
+            &self.queries.recover(State::Queries),
+            &self.document_map,
+            lang_type,
+        );
         Ok(locations)
     }
 
@@ -601,67 +2127,323 @@ impl LanguageServer for BackendHtmx {
         &self,
         params: GotoImplementationParams,
     ) -> Result<Option<GotoImplementationResponse>> {
+        if !self.has_index(&params.text_document_position_params.text_document.uri) {
+            return Ok(None);
+        }
+        self.ensure_indexed().await;
+        self.ensure_document(&params.text_document_position_params.text_document.uri)
+            .await?;
         let mut res = None;
-        if let Ok(config) = self.htmx_config.read() {
-            if !config.is_valid {
-                return Ok(res);
-            }
-            res = self.lsp_files.lock().ok().and_then(|lsp_files| {
-                self.queries.lock().ok().and_then(|queries| {
-                    let lang_types = config.file_ext(Path::new(
-                        params
-                            .text_document_position_params
-                            .text_document
-                            .uri
-                            .as_str(),
-                    ))?;
-                    lsp_files.goto_implementation(params, &queries, &self.document_map, lang_types)
-                })
-            });
+        let config = self.htmx_config.recover_read(State::Config);
+        if !config.enabled(Feature::Implementation) {
+            return Ok(res);
+        }
+        let lang_types = config.file_ext(Path::new(
+            params
+                .text_document_position_params
+                .text_document
+                .uri
+                .as_str(),
+        ));
+        drop(config);
+        if let Some(lang_types) = lang_types {
+            res = self.lsp_files.recover(State::Index).goto_implementation(
+                params,
+                &self.queries.recover(State::Queries),
+                &self.document_map,
+                lang_types,
+            );
         }
         Ok(res)
     }
 
+    async fn code_lens(&self, params: CodeLensParams) -> Result<Option<Vec<CodeLens>>> {
+        self.ensure_document(&params.text_document.uri).await?;
+        let uri = normalize_uri(&params.text_document.uri);
+        let enabled = {
+            let config = self.htmx_config.recover_read(State::Config);
+            config.code_lens
+                && config
+                    .file_ext(Path::new(&uri))
+                    .is_some_and(|lang_types| lang_types.is_lang(LangType::Template))
+        };
+        if !enabled {
+            return Ok(None);
+        }
+        Ok(self
+            .lsp_files
+            .recover(State::Index)
+            .code_lens(&uri, &self.document_map))
+    }
+
     async fn code_action(&self, params: CodeActionParams) -> Result<Option<CodeActionResponse>> {
+        if !self.has_index(&params.text_document.uri) {
+            return Ok(None);
+        }
+        self.ensure_document(&params.text_document.uri).await?;
         let mut res = None;
-        if let Ok(config) = self.htmx_config.read() {
-            if !config.is_valid {
-                return Ok(None);
-            }
+        if !self
+            .htmx_config
+            .recover_read(State::Config)
+            .enabled(Feature::CodeActions)
+        {
+            return Ok(None);
         }
-        let position = self.lsp_files.lock().ok().and_then(|lsp_files| {
-            self.queries.lock().ok().and_then(|queries| {
-                lsp_files.code_action(params, &self.htmx_config, &queries.html, &self.document_map)
-            })
-        });
+        // Unknown tag quick fix needs all tags.
+        self.ensure_indexed().await;
+        let create_file_support = *self.create_file_support.recover_read(State::Client);
+        let extract = match create_file_support {
+            true => self.extract_fragment(&params).await,
+            false => None,
+        };
+        let create_tag = self.lsp_files.recover(State::Index).create_tag_actions(
+            &params,
+            &self.htmx_config.recover_read(State::Config),
+            &self.document_map,
+            *self.create_file_support.recover_read(State::Client),
+        );
+        let organize_kind = CodeActionKind::new(ORGANIZE_HTMX_ATTRIBUTES);
+        let organize = match requested_kind(params.context.only.as_deref(), &organize_kind) {
+            true => self
+                .lsp_files
+                .recover(State::Index)
+                .organize_attributes(&params.text_document.uri, &self.document_map),
+            false => None,
+        };
+        let position = self.lsp_files.recover(State::Index).code_action(
+            params,
+            &self.htmx_config,
+            &self.queries.recover(State::Queries).html,
+            &self.document_map,
+        );
         if position.is_some() {
             res = Some(code_actions());
         }
+        for action in create_tag {
+            res.get_or_insert_with(Vec::new)
+                .push(CodeActionOrCommand::CodeAction(action));
+        }
+        if let Some(extract) = extract {
+            res.get_or_insert_with(Vec::new)
+                .push(CodeActionOrCommand::CodeAction(extract));
+        }
+        if let Some(organize) = organize {
+            res.get_or_insert_with(Vec::new)
+                .push(CodeActionOrCommand::CodeAction(organize));
+        }
 
         Ok(res)
     }
 
     async fn execute_command(&self, params: ExecuteCommandParams) -> Result<Option<Value>> {
         let command = params.command;
-        if command == "reset_tags" {
-            let diags = read_config(
-                &self.htmx_config,
-                &self.lsp_files,
-                &self.queries,
-                &self.document_map,
-            );
-            if let Ok(diags) = diags {
-                self.publish_tag_diagnostics(diags, None).await;
+        if command == "reset_tags" || command == "htmx.reindex" {
+            self.reindex().await;
+        } else if command == "htmx.analyzeBoost" {
+            // First argument is document uri.
+            let Some(uri) = params
+                .arguments
+                .first()
+                .and_then(Value::as_str)
+                .and_then(|uri| Url::parse(uri).ok())
+            else {
+                return Ok(None);
+            };
+            self.ensure_document(&uri).await?;
+            let uri = normalize_uri(&uri);
+            let report = self
+                .lsp_files
+                .recover(State::Index)
+                .analyze_boost(&uri, &self.document_map);
+            return Ok(report.map(Value::String));
+        } else if command == "htmx.indexFile" {
+            // Sent by "Create tag" quick fix, after its edit is applied.
+            if let Some(uri) = params
+                .arguments
+                .first()
+                .and_then(Value::as_str)
+                .and_then(|uri| Url::parse(uri).ok())
+            {
+                self.index_file(&uri).await;
             }
         }
         Ok(None)
     }
 
     async fn shutdown(&self) -> Result<()> {
+        self.shutdown.store(true, Ordering::SeqCst);
+        for recheck in self.rechecks.iter() {
+            recheck.abort();
+        }
+        self.rechecks.clear();
+        // Index in daemon mode is still used by other sessions.
+        if self.shared_index.is_none() {
+            self.indexing.cancel();
+            // Canceled walk stops after current batch of files.
+            drop(self.indexing.acquire().await);
+        }
+        // Wait for indexing that is still in progress, state is not left mid-write.
+        drop(self.lsp_files.recover(State::Index));
+        drop(self.queries.recover(State::Queries));
         Ok(())
     }
 }
 
+/// Service with htmx custom requests, same for stdio and every daemon session.
+pub fn build_service(
+    init: impl FnOnce(Client) -> BackendHtmx,
+) -> (
+    ItemDefaults<RequestLog<LspService<BackendHtmx>>>,
+    ClientSocket,
+) {
+    let (service, socket) = LspService::build(init)
+        .custom_method("htmx/configSchema", BackendHtmx::config_schema)
+        .custom_method("htmx/status", BackendHtmx::status)
+        .custom_method("htmx/metrics", BackendHtmx::metrics)
+        .custom_method("htmx/fragmentTree", BackendHtmx::fragment_tree)
+        .custom_method("htmx/findValue", BackendHtmx::find_value)
+        .custom_method("htmx/includeGraph", BackendHtmx::include_graph)
+        .custom_method("htmx/docs", BackendHtmx::docs)
+        .finish();
+    (ItemDefaults::new(RequestLog::new(service)), socket)
+}
+
+/// Check completion context. Some clients send no context at all, in that case
+/// completion is attempted and position decides if there is htmx attribute.
+/// Completion for incomplete results is only allowed in compatibility mode.
+pub fn completion_allowed(context: Option<&CompletionContext>, compat: bool) -> bool {
+    match context.map(|context| context.trigger_kind) {
+        None => true,
+        Some(CompletionTriggerKind::TRIGGER_CHARACTER) | Some(CompletionTriggerKind::INVOKED) => {
+            true
+        }
+        Some(_) => compat,
+    }
+}
+
+/// Template without changes for this long is checked again.
+pub const IDLE_RECHECK: Duration = Duration::from_millis(500);
+
+/// Run template rules on `dirty` ranges of document and update published diagnostics.
+pub fn recheck_document(
+    lsp_files: &Mutex<LspFiles>,
+    document_map: &DashMap<String, Rope>,
+    published: &DashMap<String, Vec<Diagnostic>>,
+    uri: &str,
+    dirty: Option<&DirtyRanges>,
+) -> Option<Vec<Diagnostic>> {
+    let url = Url::parse(uri).ok()?;
+    let text = document_map.get(uri)?.to_string();
+    let lsp_files = lsp_files.recover(State::Index);
+    let index = lsp_files.get_index(&uri.to_string())?;
+    let tree = lsp_files.get_tree(LangType::Template, index)?;
+    let mut diagnostics = published.entry(uri.to_string()).or_default();
+    recheck(
+        &mut diagnostics,
+        &url,
+        tree.root_node(),
+        &text,
+        dirty,
+        &lsp_files.parsers,
+    );
+    Some(diagnostics.clone())
+}
+
+async fn publish(clients: &[Client], uri: &str, mut diagnostics: Vec<Diagnostic>) {
+    let Ok(uri) = Url::parse(uri) else {
+        return;
+    };
+    sort_diagnostics(&mut diagnostics);
+    for client in clients {
+        client
+            .publish_diagnostics(uri.clone(), diagnostics.clone(), None)
+            .await;
+    }
+}
+
+/// Keep items that match typed text, accepted item replaces it. Best matches come
+/// first, items with own `sort_text` keep it.
+pub fn filter_typed(
+    items: Vec<CompletionItem>,
+    typed: Option<&(String, Range)>,
+) -> Vec<CompletionItem> {
+    let Some((text, range)) = typed else {
+        return items;
+    };
+    items
+        .into_iter()
+        .filter_map(|mut item| {
+            if !text.is_empty() {
+                let score = fuzzy_score(text, &item.label)?;
+                if item.sort_text.is_none() {
+                    item.sort_text = Some(format!("{:04}", 1000 - score));
+                }
+            }
+            item.filter_text = Some(item.label.clone());
+            item.text_edit = Some(CompletionTextEdit::Edit(TextEdit::new(
+                *range,
+                item.label.clone(),
+            )));
+            Some(item)
+        })
+        .collect()
+}
+
+/// Category and deprecation flag from `HxCompletion`.
+pub fn with_metadata(item: &HxCompletion, mut completion: CompletionItem) -> CompletionItem {
+    if let Some(detail) = &item.detail {
+        completion.label_details = Some(CompletionItemLabelDetails {
+            detail: None,
+            description: Some(detail.to_string()),
+        });
+    }
+    if item.deprecated {
+        completion.deprecated = Some(true);
+        completion.tags = Some(vec![CompletionItemTag::DEPRECATED]);
+    }
+    completion
+}
+
+/// Closing quote accepts value completion, items with other rules override it.
+const VALUE_COMMIT_CHARACTERS: [&str; 2] = ["\"", "'"];
+
+/// Attribute name is accepted with `=`.
+pub fn name_commit_characters() -> Vec<String> {
+    vec![String::from("=")]
+}
+
+/// Characters that accept value completion and are typed after it. Space or
+/// comma also accepts value of attributes with list of values. Value ending
+/// with `:` is not finished, so nothing accepts it.
+pub fn value_commit_characters(attribute: &str, value: &str) -> Vec<String> {
+    if value.ends_with(':') {
+        return vec![];
+    }
+    let mut characters = VALUE_COMMIT_CHARACTERS.map(String::from).to_vec();
+    let attribute = attribute.strip_prefix("data-").unwrap_or(attribute);
+    match attribute {
+        "hx-swap" | "hx-sync" | "hx-trigger" | "hx-inherit" | "hx-disinherit" => {
+            characters.push(String::from(" "))
+        }
+        "hx-ext" | "hx-params" => characters.push(String::from(",")),
+        _ => (),
+    }
+    characters
+}
+
+/// Completion options. `:` is used for `hx-on:`, `,` for list values, `#` and `.`
+/// for ids and classes in selectors.
+pub fn completion_options() -> CompletionOptions {
+    let trigger_characters = ["-", "\"", " ", "=", ":", ",", "#", ".", "@"];
+    CompletionOptions {
+        resolve_provider: Some(false),
+        trigger_characters: Some(trigger_characters.map(String::from).to_vec()),
+        all_commit_characters: Some(VALUE_COMMIT_CHARACTERS.map(String::from).to_vec()),
+        work_done_progress_options: Default::default(),
+        completion_item: None,
+    }
+}
+
 /// Returns available code actions for this language-server.
 pub fn code_actions() -> Vec<CodeActionOrCommand> {
     let mut commands = vec![];
@@ -679,6 +2461,18 @@ pub fn code_actions() -> Vec<CodeActionOrCommand> {
     commands
 }
 
+/// Client didn't filter code actions, or `kind` is one of requested(or their child).
+pub fn requested_kind(only: Option<&[CodeActionKind]>, kind: &CodeActionKind) -> bool {
+    only.is_none_or(|only| {
+        only.iter().any(|requested| {
+            kind.as_str() == requested.as_str()
+                || kind
+                    .as_str()
+                    .starts_with(&format!("{}.", requested.as_str()))
+        })
+    })
+}
+
 /// Used in didOpen request.
 pub struct ServerTextDocumentItem {
     pub uri: Url,
@@ -703,3 +2497,966 @@ impl std::io::Write for FileWriter {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use futures::{SinkExt, StreamExt};
+    use serde_json::{json, Value};
+    use tokio::sync::oneshot;
+    use tower_lsp::lsp_types::{
+        CodeActionContext, CodeActionOrCommand, CodeActionParams, CompletionContext,
+        CompletionParams, CompletionResponse, CompletionTextEdit, CompletionTriggerKind,
+        CreateFile, DidChangeTextDocumentParams, DidCloseTextDocumentParams,
+        DidOpenTextDocumentParams, DidSaveTextDocumentParams, DocumentChangeOperation,
+        DocumentChanges, GotoDefinitionParams, Hover, HoverContents, HoverParams,
+        InitializedParams, OneOf, OptionalVersionedTextDocumentIdentifier, PartialResultParams,
+        Position, Range, ResourceOp, TextDocumentContentChangeEvent, TextDocumentEdit,
+        TextDocumentIdentifier, TextDocumentItem, TextDocumentPositionParams, TextEdit, Url,
+        VersionedTextDocumentIdentifier, WorkDoneProgressParams,
+    };
+    use tower_lsp::{
+        jsonrpc::{ErrorCode, Request, Response},
+        LanguageServer, LspService,
+    };
+    use tower_service::Service;
+    use tree_sitter::Language;
+
+    use std::{future::Future, sync::mpsc::RecvTimeoutError, time::Duration};
+
+    use super::{completion_allowed, completion_options, value_commit_characters, BackendHtmx};
+    use crate::{
+        config::HtmxConfig,
+        find_value::FindValueParams,
+        init_hx::LangType,
+        state::{Recover, RecoverRw, State},
+        test_dir::TestDir,
+        uri::normalize_uri,
+    };
+
+    fn params(uri: &Url, character: u32, context: Option<CompletionContext>) -> CompletionParams {
+        CompletionParams {
+            text_document_position: TextDocumentPositionParams::new(
+                TextDocumentIdentifier::new(uri.clone()),
+                Position::new(0, character),
+            ),
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: PartialResultParams::default(),
+            context,
+        }
+    }
+
+    fn labels(response: Option<CompletionResponse>) -> Vec<String> {
+        match response {
+            Some(CompletionResponse::Array(items)) => {
+                items.into_iter().map(|item| item.label).collect()
+            }
+            _ => vec![],
+        }
+    }
+
+    #[test]
+    fn completion_context_kinds() {
+        let context = |trigger_kind| CompletionContext {
+            trigger_kind,
+            trigger_character: None,
+        };
+        assert!(completion_allowed(None, false));
+        assert!(completion_allowed(
+            Some(&context(CompletionTriggerKind::INVOKED)),
+            false
+        ));
+        let incomplete = context(CompletionTriggerKind::TRIGGER_FOR_INCOMPLETE_COMPLETIONS);
+        assert!(!completion_allowed(Some(&incomplete), false));
+        assert!(completion_allowed(Some(&incomplete), true));
+    }
+
+    #[tokio::test]
+    async fn completion_without_context() {
+        let (service, _socket) = LspService::new(BackendHtmx::new);
+        let server = service.inner();
+        let uri = Url::parse("file:///templates/index.html").unwrap();
+        let text = r#"<div hx-swap="" hx-></div>"#;
+        server
+            .document_map
+            .insert(uri.to_string(), ropey::Rope::from_str(text));
+
+        let values = labels(server.completion(params(&uri, 14, None)).await.unwrap());
+        assert!(values.contains(&String::from("innerHTML")));
+
+        let attributes = labels(server.completion(params(&uri, 19, None)).await.unwrap());
+        assert!(attributes.contains(&String::from("swap")));
+
+        let outside = labels(server.completion(params(&uri, 2, None)).await.unwrap());
+        assert!(outside.is_empty());
+    }
+
+    #[tokio::test]
+    async fn attributes_after_unfinished_tag() {
+        let (service, _socket) = LspService::new(BackendHtmx::new);
+        let server = service.inner();
+        let uri = Url::parse("file:///templates/new.html").unwrap();
+        let text = r#"<div hx-boost class="a" "#;
+        server
+            .document_map
+            .insert(uri.to_string(), ropey::Rope::from_str(text));
+
+        let attributes = labels(server.completion(params(&uri, 24, None)).await.unwrap());
+        assert!(attributes.contains(&String::from("get")));
+        assert!(attributes.contains(&String::from("swap")));
+    }
+
+    #[tokio::test]
+    async fn incompatible_grammar_fails_request() {
+        // Grammar of ABI version 0, tree-sitter reads only version before rejecting it.
+        static VERSION: [u32; 1] = [0];
+        // SAFETY: `Language` is pointer to `TSLanguage`, version is its first field.
+        let language = unsafe { std::mem::transmute::<*const u32, Language>(VERSION.as_ptr()) };
+        let (service, _socket) = LspService::new(BackendHtmx::new);
+        let server = service.inner();
+        *server.htmx_config.recover_write(State::Config) = HtmxConfig {
+            lang: String::from("rust"),
+            template_ext: String::from("html"),
+            is_valid: true,
+            ..Default::default()
+        };
+        server
+            .lsp_files
+            .recover(State::Index)
+            .parsers
+            .set_backend(language);
+        let hover = |uri: &Url| HoverParams {
+            text_document_position_params: params(uri, 4, None).text_document_position,
+            work_done_progress_params: WorkDoneProgressParams::default(),
+        };
+
+        let backend = Url::parse("file:///src/main.rs").unwrap();
+        server
+            .document_map
+            .insert(backend.to_string(), ropey::Rope::from_str("fn a() {}"));
+        let err = server.hover(hover(&backend)).await.unwrap_err();
+        assert_eq!(err.code, ErrorCode::InternalError);
+        assert!(err.message.contains("Incompatible language version 0"));
+
+        let template = Url::parse("file:///templates/index.html").unwrap();
+        server.document_map.insert(
+            template.to_string(),
+            ropey::Rope::from_str(r#"<div hx-get="/a">"#),
+        );
+        assert!(server.hover(hover(&template)).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn value_history_follows_unsaved_edits() {
+        let (service, _socket) = LspService::new(BackendHtmx::new);
+        let server = service.inner();
+        *server.htmx_config.recover_write(State::Config) = HtmxConfig {
+            lang: String::from("rust"),
+            template_ext: String::from("html"),
+            is_valid: true,
+            ..Default::default()
+        };
+        let uri = Url::parse("file:///templates/index.html").unwrap();
+        let text = r##"<div hx-target="#list"></div><p hx-target=""></p>"##;
+        server
+            .document_map
+            .insert(uri.to_string(), ropey::Rope::from_str(text));
+        {
+            let lsp_files = server.lsp_files.recover(State::Index);
+            let queries = server.queries.recover(State::Queries);
+            let index = lsp_files.add_file(uri.to_string()).unwrap();
+            lsp_files.add_tree(index, LangType::Template, text, None);
+            lsp_files.index_template(index, text, &queries.html);
+        }
+        let used = |labels: Vec<String>| labels.into_iter().filter(|label| label.starts_with('#'));
+        let values = used(labels(
+            server.completion(params(&uri, 43, None)).await.unwrap(),
+        ));
+        assert_eq!(values.collect::<Vec<_>>(), vec!["#list"]);
+
+        let change = DidChangeTextDocumentParams {
+            text_document: VersionedTextDocumentIdentifier::new(uri.clone(), 2),
+            content_changes: vec![TextDocumentContentChangeEvent {
+                range: Some(Range::new(Position::new(0, 16), Position::new(0, 21))),
+                range_length: None,
+                text: String::from("#rows"),
+            }],
+        };
+        server.did_change(change).await;
+        let values = used(labels(
+            server.completion(params(&uri, 43, None)).await.unwrap(),
+        ));
+        assert_eq!(values.collect::<Vec<_>>(), vec!["#rows"]);
+    }
+
+    #[tokio::test]
+    async fn unopened_document_is_read_from_disk() {
+        let (service, socket) = LspService::new(BackendHtmx::new);
+        // Every request for unopened document logs warning.
+        tokio::spawn(socket.for_each(|_| async {}));
+        let server = service.inner();
+        let root = TestDir::new();
+        let path = root.write("index.html", r#"<div hx-get="/a"></div>"#);
+        let uri = Url::from_file_path(path).unwrap();
+        let hover = |uri: &Url| HoverParams {
+            text_document_position_params: params(uri, 7, None).text_document_position,
+            work_done_progress_params: WorkDoneProgressParams::default(),
+        };
+        let Some(Hover {
+            contents: HoverContents::Markup(markup),
+            ..
+        }) = server.hover(hover(&uri)).await.unwrap()
+        else {
+            panic!("no hover for unopened document");
+        };
+        assert!(markup.value.contains("hx-get"));
+        assert!(server.document_map.contains_key(&normalize_uri(&uri)));
+
+        let missing = Url::from_file_path(root.join("missing.html")).unwrap();
+        assert!(server.hover(hover(&missing)).await.unwrap().is_none());
+        assert!(!server.document_map.contains_key(&normalize_uri(&missing)));
+    }
+
+    #[tokio::test]
+    async fn extract_fragment_edit() {
+        let (service, socket) = LspService::new(BackendHtmx::new);
+        tokio::spawn(socket.for_each(|_| async {}));
+        let server = service.inner();
+        let root = TestDir::new();
+        root.write("templates/row.html", "");
+        let stub = root.write("src/main.rs", "fn main() {}\n");
+        *server.htmx_config.recover_write(State::Config) = HtmxConfig {
+            lang: String::from("rust"),
+            template_ext: String::from("html"),
+            templates: vec![String::from("./templates")],
+            backend_stub: Some(String::from("src/main.rs")),
+            is_valid: true,
+            root: Some(root.to_path_buf()),
+            ..Default::default()
+        };
+        *server.create_file_support.recover_write(State::Client) = true;
+        let path = root.write("templates/index.html", "");
+        let uri = Url::from_file_path(&path).unwrap();
+        let text = "<main>\n  <ul id=\"row\"><li>a</li></ul>\n</main>";
+        server
+            .document_map
+            .insert(normalize_uri(&uri), ropey::Rope::from_str(text));
+        {
+            let lsp_files = server.lsp_files.recover(State::Index);
+            let index = lsp_files.add_file(normalize_uri(&uri)).unwrap();
+            lsp_files.add_tree(index, LangType::Template, text, None);
+        }
+        let action = |range: Range| {
+            let params = CodeActionParams {
+                text_document: TextDocumentIdentifier::new(uri.clone()),
+                range,
+                context: CodeActionContext::default(),
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                partial_result_params: PartialResultParams::default(),
+            };
+            async {
+                server
+                    .code_action(params)
+                    .await
+                    .unwrap()
+                    .unwrap_or_default()
+                    .into_iter()
+                    .find_map(|action| match action {
+                        CodeActionOrCommand::CodeAction(action)
+                            if action.title == "Extract htmx fragment" =>
+                        {
+                            Some(action)
+                        }
+                        _ => None,
+                    })
+            }
+        };
+        let cursor = Position::new(1, 5);
+        assert!(action(Range::new(cursor, cursor)).await.is_none());
+
+        let selection = Range::new(Position::new(1, 3), Position::new(1, 10));
+        let edit = action(selection).await.unwrap().edit.unwrap();
+        let Some(DocumentChanges::Operations(operations)) = edit.document_changes else {
+            panic!("no document changes");
+        };
+        let templates = std::fs::canonicalize(root.join("templates")).unwrap();
+        let new_uri = Url::from_file_path(templates.join("row_1.html")).unwrap();
+        let edit = |uri: &Url, range: Range, text: &str| {
+            DocumentChangeOperation::Edit(TextDocumentEdit {
+                text_document: OptionalVersionedTextDocumentIdentifier {
+                    uri: uri.clone(),
+                    version: None,
+                },
+                edits: vec![OneOf::Left(TextEdit::new(range, text.to_string()))],
+            })
+        };
+        let stub_end = Position::new(1, 0);
+        assert_eq!(
+            operations,
+            vec![
+                DocumentChangeOperation::Op(ResourceOp::Create(CreateFile {
+                    uri: new_uri.clone(),
+                    options: None,
+                    annotation_id: None,
+                })),
+                edit(
+                    &new_uri,
+                    Range::default(),
+                    "<ul id=\"row\"><li>a</li></ul>\n"
+                ),
+                edit(
+                    &uri,
+                    Range::new(Position::new(1, 2), Position::new(1, 30)),
+                    r#"<div hx-get="/row" hx-trigger="load" hx-lsp="row"></div>"#,
+                ),
+                edit(
+                    &Url::from_file_path(std::fs::canonicalize(stub).unwrap()).unwrap(),
+                    Range::new(stub_end, stub_end),
+                    "\nfn row() {\n    // hx@row\n}\n",
+                ),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn html_attribute_hover() {
+        let (service, _socket) = LspService::new(BackendHtmx::new);
+        let server = service.inner();
+        let uri = Url::parse("file:///templates/index.html").unwrap();
+        let text = r#"<div class="row" hx-get="/a"></div>"#;
+        server
+            .document_map
+            .insert(uri.to_string(), ropey::Rope::from_str(text));
+        {
+            let lsp_files = server.lsp_files.recover(State::Index);
+            let index = lsp_files.add_file(uri.to_string()).unwrap();
+            lsp_files.add_tree(index, LangType::Template, text, None);
+        }
+        let hover = HoverParams {
+            text_document_position_params: params(&uri, 7, None).text_document_position,
+            work_done_progress_params: WorkDoneProgressParams::default(),
+        };
+        assert!(server.hover(hover.clone()).await.unwrap().is_none());
+
+        server.htmx_config.recover_write(State::Config).html_hover = true;
+        let Some(Hover {
+            contents: HoverContents::Markup(markup),
+            ..
+        }) = server.hover(hover).await.unwrap()
+        else {
+            panic!("no hover for class attribute");
+        };
+        assert!(markup
+            .value
+            .starts_with("A space-separated list of the classes of the element."));
+        assert!(markup.value.ends_with(
+            "[MDN Reference](https://developer.mozilla.org/en-US/docs/Web/HTML/Global_attributes/class)"
+        ));
+    }
+
+    #[tokio::test]
+    async fn requests_are_routed_by_language() {
+        let (service, _socket) = LspService::new(BackendHtmx::new);
+        let server = service.inner();
+        *server.htmx_config.recover_write(State::Config) = HtmxConfig {
+            lang: String::from("rust"),
+            template_ext: String::from("html"),
+            is_valid: true,
+            ..Default::default()
+        };
+        let template = String::from("file:///templates/index.html");
+        let text = r#"<div hx-lsp="cart"></div><p hx-lsp="list"></p>"#;
+        server
+            .document_map
+            .insert(template.clone(), ropey::Rope::from_str(text));
+        {
+            let lsp_files = server.lsp_files.recover(State::Index);
+            lsp_files.parsers.change_backend("rust", LangType::Backend);
+            let index = lsp_files.add_file(template).unwrap();
+            lsp_files.add_tree(index, LangType::Template, text, None);
+        }
+        let at = |uri: &Url, line, character| {
+            let mut params = params(uri, 0, None);
+            params.text_document_position.position = Position::new(line, character);
+            params
+        };
+
+        let backend = Url::parse("file:///src/main.rs").unwrap();
+        let text = "fn a() {\n    // hx@ca\n}\n";
+        server
+            .document_map
+            .insert(backend.to_string(), ropey::Rope::from_str(text));
+        let tags = labels(server.completion(at(&backend, 1, 13)).await.unwrap());
+        assert_eq!(tags, vec!["cart"]);
+        let code = labels(server.completion(at(&backend, 0, 3)).await.unwrap());
+        assert!(code.is_empty());
+
+        let script = Url::parse("file:///static/app.js").unwrap();
+        let text = "htmx.aj\nhtmx.ajax(\"GET\", \"/users\");";
+        server
+            .document_map
+            .insert(script.to_string(), ropey::Rope::from_str(text));
+        let members = labels(server.completion(at(&script, 0, 7)).await.unwrap());
+        assert_eq!(members, vec!["ajax"]);
+        let params = HoverParams {
+            text_document_position_params: at(&script, 1, 7).text_document_position,
+            work_done_progress_params: WorkDoneProgressParams::default(),
+        };
+        let Some(Hover {
+            contents: HoverContents::Markup(markup),
+            ..
+        }) = server.hover(params).await.unwrap()
+        else {
+            panic!("no hover for htmx API");
+        };
+        assert!(markup.value.contains("htmx.ajax(verb, path, context)"));
+    }
+
+    #[tokio::test]
+    async fn id_completion_in_selector() {
+        let (service, _socket) = LspService::new(BackendHtmx::new);
+        let server = service.inner();
+        let uri = Url::parse("file:///templates/ids.html").unwrap();
+        let text =
+            r##"<div hx-target="#" hx-include="div#mo"><p id="modal"></p><p id="list"></p></div>"##;
+        server
+            .document_map
+            .insert(uri.to_string(), ropey::Rope::from_str(text));
+
+        let ids = labels(server.completion(params(&uri, 17, None)).await.unwrap());
+        assert_eq!(ids, vec!["#modal", "#list"]);
+        let Some(CompletionResponse::Array(items)) =
+            server.completion(params(&uri, 37, None)).await.unwrap()
+        else {
+            panic!("no id completion after element name");
+        };
+        assert_eq!(items.len(), 1);
+        let Some(CompletionTextEdit::Edit(edit)) = &items[0].text_edit else {
+            panic!("id token is not replaced");
+        };
+        assert_eq!(edit.range.start, Position::new(0, 34));
+        assert_eq!(edit.new_text, "#modal");
+    }
+
+    /// Edits, saves, completion, hover and reindex of same workspace from many threads.
+    /// Deadlock (wrong lock order, parsers lock taken under tree entry) fails by timeout.
+    /// Server uses std locks, dashmap and tokio directly, model checkers (loom, shuttle)
+    /// can't schedule them, so this test runs real threads.
+    #[test]
+    fn concurrent_edits_completion_and_reindex() {
+        let root = TestDir::new();
+        for i in 0..10 {
+            let template = format!("<div hx-get=\"/{i}\" hx-lsp=\"tag{i}\"></div>");
+            root.write(format!("templates/{i}.html"), template);
+        }
+        let backend = "fn a() {\n    // hx@tag1\n}\n";
+        root.write("src/main.rs", backend);
+        let config = HtmxConfig {
+            lang: String::from("rust"),
+            template_ext: String::from("html"),
+            templates: vec![root.join("templates").display().to_string()],
+            backend_tags: vec![root.join("src").display().to_string().into()],
+            is_valid: true,
+            ..Default::default()
+        };
+        let uri = Url::from_file_path(root.join("templates/0.html")).unwrap();
+        let text = |version: i32| format!("<div hx-get=\"/{version}\" hx-></div>");
+
+        let (done, finished) = std::sync::mpsc::channel();
+        let worker_uri = uri.clone();
+        let worker = std::thread::spawn(move || {
+            let uri = worker_uri;
+            let (service, _socket) = LspService::new(BackendHtmx::new);
+            let server = service.inner();
+            *server.htmx_config.recover_write(State::Config) = config;
+            fn block_on(task: impl Future<Output = ()>) {
+                tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                    .unwrap()
+                    .block_on(task)
+            }
+            std::thread::scope(|scope| {
+                scope.spawn(|| {
+                    block_on(async {
+                        let item =
+                            TextDocumentItem::new(uri.clone(), String::from("html"), 1, text(1));
+                        server
+                            .did_open(DidOpenTextDocumentParams {
+                                text_document: item,
+                            })
+                            .await;
+                        for version in 2..100 {
+                            let change = DidChangeTextDocumentParams {
+                                text_document: VersionedTextDocumentIdentifier::new(
+                                    uri.clone(),
+                                    version,
+                                ),
+                                content_changes: vec![TextDocumentContentChangeEvent {
+                                    range: None,
+                                    range_length: None,
+                                    text: text(version),
+                                }],
+                            };
+                            server.did_change(change).await;
+                        }
+                    })
+                });
+                scope.spawn(|| {
+                    block_on(async {
+                        for _ in 0..50 {
+                            let params = DidSaveTextDocumentParams {
+                                text_document: TextDocumentIdentifier::new(uri.clone()),
+                                text: None,
+                            };
+                            server.did_save(params).await;
+                        }
+                    })
+                });
+                scope.spawn(|| {
+                    block_on(async {
+                        for _ in 0..200 {
+                            let mut completion = params(&uri, 22, None);
+                            completion.text_document_position.position.character = 23;
+                            assert!(server.completion(completion).await.is_ok());
+                            let hover = HoverParams {
+                                text_document_position_params: params(&uri, 7, None)
+                                    .text_document_position,
+                                work_done_progress_params: WorkDoneProgressParams::default(),
+                            };
+                            assert!(server.hover(hover).await.is_ok());
+                        }
+                    })
+                });
+                scope.spawn(|| {
+                    block_on(async {
+                        for _ in 0..5 {
+                            server.reindex().await;
+                        }
+                    })
+                });
+            });
+            let key = normalize_uri(&uri);
+            assert_eq!(server.document_map.get(&key).unwrap().to_string(), text(99));
+            assert!(server
+                .lsp_files
+                .recover(State::Index)
+                .tags
+                .contains_key("tag1"));
+            let _ = done.send(());
+        });
+        let result = finished.recv_timeout(Duration::from_secs(60));
+        // Deadlocked worker can't be joined, it ends with test process.
+        if let Err(RecvTimeoutError::Timeout) = result {
+            panic!("requests are deadlocked");
+        }
+        if let Err(panic) = worker.join() {
+            std::panic::resume_unwind(panic);
+        }
+    }
+
+    #[tokio::test]
+    async fn documents_outside_of_workspace() {
+        let (service, _socket) = LspService::new(BackendHtmx::new);
+        let server = service.inner();
+        *server.htmx_config.recover_write(State::Config) = HtmxConfig {
+            lang: String::from("rust"),
+            template_ext: String::from("html"),
+            is_valid: true,
+            ..Default::default()
+        };
+        let text = "<div hx-></div>";
+        for uri in ["untitled:Untitled-1", "git:/app/src/main.rs?ref=HEAD"] {
+            let uri = Url::parse(uri).unwrap();
+            server
+                .document_map
+                .insert(uri.to_string(), ropey::Rope::from_str(text));
+            let attributes = labels(server.completion(params(&uri, 8, None)).await.unwrap());
+            assert!(attributes.contains(&String::from("get")));
+            let definition = GotoDefinitionParams {
+                text_document_position_params: params(&uri, 13, None).text_document_position,
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                partial_result_params: PartialResultParams::default(),
+            };
+            assert_eq!(server.goto_definition(definition).await.unwrap(), None);
+        }
+    }
+
+    #[tokio::test]
+    async fn read_only_documents() {
+        let (service, _socket) = LspService::new(BackendHtmx::new);
+        let server = service.inner();
+        *server.htmx_config.recover_write(State::Config) = HtmxConfig {
+            lang: String::from("rust"),
+            template_ext: String::from("html"),
+            is_valid: true,
+            ..Default::default()
+        };
+        let uri = Url::parse("file://remote/templates/index.html").unwrap();
+        server
+            .did_open(DidOpenTextDocumentParams {
+                text_document: TextDocumentItem::new(
+                    uri.clone(),
+                    String::from("html"),
+                    1,
+                    String::from(r#"<div hx-get="/a" hx-></div>"#),
+                ),
+            })
+            .await;
+        let attributes = labels(server.completion(params(&uri, 20, None)).await.unwrap());
+        assert!(attributes.contains(&String::from("swap")));
+        let hover = HoverParams {
+            text_document_position_params: params(&uri, 7, None).text_document_position,
+            work_done_progress_params: WorkDoneProgressParams::default(),
+        };
+        assert!(server.hover(hover).await.unwrap().is_some());
+        let definition = GotoDefinitionParams {
+            text_document_position_params: params(&uri, 14, None).text_document_position,
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: PartialResultParams::default(),
+        };
+        assert_eq!(server.goto_definition(definition).await.unwrap(), None);
+        let status = server.status().await.unwrap();
+        assert_eq!(status["read_only"][0], normalize_uri(&uri));
+
+        server
+            .did_close(DidCloseTextDocumentParams {
+                text_document: TextDocumentIdentifier::new(uri),
+            })
+            .await;
+        assert!(server.read_only.is_empty());
+    }
+
+    #[tokio::test]
+    async fn lazy_index_is_awaited_by_concurrent_requests() {
+        let root = TestDir::new();
+        root.write("templates/index.html", r#"<div hx-lsp="cart"></div>"#);
+        root.write("src/main.rs", "fn cart() {\n    // hx@cart\n}\n");
+        let (service, socket) = LspService::new(BackendHtmx::new);
+        tokio::spawn(socket.for_each(|_| async {}));
+        let server = service.inner();
+        *server.htmx_config.recover_write(State::Config) = HtmxConfig {
+            lang: String::from("rust"),
+            template_ext: String::from("html"),
+            templates: vec![root.join("templates").display().to_string()],
+            backend_tags: vec![root.join("src").display().to_string().into()],
+            lazy_index: true,
+            is_valid: true,
+            ..Default::default()
+        };
+        server.initialized(InitializedParams {}).await;
+        assert_eq!(server.indexing.status().indexed, 0);
+
+        // Only one of them starts deferred run, other one waits for it.
+        let find = FindValueParams {
+            pattern: String::from("cart"),
+            attribute: None,
+        };
+        let (values, tree) = tokio::join!(server.find_value(find), server.fragment_tree());
+        assert_eq!(values.unwrap().len(), 1);
+        assert!(tree.unwrap().to_string().contains("main.rs"));
+    }
+
+    #[tokio::test]
+    async fn shutdown_stops_background_work() {
+        let (service, _socket) = LspService::new(BackendHtmx::new);
+        let server = service.inner();
+        let run = server.indexing.start();
+        let (checked, aborted) = oneshot::channel();
+        let recheck = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            let _ = checked.send(());
+        });
+        server
+            .rechecks
+            .insert(String::from("file:///templates/index.html"), recheck);
+
+        tokio::time::timeout(Duration::from_secs(60), server.shutdown())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(run.is_canceled() && !server.indexing.is_running());
+        assert!(server.rechecks.is_empty());
+        // Aborted task drops its sender without sending.
+        assert!(aborted.await.is_err());
+    }
+
+    #[tokio::test]
+    #[allow(clippy::await_holding_lock)]
+    async fn completion_during_indexing() {
+        let (service, _socket) = LspService::new(BackendHtmx::new);
+        let server = service.inner();
+        let uri = Url::parse("file:///templates/index.html").unwrap();
+        let text = r#"<div hx-swap="" hx-></div>"#;
+        server
+            .document_map
+            .insert(uri.to_string(), ropey::Rope::from_str(text));
+
+        // Index lock is held by indexing or other request, completion is
+        // answered from catalogue without waiting.
+        let index = server.lsp_files.recover(State::Index);
+        let values = labels(server.completion(params(&uri, 14, None)).await.unwrap());
+        assert!(values.contains(&String::from("innerHTML")));
+        let attributes = labels(server.completion(params(&uri, 19, None)).await.unwrap());
+        assert!(attributes.contains(&String::from("swap")));
+        drop(index);
+    }
+
+    #[tokio::test]
+    async fn completion_registered_before_indexing() {
+        let (mut service, socket) = LspService::new(BackendHtmx::new);
+        let (registered, registration) = oneshot::channel();
+        tokio::spawn(async move {
+            let (mut requests, mut responses) = socket.split();
+            let mut registered = Some(registered);
+            while let Some(request) = requests.next().await {
+                if request.method() != "client/registerCapability" {
+                    continue;
+                }
+                if let Some(registered) = registered.take() {
+                    let _ = registered.send(request.params().cloned());
+                }
+                if let Some(id) = request.id() {
+                    let _ = responses
+                        .send(Response::from_ok(id.clone(), Value::Null))
+                        .await;
+                }
+            }
+        });
+        let initialize = Request::build("initialize")
+            .params(json!({
+                "capabilities": { "textDocument": { "completion": { "dynamicRegistration": true } } }
+            }))
+            .id(1)
+            .finish();
+        service.call(initialize).await.unwrap();
+        let server = service.inner();
+        assert!(*server.dynamic_completion.recover_read(State::Client));
+
+        // Workspace walk can't start while index run is held.
+        let running = server.indexing.acquire().await;
+        let initialized = server.initialized(InitializedParams {});
+        tokio::pin!(initialized);
+        let params = tokio::time::timeout(Duration::from_secs(60), async {
+            tokio::select! {
+                _ = &mut initialized => panic!("initialized finished without indexing"),
+                params = registration => params.unwrap().unwrap(),
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(
+            params.pointer("/registrations/0/method"),
+            Some(&Value::from("textDocument/completion"))
+        );
+        drop(running);
+        tokio::time::timeout(Duration::from_secs(60), initialized)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn completion_registration_paths() {
+        let initialize = |dynamic: bool| {
+            Request::build("initialize")
+                .params(json!({
+                    "capabilities": { "textDocument": { "completion": { "dynamicRegistration": dynamic } } }
+                }))
+                .id(1)
+                .finish()
+        };
+
+        // Without dynamic registration completion is static capability.
+        let (mut service, _socket) = LspService::new(BackendHtmx::new);
+        let response = service.call(initialize(false)).await.unwrap().unwrap();
+        let (_, result) = response.into_parts();
+        let provider = result.unwrap()["capabilities"]["completionProvider"].clone();
+        let options = serde_json::to_value(completion_options()).unwrap();
+        assert_eq!(provider, options);
+        assert!(!*service
+            .inner()
+            .dynamic_completion
+            .recover_read(State::Client));
+
+        let (mut service, socket) = LspService::new(BackendHtmx::new);
+        let (registered, registration) = oneshot::channel();
+        tokio::spawn(async move {
+            let (mut requests, mut responses) = socket.split();
+            let mut registered = Some(registered);
+            while let Some(request) = requests.next().await {
+                if request.method() != "client/registerCapability" {
+                    continue;
+                }
+                if let Some(registered) = registered.take() {
+                    let _ = registered.send(request.params().cloned());
+                }
+                if let Some(id) = request.id() {
+                    let _ = responses
+                        .send(Response::from_ok(id.clone(), Value::Null))
+                        .await;
+                }
+            }
+        });
+        let response = service.call(initialize(true)).await.unwrap().unwrap();
+        let (_, result) = response.into_parts();
+        assert!(result.unwrap()["capabilities"]
+            .get("completionProvider")
+            .is_none());
+        let server = service.inner();
+        *server.htmx_config.recover_write(State::Config) = HtmxConfig {
+            lang: String::from("python"),
+            template_ext: String::from("jinja"),
+            is_valid: true,
+            ..Default::default()
+        };
+        server.register_completion().await;
+        let params = tokio::time::timeout(Duration::from_secs(60), registration)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        let options = &params["registrations"][0]["registerOptions"];
+        let patterns: Vec<&str> = options["documentSelector"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|filter| filter["pattern"].as_str())
+            .collect();
+        assert!(patterns.contains(&"**/*.jinja"));
+        assert!(patterns.contains(&"**/*.py"));
+        assert_eq!(options["triggerCharacters"], provider["triggerCharacters"]);
+    }
+
+    #[tokio::test]
+    async fn completion_after_data_prefix() {
+        let (service, _socket) = LspService::new(BackendHtmx::new);
+        let server = service.inner();
+        let uri = Url::parse("file:///templates/data.html").unwrap();
+        let text = r#"<div data-h></div>"#;
+        server
+            .document_map
+            .insert(uri.to_string(), ropey::Rope::from_str(text));
+
+        let Some(CompletionResponse::Array(items)) =
+            server.completion(params(&uri, 11, None)).await.unwrap()
+        else {
+            panic!("no completion after data-");
+        };
+        let swap = items
+            .iter()
+            .find(|item| item.label == "data-hx-swap")
+            .unwrap();
+        assert_eq!(
+            swap.text_edit,
+            Some(CompletionTextEdit::Edit(TextEdit::new(
+                Range::new(Position::new(0, 5), Position::new(0, 11)),
+                String::from("data-hx-swap")
+            )))
+        );
+        assert_eq!(swap.commit_characters, Some(vec![String::from("=")]));
+    }
+
+    #[tokio::test]
+    async fn values_are_filtered_by_typed_text() {
+        let (service, _socket) = LspService::new(BackendHtmx::new);
+        let server = service.inner();
+        let uri = Url::parse("file:///templates/typed.html").unwrap();
+        let text = r#"<div hx-swap="inner" hx-trigger="click del"></div>"#;
+        server
+            .document_map
+            .insert(uri.to_string(), ropey::Rope::from_str(text));
+
+        let Some(CompletionResponse::Array(items)) =
+            server.completion(params(&uri, 19, None)).await.unwrap()
+        else {
+            panic!("no completion for typed value");
+        };
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].label, "innerHTML");
+        assert_eq!(
+            items[0].text_edit,
+            Some(CompletionTextEdit::Edit(TextEdit::new(
+                Range::new(Position::new(0, 14), Position::new(0, 19)),
+                String::from("innerHTML")
+            )))
+        );
+
+        let trigger = labels(server.completion(params(&uri, 42, None)).await.unwrap());
+        assert_eq!(trigger, ["delay:"]);
+    }
+
+    #[tokio::test]
+    async fn header_names_replace_typed_key() {
+        let (service, _socket) = LspService::new(BackendHtmx::new);
+        let server = service.inner();
+        let uri = Url::parse("file:///templates/headers.html").unwrap();
+        let text = r#"<div hx-headers='{"X-Req'></div>"#;
+        server
+            .document_map
+            .insert(uri.to_string(), ropey::Rope::from_str(text));
+
+        let Some(CompletionResponse::Array(items)) =
+            server.completion(params(&uri, 24, None)).await.unwrap()
+        else {
+            panic!("no completion for header name");
+        };
+        let header = items
+            .iter()
+            .find(|item| item.label == "\"X-Requested-With\"")
+            .unwrap();
+        assert!(items.iter().all(|item| item.label != "\"Accept\""));
+        assert_eq!(
+            header.text_edit,
+            Some(CompletionTextEdit::Edit(TextEdit::new(
+                Range::new(Position::new(0, 18), Position::new(0, 24)),
+                String::from("\"X-Requested-With\"")
+            )))
+        );
+    }
+
+    #[tokio::test]
+    async fn out_of_order_changes_are_ignored() {
+        let (service, _socket) = LspService::new(BackendHtmx::new);
+        let server = service.inner();
+        // Local file, read-only document would get one more message.
+        let uri = Url::from_file_path(std::env::temp_dir().join("versions.html")).unwrap();
+        server
+            .did_open(DidOpenTextDocumentParams {
+                text_document: TextDocumentItem::new(
+                    uri.clone(),
+                    String::from("html"),
+                    1,
+                    String::from("<div></div>"),
+                ),
+            })
+            .await;
+        let change = |version: i32, text: &str| DidChangeTextDocumentParams {
+            text_document: VersionedTextDocumentIdentifier::new(uri.clone(), version),
+            content_changes: vec![TextDocumentContentChangeEvent {
+                range: None,
+                range_length: None,
+                text: text.to_string(),
+            }],
+        };
+        server.did_change(change(3, "<p></p>")).await;
+        // Only one warning can be logged, socket is not read in test.
+        server.did_change(change(2, "<span></span>")).await;
+        let text = server.document_map.get(uri.as_str()).unwrap().to_string();
+        assert_eq!(text, "<p></p>");
+        assert_eq!(server.stale_version(uri.as_str(), 3), Some(3));
+        assert_eq!(server.stale_version(uri.as_str(), 4), None);
+
+        let status = server.status().await.unwrap();
+        assert_eq!(status["versions"][uri.as_str()], 4);
+    }
+
+    #[test]
+    fn commit_characters_of_values() {
+        assert_eq!(value_commit_characters("hx-target", "this"), ["\"", "'"]);
+        assert_eq!(
+            value_commit_characters("data-hx-swap", "innerHTML"),
+            ["\"", "'", " "]
+        );
+        assert_eq!(value_commit_characters("hx-ext", "sse"), ["\"", "'", ","]);
+        assert!(value_commit_characters("hx-trigger", "delay:").is_empty());
+    }
+}
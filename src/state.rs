@@ -0,0 +1,196 @@
+use std::sync::{
+    Mutex, MutexGuard, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard, TryLockError,
+};
+
+/// Shared state of language server, locks are grouped by features that use them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum State {
+    /// Trees, tags and template indexes.
+    Index,
+    Queries,
+    Config,
+    /// Attribute and value catalogues.
+    Attributes,
+    /// Client capabilities and settings.
+    Client,
+    Parsers,
+    Cache,
+}
+
+impl State {
+    pub fn name(&self) -> &'static str {
+        match self {
+            State::Index => "index",
+            State::Queries => "queries",
+            State::Config => "config",
+            State::Attributes => "attributes",
+            State::Client => "client",
+            State::Parsers => "parsers",
+            State::Cache => "completion cache",
+        }
+    }
+
+    /// Features that can return incomplete results after recovery.
+    pub fn features(&self) -> &'static str {
+        match self {
+            State::Index => "tags, goto definition, references and diagnostics",
+            State::Queries => "completion, hover and tags",
+            State::Config => "configuration",
+            State::Attributes => "attribute completion and hover",
+            State::Client => "completion settings",
+            State::Parsers => "parsing",
+            State::Cache => "completion",
+        }
+    }
+}
+
+/// Poisoned states and whether user was told about them.
+static DEGRADED: Mutex<Vec<(State, bool)>> = Mutex::new(Vec::new());
+
+fn degraded() -> MutexGuard<'static, Vec<(State, bool)>> {
+    // Nothing panics while this lock is held.
+    DEGRADED.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn poisoned(state: State) {
+    log::error!(
+        "Lock for {} was poisoned by panic, state is recovered",
+        state.name()
+    );
+    let mut degraded = degraded();
+    if !degraded.iter().any(|(old, _)| *old == state) {
+        degraded.push((state, false));
+    }
+}
+
+/// Every state that was recovered in this process.
+pub fn degraded_states() -> Vec<State> {
+    degraded().iter().map(|(state, _)| *state).collect()
+}
+
+/// Message for `window/showMessage` with states that were not reported yet,
+/// every state is reported once.
+pub fn take_degraded_report() -> Option<String> {
+    let mut degraded = degraded();
+    let features: Vec<&str> = degraded
+        .iter_mut()
+        .filter(|(_, reported)| !*reported)
+        .map(|(state, reported)| {
+            *reported = true;
+            state.features()
+        })
+        .collect();
+    if features.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "htmx-lsp recovered from internal error, results can be incomplete for: {}. Restart language server if problems continue.",
+        features.join("; ")
+    ))
+}
+
+/// `Mutex` that is used even after panic in other request. Poisoning is
+/// logged and cleared, so lock is reported only once.
+pub trait Recover<T> {
+    fn recover(&self, state: State) -> MutexGuard<'_, T>;
+    /// Same as `recover`, but `None` is returned when lock is held by someone else.
+    fn try_recover(&self, state: State) -> Option<MutexGuard<'_, T>>;
+}
+
+impl<T> Recover<T> for Mutex<T> {
+    fn recover(&self, state: State) -> MutexGuard<'_, T> {
+        self.lock().unwrap_or_else(|err| {
+            poisoned(state);
+            self.clear_poison();
+            err.into_inner()
+        })
+    }
+
+    fn try_recover(&self, state: State) -> Option<MutexGuard<'_, T>> {
+        match self.try_lock() {
+            Ok(guard) => Some(guard),
+            Err(TryLockError::WouldBlock) => None,
+            Err(TryLockError::Poisoned(err)) => {
+                poisoned(state);
+                self.clear_poison();
+                Some(err.into_inner())
+            }
+        }
+    }
+}
+
+/// Same as `Recover`, for `RwLock`.
+pub trait RecoverRw<T> {
+    fn recover_read(&self, state: State) -> RwLockReadGuard<'_, T>;
+    fn recover_write(&self, state: State) -> RwLockWriteGuard<'_, T>;
+}
+
+impl<T> RecoverRw<T> for RwLock<T> {
+    fn recover_read(&self, state: State) -> RwLockReadGuard<'_, T> {
+        self.read().unwrap_or_else(|err| {
+            poisoned(state);
+            self.clear_poison();
+            err.into_inner()
+        })
+    }
+
+    fn recover_write(&self, state: State) -> RwLockWriteGuard<'_, T> {
+        self.write().unwrap_or_else(|err| {
+            poisoned(state);
+            self.clear_poison();
+            err.into_inner()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{mpsc, Arc, Mutex, RwLock},
+        time::Duration,
+    };
+
+    use super::{degraded_states, take_degraded_report, Recover, RecoverRw, State};
+
+    #[test]
+    fn poisoned_locks_are_recovered_and_reported_once() {
+        let tags = Arc::new(Mutex::new(vec![1]));
+        let config = Arc::new(RwLock::new(String::from("rust")));
+        let (tags2, config2) = (tags.clone(), config.clone());
+        let _ = std::thread::spawn(move || {
+            let _tags = tags2.lock().unwrap();
+            let _config = config2.write().unwrap();
+            panic!("request failed");
+        })
+        .join();
+        assert!(tags.is_poisoned() && config.is_poisoned());
+
+        tags.recover(State::Index).push(2);
+        assert_eq!(*config.recover_read(State::Config), "rust");
+        assert!(!tags.is_poisoned() && !config.is_poisoned());
+        assert_eq!(*tags.recover(State::Index), vec![1, 2]);
+
+        assert!(degraded_states().contains(&State::Index));
+        let report = take_degraded_report().unwrap();
+        assert!(report.contains("goto definition") && report.contains("configuration"));
+        assert_eq!(take_degraded_report(), None);
+    }
+
+    #[test]
+    fn held_lock_is_not_awaited() {
+        let index = Arc::new(Mutex::new(1));
+        let (locked, wait) = mpsc::channel();
+        let holder = {
+            let index = index.clone();
+            std::thread::spawn(move || {
+                let _index = index.lock().unwrap();
+                locked.send(()).unwrap();
+                std::thread::sleep(Duration::from_millis(50));
+            })
+        };
+        wait.recv().unwrap();
+        assert!(index.try_recover(State::Index).is_none());
+        holder.join().unwrap();
+        assert_eq!(index.try_recover(State::Index).as_deref(), Some(&1));
+    }
+}
@@ -0,0 +1,294 @@
+use std::collections::BTreeSet;
+
+use tree_sitter::{Node, Point};
+
+use crate::{
+    embedded::advance,
+    position::{start_tags, tag_attributes},
+};
+
+/// Extensions of indexed stylesheets.
+pub const STYLESHEET_EXTS: [&str; 2] = ["css", "scss"];
+
+/// Classes added by htmx itself, together with default indicator style.
+pub const HTMX_CLASSES: [&str; 5] = [
+    "htmx-added",
+    "htmx-indicator",
+    "htmx-request",
+    "htmx-settling",
+    "htmx-swapping",
+];
+
+/// Attributes with CSS selector value, class names are completed in them.
+pub const SELECTOR_ATTRIBUTES: [&str; 6] = [
+    "hx-target",
+    "hx-indicator",
+    "hx-include",
+    "hx-select",
+    "hx-select-oob",
+    "hx-disabled-elt",
+];
+
+/// Classes defined in one stylesheet.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Stylesheet {
+    pub classes: BTreeSet<String>,
+    /// Utility framework (`@tailwind`, `@import "tailwindcss"`) generates classes
+    /// that are not in source, so classes can't be checked.
+    pub generated: bool,
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '-' || c == '_' || !c.is_ascii()
+}
+
+/// Class name after `.` at start of `text`, escaped characters (`sm\:flex`) are
+/// unescaped. Returns name and its length in `text`.
+fn class_name(text: &str) -> Option<(String, usize)> {
+    let mut name = String::new();
+    let mut chars = text.char_indices().peekable();
+    let mut end = 0;
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some((j, escaped)) => {
+                    name.push(escaped);
+                    end = j + escaped.len_utf8();
+                }
+                None => break,
+            },
+            c if is_ident_char(c) => {
+                name.push(c);
+                end = i + c.len_utf8();
+            }
+            _ => break,
+        }
+    }
+    let first = name.chars().next()?;
+    (!first.is_ascii_digit()).then_some((name, end))
+}
+
+/// Class names in selector with byte offset of their `.`. Numbers (`1.5em`) and
+/// escaped dots (`w-1\.5`) are skipped.
+pub fn selector_classes(selector: &str) -> Vec<(usize, String)> {
+    let mut classes = vec![];
+    let mut previous = None;
+    for (i, c) in selector.char_indices() {
+        if c == '.' && previous != Some('\\') {
+            if let Some((name, _)) = class_name(&selector[i + 1..]) {
+                classes.push((i, name));
+            }
+        }
+        previous = Some(c);
+    }
+    classes
+}
+
+/// Comments and strings are replaced with spaces, so offsets stay the same.
+/// SCSS line comment must start line or follow whitespace (`url(http://..)`).
+fn strip_comments(source: &str) -> String {
+    let bytes = source.as_bytes();
+    let mut stripped = String::with_capacity(source.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let rest = &source[i..];
+        let end = if rest.starts_with("/*") {
+            rest.find("*/").map_or(rest.len(), |end| end + 2)
+        } else if rest.starts_with("//") && (i == 0 || bytes[i - 1].is_ascii_whitespace()) {
+            rest.find('\n').unwrap_or(rest.len())
+        } else if rest.starts_with(['"', '\'']) {
+            let quote = bytes[i] as char;
+            rest[1..].find(quote).map_or(rest.len(), |end| end + 2)
+        } else {
+            let c = rest.chars().next().unwrap_or_default();
+            stripped.push(c);
+            i += c.len_utf8();
+            continue;
+        };
+        stripped.extend(rest[..end].chars().map(|_| ' '));
+        i += end;
+    }
+    stripped
+}
+
+impl Stylesheet {
+    /// Classes from rule selectors. Nested SCSS selectors (`&--active`, `&__icon`)
+    /// are joined with classes of parent rule.
+    pub fn parse(source: &str) -> Self {
+        let generated = ["@tailwind", "tailwindcss", "@unocss"]
+            .iter()
+            .any(|marker| source.contains(marker));
+        let source = strip_comments(source);
+        let mut classes = BTreeSet::new();
+        let mut parents: Vec<Vec<String>> = vec![];
+        let mut start = 0;
+        for (i, c) in source.char_indices() {
+            match c {
+                '{' => {
+                    let prelude = source[start..i].trim();
+                    let mut rule = vec![];
+                    if !prelude.starts_with('@') {
+                        for (_, name) in selector_classes(prelude) {
+                            rule.push(name);
+                        }
+                        let parent = parents.last().cloned().unwrap_or_default();
+                        for (j, _) in prelude.match_indices('&') {
+                            let suffix = &prelude[j + 1..];
+                            if !suffix.starts_with(['-', '_']) {
+                                continue;
+                            }
+                            let Some((suffix, _)) = class_name(suffix) else {
+                                continue;
+                            };
+                            rule.extend(parent.iter().map(|parent| format!("{parent}{suffix}")));
+                        }
+                    }
+                    classes.extend(rule.iter().cloned());
+                    parents.push(rule);
+                    start = i + 1;
+                }
+                '}' => {
+                    parents.pop();
+                    start = i + 1;
+                }
+                ';' => start = i + 1,
+                _ => (),
+            }
+        }
+        Self { classes, generated }
+    }
+}
+
+pub struct ClassDiagnostic {
+    pub range: (Point, Point),
+    pub message: String,
+}
+
+/// Templating syntax can create any class.
+fn is_templated(text: &str) -> bool {
+    text.contains(['{', '}', '%', '$', '<', '>', '('])
+}
+
+/// Classes that are not in `known` from `hx-indicator` selectors and from
+/// class attribute of elements with `htmx-indicator` class.
+pub fn unknown_classes(
+    root: Node<'_>,
+    source: &str,
+    known: &BTreeSet<String>,
+) -> Vec<ClassDiagnostic> {
+    let mut diagnostics = vec![];
+    let mut unknown = |value: Node<'_>, text: &str, offset: usize, name: &str, len: usize| {
+        if known.contains(name) {
+            return;
+        }
+        let start = advance(value.start_position(), &text[..offset]);
+        diagnostics.push(ClassDiagnostic {
+            range: (start, advance(start, &text[offset..offset + len])),
+            message: format!("Class `{name}` is not defined in any stylesheet."),
+        });
+    };
+    for tag in start_tags(root) {
+        for (name, value) in tag_attributes(tag, source) {
+            let Some(value) = value else {
+                continue;
+            };
+            let Ok(text) = value.utf8_text(source.as_bytes()) else {
+                continue;
+            };
+            if is_templated(text) {
+                continue;
+            }
+            match name.strip_prefix("data-").unwrap_or(&name) {
+                "hx-indicator" => {
+                    for (offset, class) in selector_classes(text) {
+                        let len = class_name(&text[offset + 1..]).map_or(0, |(_, len)| len);
+                        unknown(value, text, offset, &class, len + 1);
+                    }
+                }
+                "class"
+                    if text
+                        .split_whitespace()
+                        .any(|class| class == "htmx-indicator") =>
+                {
+                    let mut offset = 0;
+                    for class in text.split_whitespace() {
+                        let start = offset + text[offset..].find(class).unwrap_or_default();
+                        unknown(value, text, start, class, class.len());
+                        offset = start + class.len();
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use tree_sitter::{Parser, Point};
+
+    use super::{selector_classes, unknown_classes, Stylesheet, HTMX_CLASSES};
+
+    #[test]
+    fn classes_from_css_and_scss() {
+        let css = r#"
+/* .commented { } */
+.spinner, button.loading > .icon:hover { background: url("a.b.png"); opacity: .5; }
+@media (min-width: 1.5em) { .sm\:flex { display: flex; } }
+.card {
+  // .skipped
+  &--active { color: red; }
+  &__title { margin: 0; }
+  &.selected { }
+}
+"#;
+        let stylesheet = Stylesheet::parse(css);
+        let classes: Vec<&str> = stylesheet.classes.iter().map(String::as_str).collect();
+        assert_eq!(
+            classes,
+            [
+                "card",
+                "card--active",
+                "card__title",
+                "icon",
+                "loading",
+                "selected",
+                "sm:flex",
+                "spinner"
+            ]
+        );
+        assert!(!stylesheet.generated);
+        assert!(Stylesheet::parse("@tailwind utilities;").generated);
+        assert_eq!(
+            selector_classes("closest .row, #a.b"),
+            [(8, String::from("row")), (16, String::from("b"))]
+        );
+    }
+
+    #[test]
+    fn unknown_indicator_classes() {
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_html::language()).unwrap();
+        let text = r#"<button hx-get="/a" hx-indicator="closest .spinner, .missing"></button>
+<img class="htmx-indicator  spin {{ x }}"><img class="htmx-indicator gone">"#;
+        let tree = parser.parse(text, None).unwrap();
+        let mut known: BTreeSet<String> = HTMX_CLASSES.map(String::from).into();
+        known.insert(String::from("spinner"));
+        let diagnostics = unknown_classes(tree.root_node(), text, &known);
+        let ranges: Vec<_> = diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.range)
+            .collect();
+        assert_eq!(
+            ranges,
+            [
+                (Point::new(0, 52), Point::new(0, 60)),
+                (Point::new(1, 69), Point::new(1, 73))
+            ]
+        );
+        assert!(diagnostics[0].message.contains("`missing`"));
+    }
+}
@@ -0,0 +1,198 @@
+use tower_lsp::lsp_types::{Diagnostic, NumberOrString};
+
+/// Diagnostic codes, they can be used in suppression comments.
+pub const DUPLICATE_ATTRIBUTE: &str = "duplicate-attribute";
+pub const DUPLICATE_TAG: &str = "duplicate-tag";
+pub const EXTENSION_VALUE: &str = "extension-value";
+pub const JS_SYNTAX: &str = "js-syntax";
+pub const OOB_TARGET: &str = "oob-target";
+pub const SELECT_TARGET: &str = "select-target";
+pub const SWAP_CONFLICT: &str = "swap-conflict";
+pub const SWAP_MODIFIER: &str = "swap-modifier";
+pub const UNKNOWN_CLASS: &str = "unknown-class";
+pub const UNKNOWN_TAG: &str = "unknown-tag";
+pub const UNRESOLVED_SELECTOR: &str = "unresolved-selector";
+pub const UNUSED_TAG: &str = "unused-tag";
+pub const URL_INTERPOLATION: &str = "url-interpolation";
+
+/// Every diagnostic code with short description, ids are stable and used as
+/// SARIF rule ids.
+pub const RULES: [(&str, &str); 13] = [
+    (DUPLICATE_TAG, "Tag is defined more than once."),
+    (
+        DUPLICATE_ATTRIBUTE,
+        "Attribute is repeated on same element.",
+    ),
+    (UNUSED_TAG, "Tag is never used in templates."),
+    (UNKNOWN_TAG, "Tag from hx-lsp attribute is never defined."),
+    (
+        OOB_TARGET,
+        "Out of band swap target doesn't exist in other templates.",
+    ),
+    (
+        SELECT_TARGET,
+        "Id selected by hx-select or hx-select-oob doesn't exist in any template.",
+    ),
+    (
+        UNRESOLVED_SELECTOR,
+        "Extended selector doesn't match any element.",
+    ),
+    (
+        JS_SYNTAX,
+        "Invalid JavaScript expression in attribute value.",
+    ),
+    (
+        SWAP_MODIFIER,
+        "Unknown hx-swap modifier or invalid modifier value.",
+    ),
+    (
+        SWAP_CONFLICT,
+        "Swap attributes that don't work together on same element.",
+    ),
+    (
+        URL_INTERPOLATION,
+        "Malformed template expression in request URL.",
+    ),
+    (EXTENSION_VALUE, "Invalid value of extension attribute."),
+    (
+        UNKNOWN_CLASS,
+        "Indicator class is not defined in any stylesheet.",
+    ),
+];
+
+const DISABLE: &str = "htmx-lsp-disable";
+const DISABLE_NEXT_LINE: &str = "htmx-lsp-disable-next-line";
+const ENABLE: &str = "htmx-lsp-enable";
+
+/// Lines where diagnostics are silenced by comments:
+///
+/// ```html
+/// <!-- htmx-lsp-disable oob-target -->
+/// ...
+/// <!-- htmx-lsp-enable -->
+/// ```
+///
+/// ```rust
+/// // htmx-lsp-disable-next-line duplicate-tag
+/// // hx@tag1
+/// ```
+///
+/// Comment without codes silences every diagnostic.
+#[derive(Debug, Default, PartialEq)]
+pub struct Suppressions {
+    /// Line range (end is exclusive) and codes.
+    regions: Vec<(usize, usize, Vec<String>)>,
+}
+
+impl Suppressions {
+    /// Scan comments in document.
+    pub fn new(text: &str) -> Self {
+        let mut regions = vec![];
+        let mut open: Option<(usize, Vec<String>)> = None;
+        let mut last = 0;
+        for (line, content) in text.lines().enumerate() {
+            last = line + 1;
+            if let Some(codes) = directive(content, DISABLE_NEXT_LINE) {
+                regions.push((line + 1, line + 2, codes));
+            } else if let Some(codes) = directive(content, DISABLE) {
+                if let Some((start, codes)) = open.take() {
+                    regions.push((start, line, codes));
+                }
+                open = Some((line, codes));
+            } else if directive(content, ENABLE).is_some() {
+                if let Some((start, codes)) = open.take() {
+                    regions.push((start, line, codes));
+                }
+            }
+        }
+        if let Some((start, codes)) = open {
+            regions.push((start, last, codes));
+        }
+        Self { regions }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty()
+    }
+
+    /// Check if diagnostic is silenced.
+    pub fn is_suppressed(&self, diagnostic: &Diagnostic) -> bool {
+        let line = diagnostic.range.start.line as usize;
+        let code = match &diagnostic.code {
+            Some(NumberOrString::String(code)) => Some(code.as_str()),
+            _ => None,
+        };
+        self.regions.iter().any(|(start, end, codes)| {
+            (*start..*end).contains(&line)
+                && (codes.is_empty() || code.is_some_and(|code| codes.iter().any(|c| c == code)))
+        })
+    }
+
+    /// Remove silenced diagnostics.
+    pub fn filter(&self, diagnostics: &mut Vec<Diagnostic>) {
+        if !self.is_empty() {
+            diagnostics.retain(|diagnostic| !self.is_suppressed(diagnostic));
+        }
+    }
+}
+
+/// Codes after directive, `None` if line has no directive.
+fn directive(line: &str, name: &str) -> Option<Vec<String>> {
+    let start = line.find(name)? + name.len();
+    let rest = &line[start..];
+    // `htmx-lsp-disable` is also prefix of `htmx-lsp-disable-next-line`.
+    if rest.starts_with('-') {
+        return None;
+    }
+    let codes = rest
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .take_while(|code| !code.starts_with("-->") && !code.starts_with("#}"))
+        .filter(|code| !code.is_empty())
+        .map(String::from)
+        .collect();
+    Some(codes)
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp::lsp_types::{Diagnostic, NumberOrString, Position, Range};
+
+    use super::{Suppressions, DUPLICATE_TAG, OOB_TARGET};
+
+    fn diagnostic(line: u32, code: &str) -> Diagnostic {
+        Diagnostic {
+            range: Range::new(Position::new(line, 0), Position::new(line, 1)),
+            code: Some(NumberOrString::String(String::from(code))),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn next_line_and_regions() {
+        let text = r#"// htmx-lsp-disable-next-line duplicate-tag
+// hx@tag1
+// hx@tag1
+<!-- htmx-lsp-disable -->
+<div></div>
+<!-- htmx-lsp-enable -->
+<div></div>"#;
+        let suppressions = Suppressions::new(text);
+        assert!(suppressions.is_suppressed(&diagnostic(1, DUPLICATE_TAG)));
+        assert!(!suppressions.is_suppressed(&diagnostic(1, OOB_TARGET)));
+        assert!(!suppressions.is_suppressed(&diagnostic(2, DUPLICATE_TAG)));
+        assert!(suppressions.is_suppressed(&diagnostic(4, OOB_TARGET)));
+        assert!(!suppressions.is_suppressed(&diagnostic(6, OOB_TARGET)));
+    }
+
+    #[test]
+    fn region_until_end_of_file() {
+        let text = "<!-- htmx-lsp-disable oob-target, duplicate-tag -->\n<p></p>\n<p></p>";
+        let mut diagnostics = vec![
+            diagnostic(2, OOB_TARGET),
+            diagnostic(2, DUPLICATE_TAG),
+            diagnostic(2, "other"),
+        ];
+        Suppressions::new(text).filter(&mut diagnostics);
+        assert_eq!(diagnostics, vec![diagnostic(2, "other")]);
+    }
+}
@@ -0,0 +1,563 @@
+use tree_sitter::{Node, Point};
+
+use crate::{
+    embedded::advance,
+    init_hx::HxCompletion,
+    position::{start_tags, tag_attributes},
+};
+
+/// Swap styles, first word of `hx-swap` value.
+const STYLES: [(&str, &str); 8] = [
+    ("innerHTML", include_str!("./md/hx-swap/innerHTML.md")),
+    ("outerHTML", include_str!("./md/hx-swap/outerHTML.md")),
+    ("afterbegin", include_str!("./md/hx-swap/afterbegin.md")),
+    ("afterend", include_str!("./md/hx-swap/afterend.md")),
+    ("beforebegin", include_str!("./md/hx-swap/beforebegin.md")),
+    ("beforeend", include_str!("./md/hx-swap/beforeend.md")),
+    ("delete", include_str!("./md/hx-swap/delete.md")),
+    ("none", include_str!("./md/hx-swap/none.md")),
+];
+
+/// Value accepted after `modifier:`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ModifierValue {
+    /// `200ms`, `1s`, `1m` or milliseconds without unit.
+    Timing,
+    Bool,
+    /// `top` or `bottom`, optionally after selector (`#list:bottom`).
+    Scroll,
+}
+
+pub struct Modifier {
+    pub name: &'static str,
+    pub value: ModifierValue,
+    pub desc: &'static str,
+}
+
+/// Modifiers of `hx-swap`, shared by hover, completion and validation.
+pub const MODIFIERS: [Modifier; 7] = [
+    Modifier {
+        name: "transition",
+        value: ModifierValue::Bool,
+        desc: include_str!("./md/hx-swap/transition.md"),
+    },
+    Modifier {
+        name: "swap",
+        value: ModifierValue::Timing,
+        desc: include_str!("./md/hx-swap/swap.md"),
+    },
+    Modifier {
+        name: "settle",
+        value: ModifierValue::Timing,
+        desc: include_str!("./md/hx-swap/settle.md"),
+    },
+    Modifier {
+        name: "ignoreTitle",
+        value: ModifierValue::Bool,
+        desc: include_str!("./md/hx-swap/ignoreTitle.md"),
+    },
+    Modifier {
+        name: "scroll",
+        value: ModifierValue::Scroll,
+        desc: include_str!("./md/hx-swap/scroll.md"),
+    },
+    Modifier {
+        name: "show",
+        value: ModifierValue::Scroll,
+        desc: include_str!("./md/hx-swap/show.md"),
+    },
+    Modifier {
+        name: "focus-scroll",
+        value: ModifierValue::Bool,
+        desc: include_str!("./md/hx-swap/focus-scroll.md"),
+    },
+];
+
+pub fn modifier(name: &str) -> Option<&'static Modifier> {
+    MODIFIERS.iter().find(|modifier| modifier.name == name)
+}
+
+/// Timing in milliseconds, same units as htmx accepts.
+pub fn parse_timing(timing: &str) -> Option<f64> {
+    let (number, scale) = if let Some(number) = timing.strip_suffix("ms") {
+        (number, 1.0)
+    } else if let Some(number) = timing.strip_suffix('s') {
+        (number, 1000.0)
+    } else if let Some(number) = timing.strip_suffix('m') {
+        (number, 60_000.0)
+    } else {
+        (timing, 1.0)
+    };
+    if number.is_empty() || !number.chars().all(|c| c.is_ascii_digit() || c == '.') {
+        return None;
+    }
+    let number: f64 = number.parse().ok()?;
+    Some(number * scale)
+}
+
+/// Error message for invalid modifier value.
+fn check_value(modifier: &Modifier, value: &str) -> Option<String> {
+    match modifier.value {
+        ModifierValue::Timing => parse_timing(value).is_none().then(|| {
+            format!(
+                "Invalid timing `{value}` for {}, expected number with ms, s or m unit (200ms, 1s).",
+                modifier.name
+            )
+        }),
+        ModifierValue::Bool => (!matches!(value, "true" | "false"))
+            .then(|| format!("{} expects true or false.", modifier.name)),
+        ModifierValue::Scroll => {
+            let position = value.rsplit(':').next().unwrap_or_default();
+            let valid = matches!(position, "top" | "bottom")
+                || (modifier.name == "show" && value == "none");
+            (!valid).then(|| format!("{} expects top or bottom position.", modifier.name))
+        }
+    }
+}
+
+/// Words of value with their byte offsets.
+fn words(value: &str) -> impl Iterator<Item = (usize, &str)> {
+    value
+        .split_ascii_whitespace()
+        .map(move |word| (word.as_ptr() as usize - value.as_ptr() as usize, word))
+}
+
+/// Invalid modifier in `hx-swap` value, byte range in value.
+#[derive(Debug, PartialEq)]
+pub struct SwapError {
+    pub start: usize,
+    pub end: usize,
+    pub message: String,
+}
+
+/// Validate modifiers, style is not checked because extensions can add new ones.
+pub fn swap_errors(value: &str) -> Vec<SwapError> {
+    let mut errors = vec![];
+    for (offset, word) in words(value) {
+        let Some((name, modifier_value)) = word.split_once(':') else {
+            continue;
+        };
+        let error = match modifier(name) {
+            Some(modifier) => check_value(modifier, modifier_value).map(|message| SwapError {
+                start: offset + name.len() + 1,
+                end: offset + word.len(),
+                message,
+            }),
+            None => Some(SwapError {
+                start: offset,
+                end: offset + name.len(),
+                message: format!("Unknown swap modifier `{name}`."),
+            }),
+        };
+        errors.extend(error);
+    }
+    errors
+}
+
+/// Documentation for style or modifier under cursor (`column` is byte offset in `value`).
+pub fn swap_hover(value: &str, column: usize) -> Option<&'static str> {
+    let (_, word) =
+        words(value).find(|(offset, word)| (*offset..=offset + word.len()).contains(&column))?;
+    match word.split_once(':') {
+        Some((name, _)) => modifier(name).map(|modifier| modifier.desc),
+        None => STYLES
+            .iter()
+            .find(|(style, _)| *style == word)
+            .map(|(_, desc)| *desc),
+    }
+}
+
+/// Completion for part of value before cursor: styles for first word,
+/// modifiers later, values after `modifier:`.
+pub fn hx_swap_values(prefix: &str) -> Vec<HxCompletion> {
+    let word = prefix
+        .rsplit(|c: char| c.is_ascii_whitespace())
+        .next()
+        .unwrap_or_default();
+    let first = prefix.trim_start().len() == word.len();
+    let detailed = |name: &str, desc: &str, detail: &str| HxCompletion {
+        name: name.to_string(),
+        desc: desc.to_string(),
+        detail: Some(detail.to_string()),
+        deprecated: false,
+    };
+    if let Some((name, _)) = word.split_once(':') {
+        let values: &[&str] = match modifier(name).map(|modifier| modifier.value) {
+            Some(ModifierValue::Timing) => &["100ms", "200ms", "1s"],
+            Some(ModifierValue::Bool) => &["true", "false"],
+            Some(ModifierValue::Scroll) => &["top", "bottom"],
+            None => &[],
+        };
+        return values
+            .iter()
+            .map(|value| detailed(value, "", name))
+            .collect();
+    }
+    let modifiers = MODIFIERS.iter().map(|modifier| {
+        detailed(
+            &format!("{}:", modifier.name),
+            modifier.desc,
+            "swap modifier",
+        )
+    });
+    match first {
+        true => STYLES
+            .iter()
+            .map(|(name, desc)| detailed(name, desc, "swap style"))
+            .collect(),
+        false => modifiers.collect(),
+    }
+}
+
+/// Swap styles after `:` in last selector of `hx-select-oob` value, selectors
+/// are completed from ids and classes.
+pub fn hx_select_oob_values(prefix: &str) -> Vec<HxCompletion> {
+    let last = prefix.rsplit(',').next().unwrap_or_default();
+    if !last.contains(':') {
+        return vec![];
+    }
+    STYLES
+        .iter()
+        .map(|(name, desc)| HxCompletion {
+            name: name.to_string(),
+            desc: desc.to_string(),
+            detail: Some(String::from("swap style")),
+            deprecated: false,
+        })
+        .collect()
+}
+
+pub struct SwapDiagnostic {
+    pub range: (Point, Point),
+    pub message: String,
+}
+
+/// Invalid `hx-swap` modifiers in start tags accepted by `keep`.
+pub fn swap_value_errors(
+    root: Node<'_>,
+    source: &str,
+    keep: impl Fn(Point, Point) -> bool,
+) -> Vec<SwapDiagnostic> {
+    let mut diagnostics = vec![];
+    for tag in start_tags(root) {
+        if !keep(tag.start_position(), tag.end_position()) {
+            continue;
+        }
+        for (name, value) in tag_attributes(tag, source) {
+            if name != "hx-swap" && name != "data-hx-swap" {
+                continue;
+            }
+            let Some(value) = value else {
+                continue;
+            };
+            let Ok(text) = value.utf8_text(source.as_bytes()) else {
+                continue;
+            };
+            let value_start = value.start_position();
+            for error in swap_errors(text) {
+                diagnostics.push(SwapDiagnostic {
+                    range: (
+                        advance(value_start, &text[..error.start]),
+                        advance(value_start, &text[..error.end]),
+                    ),
+                    message: error.message,
+                });
+            }
+        }
+    }
+    diagnostics
+}
+
+/// Elements without content, swapped content can't be put inside of them.
+const VOID_ELEMENTS: [&str; 13] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// Start tag with its attributes, `data-` prefix of htmx attributes is removed.
+pub struct SwapElement {
+    pub name: String,
+    attributes: Vec<(String, Option<String>, (Point, Point))>,
+    /// `hx-swap` value of closest ancestor that passes it down.
+    inherited_swap: Option<String>,
+}
+
+impl SwapElement {
+    fn new(start_tag: Node<'_>, source: &str) -> Self {
+        let name = start_tag
+            .child(1)
+            .filter(|node| node.kind() == "tag_name")
+            .and_then(|node| node.utf8_text(source.as_bytes()).ok())
+            .unwrap_or_default()
+            .to_lowercase();
+        Self {
+            name,
+            attributes: start_tag_attributes(start_tag, source),
+            inherited_swap: inherited_swap(start_tag, source),
+        }
+    }
+
+    fn attribute(&self, name: &str) -> Option<&(String, Option<String>, (Point, Point))> {
+        find_attribute(&self.attributes, name)
+    }
+
+    /// Value of attribute, empty for attribute without value.
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.attribute(name)
+            .map(|(_, value, _)| value.as_deref().unwrap_or_default())
+    }
+
+    /// Swap style from own or inherited `hx-swap`, `innerHTML` is default.
+    pub fn swap_style(&self) -> &str {
+        self.value("hx-swap")
+            .or(self.inherited_swap.as_deref())
+            .and_then(|value| value.split_whitespace().next())
+            .unwrap_or("innerHTML")
+    }
+}
+
+fn start_tag_attributes(
+    start_tag: Node<'_>,
+    source: &str,
+) -> Vec<(String, Option<String>, (Point, Point))> {
+    let mut cursor = start_tag.walk();
+    start_tag
+        .children(&mut cursor)
+        .filter(|node| node.kind() == "attribute")
+        .filter_map(|attribute| {
+            let name = attribute.child(0)?.utf8_text(source.as_bytes()).ok()?;
+            let name = name.to_lowercase();
+            let name = match name.strip_prefix("data-") {
+                Some(rest) if rest.starts_with("hx-") => rest.to_string(),
+                _ => name,
+            };
+            let value = tag_attributes_value(attribute, source);
+            let range = (attribute.start_position(), attribute.end_position());
+            Some((name, value, range))
+        })
+        .collect()
+}
+
+fn find_attribute<'a>(
+    attributes: &'a [(String, Option<String>, (Point, Point))],
+    name: &str,
+) -> Option<&'a (String, Option<String>, (Point, Point))> {
+    attributes
+        .iter()
+        .find(|(attribute, _, _)| attribute == name)
+}
+
+/// `hx-swap` of closest ancestor. Ancestor with `hx-disinherit="*"` (or with
+/// `hx-swap` in it) stops inheritance, same as in htmx.
+fn inherited_swap(start_tag: Node<'_>, source: &str) -> Option<String> {
+    let mut element = start_tag.parent()?;
+    while let Some(parent) = element.parent() {
+        element = parent;
+        let Some(tag) = element
+            .child(0)
+            .filter(|_| element.kind() == "element")
+            .filter(|tag| tag.kind() == "start_tag")
+        else {
+            continue;
+        };
+        let attributes = start_tag_attributes(tag, source);
+        let disinherit = find_attribute(&attributes, "hx-disinherit")
+            .and_then(|(_, value, _)| value.as_deref())
+            .is_some_and(|value| {
+                value
+                    .split_whitespace()
+                    .any(|name| name == "*" || name == "hx-swap")
+            });
+        if disinherit {
+            return None;
+        }
+        if let Some((_, value, _)) = find_attribute(&attributes, "hx-swap") {
+            return Some(value.clone().unwrap_or_default());
+        }
+    }
+    None
+}
+
+fn tag_attributes_value(attribute: Node<'_>, source: &str) -> Option<String> {
+    let value = attribute.child(2)?;
+    let value = value.child(1).or(Some(value))?;
+    (value.kind() == "attribute_value")
+        .then(|| value.utf8_text(source.as_bytes()).ok().map(str::to_string))
+        .flatten()
+}
+
+/// Combination of attributes that doesn't work together.
+pub struct ConflictRule {
+    /// Diagnostic is shown on this attribute.
+    pub attribute: &'static str,
+    /// Documentation of attribute, it's linked from diagnostic.
+    pub docs: &'static str,
+    /// Message when element has conflict.
+    pub check: fn(&SwapElement) -> Option<String>,
+}
+
+/// Swap and target combinations that are checked in every start tag.
+pub const CONFLICT_RULES: [ConflictRule; 3] = [
+    ConflictRule {
+        attribute: "hx-select",
+        docs: "https://htmx.org/attributes/hx-swap/",
+        check: |element| {
+            (element.swap_style() == "delete").then(|| {
+                String::from("hx-select has no effect with hx-swap=\"delete\", target is removed and response is ignored.")
+            })
+        },
+    },
+    ConflictRule {
+        attribute: "hx-swap-oob",
+        docs: "https://htmx.org/attributes/hx-swap-oob/",
+        check: |element| {
+            let value = element.value("hx-swap-oob")?;
+            // Selector after swap style (`beforeend:#alerts`) selects target.
+            let without_target = !value.contains(':') && element.value("id").is_none();
+            without_target.then(|| {
+                String::from("hx-swap-oob element needs id, or selector after swap style (beforeend:#alerts).")
+            })
+        },
+    },
+    ConflictRule {
+        attribute: "hx-target",
+        docs: "https://htmx.org/attributes/hx-target/",
+        check: |element| {
+            let style = element.swap_style();
+            let inside = matches!(style, "innerHTML" | "afterbegin" | "beforeend");
+            let void = VOID_ELEMENTS.contains(&element.name.as_str());
+            (element.value("hx-target")? == "this" && void && inside).then(|| {
+                format!(
+                    "<{}> can't have content, hx-target=\"this\" with {style} swap does nothing. Use outerHTML, beforebegin or afterend.",
+                    element.name
+                )
+            })
+        },
+    },
+];
+
+pub struct SwapConflict {
+    pub range: (Point, Point),
+    pub message: String,
+    pub docs: &'static str,
+}
+
+/// Conflicting attributes (`CONFLICT_RULES`) in start tags accepted by `keep`.
+pub fn swap_conflicts(
+    root: Node<'_>,
+    source: &str,
+    keep: impl Fn(Point, Point) -> bool,
+) -> Vec<SwapConflict> {
+    let mut conflicts = vec![];
+    for tag in start_tags(root) {
+        if !keep(tag.start_position(), tag.end_position()) {
+            continue;
+        }
+        let element = SwapElement::new(tag, source);
+        for rule in &CONFLICT_RULES {
+            let Some((_, _, range)) = element.attribute(rule.attribute) else {
+                continue;
+            };
+            if let Some(message) = (rule.check)(&element) {
+                conflicts.push(SwapConflict {
+                    range: *range,
+                    message,
+                    docs: rule.docs,
+                });
+            }
+        }
+    }
+    conflicts
+}
+
+#[cfg(test)]
+mod tests {
+    use tree_sitter::Parser;
+
+    use super::{
+        hx_select_oob_values, hx_swap_values, parse_timing, swap_conflicts, swap_errors, swap_hover,
+    };
+
+    #[test]
+    fn timings() {
+        assert_eq!(parse_timing("200ms"), Some(200.0));
+        assert_eq!(parse_timing("1.5s"), Some(1500.0));
+        assert_eq!(parse_timing("1m"), Some(60_000.0));
+        assert_eq!(parse_timing("100"), Some(100.0));
+        assert_eq!(parse_timing("200sec"), None);
+        assert_eq!(parse_timing("ms"), None);
+        assert_eq!(parse_timing("-1s"), None);
+    }
+
+    #[test]
+    fn invalid_modifiers() {
+        assert!(
+            swap_errors("outerHTML swap:200ms settle:1s scroll:bottom show:#list:top").is_empty()
+        );
+        assert!(swap_errors("innerHTML show:none transition:true focus-scroll:false").is_empty());
+        let errors = swap_errors("innerHTML swap:200sec scroll:middle fade:1s");
+        let ranges: Vec<(usize, usize)> = errors
+            .iter()
+            .map(|error| (error.start, error.end))
+            .collect();
+        assert_eq!(ranges, vec![(15, 21), (29, 35), (36, 40)]);
+        assert!(errors[2].message.contains("fade"));
+    }
+
+    #[test]
+    fn hover_and_completion() {
+        let value = "outerHTML swap:200ms";
+        assert!(swap_hover(value, 3)
+            .unwrap()
+            .starts_with("replaces the entire"));
+        assert!(swap_hover(value, 12).unwrap().starts_with("swap:<timing"));
+        assert_eq!(swap_hover("fade:1s", 2), None);
+
+        let names = |prefix| -> Vec<String> {
+            hx_swap_values(prefix)
+                .into_iter()
+                .map(|value| value.name)
+                .collect()
+        };
+        assert!(names("").contains(&String::from("innerHTML")));
+        assert!(names("innerHTML ").contains(&String::from("settle:")));
+        assert_eq!(names("innerHTML scroll:"), vec!["top", "bottom"]);
+        assert!(hx_select_oob_values("#alert").is_empty());
+        assert_eq!(hx_select_oob_values("#a, #alert:").len(), 8);
+    }
+
+    #[test]
+    fn conflicting_attributes() {
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_html::language()).unwrap();
+        let mut messages = |text: &str| -> Vec<String> {
+            let tree = parser.parse(text, None).unwrap();
+            swap_conflicts(tree.root_node(), text, |_, _| true)
+                .into_iter()
+                .map(|conflict| conflict.message)
+                .collect()
+        };
+        assert!(
+            messages(r##"<tr data-hx-swap="delete swap:1s" hx-select="#row">"##)[0]
+                .starts_with("hx-select has no effect")
+        );
+        assert!(messages(r##"<tr hx-swap="outerHTML" hx-select="#row">"##).is_empty());
+        assert!(messages(r#"<div hx-swap-oob="true">"#)[0].contains("needs id"));
+        assert!(messages(r#"<div id="alerts" hx-swap-oob="true">"#).is_empty());
+        assert!(messages(r##"<div hx-swap-oob="beforeend:#alerts">"##).is_empty());
+        assert!(messages(r#"<input hx-target="this" hx-get="/a">"#)[0].starts_with("<input>"));
+        assert!(messages(r#"<img hx-target="this" hx-swap="outerHTML">"#).is_empty());
+        assert!(messages(r#"<div hx-target="this">"#).is_empty());
+
+        // Inherited hx-swap is used when element has none.
+        let inherited = r#"<div hx-swap="outerHTML"><p><input hx-target="this"></p></div>"#;
+        assert!(messages(inherited).is_empty());
+        let own = r#"<div hx-swap="outerHTML"><input hx-target="this" hx-swap="beforeend"></div>"#;
+        assert!(messages(own)[0].starts_with("<input>"));
+        let disinherited =
+            r#"<div hx-swap="outerHTML" hx-disinherit="hx-swap"><input hx-target="this"></div>"#;
+        assert!(messages(disinherited)[0].starts_with("<input>"));
+        let deleted = r##"<ul hx-swap="delete"><li hx-select="#row"></li></ul>"##;
+        assert!(messages(deleted)[0].starts_with("hx-select has no effect"));
+    }
+}
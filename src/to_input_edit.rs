@@ -2,7 +2,27 @@ use ropey::Rope;
 use tower_lsp::lsp_types::{Position, Range};
 use tree_sitter::{InputEdit, Point};
 
-use crate::htmx_tags::Tag;
+use crate::{embedded::advance, htmx_tags::Tag};
+
+/// Text without UTF-8 byte order mark. Editors don't count BOM in positions,
+/// so it is stripped from every document before it is parsed.
+pub fn strip_bom(text: &str) -> &str {
+    text.strip_prefix('\u{feff}').unwrap_or(text)
+}
+
+/// Length of line in bytes, without `\n` or `\r\n`.
+pub fn line_len(rope: &Rope, line: usize) -> usize {
+    let Some(line) = rope.get_line(line) else {
+        return 0;
+    };
+    let mut len = line.len_bytes();
+    for end in ['\n', '\r'] {
+        if len > 0 && line.byte(len - 1) == end as u8 {
+            len -= 1;
+        }
+    }
+    len
+}
 
 /// Convert Tag to Positon range for lsp_types.
 pub fn to_position(tag: &Tag) -> (Position, Position) {
@@ -24,9 +44,15 @@ impl ToInputEdit for Rope {
         Point::new(position.line as usize, position.character as usize)
     }
 
+    /// Character after end of line is clamped to line length, line break is
+    /// never split.
     fn to_byte(&self, position: Position) -> usize {
-        let start_line = self.line_to_byte(position.line as usize);
-        start_line + position.character as usize
+        let line = position.line as usize;
+        if line >= self.len_lines() {
+            return self.len_bytes();
+        }
+        let character = (position.character as usize).min(line_len(self, line));
+        self.line_to_byte(line) + character
     }
 
     fn to_position(&self, mut offset: usize) -> Position {
@@ -57,14 +83,13 @@ impl ToInputEdit for Rope {
         let end = range.end;
 
         let start_byte = self.to_byte(start);
-        let start_position = self.to_point(start);
+        let start_position = self.to_point(self.to_position(start_byte));
 
         let new_end_byte = start_byte + text.len();
-        let new_end_position = self.to_position(new_end_byte);
-        let new_end_position = self.to_point(new_end_position);
+        let new_end_position = advance(start_position, text);
 
         let old_end_byte = self.to_byte(end);
-        let old_end_position = self.to_point(end);
+        let old_end_position = self.to_point(self.to_position(old_end_byte));
 
         InputEdit {
             start_byte,
@@ -80,3 +105,49 @@ impl ToInputEdit for Rope {
 pub fn to_position2(point: Point) -> Position {
     Position::new(point.row as u32, point.column as u32)
 }
+
+#[cfg(test)]
+mod tests {
+    use ropey::Rope;
+    use tower_lsp::lsp_types::{Position, Range};
+    use tree_sitter::{Parser, Point};
+
+    use super::{line_len, strip_bom, ToInputEdit};
+
+    #[test]
+    fn crlf_positions_match_tree_sitter() {
+        let text = "<div>\r\n  <p hx-get=\"/a\"></p>\u{2028}\r\n</div>\r\n";
+        let rope = Rope::from_str(text);
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_html::language()).unwrap();
+        let tree = parser.parse(text, None).unwrap();
+        let value = tree
+            .root_node()
+            .descendant_for_point_range(Point::new(1, 13), Point::new(1, 13))
+            .unwrap();
+        assert_eq!(value.utf8_text(text.as_bytes()), Ok("/a"));
+        assert_eq!(rope.len_lines(), 4);
+        assert_eq!(rope.to_position(value.start_byte()), Position::new(1, 13));
+        assert_eq!(rope.to_byte(Position::new(1, 13)), value.start_byte());
+
+        assert_eq!(line_len(&rope, 0), 5);
+        assert_eq!(rope.to_byte(Position::new(0, 40)), 5);
+        assert_eq!(rope.to_byte(Position::new(9, 0)), text.len());
+    }
+
+    #[test]
+    fn crlf_input_edit() {
+        let rope = Rope::from_str("<div>\r\n</div>\r\n");
+        let range = Range::new(Position::new(0, 5), Position::new(0, 5));
+        let edit = rope.to_input_edit(range, "\r\n  <p></p>");
+        assert_eq!(edit.start_byte, 5);
+        assert_eq!(edit.new_end_byte, 5 + 11);
+        assert_eq!(edit.new_end_position, Point::new(1, 9));
+    }
+
+    #[test]
+    fn bom_is_stripped() {
+        assert_eq!(strip_bom("\u{feff}<div></div>"), "<div></div>");
+        assert_eq!(strip_bom("<div></div>"), "<div></div>");
+    }
+}
@@ -0,0 +1,302 @@
+use std::path::{Path, PathBuf};
+
+use tower_lsp::lsp_types::Url;
+
+/// Path in one form on every platform: `/` separators, no `\\?\` prefix,
+/// no `.` components and lowercase drive letter.
+/// ```text
+/// .\templates\\partials  ->  templates/partials
+/// \\?\C:\app\index.html ->  c:/app/index.html
+/// \\?\UNC\server\share  ->  //server/share
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct NormalizedPath(String);
+
+impl NormalizedPath {
+    /// Path written by user (config), `\` is separator on every platform.
+    pub fn new(path: &str) -> Self {
+        Self::normalize(path, true)
+    }
+
+    /// Path from file system, `\` is separator only on Windows.
+    pub fn from_path(path: &Path) -> Self {
+        Self::normalize(&path.to_string_lossy(), cfg!(windows))
+    }
+
+    fn normalize(path: &str, backslash: bool) -> Self {
+        let path = match backslash {
+            true => path.replace('\\', "/"),
+            false => path.to_string(),
+        };
+        let (prefix, rest) = if let Some(rest) = path.strip_prefix("//?/UNC/") {
+            ("//", rest)
+        } else if let Some(rest) = path.strip_prefix("//?/") {
+            ("", rest)
+        } else if let Some(rest) = path.strip_prefix("//") {
+            ("//", rest)
+        } else if let Some(rest) = path.strip_prefix('/') {
+            ("/", rest)
+        } else {
+            ("", path.as_str())
+        };
+        let mut normalized = String::from(prefix);
+        let components = rest
+            .split('/')
+            .filter(|component| !component.is_empty() && *component != ".");
+        for (i, component) in components.enumerate() {
+            if i > 0 {
+                normalized.push('/');
+            }
+            normalized.push_str(component);
+        }
+        if prefix.is_empty() && is_drive(&normalized) {
+            normalized[..1].make_ascii_lowercase();
+            if normalized.len() == 2 {
+                normalized.push('/');
+            }
+        }
+        if normalized.is_empty() {
+            normalized.push('.');
+        }
+        Self(normalized)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Starts with `/`, drive letter or is UNC path.
+    pub fn is_absolute(&self) -> bool {
+        self.0.starts_with('/') || is_drive(&self.0)
+    }
+
+    /// Relative path is appended, absolute path replaces this one.
+    pub fn join(&self, path: &NormalizedPath) -> Self {
+        match path.is_absolute() || self.0 == "." {
+            true => path.clone(),
+            false => Self::new(&format!("{}/{}", self.0, path.0)),
+        }
+    }
+
+    pub fn to_path_buf(&self) -> PathBuf {
+        PathBuf::from(&self.0)
+    }
+
+    /// `file://` URI for absolute path, same as `normalize_uri` returns for it.
+    pub fn to_uri(&self) -> Option<String> {
+        if !self.is_absolute() {
+            return None;
+        }
+        let path = self
+            .0
+            .replace('%', "%25")
+            .replace('#', "%23")
+            .replace('?', "%3F");
+        let uri = match path.strip_prefix("//") {
+            Some(unc) => format!("file://{unc}"),
+            None => format!("file:///{}", path.trim_start_matches('/')),
+        };
+        Url::parse(&uri).ok().map(|url| url.to_string())
+    }
+}
+
+/// `c:` or `c:/...`
+fn is_drive(path: &str) -> bool {
+    let mut chars = path.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.next() == Some(':')
+        && matches!(chars.next(), None | Some('/'))
+}
+
+/// Canonical path of directory or file from config, without `\\?\` prefix
+/// that Windows adds.
+pub fn canonicalize(path: &str) -> Option<PathBuf> {
+    let path = std::fs::canonicalize(NormalizedPath::new(path).to_path_buf()).ok()?;
+    match cfg!(windows) {
+        true => Some(NormalizedPath::from_path(&path).to_path_buf()),
+        false => Some(path),
+    }
+}
+
+/// Convert file path to URI. Works with Windows paths too (drive letters,
+/// backslashes, `\\?\` prefix).
+pub fn path_to_uri(path: &Path) -> Option<String> {
+    NormalizedPath::from_path(path).to_uri()
+}
+
+/// Documents with other schemes (`untitled:`, `git:`) are not in workspace, only
+/// completion and hover work for them.
+pub fn is_file_uri(uri: &Url) -> bool {
+    uri.scheme() == "file"
+}
+
+/// File URI that points to local file. Remote mounts and paths from container
+/// can't be canonicalized, such documents are read-only for server.
+pub fn is_local_file(uri: &Url) -> bool {
+    if !is_file_uri(uri) {
+        return false;
+    }
+    let Ok(path) = uri.to_file_path() else {
+        return false;
+    };
+    // New file isn't saved yet, but its directory exists.
+    std::fs::canonicalize(&path).is_ok()
+        || path
+            .parent()
+            .is_some_and(|dir| std::fs::canonicalize(dir).is_ok())
+}
+
+/// Editors can send the same file in different forms, for example VSCode
+/// percent-encodes drive letter colon (`file:///c%3A/index.html`).
+/// Every URI used as key for documents and file indexes goes through this function.
+pub fn normalize_uri(uri: &Url) -> String {
+    if uri.scheme() != "file" {
+        return uri.to_string();
+    }
+    let url = uri
+        .to_file_path()
+        .ok()
+        .and_then(|path| Url::from_file_path(path).ok())
+        .unwrap_or_else(|| uri.clone());
+    let mut uri = url.to_string();
+    let prefix = "file:///".len();
+    let drive = uri.get(prefix..prefix + 2).is_some_and(|drive| {
+        let mut chars = drive.chars();
+        chars.next().is_some_and(|c| c.is_ascii_alphabetic()) && chars.next() == Some(':')
+    });
+    if drive {
+        uri[prefix..prefix + 1].make_ascii_lowercase();
+    }
+    uri
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp::lsp_types::Url;
+
+    use super::{is_file_uri, is_local_file, normalize_uri, NormalizedPath};
+    use crate::test_dir::TestDir;
+
+    #[test]
+    fn same_uri_for_encoded_paths() {
+        let cases = [
+            (
+                "file:///home/user/templates/index.html",
+                "file:///home/user/templates/index.html",
+            ),
+            (
+                "file:///home/user/my%20app/index.html",
+                "file:///home/user/my%20app/index.html",
+            ),
+            (
+                "file:///c%3A/Users/app/index.html",
+                "file:///c:/Users/app/index.html",
+            ),
+            (
+                "file:///C:/Users/app/index.html",
+                "file:///c:/Users/app/index.html",
+            ),
+            (
+                "file:///C%3A/Users/app/index.html",
+                "file:///c:/Users/app/index.html",
+            ),
+        ];
+        for (uri, expected) in cases {
+            let uri = Url::parse(uri).unwrap();
+            assert_eq!(normalize_uri(&uri), expected);
+        }
+    }
+
+    #[test]
+    fn other_schemes_are_unchanged() {
+        let uri = Url::parse("untitled:Untitled-1").unwrap();
+        assert_eq!(normalize_uri(&uri), "untitled:Untitled-1");
+        assert!(!is_file_uri(&uri));
+        assert!(is_file_uri(&Url::parse("file:///index.html").unwrap()));
+    }
+
+    #[test]
+    fn local_files() {
+        let dir = TestDir::new();
+        let missing = dir.join("missing/index.html");
+        assert!(!is_local_file(&Url::from_file_path(missing).unwrap()));
+        let file = dir.join("index.html");
+        assert!(is_local_file(&Url::from_file_path(file).unwrap()));
+        assert!(!is_local_file(
+            &Url::parse("file://remote/templates/index.html").unwrap()
+        ));
+        assert!(!is_local_file(&Url::parse("untitled:Untitled-1").unwrap()));
+    }
+
+    #[test]
+    fn config_paths_are_normalized() {
+        let cases = [
+            ("./templates", "templates"),
+            (r".\templates\partials\", "templates/partials"),
+            (r"src\\views/./htmx", "src/views/htmx"),
+            ("../shared//templates", "../shared/templates"),
+            (".", "."),
+            (r"C:\Users\app", "c:/Users/app"),
+            (r"\\?\C:\Users\app", "c:/Users/app"),
+            (r"\\?\UNC\server\share\app", "//server/share/app"),
+            (r"\\server\share", "//server/share"),
+            ("/home/user/app/", "/home/user/app"),
+        ];
+        for (path, expected) in cases {
+            assert_eq!(NormalizedPath::new(path).as_str(), expected, "{path}");
+        }
+        let root = NormalizedPath::new(r"\\?\D:\projects\app");
+        assert_eq!(
+            root.join(&NormalizedPath::new(r".\templates")).as_str(),
+            "d:/projects/app/templates"
+        );
+        assert_eq!(
+            root.join(&NormalizedPath::new("/srv/templates")).as_str(),
+            "/srv/templates"
+        );
+    }
+
+    #[test]
+    fn uris_from_windows_paths() {
+        let cases = [
+            (
+                r"\\?\C:\Users\app\my app\index.html",
+                "file:///c:/Users/app/my%20app/index.html",
+            ),
+            (r"C:/Users/app/a#b.html", "file:///c:/Users/app/a%23b.html"),
+            (
+                r"\\?\UNC\server\share\index.html",
+                "file://server/share/index.html",
+            ),
+            ("/home/user/index.html", "file:///home/user/index.html"),
+        ];
+        for (path, expected) in cases {
+            let uri = NormalizedPath::new(path).to_uri();
+            assert_eq!(uri.as_deref(), Some(expected), "{path}");
+        }
+        assert_eq!(NormalizedPath::new(r".\templates").to_uri(), None);
+
+        // Same key as document URI sent by editor.
+        let editor = Url::parse("file:///C%3A/Users/app/index.html").unwrap();
+        assert_eq!(
+            NormalizedPath::new(r"\\?\C:\Users\app\index.html").to_uri(),
+            Some(normalize_uri(&editor))
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn windows_paths() {
+        use super::path_to_uri;
+        use std::path::Path;
+
+        assert_eq!(
+            path_to_uri(Path::new(r"C:\Users\app\templates\index.html")),
+            Some(String::from("file:///c:/Users/app/templates/index.html"))
+        );
+        assert_eq!(
+            path_to_uri(Path::new(r"\\?\C:\Users\app\my app\index.html")),
+            Some(String::from("file:///c:/Users/app/my%20app/index.html"))
+        );
+    }
+}
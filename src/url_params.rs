@@ -0,0 +1,247 @@
+use tree_sitter::{Node, Point};
+
+use crate::{
+    embedded::advance,
+    position::{start_tags, tag_attributes},
+};
+
+/// Attributes with request URL.
+pub const URL_ATTRIBUTES: [&str; 5] = ["hx-get", "hx-post", "hx-put", "hx-patch", "hx-delete"];
+
+/// Delimiters of template expressions for template file extension.
+/// Templ has no expressions inside of strings.
+pub fn interpolations(template_ext: &str) -> &'static [(&'static str, &'static str)] {
+    match template_ext {
+        "erb" | "ejs" => &[("<%", "%>")],
+        "templ" => &[],
+        _ => &[("{{", "}}"), ("{%", "%}")],
+    }
+}
+
+/// Path parameter (`/users/{id}` or `/users/:id`), byte range in URL.
+#[derive(Debug, PartialEq)]
+pub struct UrlParam {
+    pub name: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+fn is_name(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-'
+}
+
+/// URL with template expressions replaced by spaces, so they are never
+/// mistaken for parameters. Unclosed expression hides rest of URL.
+fn without_expressions(url: &str, delimiters: &[(&str, &str)]) -> String {
+    let mut masked = url.to_string();
+    for (open, close) in delimiters {
+        let mut from = 0;
+        while let Some(start) = masked[from..].find(open).map(|start| start + from) {
+            let end = masked[start + open.len()..]
+                .find(close)
+                .map(|end| start + open.len() + end + close.len())
+                .unwrap_or(masked.len());
+            masked.replace_range(start..end, &" ".repeat(end - start));
+            from = end;
+        }
+    }
+    masked
+}
+
+/// Path parameters in URL, template expressions are skipped.
+pub fn url_params(url: &str, delimiters: &[(&str, &str)]) -> Vec<UrlParam> {
+    let masked = without_expressions(url, delimiters);
+    let mut params = vec![];
+    let mut chars = masked.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let param = match c {
+            '{' => masked[i + 1..]
+                .find('}')
+                .map(|len| (i + 1, i + 1 + len, i + len + 2))
+                .filter(|(start, end, _)| {
+                    *start < *end && masked[*start..*end].chars().all(is_name)
+                }),
+            ':' if i > 0 && masked[..i].ends_with('/') => {
+                let len = masked[i + 1..]
+                    .find(|c: char| !is_name(c))
+                    .unwrap_or(masked.len() - i - 1);
+                (len > 0).then_some((i + 1, i + 1 + len, i + 1 + len))
+            }
+            _ => None,
+        };
+        if let Some((start, end, next)) = param {
+            params.push(UrlParam {
+                name: masked[start..end].to_string(),
+                start: i,
+                end: next,
+            });
+            while chars.peek().is_some_and(|(j, _)| *j < next) {
+                chars.next();
+            }
+        }
+    }
+    params
+}
+
+/// Malformed template expression in URL, byte range in URL.
+#[derive(Debug, PartialEq)]
+pub struct UrlError {
+    pub start: usize,
+    pub end: usize,
+    pub message: String,
+}
+
+/// Unclosed, unopened or empty template expressions.
+pub fn interpolation_errors(url: &str, delimiters: &[(&str, &str)]) -> Vec<UrlError> {
+    let mut errors = vec![];
+    for (open, close) in delimiters {
+        let mut from = 0;
+        loop {
+            let next_open = url[from..].find(open).map(|i| i + from);
+            let next_close = url[from..].find(close).map(|i| i + from);
+            match (next_open, next_close) {
+                (Some(start), close_at) if close_at.is_none_or(|end| end > start) => {
+                    let inner = start + open.len();
+                    let end = url[inner..].find(close).map(|end| end + inner);
+                    let reopened = url[inner..].find(open).map(|i| i + inner);
+                    match end {
+                        Some(end) if reopened.is_none_or(|i| i > end) => {
+                            if url[inner..end].trim().is_empty() {
+                                errors.push(UrlError {
+                                    start,
+                                    end: end + close.len(),
+                                    message: format!("Empty `{open} {close}` expression in URL."),
+                                });
+                            }
+                            from = end + close.len();
+                        }
+                        _ => {
+                            errors.push(UrlError {
+                                start,
+                                end: inner,
+                                message: format!("`{open}` is not closed with `{close}`."),
+                            });
+                            from = inner;
+                        }
+                    }
+                }
+                (_, Some(end)) => {
+                    errors.push(UrlError {
+                        start: end,
+                        end: end + close.len(),
+                        message: format!("`{close}` without opening `{open}`."),
+                    });
+                    from = end + close.len();
+                }
+                (_, None) => break,
+            }
+        }
+    }
+    errors.sort_by_key(|error| error.start);
+    errors
+}
+
+/// Markdown with path parameters for hover.
+pub fn url_hover(url: &str, delimiters: &[(&str, &str)]) -> Option<String> {
+    let params = url_params(url, delimiters);
+    if params.is_empty() {
+        return None;
+    }
+    let mut hover = String::from("**Path parameters**\n");
+    for param in params {
+        hover.push_str(&format!("\n* `{}`", param.name));
+    }
+    Some(hover)
+}
+
+pub struct UrlDiagnostic {
+    pub range: (Point, Point),
+    pub message: String,
+}
+
+/// Malformed template expressions in URL attributes of start tags accepted by `keep`.
+pub fn url_value_errors(
+    root: Node<'_>,
+    source: &str,
+    delimiters: &[(&str, &str)],
+    keep: impl Fn(Point, Point) -> bool,
+) -> Vec<UrlDiagnostic> {
+    let mut diagnostics = vec![];
+    for tag in start_tags(root) {
+        if !keep(tag.start_position(), tag.end_position()) {
+            continue;
+        }
+        for (name, value) in tag_attributes(tag, source) {
+            let name = name.strip_prefix("data-").unwrap_or(&name);
+            if !URL_ATTRIBUTES.contains(&name) {
+                continue;
+            }
+            let Some(value) = value else {
+                continue;
+            };
+            let Ok(text) = value.utf8_text(source.as_bytes()) else {
+                continue;
+            };
+            let value_start = value.start_position();
+            for error in interpolation_errors(text, delimiters) {
+                diagnostics.push(UrlDiagnostic {
+                    range: (
+                        advance(value_start, &text[..error.start]),
+                        advance(value_start, &text[..error.end]),
+                    ),
+                    message: error.message,
+                });
+            }
+        }
+    }
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{interpolation_errors, interpolations, url_hover, url_params};
+
+    #[test]
+    fn path_params() {
+        let jinja = interpolations("jinja");
+        let names = |url| -> Vec<String> {
+            url_params(url, jinja)
+                .into_iter()
+                .map(|param| param.name)
+                .collect()
+        };
+        assert_eq!(names("/users/{id}/posts/:post_id"), vec!["id", "post_id"]);
+        assert_eq!(names("/users/{{ user.id }}/edit"), Vec::<String>::new());
+        assert_eq!(names("https://example.com:8080/a"), Vec::<String>::new());
+        assert_eq!(names("/search/{ }"), Vec::<String>::new());
+        let params = url_params("/a/{id}", jinja);
+        assert_eq!((params[0].start, params[0].end), (3, 7));
+        assert_eq!(
+            url_hover("/users/{id}", jinja).as_deref(),
+            Some("**Path parameters**\n\n* `id`")
+        );
+    }
+
+    #[test]
+    fn malformed_expressions() {
+        let jinja = interpolations("jinja");
+        assert!(
+            interpolation_errors("/users/{{ user.id }}/{% if a %}x{% endif %}", jinja).is_empty()
+        );
+        let ranges = |url| -> Vec<(usize, usize)> {
+            interpolation_errors(url, jinja)
+                .into_iter()
+                .map(|error| (error.start, error.end))
+                .collect()
+        };
+        assert_eq!(ranges("/users/{{ user.id /edit"), vec![(7, 9)]);
+        assert_eq!(ranges("/users/ user.id }}/edit"), vec![(16, 18)]);
+        assert_eq!(ranges("/users/{{ }}"), vec![(7, 12)]);
+        assert_eq!(ranges("/{{ a /{{ b }}"), vec![(1, 3)]);
+        assert!(interpolation_errors("/users/{{ id", interpolations("templ")).is_empty());
+        assert_eq!(
+            interpolation_errors("/users/<%= id", interpolations("erb")).len(),
+            1
+        );
+    }
+}